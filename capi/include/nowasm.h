@@ -0,0 +1,77 @@
+/*
+ * C API of nowasm (provided by the `nowasm-capi` crate).
+ *
+ * Build the library with:
+ *   cargo build --release                         # hosted targets
+ *   cargo build --release --no-default-features   # bare-metal targets (uses malloc/free/abort)
+ */
+#ifndef NOWASM_H
+#define NOWASM_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define NOWASM_NONE 0
+#define NOWASM_I32 1
+#define NOWASM_I64 2
+#define NOWASM_F32 3
+#define NOWASM_F64 4
+
+#define NOWASM_OK 0
+#define NOWASM_ERROR 1
+#define NOWASM_TRAP 2
+
+/* A value tagged with NOWASM_{NONE,I32,I64,F32,F64}. Integers are stored in the low bits
+ * of `bits` and floats are stored as their bit patterns. */
+typedef struct nowasm_val {
+    uint8_t tag;
+    uint64_t bits;
+} nowasm_val;
+
+/* Unless noted otherwise, pointer arguments must be non-NULL and valid for the given lengths,
+ * and modules and instances must not be used after being freed. */
+
+typedef struct nowasm_module nowasm_module;
+typedef struct nowasm_instance nowasm_instance;
+
+/* Called for the imported function at `import_index` of the import section.
+ * Returning a non-zero value makes the call trap. */
+typedef int32_t (*nowasm_host_func)(void *user_data, uint32_t import_index,
+                                    const nowasm_val *args, size_t args_len,
+                                    nowasm_val *result);
+
+/* Returns NULL if the module is malformed. */
+nowasm_module *nowasm_module_decode(const uint8_t *bytes, size_t len);
+void nowasm_module_free(nowasm_module *module);
+
+/* Instantiates `module` (which is left intact) with `host_func` serving all the imported
+ * functions. Returns NULL if the module is invalid, imports something other than functions
+ * or the start function traps. */
+nowasm_instance *nowasm_instantiate(const nowasm_module *module, nowasm_host_func host_func,
+                                    void *user_data);
+void nowasm_instance_free(nowasm_instance *instance);
+
+/* Returns NOWASM_OK, NOWASM_TRAP or NOWASM_ERROR (e.g., unknown name or mismatched arguments).
+ * `result` (if not NULL) is set to the returned value, tagged NOWASM_NONE if there is none. */
+int32_t nowasm_invoke(nowasm_instance *instance, const char *name, const nowasm_val *args,
+                      size_t args_len, nowasm_val *result);
+
+/* Size of the memory in bytes. */
+size_t nowasm_memory_size(const nowasm_instance *instance);
+
+/* Return false if the range is out of bounds. */
+bool nowasm_memory_read(const nowasm_instance *instance, size_t offset, uint8_t *buf,
+                        size_t len);
+bool nowasm_memory_write(nowasm_instance *instance, size_t offset, const uint8_t *data,
+                         size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,434 @@
+// C API of nowasm (see `include/nowasm.h` for the declarations).
+//
+// Without the `std` feature, the static library uses `malloc`, `realloc`, `free` and `abort`
+// of the C library as its allocator and panic handler, so it can be linked into bare-metal firmware.
+#![cfg_attr(not(feature = "std"), no_std)]
+// The pointer requirements of the functions are documented in `include/nowasm.h`.
+#![allow(clippy::missing_safety_doc)]
+
+extern crate alloc;
+
+use alloc::{boxed::Box, vec::Vec};
+use core::ffi::{c_char, c_void, CStr};
+use core::ops::RangeBounds;
+use core::ptr::null_mut;
+use nowasm::{
+    components::{Importdesc, Valtype},
+    Env, ExecuteError, Extern, HostFunc, LinearMemory, Module, ModuleInstance, Val, Vector,
+    VectorFactory,
+};
+
+pub const NOWASM_NONE: u8 = 0;
+pub const NOWASM_I32: u8 = 1;
+pub const NOWASM_I64: u8 = 2;
+pub const NOWASM_F32: u8 = 3;
+pub const NOWASM_F64: u8 = 4;
+
+pub const NOWASM_OK: i32 = 0;
+pub const NOWASM_ERROR: i32 = 1;
+pub const NOWASM_TRAP: i32 = 2;
+
+// A value tagged with `NOWASM_*`. Integers are stored in the low bits of `bits`
+// and floats are stored as their bit patterns.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NowasmVal {
+    pub tag: u8,
+    pub bits: u64,
+}
+
+impl NowasmVal {
+    const NONE: Self = Self {
+        tag: NOWASM_NONE,
+        bits: 0,
+    };
+
+    fn new(v: Option<Val>) -> Self {
+        let Some(v) = v else {
+            return Self::NONE;
+        };
+        let tag = match v.ty() {
+            Valtype::I32 => NOWASM_I32,
+            Valtype::I64 => NOWASM_I64,
+            Valtype::F32 => NOWASM_F32,
+            Valtype::F64 => NOWASM_F64,
+        };
+        Self {
+            tag,
+            bits: v.to_bits(),
+        }
+    }
+
+    // Returns `Err(())` if the tag is unknown.
+    fn to_val(self) -> Result<Option<Val>, ()> {
+        match self.tag {
+            NOWASM_NONE => Ok(None),
+            NOWASM_I32 => Ok(Some(Val::I32(self.bits as i32))),
+            NOWASM_I64 => Ok(Some(Val::I64(self.bits as i64))),
+            NOWASM_F32 => Ok(Some(Val::f32_from_bits(self.bits as u32))),
+            NOWASM_F64 => Ok(Some(Val::f64_from_bits(self.bits))),
+            _ => Err(()),
+        }
+    }
+}
+
+// Called for the imported function at `import_index` of the import section.
+// Returning a non-zero value makes the call trap.
+pub type NowasmHostFunc = extern "C" fn(
+    user_data: *mut c_void,
+    import_index: u32,
+    args: *const NowasmVal,
+    args_len: usize,
+    result: *mut NowasmVal,
+) -> i32;
+
+#[derive(Debug, Clone)]
+pub struct CHostFunc {
+    callback: NowasmHostFunc,
+    user_data: *mut c_void,
+    import_index: u32,
+}
+
+impl HostFunc for CHostFunc {
+    fn invoke(&mut self, args: &[Val], env: &mut Env) -> Option<Val> {
+        let args = args
+            .iter()
+            .map(|&v| NowasmVal::new(Some(v)))
+            .collect::<Vec<_>>();
+        let mut result = NowasmVal::NONE;
+        let status = (self.callback)(
+            self.user_data,
+            self.import_index,
+            args.as_ptr(),
+            args.len(),
+            &mut result,
+        );
+        if status != 0 {
+            env.abort();
+            return None;
+        }
+        // An unknown tag is reported as `ExecuteError::InvalidReturnValue` by the executor.
+        result.to_val().ok().flatten()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AllocVectorFactory;
+
+impl VectorFactory for AllocVectorFactory {
+    type Vector<T> = AllocVector<T>;
+
+    fn create_vector<T>(capacity: Option<usize>) -> Self::Vector<T> {
+        AllocVector(Vec::with_capacity(capacity.unwrap_or(0)))
+    }
+
+    fn clone_vector<T: Clone>(vector: &[T]) -> Self::Vector<T> {
+        AllocVector(vector.to_vec())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AllocVector<T>(Vec<T>);
+
+impl<T> Vector<T> for AllocVector<T> {
+    fn push(&mut self, item: T) {
+        self.0.push(item);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.0.pop()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.0.truncate(len);
+    }
+
+    fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        self.0.drain(range);
+    }
+
+    fn insert(&mut self, index: usize, item: T) {
+        self.0.insert(index, item);
+    }
+
+    fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional);
+    }
+
+    fn try_reserve(&mut self, additional: usize) -> bool {
+        self.0.try_reserve(additional).is_ok()
+    }
+}
+
+impl<T> core::ops::Deref for AllocVector<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> core::ops::DerefMut for AllocVector<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+pub type NowasmModule = Module<AllocVectorFactory>;
+
+pub type NowasmInstance = ModuleInstance<AllocVectorFactory, CHostFunc>;
+
+// Returns NULL if the module is malformed.
+#[no_mangle]
+pub unsafe extern "C" fn nowasm_module_decode(bytes: *const u8, len: usize) -> *mut NowasmModule {
+    let bytes = unsafe { core::slice::from_raw_parts(bytes, len) };
+    match Module::decode(bytes) {
+        Ok(module) => Box::into_raw(Box::new(module)),
+        Err(_) => null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nowasm_module_free(module: *mut NowasmModule) {
+    if !module.is_null() {
+        drop(unsafe { Box::from_raw(module) });
+    }
+}
+
+// Instantiates `module` (which is left intact) with `host_func` serving all the imported
+// functions. Returns NULL if the module is invalid, imports something other than functions
+// or the start function traps.
+#[no_mangle]
+pub unsafe extern "C" fn nowasm_instantiate(
+    module: *const NowasmModule,
+    host_func: Option<NowasmHostFunc>,
+    user_data: *mut c_void,
+) -> *mut NowasmInstance {
+    let module = unsafe { &*module };
+    let mut externs = Vec::with_capacity(module.imports().len());
+    for (i, import) in module.imports().iter().enumerate() {
+        let (Importdesc::Func(_), Some(callback)) = (&import.desc, host_func) else {
+            return null_mut();
+        };
+        externs.push(Extern::Func(CHostFunc {
+            callback,
+            user_data,
+            import_index: i as u32,
+        }));
+    }
+    match module.clone().instantiate_with(&externs) {
+        Ok(instance) => Box::into_raw(Box::new(instance)),
+        Err(_) => null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nowasm_instance_free(instance: *mut NowasmInstance) {
+    if !instance.is_null() {
+        drop(unsafe { Box::from_raw(instance) });
+    }
+}
+
+// Invokes the exported function `name` (a NUL-terminated UTF-8 string).
+// `result` (if not NULL) is set to the returned value, whose tag is `NOWASM_NONE` if there is none.
+#[no_mangle]
+pub unsafe extern "C" fn nowasm_invoke(
+    instance: *mut NowasmInstance,
+    name: *const c_char,
+    args: *const NowasmVal,
+    args_len: usize,
+    result: *mut NowasmVal,
+) -> i32 {
+    let instance = unsafe { &mut *instance };
+    let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+        return NOWASM_ERROR;
+    };
+    let args: &[NowasmVal] = if args_len == 0 {
+        &[]
+    } else {
+        unsafe { core::slice::from_raw_parts(args, args_len) }
+    };
+    let Ok(Some(args)) = args
+        .iter()
+        .map(|v| v.to_val())
+        .collect::<Result<Option<Vec<_>>, _>>()
+    else {
+        return NOWASM_ERROR;
+    };
+
+    match instance.invoke(name, &args) {
+        Ok(v) => {
+            if !result.is_null() {
+                unsafe { *result = NowasmVal::new(v) };
+            }
+            NOWASM_OK
+        }
+        Err(ExecuteError::Trap(_)) => NOWASM_TRAP,
+        Err(_) => NOWASM_ERROR,
+    }
+}
+
+// Size of the memory in bytes.
+#[no_mangle]
+pub unsafe extern "C" fn nowasm_memory_size(instance: *const NowasmInstance) -> usize {
+    unsafe { &*instance }.mem().size()
+}
+
+// Returns `false` if the range is out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn nowasm_memory_read(
+    instance: *const NowasmInstance,
+    offset: usize,
+    buf: *mut u8,
+    len: usize,
+) -> bool {
+    if len == 0 {
+        return offset <= unsafe { nowasm_memory_size(instance) };
+    }
+    let buf = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+    unsafe { &*instance }.mem().read(offset, buf)
+}
+
+// Returns `false` if the range is out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn nowasm_memory_write(
+    instance: *mut NowasmInstance,
+    offset: usize,
+    data: *const u8,
+    len: usize,
+) -> bool {
+    if len == 0 {
+        return offset <= unsafe { nowasm_memory_size(instance) };
+    }
+    let data = unsafe { core::slice::from_raw_parts(data, len) };
+    unsafe { &mut *instance }.mem_mut().write(offset, data)
+}
+
+// The C library provides the allocator and the panic handler of bare-metal builds.
+#[cfg(not(feature = "std"))]
+mod c_runtime {
+    use core::alloc::{GlobalAlloc, Layout};
+    use core::ffi::c_void;
+
+    extern "C" {
+        fn malloc(size: usize) -> *mut c_void;
+        fn realloc(ptr: *mut c_void, size: usize) -> *mut c_void;
+        fn free(ptr: *mut c_void);
+        fn abort() -> !;
+    }
+
+    // `malloc()` is only guaranteed to align to 8 bytes on 32-bit targets,
+    // which is enough for any value nowasm allocates.
+    const MALLOC_ALIGN: usize = 8;
+
+    struct CAllocator;
+
+    unsafe impl GlobalAlloc for CAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            if layout.align() > MALLOC_ALIGN {
+                return core::ptr::null_mut();
+            }
+            unsafe { malloc(layout.size()) }.cast()
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+            unsafe { free(ptr.cast()) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            if layout.align() > MALLOC_ALIGN {
+                return core::ptr::null_mut();
+            }
+            unsafe { realloc(ptr.cast(), new_size) }.cast()
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CAllocator = CAllocator;
+
+    #[panic_handler]
+    fn panic(_info: &core::panic::PanicInfo) -> ! {
+        unsafe { abort() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn double(
+        user_data: *mut c_void,
+        import_index: u32,
+        args: *const NowasmVal,
+        args_len: usize,
+        result: *mut NowasmVal,
+    ) -> i32 {
+        let calls = unsafe { &mut *(user_data as *mut u32) };
+        *calls += 1;
+        let args = unsafe { core::slice::from_raw_parts(args, args_len) };
+        let v = args[0].bits as i32;
+        if import_index != 0 || v < 0 {
+            return 1;
+        }
+        unsafe { *result = NowasmVal::new(Some(Val::I32(v * 2))) };
+        0
+    }
+
+    #[test]
+    fn c_api() {
+        // (module
+        //   (import "env" "double" (func $double (param i32) (result i32)))
+        //   (memory (export "mem") 1)
+        //   (func (export "run") (param i32) (result i32)
+        //     (i32.store (i32.const 0) (call $double (local.get 0)))
+        //     (i32.add (i32.load (i32.const 0)) (i32.const 1))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 2, 14, 1, 3, 101, 110, 118,
+            6, 100, 111, 117, 98, 108, 101, 0, 0, 3, 2, 1, 0, 5, 3, 1, 0, 1, 7, 13, 2, 3, 114, 117,
+            110, 0, 1, 3, 109, 101, 109, 2, 0, 10, 21, 1, 19, 0, 65, 0, 32, 0, 16, 0, 54, 2, 0, 65,
+            0, 40, 2, 0, 65, 1, 106, 11,
+        ];
+        unsafe {
+            assert!(nowasm_module_decode(input.as_ptr(), 4).is_null());
+            let module = nowasm_module_decode(input.as_ptr(), input.len());
+            assert!(!module.is_null());
+            assert!(nowasm_instantiate(module, None, null_mut()).is_null());
+
+            let mut calls = 0u32;
+            let user_data = &mut calls as *mut u32 as *mut c_void;
+            let instance = nowasm_instantiate(module, Some(double), user_data);
+            nowasm_module_free(module);
+            assert!(!instance.is_null());
+
+            let name = c"run".as_ptr();
+            let args = [NowasmVal::new(Some(Val::I32(20)))];
+            let mut result = NowasmVal::NONE;
+            let status = nowasm_invoke(instance, name, args.as_ptr(), 1, &mut result);
+            assert_eq!(NOWASM_OK, status);
+            assert_eq!(NowasmVal::new(Some(Val::I32(41))), result);
+
+            let mut buf = [0; 4];
+            assert!(nowasm_memory_read(instance, 0, buf.as_mut_ptr(), 4));
+            assert_eq!(40, i32::from_le_bytes(buf));
+            assert!(nowasm_memory_write(instance, 0, [7, 0, 0, 0].as_ptr(), 4));
+            assert!(nowasm_memory_read(instance, 0, buf.as_mut_ptr(), 4));
+            assert_eq!(7, i32::from_le_bytes(buf));
+            let size = nowasm_memory_size(instance);
+            assert_eq!(nowasm::PAGE_SIZE, size);
+            assert!(!nowasm_memory_read(instance, size - 2, buf.as_mut_ptr(), 4));
+
+            let args = [NowasmVal::new(Some(Val::I32(-1)))];
+            let status = nowasm_invoke(instance, name, args.as_ptr(), 1, &mut result);
+            assert_eq!(NOWASM_TRAP, status);
+            let status = nowasm_invoke(instance, name, null_mut(), 0, &mut result);
+            assert_eq!(NOWASM_ERROR, status);
+            assert_eq!(2, calls);
+
+            nowasm_instance_free(instance);
+        }
+    }
+}
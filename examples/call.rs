@@ -1,5 +1,5 @@
 use clap::Parser;
-use nowasm::{Env, HostFunc, Module, Resolve, StdVectorFactory, Val};
+use nowasm::{Env, ExecuteError, HostFunc, Module, Resolve, StdVectorFactory, Val};
 use orfail::{Failure, OrFail};
 use std::{fmt::Debug, path::PathBuf};
 
@@ -7,7 +7,12 @@ use std::{fmt::Debug, path::PathBuf};
 struct Args {
     wasm_path: PathBuf,
     func_name: String,
-    func_args: Vec<i32>,
+    // Typed values such as `i64:7` or `f32:0x1.8p1` (untyped values are `i32`).
+    func_args: Vec<Val>,
+
+    // Writes the final contents of the memory to the given file.
+    #[clap(long)]
+    dump_memory: Option<PathBuf>,
 }
 
 pub fn main() -> orfail::Result<()> {
@@ -23,12 +28,18 @@ pub fn main() -> orfail::Result<()> {
         .map_err(|e| Failure::new(format!("{e:?}")))
         .or_fail()?;
 
-    let func_args: Vec<_> = args.func_args.iter().copied().map(Val::I32).collect();
-    let result = instance
-        .invoke(&args.func_name, &func_args)
-        .map_err(|e| Failure::new(format!("{e:?}")))
-        .or_fail()?;
-    println!("=> {:?}", result);
+    let result = instance.invoke(&args.func_name, &args.func_args);
+    if let Some(path) = &args.dump_memory {
+        std::fs::write(path, instance.mem().as_slice()).or_fail()?;
+    }
+    match result {
+        Ok(Some(v)) => println!("=> {v}"),
+        Ok(None) => {}
+        Err(e @ ExecuteError::Trap(_)) => {
+            return Err(Failure::new(format!("{e} at {}", instance.backtrace())));
+        }
+        Err(e) => return Err(Failure::new(format!("{e}"))),
+    }
 
     Ok(())
 }
@@ -55,8 +66,9 @@ impl HostFunc for Print {
         // TODO: add module and store to args
         let ptr = args[0].as_i32().unwrap() as usize;
         let len = args[1].as_i32().unwrap() as usize;
-        let slice = &env.mem[ptr..ptr + len];
-        let string = std::str::from_utf8(slice).unwrap();
+        let mut bytes = vec![0; len];
+        assert!(env.mem.read(ptr, &mut bytes));
+        let string = std::str::from_utf8(&bytes).unwrap();
         print!("{string}");
         None
     }
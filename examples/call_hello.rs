@@ -1,4 +1,7 @@
-use nowasm::{Env, HostFunc, Module, Resolve, StdVectorFactory, Val};
+use nowasm::{
+    abi::{self, GuestSlice},
+    Env, HostFunc, Module, Resolve, StdVectorFactory, Val,
+};
 
 pub fn main() {
     #[cfg(test)]
@@ -33,9 +36,8 @@ struct Print;
 
 impl HostFunc for Print {
     fn invoke(&mut self, args: &[Val], env: &mut Env) -> Option<Val> {
-        let ptr = args[0].as_i32().expect("Not a i32") as usize;
-        let len = args[1].as_i32().expect("Not a i32") as usize;
-        let msg = std::str::from_utf8(&env.mem[ptr..ptr + len]).expect("Invalid utf8");
+        let msg = GuestSlice::from_vals(args[0], args[1]).expect("Not a i32");
+        let msg = abi::read_string(&*env.mem, msg).expect("Invalid string");
         print!("{msg}");
         None
     }
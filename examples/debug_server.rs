@@ -0,0 +1,34 @@
+use clap::Parser;
+use nowasm::{Debugger, Module, StdVectorFactory};
+use orfail::{Failure, OrFail};
+use std::{net::TcpListener, path::PathBuf};
+
+#[derive(Debug, Parser)]
+struct Args {
+    wasm_path: PathBuf,
+
+    #[clap(long, default_value_t = 9000)]
+    port: u16,
+}
+
+pub fn main() -> orfail::Result<()> {
+    let args = Args::parse();
+    let wasm_bytes = std::fs::read(&args.wasm_path).or_fail()?;
+
+    let module = Module::<StdVectorFactory>::decode(&wasm_bytes)
+        .map_err(|e| Failure::new(format!("{e:?}")))
+        .or_fail()?;
+    let mut instance = module
+        .instantiate(())
+        .map_err(|e| Failure::new(format!("{e:?}")))
+        .or_fail()?;
+
+    // e.g., `$ nc localhost 9000` and type `call fib 10`, `step`, `backtrace`, ...
+    let listener = TcpListener::bind(("127.0.0.1", args.port)).or_fail()?;
+    println!("Listening on {}", listener.local_addr().or_fail()?);
+    let (stream, addr) = listener.accept().or_fail()?;
+    println!("Accepted {addr}");
+    Debugger::new(&mut instance).serve(stream).or_fail()?;
+
+    Ok(())
+}
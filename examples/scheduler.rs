@@ -0,0 +1,63 @@
+// Runs an exported function with each of the given arguments as a separate task
+// interleaved by `Scheduler`, e.g.:
+//
+// $ cargo build --target wasm32-unknown-unknown --example fib
+// $ cargo run --example scheduler -- target/wasm32-unknown-unknown/debug/examples/fib.wasm fib 25 10 20
+use clap::Parser;
+use nowasm::{Module, Scheduler, StdVectorFactory, Val};
+use orfail::{Failure, OrFail};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+struct Args {
+    wasm_path: PathBuf,
+    func_name: String,
+    // Typed values such as `i64:7` (untyped values are `i32`).
+    func_args: Vec<Val>,
+
+    // Instructions executed by a task per turn.
+    #[clap(long, default_value_t = 10000)]
+    fuel: usize,
+}
+
+pub fn main() -> orfail::Result<()> {
+    let args = Args::parse();
+    let wasm_bytes = std::fs::read(&args.wasm_path).or_fail()?;
+    let module = Module::<StdVectorFactory>::decode(&wasm_bytes)
+        .map_err(|e| Failure::new(format!("{e:?}")))
+        .or_fail()?;
+
+    let mut scheduler = Scheduler::new(args.fuel);
+    let mut tasks = Vec::new();
+    for arg in &args.func_args {
+        let instance = module
+            .clone()
+            .instantiate(())
+            .map_err(|e| Failure::new(format!("{e:?}")))
+            .or_fail()?;
+        let task = scheduler
+            .spawn(instance, &args.func_name, &[*arg])
+            .map_err(|e| Failure::new(format!("{e}")))
+            .or_fail()?;
+        tasks.push((task, *arg));
+    }
+
+    let mut ticks = 0;
+    while !tasks.is_empty() {
+        scheduler.tick();
+        ticks += 1;
+        tasks.retain(|&(task, arg)| {
+            let Some(result) = scheduler.result(task) else {
+                return true;
+            };
+            match result {
+                Ok(Some(v)) => println!("[tick {ticks}] {}({arg}) => {v}", args.func_name),
+                Ok(None) => println!("[tick {ticks}] {}({arg})", args.func_name),
+                Err(e) => println!("[tick {ticks}] {}({arg}) failed: {e}", args.func_name),
+            }
+            scheduler.remove(task);
+            false
+        });
+    }
+    Ok(())
+}
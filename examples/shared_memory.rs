@@ -0,0 +1,48 @@
+use nowasm::{Module, Resolve, SharedMemory, StdVectorFactory, Val};
+
+// (module
+//   (memory (export "memory") 1)
+//   (func (export "put") (param i32 i32)
+//     (i32.store8 (local.get 0) (local.get 1))))
+const PRODUCER: &[u8] = &[
+    0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 2, 127, 127, 0, 3, 2, 1, 0, 5, 3, 1, 0, 1, 7, 16, 2,
+    6, 109, 101, 109, 111, 114, 121, 2, 0, 3, 112, 117, 116, 0, 0, 10, 11, 1, 9, 0, 32, 0, 32, 1,
+    58, 0, 0, 11,
+];
+
+// (module
+//   (import "env" "memory" (memory 1))
+//   (func (export "get") (param i32) (result i32)
+//     (i32.load8_u (local.get 0))))
+const CONSUMER: &[u8] = &[
+    0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 2, 15, 1, 3, 101, 110, 118, 6, 109,
+    101, 109, 111, 114, 121, 2, 0, 1, 3, 2, 1, 0, 7, 7, 1, 3, 103, 101, 116, 0, 0, 10, 9, 1, 7, 0,
+    32, 0, 45, 0, 0, 11,
+];
+
+// Links the "env" "memory" import to the memory given at instantiation.
+struct Env;
+
+impl Resolve for Env {
+    type HostFunc = ();
+
+    fn alias_mem(&self, module: &str, name: &str) -> bool {
+        module == "env" && name == "memory"
+    }
+}
+
+pub fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let memory = SharedMemory::new();
+
+    let mut producer = Module::<StdVectorFactory>::decode(PRODUCER)?
+        .instantiate_with_memory((), memory.clone())?;
+    let mut consumer = Module::<StdVectorFactory>::decode(CONSUMER)?
+        .instantiate_with_memory(Env, memory.clone())?;
+
+    producer.invoke("put", &[Val::I32(8), Val::I32(123)])?;
+    let result = consumer.invoke("get", &[Val::I32(8)])?;
+    println!("consumer => {:?}", result);
+    println!("host     => {:?}", memory.bytes()[8]);
+
+    Ok(())
+}
@@ -0,0 +1,275 @@
+// Helpers for exchanging strings and byte arrays with a guest following the common convention:
+// the host allocates a buffer by calling an allocator exported by the guest, copies the data in
+// and passes `(ptr, len)` as two `i32` arguments (and reads back results passed the same way).
+use crate::{
+    components::{Exportdesc, Valtype},
+    ExecuteError, HostFunc, LinearMemory, ModuleInstance, StackStorage, Val, VectorFactory,
+};
+use core::fmt::{Display, Formatter};
+
+// Exports tried (in order) by `ModuleInstance::guest_alloc()`:
+// - `(old_ptr: i32, old_len: i32, align: i32, new_len: i32) -> i32`
+//   (the `realloc` of the canonical ABI, called with `old_ptr = old_len = 0`)
+// - `(len: i32) -> i32`
+pub const REALLOC_EXPORTS: &[&str] = &["cabi_realloc", "canonical_abi_realloc"];
+pub const MALLOC_EXPORTS: &[&str] = &["malloc"];
+
+// A `(ptr, len)` pair referring to bytes in the memory of a guest.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GuestSlice {
+    pub ptr: u32,
+    pub len: u32,
+}
+
+impl GuestSlice {
+    // Takes the pointer and the length from the arguments of a host function (or the results
+    // of guest functions). Returns `None` if they aren't `i32`.
+    pub fn from_vals(ptr: Val, len: Val) -> Option<Self> {
+        Some(Self {
+            ptr: ptr.as_i32()? as u32,
+            len: len.as_i32()? as u32,
+        })
+    }
+
+    pub fn to_vals(self) -> [Val; 2] {
+        [Val::I32(self.ptr as i32), Val::I32(self.len as i32)]
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum AbiError {
+    NoAllocator,
+    AllocationFailed,
+    OutOfBounds,
+    InvalidUtf8,
+    InteriorNul,
+    UnterminatedCStr,
+    Execute(ExecuteError),
+}
+
+impl From<ExecuteError> for AbiError {
+    fn from(e: ExecuteError) -> Self {
+        Self::Execute(e)
+    }
+}
+
+impl Display for AbiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoAllocator => write!(f, "No allocator is exported by the guest"),
+            Self::AllocationFailed => write!(f, "Guest allocation failed"),
+            Self::OutOfBounds => write!(f, "Out of bounds memory access"),
+            Self::InvalidUtf8 => write!(f, "Invalid UTF-8 string"),
+            Self::InteriorNul => write!(f, "Interior NUL byte in C string"),
+            Self::UnterminatedCStr => write!(f, "Unterminated C string"),
+            Self::Execute(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AbiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Execute(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// Returns the first `slice.len` bytes of `buf` filled with the referred bytes.
+pub fn read_bytes<'a, M: LinearMemory + ?Sized>(
+    mem: &M,
+    slice: GuestSlice,
+    buf: &'a mut [u8],
+) -> Result<&'a [u8], AbiError> {
+    let buf = buf
+        .get_mut(..slice.len as usize)
+        .ok_or(AbiError::OutOfBounds)?;
+    if !mem.read(slice.ptr as usize, buf) {
+        return Err(AbiError::OutOfBounds);
+    }
+    Ok(buf)
+}
+
+pub fn read_str<'a, M: LinearMemory + ?Sized>(
+    mem: &M,
+    slice: GuestSlice,
+    buf: &'a mut [u8],
+) -> Result<&'a str, AbiError> {
+    let bytes = read_bytes(mem, slice, buf)?;
+    core::str::from_utf8(bytes).map_err(|_| AbiError::InvalidUtf8)
+}
+
+// Reads a NUL-terminated string (excluding the terminator) of at most `buf.len()` bytes.
+pub fn read_c_str<'a, M: LinearMemory + ?Sized>(
+    mem: &M,
+    ptr: u32,
+    buf: &'a mut [u8],
+) -> Result<&'a str, AbiError> {
+    let mut len = 0;
+    loop {
+        let mut b = [0];
+        let addr = (ptr as usize).checked_add(len);
+        if !addr.is_some_and(|addr| mem.read(addr, &mut b)) {
+            return Err(AbiError::OutOfBounds);
+        }
+        if b[0] == 0 {
+            break;
+        }
+        *buf.get_mut(len).ok_or(AbiError::UnterminatedCStr)? = b[0];
+        len += 1;
+    }
+    core::str::from_utf8(&buf[..len]).map_err(|_| AbiError::InvalidUtf8)
+}
+
+#[cfg(feature = "std")]
+pub fn read_string<M: LinearMemory + ?Sized>(
+    mem: &M,
+    slice: GuestSlice,
+) -> Result<String, AbiError> {
+    // `slice.len` comes from the guest, so it's validated before allocating the buffer.
+    let end = (slice.ptr as usize).checked_add(slice.len as usize);
+    if end.is_none_or(|end| end > mem.size()) {
+        return Err(AbiError::OutOfBounds);
+    }
+    let mut buf = vec![0; slice.len as usize];
+    read_str(mem, slice, &mut buf).map(String::from)
+}
+
+impl<V: VectorFactory, H: HostFunc, M: LinearMemory, S: StackStorage> ModuleInstance<V, H, M, S> {
+    // Allocates `len` bytes in the memory of the guest (see `REALLOC_EXPORTS`).
+    // `align` is ignored by `malloc`-like allocators.
+    pub fn guest_alloc(&mut self, len: u32, align: u32) -> Result<u32, AbiError> {
+        let ptr = if let Some(name) = self.find_allocator(REALLOC_EXPORTS, 4) {
+            let args = [0, 0, align, len].map(|v| Val::I32(v as i32));
+            self.invoke(name, &args)?
+        } else if let Some(name) = self.find_allocator(MALLOC_EXPORTS, 1) {
+            self.invoke(name, &[Val::I32(len as i32)])?
+        } else {
+            return Err(AbiError::NoAllocator);
+        };
+        let ptr = ptr
+            .and_then(|v| v.as_i32())
+            .ok_or(AbiError::AllocationFailed)? as u32;
+        let in_bounds = (ptr as usize)
+            .checked_add(len as usize)
+            .is_some_and(|end| end <= self.mem().size());
+        if (ptr == 0 && len > 0) || !in_bounds {
+            return Err(AbiError::AllocationFailed);
+        }
+        Ok(ptr)
+    }
+
+    // Copies `bytes` into a buffer allocated by `guest_alloc()`.
+    pub fn pass_bytes(&mut self, bytes: &[u8]) -> Result<GuestSlice, AbiError> {
+        let len = u32::try_from(bytes.len()).map_err(|_| AbiError::AllocationFailed)?;
+        let ptr = self.guest_alloc(len, 1)?;
+        if !self.mem_mut().write(ptr as usize, bytes) {
+            return Err(AbiError::OutOfBounds);
+        }
+        Ok(GuestSlice { ptr, len })
+    }
+
+    pub fn pass_str(&mut self, s: &str) -> Result<GuestSlice, AbiError> {
+        self.pass_bytes(s.as_bytes())
+    }
+
+    // Same as `pass_str()` but appends a NUL terminator (not counted in the returned length).
+    pub fn pass_c_str(&mut self, s: &str) -> Result<GuestSlice, AbiError> {
+        if s.as_bytes().contains(&0) {
+            return Err(AbiError::InteriorNul);
+        }
+        let len = u32::try_from(s.len()).map_err(|_| AbiError::AllocationFailed)?;
+        let ptr = self.guest_alloc(len.checked_add(1).ok_or(AbiError::AllocationFailed)?, 1)?;
+        let mut mem = self.mem_mut();
+        if !mem.write(ptr as usize, s.as_bytes()) || !mem.write((ptr + len) as usize, &[0]) {
+            return Err(AbiError::OutOfBounds);
+        }
+        Ok(GuestSlice { ptr, len })
+    }
+
+    fn find_allocator(&self, names: &[&'static str], params: usize) -> Option<&'static str> {
+        names.iter().copied().find(|name| {
+            let Some(Exportdesc::Func(idx)) = self.export(name) else {
+                return false;
+            };
+            self.funcs()
+                .get(idx.get())
+                .and_then(|func| func.get_type(&self.module))
+                .is_some_and(|ty| {
+                    ty.params.len() == params
+                        && ty.params.iter().all(|t| *t == Valtype::I32)
+                        && ty.result.get() == Some(Valtype::I32)
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Module, StdVectorFactory};
+
+    #[test]
+    fn pass_and_read_strings() {
+        // (module
+        //   (memory (export "memory") 1)
+        //   (global $next (mut i32) (i32.const 16))
+        //   (func (export "malloc") (param i32) (result i32)
+        //     (global.get $next)
+        //     (global.set $next (i32.add (global.get $next) (local.get 0)))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 3, 2, 1, 0, 5, 3, 1, 0, 1, 6,
+            6, 1, 127, 1, 65, 16, 11, 7, 19, 2, 6, 109, 101, 109, 111, 114, 121, 2, 0, 6, 109, 97,
+            108, 108, 111, 99, 0, 0, 10, 13, 1, 11, 0, 35, 0, 35, 0, 32, 0, 106, 36, 0, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+
+        let hello = instance.pass_str("hello").expect("pass_str");
+        assert_eq!(GuestSlice { ptr: 16, len: 5 }, hello);
+        let hi = instance.pass_c_str("hi").expect("pass_c_str");
+        assert_eq!(GuestSlice { ptr: 21, len: 2 }, hi);
+        assert!(matches!(
+            instance.pass_c_str("a\0b"),
+            Err(AbiError::InteriorNul)
+        ));
+
+        let [ptr, len] = hello.to_vals();
+        let hello = GuestSlice::from_vals(ptr, len).expect("from_vals");
+        assert_eq!(
+            Ok("hello".to_owned()),
+            read_string(instance.mem(), hello).map_err(|_| ())
+        );
+        let mut buf = [0; 8];
+        assert_eq!(
+            Ok("hi"),
+            read_c_str(instance.mem(), hi.ptr, &mut buf).map_err(|_| ())
+        );
+        assert!(matches!(
+            read_c_str(instance.mem(), hello.ptr, &mut [0; 4]),
+            Err(AbiError::UnterminatedCStr)
+        ));
+        assert!(matches!(
+            read_bytes(instance.mem(), GuestSlice { ptr: 65535, len: 2 }, &mut buf),
+            Err(AbiError::OutOfBounds)
+        ));
+        let huge = GuestSlice {
+            ptr: 16,
+            len: u32::MAX,
+        };
+        assert!(matches!(
+            read_string(instance.mem(), huge),
+            Err(AbiError::OutOfBounds)
+        ));
+
+        let module =
+            Module::<StdVectorFactory>::decode(&[0, 97, 115, 109, 1, 0, 0, 0]).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+        assert!(matches!(
+            instance.pass_str("hello"),
+            Err(AbiError::NoAllocator)
+        ));
+    }
+}
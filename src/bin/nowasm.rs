@@ -0,0 +1,248 @@
+use clap::{Parser, Subcommand};
+use nowasm::{
+    abi::{self, GuestSlice},
+    components::{Exportdesc, Functype, Importdesc},
+    instructions::Instr,
+    Env, ExecuteError, HostFunc, InvokeDynError, Module, Resolve, StdVectorFactory, Val,
+};
+use std::{error::Error, path::PathBuf};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+#[derive(Debug, Parser)]
+#[clap(version, about)]
+struct Args {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Invoke an exported function
+    Run {
+        wasm_path: PathBuf,
+        func_name: String,
+
+        /// Values such as `7` or `0x1.8p1` (parsed as the parameter types) or typed values such as `i64:7`
+        #[clap(allow_hyphen_values = true)]
+        func_args: Vec<String>,
+
+        /// Write the final contents of the memory to the given file
+        #[clap(long)]
+        dump_memory: Option<PathBuf>,
+    },
+
+    /// Print the interface of a module
+    Inspect { wasm_path: PathBuf },
+
+    /// Print the size metrics of the functions of a module (largest first)
+    Metrics { wasm_path: PathBuf },
+
+    /// Decode and validate a module
+    Validate { wasm_path: PathBuf },
+
+    /// Print the functions of a module in a text format
+    Wat { wasm_path: PathBuf },
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    match args.command {
+        Command::Run {
+            wasm_path,
+            func_name,
+            func_args,
+            dump_memory,
+        } => run(wasm_path, &func_name, &func_args, dump_memory),
+        Command::Inspect { wasm_path } => inspect(wasm_path),
+        Command::Metrics { wasm_path } => metrics(wasm_path),
+        Command::Validate { wasm_path } => validate(wasm_path),
+        Command::Wat { wasm_path } => wat(wasm_path),
+    }
+}
+
+fn decode(wasm_path: PathBuf) -> Result<Module<StdVectorFactory>> {
+    let wasm_bytes = std::fs::read(wasm_path)?;
+    Ok(Module::decode(&wasm_bytes)?)
+}
+
+fn run(
+    wasm_path: PathBuf,
+    func_name: &str,
+    func_args: &[String],
+    dump_memory: Option<PathBuf>,
+) -> Result<()> {
+    let module = decode(wasm_path)?;
+    let mut instance = module.instantiate(Resolver)?;
+    let func_args = func_args.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    let result = instance.invoke_dyn(func_name, &func_args);
+    if let Some(path) = dump_memory {
+        std::fs::write(path, instance.mem().as_slice())?;
+    }
+    match result {
+        Ok(Some(v)) => println!("=> {v}"),
+        Ok(None) => {}
+        Err(InvokeDynError::Execute(e @ ExecuteError::Trap(_))) => {
+            return Err(format!("{e} at {}", instance.backtrace()).into());
+        }
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
+fn inspect(wasm_path: PathBuf) -> Result<()> {
+    let wasm_bytes = std::fs::read(wasm_path)?;
+    let metadata = Module::<StdVectorFactory>::decode_metadata(&wasm_bytes)?;
+    println!("digest: {}", metadata.digest());
+    println!("types: {}", metadata.types().len());
+    println!("funcs: {}", metadata.funcs().len());
+    println!("globals: {}", metadata.globals().len());
+    if let Some(table) = metadata.table() {
+        println!("table: {:?}", table.limits);
+    }
+    if let Some(mem) = metadata.mem() {
+        println!("memory: {:?}", mem.limits);
+    }
+    if let Some(start) = metadata.start() {
+        println!("start: {}", start.get());
+    }
+    println!("imports: {}", metadata.imports().len());
+    for import in metadata.imports() {
+        let (module, name) = (import.module.as_str(), import.name.as_str());
+        let kind = match import.desc {
+            Importdesc::Func(_) => "func",
+            Importdesc::Table(_) => "table",
+            Importdesc::Mem(_) => "memory",
+            Importdesc::Global(_) => "global",
+        };
+        println!("  {module}.{name} ({kind})");
+    }
+    println!("exports: {}", metadata.exports().len());
+    for export in metadata.exports() {
+        let kind = match export.desc {
+            Exportdesc::Func(_) => "func",
+            Exportdesc::Table(_) => "table",
+            Exportdesc::Mem(_) => "memory",
+            Exportdesc::Global(_) => "global",
+        };
+        println!("  {} ({kind})", export.name.as_str());
+    }
+    Ok(())
+}
+
+fn metrics(wasm_path: PathBuf) -> Result<()> {
+    let module = decode(wasm_path)?;
+    let mut metrics = module.func_metrics().collect::<Vec<_>>();
+    metrics.sort_by_key(|m| std::cmp::Reverse(m.code_size));
+    println!("func\tbytes\tinstrs\tlocals\tnesting\tname");
+    for m in metrics {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            m.idx.get(),
+            m.code_size,
+            m.instrs,
+            m.locals,
+            m.max_nesting,
+            module.func_name(m.idx).unwrap_or("-")
+        );
+    }
+    Ok(())
+}
+
+fn validate(wasm_path: PathBuf) -> Result<()> {
+    decode(wasm_path)?.validate()?;
+    println!("ok");
+    Ok(())
+}
+
+fn wat(wasm_path: PathBuf) -> Result<()> {
+    let module = decode(wasm_path)?;
+    println!("(module");
+    for (i, ty) in module.types().iter().enumerate() {
+        println!("  (type {i} (func{}))", signature(ty));
+    }
+    let mut funcidx = 0;
+    for import in module.imports() {
+        let Importdesc::Func(ty) = import.desc else {
+            continue;
+        };
+        let (module, name) = (import.module.as_str(), import.name.as_str());
+        println!(
+            "  (import {module:?} {name:?} (func {funcidx} (type {})))",
+            ty.get()
+        );
+        funcidx += 1;
+    }
+    for func in module.funcs() {
+        print!("  (func {funcidx} (type {})", func.ty.get());
+        for ty in func.locals.iter() {
+            print!(" (local {ty})");
+        }
+        println!();
+        print_instrs(func.body.instrs(), 2);
+        println!("  )");
+        funcidx += 1;
+    }
+    println!(")");
+    Ok(())
+}
+
+fn print_instrs(instrs: &[Instr<StdVectorFactory>], depth: usize) {
+    let indent = "  ".repeat(depth);
+    for instr in instrs {
+        println!("{indent}{instr}");
+        match instr {
+            Instr::Block(block) => print_instrs(&block.instrs, depth + 1),
+            Instr::Loop(block) => print_instrs(&block.instrs, depth + 1),
+            Instr::If(block) => {
+                print_instrs(&block.then_instrs, depth + 1);
+                if !block.else_instrs.is_empty() {
+                    println!("{indent}else");
+                    print_instrs(&block.else_instrs, depth + 1);
+                }
+            }
+            _ => continue,
+        }
+        println!("{indent}end");
+    }
+}
+
+fn signature(ty: &Functype<StdVectorFactory>) -> String {
+    let mut s = String::new();
+    for param in ty.params.iter() {
+        s += &format!(" (param {param})");
+    }
+    if let Some(result) = ty.result.get() {
+        s += &format!(" (result {result})");
+    }
+    s
+}
+
+// Provides `env.print(ptr, len)` used by the example modules.
+struct Resolver;
+
+impl Resolve for Resolver {
+    type HostFunc = Print;
+
+    fn resolve_func(&self, module: &str, name: &str) -> Option<Self::HostFunc> {
+        (module == "env" && name == "print").then_some(Print)
+    }
+}
+
+struct Print;
+
+impl HostFunc for Print {
+    // A mismatched signature or an invalid string makes the call trap.
+    fn invoke(&mut self, args: &[Val], env: &mut Env) -> Option<Val> {
+        let msg = match args {
+            &[ptr, len] => GuestSlice::from_vals(ptr, len)
+                .and_then(|msg| abi::read_string(&*env.mem, msg).ok()),
+            _ => None,
+        };
+        match msg {
+            Some(msg) => print!("{msg}"),
+            None => env.abort(),
+        }
+        None
+    }
+}
@@ -0,0 +1,77 @@
+use crate::{
+    components::{Dataidx, Elemidx, Memidx},
+    decode::Decode,
+    features::unknown_opcode_error,
+    reader::Reader,
+    DecodeError, VectorFactory,
+};
+use core::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy)]
+pub enum BulkMemoryInstr {
+    MemoryInit(Dataidx),
+    DataDrop(Dataidx),
+    MemoryCopy,
+    MemoryFill,
+    TableInit(Elemidx),
+    ElemDrop(Elemidx),
+    TableCopy,
+}
+
+impl<V: VectorFactory> Decode<V> for BulkMemoryInstr {
+    // Decodes the sub-opcode that follows the 0xFC prefix.
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        match reader.read_u32()? {
+            8 => {
+                let data = Decode::<V>::decode(reader)?;
+                let _: Memidx = Decode::<V>::decode(reader)?;
+                Ok(Self::MemoryInit(data))
+            }
+            9 => Ok(Self::DataDrop(Decode::<V>::decode(reader)?)),
+            10 => {
+                let _: Memidx = Decode::<V>::decode(reader)?;
+                let _: Memidx = Decode::<V>::decode(reader)?;
+                Ok(Self::MemoryCopy)
+            }
+            11 => {
+                let _: Memidx = Decode::<V>::decode(reader)?;
+                Ok(Self::MemoryFill)
+            }
+            12 => {
+                let elem = Decode::<V>::decode(reader)?;
+                read_tableidx(reader)?;
+                Ok(Self::TableInit(elem))
+            }
+            13 => Ok(Self::ElemDrop(Decode::<V>::decode(reader)?)),
+            14 => {
+                read_tableidx(reader)?;
+                read_tableidx(reader)?;
+                Ok(Self::TableCopy)
+            }
+            sub => Err(unknown_opcode_error(0xfc, sub)),
+        }
+    }
+}
+
+// Only the table 0 exists without the multi-table extension of `reference_types`.
+fn read_tableidx(reader: &mut Reader) -> Result<(), DecodeError> {
+    let table = reader.read_u32()?;
+    if table != 0 {
+        return Err(DecodeError::InvalidTableIdx { value: table });
+    }
+    Ok(())
+}
+
+impl Display for BulkMemoryInstr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::MemoryInit(idx) => write!(f, "memory.init {}", idx.get()),
+            Self::DataDrop(idx) => write!(f, "data.drop {}", idx.get()),
+            Self::MemoryCopy => write!(f, "memory.copy"),
+            Self::MemoryFill => write!(f, "memory.fill"),
+            Self::TableInit(idx) => write!(f, "table.init {}", idx.get()),
+            Self::ElemDrop(idx) => write!(f, "elem.drop {}", idx.get()),
+            Self::TableCopy => write!(f, "table.copy"),
+        }
+    }
+}
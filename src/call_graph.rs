@@ -0,0 +1,206 @@
+use crate::{
+    components::{Funcidx, Importdesc, Typeidx},
+    instructions::Instr,
+    optimize::visit_instrs,
+    vector::Vector,
+    Module, VectorFactory,
+};
+use core::fmt::{Debug, Formatter};
+
+impl<V: VectorFactory> Module<V> {
+    // Builds the static call graph of the functions defined in the module.
+    //
+    // The candidates of `call_indirect` are the functions in the element segments whose types
+    // (structurally) match the expected type. Functions put into the table by the host
+    // (e.g. through an imported table) are not taken into account.
+    pub fn call_graph(&self) -> CallGraph<V> {
+        let imported_funcs = self.imported_funcs_len();
+        let funcs_len = imported_funcs + self.funcs.len();
+
+        let mut in_elems = V::create_vector(None);
+        in_elems.extend_with(funcs_len, false);
+        let mut candidates = V::create_vector(None);
+        for idx in self.elems.iter().flat_map(|e| e.init.iter().flatten()) {
+            if in_elems.get(idx.get()).is_some_and(|seen| !*seen) {
+                in_elems[idx.get()] = true;
+                candidates.push(*idx);
+            }
+        }
+
+        let mut edges = V::create_vector(None);
+        let mut callees = V::create_vector(None);
+        for (i, func) in self.funcs.iter().enumerate() {
+            callees.clear();
+            visit_instrs(func.body.instrs(), &mut |instr| match *instr {
+                Instr::Call(callee) => callees.push((callee, false)),
+                Instr::CallIndirect(typeidx) => {
+                    let Some(expected) = self.types.get(typeidx.get()) else {
+                        return;
+                    };
+                    for &callee in candidates.iter() {
+                        let actual = self
+                            .func_typeidx(callee)
+                            .and_then(|t| self.types.get(t.get()));
+                        if actual == Some(expected) {
+                            callees.push((callee, true));
+                        }
+                    }
+                }
+                _ => {}
+            });
+
+            let caller = Funcidx::new((imported_funcs + i) as u32);
+            callees.sort_unstable_by_key(|&(callee, indirect)| (callee.get(), indirect));
+            for (j, &(callee, indirect)) in callees.iter().enumerate() {
+                if j == 0 || callees[j - 1] != (callee, indirect) {
+                    edges.push(CallEdge {
+                        caller,
+                        callee,
+                        indirect,
+                    });
+                }
+            }
+        }
+
+        CallGraph { funcs_len, edges }
+    }
+
+    pub(crate) fn func_typeidx(&self, idx: Funcidx) -> Option<Typeidx> {
+        let imported_funcs = self.imported_funcs_len();
+        match idx.get().checked_sub(imported_funcs) {
+            Some(i) => self.funcs.get(i).map(|func| func.ty),
+            None => self
+                .imports
+                .iter()
+                .filter_map(|import| match import.desc {
+                    Importdesc::Func(ty) => Some(ty),
+                    _ => None,
+                })
+                .nth(idx.get()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallEdge {
+    pub caller: Funcidx,
+    pub callee: Funcidx,
+
+    // `true` if the callee is a candidate of `call_indirect`.
+    // A caller calling the same function directly and indirectly has both edges.
+    pub indirect: bool,
+}
+
+// Edges are ordered by caller, then by callee (without duplicates).
+pub struct CallGraph<V: VectorFactory> {
+    funcs_len: usize,
+    edges: V::Vector<CallEdge>,
+}
+
+impl<V: VectorFactory> CallGraph<V> {
+    pub fn edges(&self) -> &[CallEdge] {
+        &self.edges
+    }
+
+    // Returns an empty slice for imported functions.
+    pub fn callees(&self, caller: Funcidx) -> &[CallEdge] {
+        let start = self
+            .edges
+            .partition_point(|e| e.caller.get() < caller.get());
+        let end = self
+            .edges
+            .partition_point(|e| e.caller.get() <= caller.get());
+        &self.edges[start..end]
+    }
+
+    // Returns whether each function (indexed by `Funcidx`) is reachable from `roots`.
+    pub fn reachable(&self, roots: impl IntoIterator<Item = Funcidx>) -> V::Vector<bool> {
+        let mut reachable = V::create_vector(None);
+        reachable.extend_with(self.funcs_len, false);
+        let mut worklist = V::create_vector(None);
+        for idx in roots {
+            worklist.push(idx);
+        }
+        while let Some(idx) = worklist.pop() {
+            let Some(seen) = reachable.get(idx.get()) else {
+                continue;
+            };
+            if *seen {
+                continue;
+            }
+            reachable[idx.get()] = true;
+            for edge in self.callees(idx) {
+                worklist.push(edge.callee);
+            }
+        }
+        reachable
+    }
+}
+
+impl<V: VectorFactory> Debug for CallGraph<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CallGraph")
+            .field("funcs_len", &self.funcs_len)
+            .field("edges", &self.edges.as_ref())
+            .finish()
+    }
+}
+
+impl<V: VectorFactory> Clone for CallGraph<V> {
+    fn clone(&self) -> Self {
+        Self {
+            funcs_len: self.funcs_len,
+            edges: V::clone_vector(&self.edges),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StdVectorFactory;
+
+    #[test]
+    fn call_graph() {
+        // (module
+        //   (import "env" "log" (func $log (param i32)))
+        //   (table 2 funcref)
+        //   (elem (i32.const 0) $one $two)
+        //   (func $main (export "main") (param i32)
+        //     (call $log (call $one))
+        //     (drop (call_indirect (result i32) (local.get 0))))
+        //   (func $one (result i32) (i32.const 1))
+        //   (func $two (param i32) (call $log (local.get 0)))
+        //   (func $dead (result i32) (call $dead)))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 9, 2, 96, 1, 127, 0, 96, 0, 1, 127, 2, 11, 1, 3, 101,
+            110, 118, 3, 108, 111, 103, 0, 0, 3, 5, 4, 0, 1, 0, 1, 4, 4, 1, 112, 0, 2, 7, 8, 1, 4,
+            109, 97, 105, 110, 0, 1, 9, 8, 1, 0, 65, 0, 11, 2, 2, 3, 10, 31, 4, 12, 0, 16, 2, 16,
+            0, 32, 0, 17, 1, 0, 26, 11, 4, 0, 65, 1, 11, 6, 0, 32, 0, 16, 0, 11, 4, 0, 16, 4, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let graph = module.call_graph();
+
+        let edge = |caller, callee, indirect| CallEdge {
+            caller: Funcidx::new(caller),
+            callee: Funcidx::new(callee),
+            indirect,
+        };
+        assert_eq!(
+            graph.edges(),
+            [
+                edge(1, 0, false),
+                edge(1, 2, false),
+                edge(1, 2, true),
+                edge(3, 0, false),
+                edge(4, 4, false),
+            ]
+        );
+        assert!(graph.callees(Funcidx::new(0)).is_empty());
+        assert_eq!(graph.callees(Funcidx::new(3)), [edge(3, 0, false)]);
+        assert_eq!(
+            *graph.reachable([Funcidx::new(1)]),
+            [true, true, true, false, false]
+        );
+    }
+}
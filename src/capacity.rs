@@ -0,0 +1,126 @@
+use crate::{
+    components::{Import, Importdesc, Limits, Memtype, Tabletype},
+    memory::pages_to_bytes,
+    FuncRef, Module, ModuleMetadata, VectorFactory,
+};
+
+// Size range of the memory (in pages) or the table (in elements) of a module.
+//
+// For an imported one, the size of the provided memory or table must be at least `min`
+// (and its maximum must not exceed `max` if any).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capacity {
+    pub min: u32,
+    pub max: Option<u32>,
+    pub imported: bool,
+}
+
+impl Capacity {
+    fn new(limits: Limits, imported: bool) -> Self {
+        Self {
+            min: limits.min,
+            max: limits.max,
+            imported,
+        }
+    }
+}
+
+// Resources that have to be allocated to instantiate a module.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityReport {
+    pub memory: Option<Capacity>,
+    pub table: Option<Capacity>,
+}
+
+impl CapacityReport {
+    fn new<V: VectorFactory>(
+        mem: Option<Memtype>,
+        table: Option<Tabletype>,
+        imports: &[Import<V>],
+    ) -> Self {
+        let mut report = Self {
+            memory: mem.map(|ty| Capacity::new(ty.limits, false)),
+            table: table.map(|ty| Capacity::new(ty.limits, false)),
+        };
+        for import in imports {
+            match import.desc {
+                Importdesc::Mem(ty) => report.memory = Some(Capacity::new(ty.limits, true)),
+                Importdesc::Table(ty) => report.table = Some(Capacity::new(ty.limits, true)),
+                _ => {}
+            }
+        }
+        report
+    }
+
+    // Saturates at `usize::MAX` if the size isn't addressable on the target.
+    pub fn min_memory_bytes(self) -> usize {
+        self.memory
+            .map_or(0, |mem| pages_to_bytes(mem.min).unwrap_or(usize::MAX))
+    }
+
+    // Bytes occupied by the elements of the table of the initial size.
+    pub fn min_table_bytes(self) -> usize {
+        self.table.map_or(0, |table| {
+            (table.min as usize).saturating_mul(core::mem::size_of::<Option<FuncRef>>())
+        })
+    }
+}
+
+impl<V: VectorFactory> Module<V> {
+    // Returns `None` if the module has no memory (whether defined or imported).
+    pub fn required_memory_pages(&self) -> Option<Capacity> {
+        self.capacity_report().memory
+    }
+
+    // Returns `None` if the module has no table (whether defined or imported).
+    pub fn required_table_size(&self) -> Option<Capacity> {
+        self.capacity_report().table
+    }
+
+    pub fn capacity_report(&self) -> CapacityReport {
+        CapacityReport::new(self.mem(), self.table(), self.imports())
+    }
+}
+
+impl<V: VectorFactory> ModuleMetadata<V> {
+    // Same as `Module::capacity_report()`.
+    pub fn capacity_report(&self) -> CapacityReport {
+        CapacityReport::new(self.mem(), self.table(), self.imports())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StdVectorFactory, PAGE_SIZE};
+
+    #[test]
+    fn capacity_report() {
+        // (module
+        //   (import "env" "mem" (memory 2 10))
+        //   (table 3 funcref))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 2, 13, 1, 3, 101, 110, 118, 3, 109, 101, 109, 2, 1, 2, 10,
+            4, 4, 1, 112, 0, 3,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let memory = Capacity {
+            min: 2,
+            max: Some(10),
+            imported: true,
+        };
+        let table = Capacity {
+            min: 3,
+            max: None,
+            imported: false,
+        };
+        assert_eq!(Some(memory), module.required_memory_pages());
+        assert_eq!(Some(table), module.required_table_size());
+
+        let report = module.capacity_report();
+        assert_eq!(2 * PAGE_SIZE, report.min_memory_bytes());
+        assert!(report.min_table_bytes() >= 3 * 4);
+        let metadata = Module::<StdVectorFactory>::decode_metadata(&input).expect("decode");
+        assert_eq!(report, metadata.capacity_report());
+    }
+}
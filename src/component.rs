@@ -0,0 +1,66 @@
+use crate::{module::Magic, reader::Reader, vector::Vector, DecodeError, Module, VectorFactory};
+
+const SECTION_ID_CORE_MODULE: u8 = 1;
+const SECTION_ID_COMPONENT: u8 = 4;
+
+impl<V: VectorFactory> Module<V> {
+    // Returns the binaries of the core modules embedded in a component (including those in
+    // nested components) in the order they appear, so that they can be decoded individually.
+    pub fn extract_core_modules(component_bytes: &[u8]) -> Result<V::Vector<&[u8]>, DecodeError> {
+        let mut modules = V::create_vector(None);
+        collect_core_modules::<V>(component_bytes, &mut modules)?;
+        Ok(modules)
+    }
+}
+
+fn collect_core_modules<'a, V: VectorFactory>(
+    component_bytes: &'a [u8],
+    modules: &mut V::Vector<&'a [u8]>,
+) -> Result<(), DecodeError> {
+    let mut reader = Reader::new(component_bytes);
+    let _ = Magic::decode(&mut reader)?;
+    let mut version = [0; 4];
+    reader.read_exact(&mut version)?;
+    if version[2..] != [1, 0] {
+        return Err(DecodeError::InvalidVersion { value: version });
+    }
+
+    while !reader.is_empty() {
+        let section_id = reader.read_u8()?;
+        let size = reader.read_usize()?;
+        let payload = reader.read(size)?;
+        match section_id {
+            SECTION_ID_CORE_MODULE => modules.push(payload),
+            SECTION_ID_COMPONENT => collect_core_modules::<V>(payload, modules)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StdVectorFactory;
+
+    #[test]
+    fn extract_core_modules_test() {
+        // (component
+        //   (core module)
+        //   (component
+        //     (core module (memory 1))))
+        let input = [
+            0, 97, 115, 109, 13, 0, 1, 0, 1, 8, 0, 97, 115, 109, 1, 0, 0, 0, 4, 23, 0, 97, 115,
+            109, 13, 0, 1, 0, 1, 13, 0, 97, 115, 109, 1, 0, 0, 0, 5, 3, 1, 0, 1,
+        ];
+        assert_eq!(
+            Err(DecodeError::ComponentModelNotSupported),
+            Module::<StdVectorFactory>::decode(&input).map(|_| ())
+        );
+
+        let modules = Module::<StdVectorFactory>::extract_core_modules(&input).expect("extract");
+        assert_eq!(2, modules.len());
+        let module = Module::<StdVectorFactory>::decode(modules[1]).expect("decode");
+        assert!(module.mem().is_some());
+    }
+}
@@ -1,13 +1,18 @@
 use crate::decode::Decode;
 use crate::instructions::Instr;
+use crate::memory::pages_to_bytes;
 use crate::reader::Reader;
 use crate::vector::Vector;
-use crate::{DecodeError, ExecuteError, GlobalVal, Module, Val, VectorFactory, PAGE_SIZE};
-use core::fmt::{Debug, Formatter};
+use crate::{DecodeError, ExecuteError, Features, GlobalVal, Module, Val, VectorFactory};
+use core::fmt::{Debug, Display, Formatter};
 
-pub struct Name<V: VectorFactory>(V::Vector<u8>);
+pub struct Name<V: VectorFactory>(pub(crate) V::Vector<u8>);
 
 impl<V: VectorFactory> Name<V> {
+    pub(crate) fn new(s: &str) -> Self {
+        Self(V::clone_vector(s.as_bytes()))
+    }
+
     pub fn as_str(&self) -> &str {
         core::str::from_utf8(&self.0).expect("unreachable")
     }
@@ -77,6 +82,7 @@ impl<V: VectorFactory> Clone for Import<V> {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Importdesc {
     Func(Typeidx),
     Table(Tabletype),
@@ -128,6 +134,7 @@ impl<V: VectorFactory> Clone for Export<V> {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Exportdesc {
     Func(Funcidx),
     Table(Tableidx),
@@ -147,10 +154,15 @@ impl<V: VectorFactory> Decode<V> for Exportdesc {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Typeidx(u32);
 
 impl Typeidx {
+    pub const fn new(v: u32) -> Self {
+        Self(v)
+    }
+
     pub const fn get(self) -> usize {
         self.0 as usize
     }
@@ -162,10 +174,15 @@ impl<V: VectorFactory> Decode<V> for Typeidx {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Funcidx(u32);
 
 impl Funcidx {
+    pub const fn new(v: u32) -> Self {
+        Self(v)
+    }
+
     pub const fn get(self) -> usize {
         self.0 as usize
     }
@@ -178,6 +195,7 @@ impl<V: VectorFactory> Decode<V> for Funcidx {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tableidx;
 
 impl<V: VectorFactory> Decode<V> for Tableidx {
@@ -191,6 +209,7 @@ impl<V: VectorFactory> Decode<V> for Tableidx {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Memidx;
 
 impl<V: VectorFactory> Decode<V> for Memidx {
@@ -203,10 +222,45 @@ impl<V: VectorFactory> Decode<V> for Memidx {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dataidx(u32);
+
+impl Dataidx {
+    pub const fn get(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl<V: VectorFactory> Decode<V> for Dataidx {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        reader.read_u32().map(Self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elemidx(u32);
+
+impl Elemidx {
+    pub const fn get(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl<V: VectorFactory> Decode<V> for Elemidx {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        reader.read_u32().map(Self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Globalidx(u32);
 
 impl Globalidx {
+    pub const fn new(v: u32) -> Self {
+        Self(v)
+    }
+
     pub const fn get(self) -> usize {
         self.0 as usize
     }
@@ -253,6 +307,7 @@ impl<V: VectorFactory> Decode<V> for Labelidx {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tabletype {
     pub elemtype: Elemtype,
     pub limits: Limits,
@@ -276,6 +331,7 @@ impl<V: VectorFactory> Decode<V> for Tabletype {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Elemtype;
 
 impl<V: VectorFactory> Decode<V> for Elemtype {
@@ -289,6 +345,7 @@ impl<V: VectorFactory> Decode<V> for Elemtype {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Limits {
     pub min: u32,
     pub max: Option<u32>,
@@ -306,23 +363,34 @@ impl<V: VectorFactory> Decode<V> for Limits {
                 let max = Some(reader.read_u32()?);
                 Ok(Self { min, max })
             }
+            0x02 | 0x03 => Err(DecodeError::UnsupportedFeature {
+                feature: Features::THREADS,
+            }),
+            0x04..=0x07 => Err(DecodeError::UnsupportedFeature {
+                feature: Features::MEMORY64,
+            }),
             value => Err(DecodeError::InvalidLimitsFlag { value }),
         }
     }
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Memtype {
     pub limits: Limits,
 }
 
 impl Memtype {
+    // Saturates at `usize::MAX` if the size isn't addressable on the target.
     pub fn min_bytes(self) -> usize {
-        self.limits.min as usize * PAGE_SIZE
+        pages_to_bytes(self.limits.min).unwrap_or(usize::MAX)
     }
 
+    // Saturates at `usize::MAX` if the size isn't addressable on the target.
     pub fn max_bytes(self) -> Option<usize> {
-        self.limits.max.map(|max| max as usize * PAGE_SIZE)
+        self.limits
+            .max
+            .map(|max| pages_to_bytes(max).unwrap_or(usize::MAX))
     }
 
     pub fn contains(self, bytes: usize) -> bool {
@@ -343,6 +411,7 @@ impl<V: VectorFactory> Decode<V> for Memtype {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Globaltype {
     Const(Valtype),
     Var(Valtype),
@@ -373,6 +442,7 @@ impl<V: VectorFactory> Decode<V> for Globaltype {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Valtype {
     I32,
     I64,
@@ -387,7 +457,21 @@ impl<V: VectorFactory> Decode<V> for Valtype {
             0x7e => Ok(Self::I64),
             0x7d => Ok(Self::F32),
             0x7c => Ok(Self::F64),
-            value => Err(DecodeError::InvalidValType { value }),
+            value => Err(Features::of_valtype(value)
+                .map_or(DecodeError::InvalidValType { value }, |feature| {
+                    DecodeError::UnsupportedFeature { feature }
+                })),
+        }
+    }
+}
+
+impl Display for Valtype {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::I32 => f.write_str("i32"),
+            Self::I64 => f.write_str("i64"),
+            Self::F32 => f.write_str("f32"),
+            Self::F64 => f.write_str("f64"),
         }
     }
 }
@@ -396,6 +480,19 @@ pub struct Func<V: VectorFactory> {
     pub ty: Typeidx,
     pub locals: V::Vector<Valtype>,
     pub body: Expr<V>,
+    pub(crate) code_offset: usize,
+    pub(crate) code_size: usize,
+}
+
+impl<V: VectorFactory> Func<V> {
+    // Byte offset of the function body (just after its size field) from the start of the module binary.
+    pub fn code_offset(&self) -> usize {
+        self.code_offset
+    }
+
+    pub fn code_size(&self) -> usize {
+        self.code_size
+    }
 }
 
 impl<V: VectorFactory> Debug for Func<V> {
@@ -404,6 +501,8 @@ impl<V: VectorFactory> Debug for Func<V> {
             .field("ty", &self.ty)
             .field("locals", &self.locals.as_ref())
             .field("body", &self.body)
+            .field("code_offset", &self.code_offset)
+            .field("code_size", &self.code_size)
             .finish()
     }
 }
@@ -414,6 +513,8 @@ impl<V: VectorFactory> Clone for Func<V> {
             ty: self.ty,
             locals: V::clone_vector(&self.locals),
             body: self.body.clone(),
+            code_offset: self.code_offset,
+            code_size: self.code_size,
         }
     }
 }
@@ -482,9 +583,14 @@ impl<V: VectorFactory> Clone for Functype<V> {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Resulttype(Option<Valtype>);
 
 impl Resulttype {
+    pub(crate) const fn new(ty: Option<Valtype>) -> Self {
+        Self(ty)
+    }
+
     pub fn len(self) -> usize {
         self.0.is_some() as usize
     }
@@ -504,49 +610,29 @@ impl<V: VectorFactory> Decode<V> for Resulttype {
         match size {
             0 => Ok(Self(None)),
             1 => Ok(Self(Some(Decode::<V>::decode(reader)?))),
-            _ => Err(DecodeError::InvalidResultArity { value: size }),
+            _ => Err(DecodeError::UnsupportedFeature {
+                feature: Features::MULTI_VALUE,
+            }),
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Global {
+pub struct Global<V: VectorFactory> {
     pub ty: Globaltype,
-    pub init: ConstantExpr,
+    pub init: ConstantExpr<V>,
 }
 
-impl Global {
+impl<V: VectorFactory> Global<V> {
     pub fn init(&self, imported_globals: &[GlobalVal]) -> Option<GlobalVal> {
-        match (self.ty.valtype(), self.init) {
-            (Valtype::I32, ConstantExpr::I32(v)) => {
-                Some(GlobalVal::new(self.ty.is_const(), Val::I32(v)))
-            }
-            (Valtype::I64, ConstantExpr::I64(v)) => {
-                Some(GlobalVal::new(self.ty.is_const(), Val::I64(v)))
-            }
-            (Valtype::F32, ConstantExpr::F32(v)) => {
-                Some(GlobalVal::new(self.ty.is_const(), Val::F32(v)))
-            }
-            (Valtype::F64, ConstantExpr::F64(v)) => {
-                Some(GlobalVal::new(self.ty.is_const(), Val::F64(v)))
-            }
-            (ty, ConstantExpr::Global(idx)) => {
-                let g = imported_globals.get(idx.get()).copied()?;
-                if !g.is_const() {
-                    return None;
-                }
-                if g.get().ty() == ty {
-                    Some(g)
-                } else {
-                    None
-                }
-            }
-            _ => None,
+        let v = self.init.evaluate(imported_globals).ok()?;
+        if v.ty() != self.ty.valtype() {
+            return None;
         }
+        Some(GlobalVal::new(self.ty.is_const(), v))
     }
 }
 
-impl<V: VectorFactory> Decode<V> for Global {
+impl<V: VectorFactory> Decode<V> for Global<V> {
     fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
         let ty = Decode::<V>::decode(reader)?;
         let init = Decode::<V>::decode(reader)?;
@@ -554,78 +640,172 @@ impl<V: VectorFactory> Decode<V> for Global {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum I32ConstantExpr {
-    I32(i32),
-    Global(Globalidx),
+impl<V: VectorFactory> Debug for Global<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Global")
+            .field("ty", &self.ty)
+            .field("init", &self.init)
+            .finish()
+    }
 }
 
-impl I32ConstantExpr {
-    pub fn get(self, globals: &[GlobalVal]) -> Option<i32> {
-        match self {
-            Self::I32(v) => Some(v),
-            Self::Global(idx) => {
-                let g = globals.get(idx.get()).copied()?;
-                if !g.is_const() {
-                    return None;
+impl<V: VectorFactory> Clone for Global<V> {
+    fn clone(&self) -> Self {
+        Self {
+            ty: self.ty,
+            init: self.init.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstantInstr {
+    I32Const(i32),
+    I64Const(i64),
+    // Bit patterns (see `Instr::F32Const`).
+    F32Const(u32),
+    F64Const(u64),
+    GlobalGet(Globalidx),
+    #[cfg(feature = "extended_const")]
+    I32Add,
+    #[cfg(feature = "extended_const")]
+    I32Sub,
+    #[cfg(feature = "extended_const")]
+    I32Mul,
+    #[cfg(feature = "extended_const")]
+    I64Add,
+    #[cfg(feature = "extended_const")]
+    I64Sub,
+    #[cfg(feature = "extended_const")]
+    I64Mul,
+}
+
+// Used for global initializers and data / elem offsets.
+pub struct ConstantExpr<V: VectorFactory> {
+    pub(crate) instrs: V::Vector<ConstantInstr>,
+}
+
+impl<V: VectorFactory> ConstantExpr<V> {
+    pub fn instrs(&self) -> &[ConstantInstr] {
+        &self.instrs
+    }
+
+    // `globals` must contain only the globals visible to the expression
+    // (i.e., the imported globals during instantiation).
+    pub fn evaluate(&self, globals: &[GlobalVal]) -> Result<Val, ExecuteError> {
+        let mut stack = V::create_vector(Some(self.instrs.len()));
+        for instr in self.instrs.iter() {
+            let v = match *instr {
+                ConstantInstr::I32Const(v) => Val::I32(v),
+                ConstantInstr::I64Const(v) => Val::I64(v),
+                ConstantInstr::F32Const(v) => Val::F32(f32::from_bits(v)),
+                ConstantInstr::F64Const(v) => Val::F64(f64::from_bits(v)),
+                ConstantInstr::GlobalGet(idx) => {
+                    let g = globals
+                        .get(idx.get())
+                        .ok_or(ExecuteError::InvalidConstantExpr)?;
+                    if !g.is_const() {
+                        return Err(ExecuteError::InvalidConstantExpr);
+                    }
+                    g.get()
                 }
-                let Val::I32(v) = g.get() else {
-                    return None;
-                };
-                Some(v)
-            }
+                #[cfg(feature = "extended_const")]
+                op => {
+                    let rhs = stack.pop().ok_or(ExecuteError::InvalidConstantExpr)?;
+                    let lhs = stack.pop().ok_or(ExecuteError::InvalidConstantExpr)?;
+                    match (op, lhs, rhs) {
+                        (ConstantInstr::I32Add, Val::I32(a), Val::I32(b)) => {
+                            Val::I32(a.wrapping_add(b))
+                        }
+                        (ConstantInstr::I32Sub, Val::I32(a), Val::I32(b)) => {
+                            Val::I32(a.wrapping_sub(b))
+                        }
+                        (ConstantInstr::I32Mul, Val::I32(a), Val::I32(b)) => {
+                            Val::I32(a.wrapping_mul(b))
+                        }
+                        (ConstantInstr::I64Add, Val::I64(a), Val::I64(b)) => {
+                            Val::I64(a.wrapping_add(b))
+                        }
+                        (ConstantInstr::I64Sub, Val::I64(a), Val::I64(b)) => {
+                            Val::I64(a.wrapping_sub(b))
+                        }
+                        (ConstantInstr::I64Mul, Val::I64(a), Val::I64(b)) => {
+                            Val::I64(a.wrapping_mul(b))
+                        }
+                        _ => return Err(ExecuteError::InvalidConstantExpr),
+                    }
+                }
+            };
+            stack.push(v);
+        }
+        match &stack[..] {
+            [v] => Ok(*v),
+            _ => Err(ExecuteError::InvalidConstantExpr),
         }
     }
 }
 
-impl<V: VectorFactory> Decode<V> for I32ConstantExpr {
+impl<V: VectorFactory> Decode<V> for ConstantExpr<V> {
     fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
         let expr = Expr::<V>::decode(reader)?;
-        if expr.instrs().len() != 1 {
+        if !cfg!(feature = "extended_const") && expr.instrs().len() != 1 {
             return Err(DecodeError::UnexpectedExpr);
         }
-        match &expr.instrs()[0] {
-            Instr::I32Const(x) => Ok(Self::I32(*x)),
-            Instr::GlobalGet(x) => Ok(Self::Global(*x)),
-            _ => Err(DecodeError::UnexpectedExpr),
+        let mut instrs = V::create_vector(Some(expr.instrs().len()));
+        for instr in expr.instrs() {
+            instrs.push(match instr {
+                Instr::I32Const(x) => ConstantInstr::I32Const(*x),
+                Instr::I64Const(x) => ConstantInstr::I64Const(*x),
+                Instr::F32Const(x) => ConstantInstr::F32Const(*x),
+                Instr::F64Const(x) => ConstantInstr::F64Const(*x),
+                Instr::GlobalGet(x) => ConstantInstr::GlobalGet(*x),
+                #[cfg(feature = "extended_const")]
+                Instr::I32Add => ConstantInstr::I32Add,
+                #[cfg(feature = "extended_const")]
+                Instr::I32Sub => ConstantInstr::I32Sub,
+                #[cfg(feature = "extended_const")]
+                Instr::I32Mul => ConstantInstr::I32Mul,
+                #[cfg(feature = "extended_const")]
+                Instr::I64Add => ConstantInstr::I64Add,
+                #[cfg(feature = "extended_const")]
+                Instr::I64Sub => ConstantInstr::I64Sub,
+                #[cfg(feature = "extended_const")]
+                Instr::I64Mul => ConstantInstr::I64Mul,
+                _ => return Err(DecodeError::UnexpectedExpr),
+            });
         }
+        Ok(Self { instrs })
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum ConstantExpr {
-    I32(i32),
-    I64(i64),
-    F32(f32),
-    F64(f64),
-    Global(Globalidx),
+impl<V: VectorFactory> Debug for ConstantExpr<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ConstantExpr")
+            .field("instrs", &self.instrs.as_ref())
+            .finish()
+    }
 }
 
-impl<V: VectorFactory> Decode<V> for ConstantExpr {
-    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
-        let expr = Expr::<V>::decode(reader)?;
-        if expr.instrs().len() != 1 {
-            return Err(DecodeError::UnexpectedExpr);
-        }
-        match &expr.instrs()[0] {
-            Instr::I32Const(x) => Ok(Self::I32(*x)),
-            Instr::I64Const(x) => Ok(Self::I64(*x)),
-            Instr::F32Const(x) => Ok(Self::F32(*x)),
-            Instr::F64Const(x) => Ok(Self::F64(*x)),
-            Instr::GlobalGet(x) => Ok(Self::Global(*x)),
-            _ => Err(DecodeError::UnexpectedExpr),
+impl<V: VectorFactory> Clone for ConstantExpr<V> {
+    fn clone(&self) -> Self {
+        Self {
+            instrs: V::clone_vector(&self.instrs),
         }
     }
 }
 
 pub struct Expr<V: VectorFactory> {
-    instrs: V::Vector<Instr<V>>,
+    pub(crate) instrs: V::Vector<Instr<V>>,
 }
 
 impl<V: VectorFactory> Expr<V> {
     pub fn instrs(&self) -> &[Instr<V>] {
         &self.instrs
     }
+
+    pub(crate) fn instrs_mut(&mut self) -> &mut V::Vector<Instr<V>> {
+        &mut self.instrs
+    }
 }
 
 impl<V: VectorFactory> Decode<V> for Expr<V> {
@@ -670,19 +850,100 @@ impl<V: VectorFactory> Decode<V> for Memarg {
 }
 
 pub struct Elem<V: VectorFactory> {
-    pub table: Tableidx,
-    pub offset: I32ConstantExpr,
-    pub init: V::Vector<Funcidx>,
+    pub mode: ElemMode<V>,
+    pub init: V::Vector<Option<Funcidx>>,
+}
+
+impl<V: VectorFactory> Elem<V> {
+    fn decode_funcidxs(reader: &mut Reader) -> Result<V::Vector<Option<Funcidx>>, DecodeError> {
+        let len = reader.read_usize()?;
+        let mut init = V::create_vector(Some(reader.capacity_hint(len)));
+        for _ in 0..len {
+            init.push(Some(Decode::<V>::decode(reader)?));
+        }
+        Ok(init)
+    }
+
+    // Decodes the segment encodings introduced by the bulk memory proposal (flags 1..=7).
+    #[cfg(feature = "bulk_memory")]
+    fn decode_with_flags(flags: u32, reader: &mut Reader) -> Result<Self, DecodeError> {
+        if flags > 7 {
+            return Err(DecodeError::InvalidElemSegmentFlags { value: flags });
+        }
+
+        let mode = if flags & 0b001 == 0 {
+            let table = if flags & 0b010 != 0 {
+                Decode::<V>::decode(reader)?
+            } else {
+                Tableidx
+            };
+            let offset = Decode::<V>::decode(reader)?;
+            ElemMode::Active { table, offset }
+        } else if flags & 0b010 == 0 {
+            ElemMode::Passive
+        } else {
+            ElemMode::Declarative
+        };
+
+        let uses_exprs = flags & 0b100 != 0;
+        if flags & 0b011 != 0 {
+            if uses_exprs {
+                let _: Elemtype = Decode::<V>::decode(reader)?;
+            } else {
+                let elemkind = reader.read_u8()?;
+                if elemkind != 0x00 {
+                    return Err(DecodeError::InvalidElemType { value: elemkind });
+                }
+            }
+        }
+
+        let init = if uses_exprs {
+            let len = reader.read_usize()?;
+            let mut init = V::create_vector(Some(reader.capacity_hint(len)));
+            for _ in 0..len {
+                init.push(Self::decode_init_expr(reader)?);
+            }
+            init
+        } else {
+            Self::decode_funcidxs(reader)?
+        };
+        Ok(Self { mode, init })
+    }
+
+    #[cfg(feature = "bulk_memory")]
+    fn decode_init_expr(reader: &mut Reader) -> Result<Option<Funcidx>, DecodeError> {
+        let funcidx = match reader.read_u8()? {
+            0xd2 => Some(Decode::<V>::decode(reader)?),
+            0xd0 => {
+                let _: Elemtype = Decode::<V>::decode(reader)?;
+                None
+            }
+            _ => return Err(DecodeError::UnexpectedExpr),
+        };
+        if reader.read_u8()? != 0x0b {
+            return Err(DecodeError::UnexpectedExpr);
+        }
+        Ok(funcidx)
+    }
 }
 
 impl<V: VectorFactory> Decode<V> for Elem<V> {
     fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
-        let table = Decode::<V>::decode(reader)?;
+        let flags = reader.read_u32()?;
+        if flags != 0 {
+            #[cfg(feature = "bulk_memory")]
+            return Self::decode_with_flags(flags, reader);
+            #[cfg(not(feature = "bulk_memory"))]
+            return Err(DecodeError::InvalidElemSegmentFlags { value: flags });
+        }
+
         let offset = Decode::<V>::decode(reader)?;
-        let init = Decode::<V>::decode_vector(reader)?;
+        let init = Self::decode_funcidxs(reader)?;
         Ok(Self {
-            table,
-            offset,
+            mode: ElemMode::Active {
+                table: Tableidx,
+                offset,
+            },
             init,
         })
     }
@@ -691,8 +952,7 @@ impl<V: VectorFactory> Decode<V> for Elem<V> {
 impl<V: VectorFactory> Debug for Elem<V> {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Elem")
-            .field("table", &self.table)
-            .field("offset", &self.offset)
+            .field("mode", &self.mode)
             .field("init", &self.init.as_ref())
             .finish()
     }
@@ -701,33 +961,103 @@ impl<V: VectorFactory> Debug for Elem<V> {
 impl<V: VectorFactory> Clone for Elem<V> {
     fn clone(&self) -> Self {
         Self {
-            table: self.table,
-            offset: self.offset,
+            mode: self.mode.clone(),
             init: V::clone_vector(&self.init),
         }
     }
 }
 
+pub enum ElemMode<V: VectorFactory> {
+    Active {
+        table: Tableidx,
+        offset: ConstantExpr<V>,
+    },
+    Passive,
+    Declarative,
+}
+
+impl<V: VectorFactory> Debug for ElemMode<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Active { table, offset } => f
+                .debug_struct("Active")
+                .field("table", table)
+                .field("offset", offset)
+                .finish(),
+            Self::Passive => write!(f, "Passive"),
+            Self::Declarative => write!(f, "Declarative"),
+        }
+    }
+}
+
+impl<V: VectorFactory> Clone for ElemMode<V> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Active { table, offset } => Self::Active {
+                table: *table,
+                offset: offset.clone(),
+            },
+            Self::Passive => Self::Passive,
+            Self::Declarative => Self::Declarative,
+        }
+    }
+}
+
 pub(crate) struct Code<V: VectorFactory> {
     pub locals: V::Vector<Valtype>,
     pub body: Expr<V>,
-}
+    pub offset: usize, // Relative to the start of the code section.
+    pub size: usize,
+}
+
+impl<V: VectorFactory> Code<V> {
+    // Decodes the contents of a code entry whose size prefix has already been read.
+    pub fn decode_contents(
+        reader: &mut Reader,
+        offset: usize,
+        size: usize,
+    ) -> Result<Self, DecodeError> {
+        // Check the total count of the locals before expanding them
+        // as it is bounded by 2^32-1 according to the spec (and by `DecodeConfig::max_locals`).
+        let max_locals = (reader.config().max_locals as u64).min(u32::MAX as u64);
+        let entries_len = reader.read_usize()?;
+        let mut entries = V::create_vector(None);
+        let mut total_locals = 0u64;
+        for _ in 0..entries_len {
+            let val_types_len = reader.read_u32()?;
+            let val_type: Valtype = Decode::<V>::decode(reader)?;
+            total_locals += val_types_len as u64;
+            if total_locals > max_locals {
+                return Err(DecodeError::TooManyLocals);
+            }
+            entries.push((val_types_len, val_type));
+        }
 
-impl<V: VectorFactory> Decode<V> for Code<V> {
-    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
-        let code_size = reader.read_usize()?;
-        let mut reader = Reader::new(reader.read(code_size)?);
-        let mut locals = V::create_vector(None);
-        let locals_len = reader.read_usize()?;
-        for _ in 0..locals_len {
-            let val_types_len = reader.read_usize()?;
-            let val_type = Decode::<V>::decode(&mut reader)?;
+        // As with the other vectors, the preallocation is bounded by
+        // `DecodeConfig::max_preallocation` and the rest grows incrementally.
+        let capacity = (total_locals as usize).min(reader.config().max_preallocation);
+        let mut locals = V::create_vector(Some(capacity));
+        for &(val_types_len, val_type) in entries.iter() {
             for _ in 0..val_types_len {
                 locals.push(val_type);
             }
         }
-        let body = Expr::decode(&mut reader)?;
-        Ok(Self { locals, body })
+        let body = Expr::decode(reader)?;
+        Ok(Self {
+            locals,
+            body,
+            offset,
+            size,
+        })
+    }
+}
+
+impl<V: VectorFactory> Decode<V> for Code<V> {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let size = reader.read_usize()?;
+        let offset = reader.position();
+        let mut reader = reader.sub_reader(size)?;
+        Self::decode_contents(&mut reader, offset, size)
     }
 }
 
@@ -748,10 +1078,16 @@ impl Blocktype {
 
 impl<V: VectorFactory> Decode<V> for Blocktype {
     fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
-        if reader.read_u8()? == 0x40 {
-            return Ok(Self::Empty);
+        match reader.read_u8()? {
+            0x40 => return Ok(Self::Empty),
+            // A type index (non-negative s33) instead of a value type.
+            0x00..=0x3f | 0x80..=0xff => {
+                return Err(DecodeError::UnsupportedFeature {
+                    feature: Features::MULTI_VALUE,
+                })
+            }
+            _ => reader.unread_u8(),
         }
-        reader.unread_u8();
 
         let t = Decode::<V>::decode(reader)?;
         Ok(Self::Val(t))
@@ -759,25 +1095,36 @@ impl<V: VectorFactory> Decode<V> for Blocktype {
 }
 
 pub struct Data<V: VectorFactory> {
-    pub data: Memidx,
-    pub offset: I32ConstantExpr,
+    pub mode: DataMode<V>,
     pub init: V::Vector<u8>,
 }
 
 impl<V: VectorFactory> Decode<V> for Data<V> {
     fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
-        let data = Decode::<V>::decode(reader)?;
-        let offset = Decode::<V>::decode(reader)?;
+        let flags = reader.read_u32()?;
+        let mode = match flags {
+            0 => DataMode::Active {
+                mem: Memidx,
+                offset: Decode::<V>::decode(reader)?,
+            },
+            #[cfg(feature = "bulk_memory")]
+            1 => DataMode::Passive,
+            #[cfg(feature = "bulk_memory")]
+            2 => DataMode::Active {
+                mem: Decode::<V>::decode(reader)?,
+                offset: Decode::<V>::decode(reader)?,
+            },
+            _ => return Err(DecodeError::InvalidMemIdx { value: flags }),
+        };
         let init = Decode::<V>::decode_vector(reader)?;
-        Ok(Self { data, offset, init })
+        Ok(Self { mode, init })
     }
 }
 
 impl<V: VectorFactory> Debug for Data<V> {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Data")
-            .field("data", &self.data)
-            .field("offset", &self.offset)
+            .field("mode", &self.mode)
             .field("init", &self.init.as_ref())
             .finish()
     }
@@ -786,9 +1133,72 @@ impl<V: VectorFactory> Debug for Data<V> {
 impl<V: VectorFactory> Clone for Data<V> {
     fn clone(&self) -> Self {
         Self {
-            data: self.data,
-            offset: self.offset,
+            mode: self.mode.clone(),
             init: V::clone_vector(&self.init),
         }
     }
 }
+
+pub enum DataMode<V: VectorFactory> {
+    Active {
+        mem: Memidx,
+        offset: ConstantExpr<V>,
+    },
+    Passive,
+}
+
+impl<V: VectorFactory> Debug for DataMode<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Active { mem, offset } => f
+                .debug_struct("Active")
+                .field("mem", mem)
+                .field("offset", offset)
+                .finish(),
+            Self::Passive => write!(f, "Passive"),
+        }
+    }
+}
+
+impl<V: VectorFactory> Clone for DataMode<V> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Active { mem, offset } => Self::Active {
+                mem: *mem,
+                offset: offset.clone(),
+            },
+            Self::Passive => Self::Passive,
+        }
+    }
+}
+
+pub struct CustomSection<V: VectorFactory> {
+    pub name: Name<V>,
+    pub data: V::Vector<u8>,
+}
+
+impl<V: VectorFactory> Decode<V> for CustomSection<V> {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let name = Name::decode(reader)?;
+        let data = V::clone_vector(reader.read(reader.len())?);
+        Ok(Self { name, data })
+    }
+}
+
+impl<V: VectorFactory> Debug for CustomSection<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CustomSection")
+            .field("name", &self.name)
+            .field("data", &self.data.as_ref())
+            .finish()
+    }
+}
+
+impl<V: VectorFactory> Clone for CustomSection<V> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            data: V::clone_vector(&self.data),
+        }
+    }
+}
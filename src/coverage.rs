@@ -0,0 +1,132 @@
+use crate::{
+    components::Funcidx, execute::instr_count, vector::Vector, HostFunc, LinearMemory, Module,
+    ModuleInstance, StackStorage, VectorFactory,
+};
+use core::fmt::{Debug, Display, Formatter};
+
+impl<V: VectorFactory, H: HostFunc, M: LinearMemory, S: StackStorage> ModuleInstance<V, H, M, S> {
+    // Starts recording which instructions are executed (the previous records are discarded).
+    // Note that the recording slows down the execution.
+    pub fn enable_coverage(&mut self) {
+        let mut bitmaps = V::create_vector(Some(self.module.funcs().len()));
+        for func in self.module.funcs() {
+            let n = func.body.instrs().iter().map(instr_count).sum::<usize>();
+            let mut bitmap = V::create_vector(None);
+            bitmap.extend_with(n.div_ceil(64), 0);
+            bitmaps.push(bitmap);
+        }
+        self.executor.coverage = Some(bitmaps);
+    }
+
+    pub fn disable_coverage(&mut self) {
+        self.executor.coverage = None;
+    }
+
+    // Returns `None` if the coverage recording is disabled.
+    pub fn coverage(&self) -> Option<Coverage<'_, V>> {
+        let bitmaps = self.executor.coverage.as_ref()?;
+        Some(Coverage {
+            module: &self.module,
+            bitmaps,
+        })
+    }
+}
+
+// Executed instructions of the functions defined in a module.
+// Instructions are identified by their offsets as in `TraceFrame::instr_offset`.
+pub struct Coverage<'a, V: VectorFactory> {
+    module: &'a Module<V>,
+    bitmaps: &'a [V::Vector<u64>],
+}
+
+impl<V: VectorFactory> Coverage<'_, V> {
+    pub fn is_executed(&self, funcidx: Funcidx, instr_offset: usize) -> bool {
+        self.bitmap(funcidx)
+            .and_then(|bitmap| bitmap.get(instr_offset / 64))
+            .is_some_and(|word| word & (1 << (instr_offset % 64)) != 0)
+    }
+
+    pub fn executed_count(&self, funcidx: Funcidx) -> usize {
+        self.bitmap(funcidx).map_or(0, |bitmap| {
+            bitmap.iter().map(|word| word.count_ones() as usize).sum()
+        })
+    }
+
+    // Returns 0 for imported functions.
+    pub fn instr_count(&self, funcidx: Funcidx) -> usize {
+        let Some(i) = funcidx.get().checked_sub(self.module.imported_funcs_len()) else {
+            return 0;
+        };
+        self.module
+            .funcs()
+            .get(i)
+            .map_or(0, |func| func.body.instrs().iter().map(instr_count).sum())
+    }
+
+    fn bitmap(&self, funcidx: Funcidx) -> Option<&[u64]> {
+        let i = funcidx
+            .get()
+            .checked_sub(self.module.imported_funcs_len())?;
+        self.bitmaps.get(i).map(|bitmap| &bitmap[..])
+    }
+}
+
+// Prints the number of the executed instructions of each function.
+impl<V: VectorFactory> Display for Coverage<'_, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let imported = self.module.imported_funcs_len();
+        for i in 0..self.module.funcs().len() {
+            let funcidx = Funcidx::new((imported + i) as u32);
+            if let Some(name) = self.module.func_name(funcidx) {
+                write!(f, "{name}")?;
+            } else {
+                write!(f, "func{}", funcidx.get())?;
+            }
+            writeln!(
+                f,
+                ": {}/{}",
+                self.executed_count(funcidx),
+                self.instr_count(funcidx)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<V: VectorFactory> Debug for Coverage<'_, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_list()
+            .entries(self.bitmaps.iter().map(|bitmap| &bitmap[..]))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StdVectorFactory;
+
+    #[test]
+    fn coverage_test() {
+        // (module
+        //   (func (export "f") (param i32) (result i32)
+        //     (if (result i32) (local.get 0)
+        //       (then (i32.const 1))
+        //       (else (i32.const 2)))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 3, 2, 1, 0, 7, 5, 1, 1, 102,
+            0, 0, 10, 14, 1, 12, 0, 32, 0, 4, 127, 65, 1, 5, 65, 2, 11, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+        assert!(instance.coverage().is_none());
+
+        instance.enable_coverage();
+        instance.invoke("f", &[crate::Val::I32(1)]).expect("invoke");
+        let coverage = instance.coverage().expect("coverage");
+        let f = Funcidx::new(0);
+        assert!(coverage.is_executed(f, 2));
+        assert!(!coverage.is_executed(f, 3));
+        assert_eq!("func0: 3/4\n", coverage.to_string());
+    }
+}
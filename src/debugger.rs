@@ -0,0 +1,300 @@
+use crate::{
+    components::Funcidx,
+    instance::ModuleInstance,
+    memory::{LinearMemory, VectorMemory},
+    HostFunc, StackBuffers, StackStorage, StepResult, Val, VectorFactory,
+};
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+
+// A minimal line-oriented debug protocol.
+//
+// Commands:
+// - `call <name> [<arg>...]`
+// - `break <funcidx> <instr_offset>` / `delete <funcidx> <instr_offset>`
+// - `step` / `continue`
+// - `backtrace` / `stack` / `locals` / `memory <addr> <len>`
+// - `quit`
+//
+// Each response consists of the output lines of the command followed by `ok` or `error: <reason>`.
+pub struct Debugger<
+    'a,
+    V: VectorFactory,
+    H,
+    M = VectorMemory<V>,
+    S: StackStorage = StackBuffers<'static>,
+> {
+    instance: &'a mut ModuleInstance<V, H, M, S>,
+}
+
+impl<'a, V: VectorFactory, H: HostFunc, M: LinearMemory, S: StackStorage> Debugger<'a, V, H, M, S> {
+    pub fn new(instance: &'a mut ModuleInstance<V, H, M, S>) -> Self {
+        Self { instance }
+    }
+
+    pub fn instance(&self) -> &ModuleInstance<V, H, M, S> {
+        self.instance
+    }
+
+    pub fn serve<T: Read + Write>(&mut self, mut transport: T) -> std::io::Result<()> {
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if !read_line(&mut transport, &mut line)? {
+                return Ok(());
+            }
+            let line = String::from_utf8_lossy(&line);
+            if line.trim() == "quit" {
+                writeln!(transport, "ok")?;
+                transport.flush()?;
+                return Ok(());
+            }
+            match self.execute_command(&line) {
+                Ok(output) => writeln!(transport, "{output}ok")?,
+                Err(reason) => writeln!(transport, "error: {reason}")?,
+            }
+            transport.flush()?;
+        }
+    }
+
+    pub fn execute_command(&mut self, line: &str) -> Result<String, String> {
+        let mut tokens = line.split_whitespace();
+        let command = tokens.next().unwrap_or_default();
+        let mut output = String::new();
+        match command {
+            "" => {}
+            "call" => {
+                let name = tokens.next().ok_or("missing function name")?;
+                let args = self.parse_args(name, tokens)?;
+                self.instance
+                    .start_invoke(name, &args)
+                    .map_err(|e| e.to_string())?;
+            }
+            "break" => {
+                let (funcidx, instr_offset) = parse_position(tokens)?;
+                self.instance.set_breakpoint(funcidx, instr_offset);
+            }
+            "delete" => {
+                let (funcidx, instr_offset) = parse_position(tokens)?;
+                if !self.instance.remove_breakpoint(funcidx, instr_offset) {
+                    return Err("no such breakpoint".to_owned());
+                }
+            }
+            "step" | "continue" => {
+                let result = if command == "step" {
+                    self.instance.step()
+                } else {
+                    self.instance.resume()
+                };
+                match result {
+                    Ok(StepResult::Running) => writeln!(output, "running"),
+                    Ok(StepResult::Paused) => writeln!(output, "paused"),
+                    Ok(StepResult::Breakpoint(frame)) => writeln!(
+                        output,
+                        "breakpoint {} {}",
+                        frame.funcidx.get(),
+                        frame.instr_offset
+                    ),
+                    Ok(StepResult::Returned(None)) => writeln!(output, "returned"),
+                    Ok(StepResult::Returned(Some(v))) => writeln!(output, "returned {v:?}"),
+                    Err(e) => writeln!(output, "trapped {e} at {}", self.instance.backtrace()),
+                }
+                .expect("unreachable");
+            }
+            "backtrace" => {
+                let module = self.instance.module();
+                for (i, frame) in self.instance.call_stack().enumerate() {
+                    let funcidx = frame.funcidx.get();
+                    let name = module.func_name(frame.funcidx).unwrap_or_default();
+                    writeln!(output, "#{i} {funcidx} {} {name}", frame.instr_offset)
+                        .expect("unreachable");
+                }
+            }
+            "stack" => {
+                for v in self.instance.runtime().values() {
+                    writeln!(output, "{v:?}").expect("unreachable");
+                }
+            }
+            "locals" => {
+                let locals = self.instance.runtime().frame_locals();
+                for v in locals.ok_or("not running")? {
+                    writeln!(output, "{v:?}").expect("unreachable");
+                }
+            }
+            "memory" => {
+                let addr = parse_number(tokens.next())?;
+                let len = parse_number(tokens.next())?;
+                // `len` comes from the client, so it's checked before allocating the buffer.
+                if len > self.instance.mem().size().saturating_sub(addr) {
+                    return Err("out of bounds memory access".to_owned());
+                }
+                let mut bytes = vec![0; len];
+                if !self.instance.mem().read(addr, &mut bytes) {
+                    return Err("out of bounds memory access".to_owned());
+                }
+                for b in bytes {
+                    write!(output, "{b:02x}").expect("unreachable");
+                }
+                writeln!(output).expect("unreachable");
+            }
+            _ => return Err(format!("unknown command: {command}")),
+        }
+        Ok(output)
+    }
+
+    fn parse_args<'b>(
+        &self,
+        name: &str,
+        tokens: impl Iterator<Item = &'b str>,
+    ) -> Result<Vec<Val>, String> {
+        let module = self.instance.module();
+        let handle = self.instance.func_handle(name).map_err(|e| e.to_string())?;
+        let func_type = self.instance.funcs()[handle.funcidx().get()]
+            .get_type(module)
+            .ok_or("invalid function")?;
+
+        let tokens = tokens.collect::<Vec<_>>();
+        if tokens.len() != func_type.params.len() {
+            return Err("invalid function arguments".to_owned());
+        }
+        func_type
+            .params
+            .iter()
+            .zip(tokens)
+            .map(|(&ty, token)| {
+                Val::parse_as(ty, token).map_err(|e| format!("invalid argument: {token}: {e}"))
+            })
+            .collect()
+    }
+}
+
+fn parse_number(token: Option<&str>) -> Result<usize, String> {
+    let token = token.ok_or("missing argument")?;
+    token
+        .parse()
+        .map_err(|_| format!("invalid number: {token}"))
+}
+
+fn parse_position<'a>(
+    mut tokens: impl Iterator<Item = &'a str>,
+) -> Result<(Funcidx, usize), String> {
+    let funcidx = parse_number(tokens.next())?;
+    let instr_offset = parse_number(tokens.next())?;
+    let funcidx = u32::try_from(funcidx).map_err(|_| "invalid funcidx")?;
+    Ok((Funcidx::new(funcidx), instr_offset))
+}
+
+// Commands are short, so longer lines are rejected rather than buffered without bound.
+const MAX_LINE: usize = 4096;
+
+fn read_line<R: Read>(reader: &mut R, line: &mut Vec<u8>) -> std::io::Result<bool> {
+    let mut reader = reader.take(MAX_LINE as u64 + 1);
+    let mut b = [0];
+    loop {
+        if reader.read(&mut b)? == 0 {
+            if reader.limit() == 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "too long line",
+                ));
+            }
+            return Ok(!line.is_empty());
+        }
+        if b[0] == b'\n' {
+            return Ok(true);
+        }
+        line.push(b[0]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Module, StdVectorFactory};
+
+    struct Transport {
+        input: std::io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Transport {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Transport {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn serve_test() {
+        // (module
+        //   (memory 1)
+        //   (func $fib (export "fib") (param i32) (result i32)
+        //     local.get 0
+        //     i32.const 2
+        //     i32.lt_s
+        //     if (result i32)
+        //       local.get 0
+        //     else
+        //       local.get 0
+        //       i32.const 1
+        //       i32.sub
+        //       call $fib
+        //       local.get 0
+        //       i32.const 2
+        //       i32.sub
+        //       call $fib
+        //       i32.add
+        //     end)
+        //   (data (i32.const 16) "hi"))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 3, 2, 1, 0, 5, 3, 1, 0, 1, 7,
+            7, 1, 3, 102, 105, 98, 0, 0, 10, 30, 1, 28, 0, 32, 0, 65, 2, 72, 4, 127, 32, 0, 5, 32,
+            0, 65, 1, 107, 16, 0, 32, 0, 65, 2, 107, 16, 0, 106, 11, 11, 11, 8, 1, 0, 65, 16, 11,
+            2, 104, 105, 0, 13, 4, 110, 97, 109, 101, 1, 6, 1, 0, 3, 102, 105, 98,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+
+        let commands = "break 0 13\ncall fib 2\ncontinue\nbacktrace\nstack\nlocals\nmemory 16 2\n\
+                        memory 16 65521\ncontinue\nfoo\nquit\n";
+        let mut transport = Transport {
+            input: std::io::Cursor::new(commands.as_bytes().to_owned()),
+            output: Vec::new(),
+        };
+        Debugger::new(&mut instance)
+            .serve(&mut transport)
+            .expect("serve");
+        assert_eq!(
+            String::from_utf8(transport.output).expect("utf8"),
+            "ok\n\
+             ok\n\
+             breakpoint 0 13\nok\n\
+             #0 0 13 fib\nok\n\
+             I32(1)\nI32(0)\nok\n\
+             I32(2)\nok\n\
+             6869\nok\n\
+             error: out of bounds memory access\n\
+             returned I32(1)\nok\n\
+             error: unknown command: foo\n\
+             ok\n"
+        );
+
+        let mut transport = Transport {
+            input: std::io::Cursor::new(vec![b' '; MAX_LINE + 1]),
+            output: Vec::new(),
+        };
+        let error = Debugger::new(&mut instance)
+            .serve(&mut transport)
+            .expect_err("too long line");
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+    }
+}
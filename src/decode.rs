@@ -1,5 +1,5 @@
 use crate::vector::Vector;
-use crate::{reader::Reader, VectorFactory};
+use crate::{reader::Reader, Features, SectionId, VectorFactory};
 use core::fmt::{Display, Formatter};
 use core::str::Utf8Error;
 
@@ -8,7 +8,7 @@ pub trait Decode<V: VectorFactory>: Sized {
 
     fn decode_vector(reader: &mut Reader) -> Result<V::Vector<Self>, DecodeError> {
         let len = reader.read_usize()?;
-        let mut items = V::create_vector(Some(len));
+        let mut items = V::create_vector(Some(reader.capacity_hint(len)));
         for _ in 0..len {
             items.push(Self::decode(reader)?);
         }
@@ -20,6 +20,37 @@ impl<V: VectorFactory> Decode<V> for u8 {
     fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
         reader.read_u8()
     }
+
+    fn decode_vector(reader: &mut Reader) -> Result<V::Vector<Self>, DecodeError> {
+        let len = reader.read_usize()?;
+        let bytes = reader.read(len)?;
+        Ok(V::clone_vector(bytes))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeConfig {
+    // Upper bound of the number of items preallocated for a vector based on its declared length.
+    pub max_preallocation: usize,
+
+    // Upper bound of the number of locals declared by a function (excluding the parameters),
+    // which are expanded to one value each when decoded.
+    pub max_locals: usize,
+
+    // Decoder of the instructions with an embedder-defined prefix byte.
+    #[cfg(feature = "extension")]
+    pub extension: Option<crate::Extension>,
+}
+
+impl Default for DecodeConfig {
+    fn default() -> Self {
+        Self {
+            max_preallocation: 4096,
+            max_locals: 50000,
+            #[cfg(feature = "extension")]
+            extension: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -31,6 +62,7 @@ pub enum DecodeError {
     InvalidVersion {
         value: [u8; 4],
     },
+    ComponentModelNotSupported,
     InvalidSectionId {
         value: u8,
     },
@@ -73,13 +105,19 @@ pub enum DecodeError {
     InvalidOpcode {
         value: u8,
     },
+    UnsupportedFeature {
+        feature: Features,
+    },
     UnexpectedExpr,
     InvalidSectionOrder {
-        last_section_id: u8,
-        current_section_id: u8,
+        last_section_id: SectionId,
+        current_section_id: SectionId,
+    },
+    DuplicateSection {
+        section_id: SectionId,
     },
     InvalidSectionByteSize {
-        section_id: u8,
+        section_id: SectionId,
         expected_byte_size: usize,
         actual_byte_size: usize,
     },
@@ -89,6 +127,15 @@ pub enum DecodeError {
         code_section_size: usize,
     },
     MalformedInteger,
+    TooManyLocals,
+    InvalidElemSegmentFlags {
+        value: u32,
+    },
+    MismatchDataCountAndDataSectionSize {
+        data_count: usize,
+        data_section_size: usize,
+    },
+    RejectedByVerifier,
 }
 
 impl Display for DecodeError {
@@ -97,6 +144,9 @@ impl Display for DecodeError {
             Self::UnexpectedEndOfBytes => write!(f, "Unexpected end-of-bytes"),
             Self::InvalidMagic { value } => write!(f, "Invalid magic number {value:?}"),
             Self::InvalidVersion { value } => write!(f, "Invalid version number {value:?}"),
+            Self::ComponentModelNotSupported => {
+                write!(f, "Component model binaries are not supported")
+            }
             Self::InvalidSectionId { value } => write!(f, "Invalid section ID {value:?}"),
             Self::InvalidImportDescTag { value } => {
                 write!(f, "Invalid import description tag {value:?})")
@@ -115,25 +165,38 @@ impl Display for DecodeError {
             Self::InvalidMemIdx { value } => write!(f, "Invalid memory index {value:?}"),
             Self::InvalidTableIdx { value } => write!(f, "Invalid table index {value:?}"),
             Self::InvalidOpcode { value } => write!(f, "Invalid opcode {value:?}"),
+            Self::UnsupportedFeature { feature } => {
+                write!(f, "Module requires {feature}, which is not supported")
+            }
             Self::UnexpectedExpr => write!(f, "Unexpected expression"),
             Self::InvalidSectionOrder {
                 last_section_id,
                 current_section_id,
             } => write!(
                 f,
-                "Invalid section order (last={last_section_id:?}), current={current_section_id:?})"
+                "Invalid section order ({current_section_id} section after {last_section_id} section)"
             ),
+            Self::DuplicateSection { section_id } => {
+                write!(f, "Duplicate {section_id} section")
+            }
             Self::InvalidSectionByteSize {
                 section_id,
                 expected_byte_size,
                 actual_byte_size
-            } => write!(f, "Invalid section {section_id:?} byte size (expected={expected_byte_size:?} bytes, actual={actual_byte_size:?} bytes)"),
+            } => write!(f, "Invalid {section_id} section byte size (expected={expected_byte_size:?} bytes, actual={actual_byte_size:?} bytes)"),
             Self::InvalidUtf8(e) => write!(f,"Invalid UTF-8 bytes ({e})"),
             Self::MismatchFunctionAndCodeSectionSize {
                 function_section_size,
                 code_section_size
             } => write!(f, "Mismatch function section size ({function_section_size:?}) and code section size ({code_section_size:?})"),
             Self::MalformedInteger => write!(f,"Malformed LEB128 integer"),
+            Self::TooManyLocals => write!(f,"Too many locals"),
+            Self::InvalidElemSegmentFlags { value } => write!(f, "Invalid element segment flags {value:?}"),
+            Self::MismatchDataCountAndDataSectionSize {
+                data_count,
+                data_section_size
+            } => write!(f, "Mismatch data count ({data_count:?}) and data section size ({data_section_size:?})"),
+            Self::RejectedByVerifier => write!(f, "Module rejected by verifier"),
         }
     }
 }
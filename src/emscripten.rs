@@ -0,0 +1,229 @@
+use crate::{Env, HostFunc, Resolve, Val};
+use std::{
+    cell::RefCell,
+    io::{Stdout, Write},
+    rc::Rc,
+};
+
+// Resolves the handful of `env` functions commonly imported by modules compiled from C
+// without WASI (e.g., by Emscripten in standalone mode), so that they run without any host code.
+//
+// - `abort()` and `__assert_fail(...)` trap with `TrapKind::Aborted`
+// - `emscripten_notify_memory_growth(...)` does nothing
+// - `emscripten_resize_heap(...)` always fails (the memory is grown by `memory.grow` instead)
+// - `emscripten_memcpy_big(dest, src, n)` copies within the memory
+// - `putchar(c)` and `puts(s)` write to the output (stdout by default)
+#[derive(Debug)]
+pub struct EmscriptenLikeResolver<W = Stdout> {
+    output: Rc<RefCell<W>>,
+}
+
+impl EmscriptenLikeResolver {
+    pub fn new() -> Self {
+        Self::with_output(std::io::stdout())
+    }
+}
+
+impl Default for EmscriptenLikeResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> EmscriptenLikeResolver<W> {
+    pub fn with_output(output: W) -> Self {
+        Self {
+            output: Rc::new(RefCell::new(output)),
+        }
+    }
+
+    // Shared with the functions resolved by this resolver.
+    pub fn output(&self) -> Rc<RefCell<W>> {
+        self.output.clone()
+    }
+}
+
+impl<W: Write> Resolve for EmscriptenLikeResolver<W> {
+    type HostFunc = EmscriptenFunc<W>;
+
+    fn resolve_func(&self, module: &str, name: &str) -> Option<Self::HostFunc> {
+        if module != "env" {
+            return None;
+        }
+        let kind = match name {
+            "abort" => EmscriptenFuncKind::Abort,
+            "__assert_fail" => EmscriptenFuncKind::AssertFail,
+            "emscripten_notify_memory_growth" => EmscriptenFuncKind::NotifyMemoryGrowth,
+            "emscripten_resize_heap" => EmscriptenFuncKind::ResizeHeap,
+            "emscripten_memcpy_big" => EmscriptenFuncKind::MemcpyBig,
+            "putchar" => EmscriptenFuncKind::Putchar,
+            "puts" => EmscriptenFuncKind::Puts,
+            _ => return None,
+        };
+        Some(EmscriptenFunc {
+            kind,
+            output: self.output.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmscriptenFuncKind {
+    Abort,
+    AssertFail,
+    NotifyMemoryGrowth,
+    ResizeHeap,
+    MemcpyBig,
+    Putchar,
+    Puts,
+}
+
+#[derive(Debug)]
+pub struct EmscriptenFunc<W> {
+    kind: EmscriptenFuncKind,
+    output: Rc<RefCell<W>>,
+}
+
+impl<W> EmscriptenFunc<W> {
+    pub fn kind(&self) -> EmscriptenFuncKind {
+        self.kind
+    }
+}
+
+impl<W: Write> HostFunc for EmscriptenFunc<W> {
+    fn invoke(&mut self, args: &[Val], env: &mut Env) -> Option<Val> {
+        // Arguments of unexpected types make the call trap.
+        let Some(args) = args.iter().map(|v| v.as_i32()).collect::<Option<Vec<_>>>() else {
+            env.abort();
+            return None;
+        };
+        let mut output = self.output.borrow_mut();
+        match (self.kind, &args[..]) {
+            (EmscriptenFuncKind::Abort, _) => env.abort(),
+            (EmscriptenFuncKind::AssertFail, &[cond, file, line, func, ..]) => {
+                let cond = read_c_str(env, cond);
+                let file = read_c_str(env, file);
+                let func = read_c_str(env, func);
+                let _ = writeln!(
+                    output,
+                    "Assertion failed: {cond} ({file}: {func}: {})",
+                    line as u32
+                );
+                env.abort();
+            }
+            (EmscriptenFuncKind::NotifyMemoryGrowth, _) => {}
+            (EmscriptenFuncKind::ResizeHeap, _) => return Some(Val::I32(0)),
+            (EmscriptenFuncKind::MemcpyBig, &[dest, src, n, ..]) => {
+                // The ranges are checked before allocating a buffer of the guest-provided size.
+                let n = n as u32 as usize;
+                let size = env.mem.size();
+                let fits = |addr: i32| {
+                    (addr as u32 as usize)
+                        .checked_add(n)
+                        .is_some_and(|end| end <= size)
+                };
+                if !fits(src) || !fits(dest) {
+                    env.abort();
+                    return None;
+                }
+                let mut buf = vec![0; n];
+                if !env.mem.read(src as u32 as usize, &mut buf)
+                    || !env.mem.write(dest as u32 as usize, &buf)
+                {
+                    env.abort();
+                }
+                return Some(Val::I32(dest));
+            }
+            (EmscriptenFuncKind::Putchar, &[c, ..]) => {
+                let _ = output.write_all(&[c as u8]);
+                return Some(Val::I32(c));
+            }
+            (EmscriptenFuncKind::Puts, &[s, ..]) => {
+                let s = read_c_str(env, s);
+                let _ = writeln!(output, "{s}");
+                return Some(Val::I32(0));
+            }
+            _ => env.abort(),
+        }
+        None
+    }
+}
+
+fn read_c_str(env: &mut Env, ptr: i32) -> String {
+    let mut bytes = Vec::new();
+    let mut b = [0];
+    let mut addr = ptr as u32 as usize;
+    while env.mem.read(addr, &mut b) && b[0] != 0 {
+        bytes.push(b[0]);
+        addr += 1;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExecuteError, Module, StdVectorFactory, TrapKind};
+
+    #[test]
+    fn puts_and_abort() {
+        // (module
+        //   (import "env" "puts" (func $puts (param i32) (result i32)))
+        //   (import "env" "abort" (func $abort))
+        //   (memory 1)
+        //   (func (export "main")
+        //     (drop (call $puts (i32.const 0)))
+        //     (call $abort))
+        //   (data (i32.const 0) "hi\00"))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 12, 3, 96, 1, 127, 1, 127, 96, 0, 0, 96, 0, 0, 2, 24,
+            2, 3, 101, 110, 118, 4, 112, 117, 116, 115, 0, 0, 3, 101, 110, 118, 5, 97, 98, 111,
+            114, 116, 0, 1, 3, 2, 1, 2, 5, 3, 1, 0, 1, 7, 8, 1, 4, 109, 97, 105, 110, 0, 2, 10, 11,
+            1, 9, 0, 65, 0, 16, 0, 26, 16, 1, 11, 11, 9, 1, 0, 65, 0, 11, 3, 104, 105, 0,
+        ];
+        let resolver = EmscriptenLikeResolver::with_output(Vec::new());
+        let output = resolver.output();
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(resolver).expect("instantiate");
+
+        let result = instance.invoke("main", &[]);
+        assert!(matches!(result, Err(ExecuteError::Trap(TrapKind::Aborted))));
+        assert_eq!(b"hi\n", &output.borrow()[..]);
+    }
+
+    #[test]
+    fn memcpy_big() {
+        // (module
+        //   (import "env" "emscripten_memcpy_big" (func $memcpy (param i32 i32 i32) (result i32)))
+        //   (memory 1)
+        //   (func (export "copy") (param i32 i32 i32) (result i32)
+        //     (call $memcpy (local.get 0) (local.get 1) (local.get 2)))
+        //   (data (i32.const 0) "hi"))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 8, 1, 96, 3, 127, 127, 127, 1, 127, 2, 29, 1, 3, 101,
+            110, 118, 21, 101, 109, 115, 99, 114, 105, 112, 116, 101, 110, 95, 109, 101, 109, 99,
+            112, 121, 95, 98, 105, 103, 0, 0, 3, 2, 1, 0, 5, 3, 1, 0, 1, 7, 8, 1, 4, 99, 111, 112,
+            121, 0, 1, 10, 12, 1, 10, 0, 32, 0, 32, 1, 32, 2, 16, 0, 11, 11, 8, 1, 0, 65, 0, 11, 2,
+            104, 105,
+        ];
+        let resolver = EmscriptenLikeResolver::with_output(Vec::new());
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(resolver).expect("instantiate");
+
+        let args = [Val::I32(16), Val::I32(0), Val::I32(2)];
+        assert!(matches!(
+            instance.invoke("copy", &args),
+            Ok(Some(Val::I32(16)))
+        ));
+        assert_eq!(b"hi", &instance.mem().as_slice()[16..18]);
+
+        // Out-of-bounds ranges (even huge ones) trap before a buffer is allocated.
+        for args in [
+            [Val::I32(16), Val::I32(0), Val::I32(-1)],
+            [Val::I32(65535), Val::I32(0), Val::I32(2)],
+        ] {
+            let result = instance.invoke("copy", &args);
+            assert!(matches!(result, Err(ExecuteError::Trap(TrapKind::Aborted))));
+        }
+    }
+}
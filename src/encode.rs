@@ -0,0 +1,155 @@
+use crate::{
+    components::{CustomSection, Name},
+    vector::Vector,
+    SectionId, VectorFactory,
+};
+use core::fmt::{Debug, Formatter};
+
+// Builds a custom section whose payload can be read back with the corresponding `Reader` methods.
+pub struct CustomSectionBuilder<V: VectorFactory> {
+    name: Name<V>,
+    data: V::Vector<u8>,
+}
+
+impl<V: VectorFactory> CustomSectionBuilder<V> {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: Name::new(name),
+            data: V::create_vector(None),
+        }
+    }
+
+    pub fn write_u8(&mut self, v: u8) {
+        self.data.push(v);
+    }
+
+    // Writes `bytes` as is (without a length prefix).
+    pub fn write_raw(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_u32(bytes.len() as u32);
+        self.write_raw(bytes);
+    }
+
+    pub fn write_name(&mut self, name: &str) {
+        self.write_bytes(name.as_bytes());
+    }
+
+    pub fn write_u32(&mut self, v: u32) {
+        write_integer_u(&mut self.data, v as u64);
+    }
+
+    pub fn write_u64(&mut self, v: u64) {
+        write_integer_u(&mut self.data, v);
+    }
+
+    pub fn write_i32(&mut self, v: i32) {
+        write_integer_s(&mut self.data, v as i64);
+    }
+
+    pub fn write_i64(&mut self, v: i64) {
+        write_integer_s(&mut self.data, v);
+    }
+
+    pub fn write_f32(&mut self, v: f32) {
+        self.write_raw(&v.to_le_bytes());
+    }
+
+    pub fn write_f64(&mut self, v: f64) {
+        self.write_raw(&v.to_le_bytes());
+    }
+
+    pub fn finish(self) -> CustomSection<V> {
+        CustomSection {
+            name: self.name,
+            data: self.data,
+        }
+    }
+}
+
+impl<V: VectorFactory> Debug for CustomSectionBuilder<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CustomSectionBuilder")
+            .field("name", &self.name)
+            .field("data", &self.data.as_ref())
+            .finish()
+    }
+}
+
+impl<V: VectorFactory> CustomSection<V> {
+    // Appends the binary encoding of this section (including the section header) to `buf`.
+    //
+    // As custom sections may appear anywhere, appending to a complete module binary is valid.
+    pub fn encode<W: Vector<u8>>(&self, buf: &mut W) {
+        let name_len = self.name.len() as u64;
+        let size = integer_u_len(name_len) + self.name.len() + self.data.len();
+        buf.push(SectionId::Custom.as_u8());
+        write_integer_u(buf, size as u64);
+        write_integer_u(buf, name_len);
+        buf.extend_from_slice(self.name.as_str().as_bytes());
+        buf.extend_from_slice(&self.data);
+    }
+}
+
+fn integer_u_len(v: u64) -> usize {
+    (64 - v.leading_zeros() as usize).div_ceil(7).max(1)
+}
+
+fn write_integer_u<W: Vector<u8>>(buf: &mut W, mut v: u64) {
+    loop {
+        let b = (v & 0b0111_1111) as u8;
+        v >>= 7;
+        if v == 0 {
+            buf.push(b);
+            return;
+        }
+        buf.push(b | 0b1000_0000);
+    }
+}
+
+fn write_integer_s<W: Vector<u8>>(buf: &mut W, mut v: i64) {
+    loop {
+        let b = (v & 0b0111_1111) as u8;
+        v >>= 7;
+        if (v == 0 && b & 0b0100_0000 == 0) || (v == -1 && b & 0b0100_0000 != 0) {
+            buf.push(b);
+            return;
+        }
+        buf.push(b | 0b1000_0000);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Module, Reader, StdVectorFactory};
+
+    #[test]
+    fn append_custom_section() {
+        // (module)
+        let mut wasm = vec![0, 97, 115, 109, 1, 0, 0, 0];
+
+        let mut builder = CustomSectionBuilder::<StdVectorFactory>::new("build_id");
+        builder.write_bytes(&[0xde, 0xad, 0xbe, 0xef]);
+        builder.write_name("release");
+        builder.write_i64(-1000);
+        builder.write_raw(&[0; 200]);
+        let section = builder.finish();
+        let mut buf = StdVectorFactory::create_vector(None);
+        section.encode(&mut buf);
+        wasm.extend_from_slice(&buf);
+
+        let module = Module::<StdVectorFactory>::decode(&wasm).expect("decode");
+        let section = &module.custom_sections()[0];
+        assert_eq!("build_id", section.name.as_str());
+
+        let mut reader = Reader::new(&section.data);
+        assert_eq!(Ok(&[0xde, 0xad, 0xbe, 0xef][..]), reader.read_bytes());
+        assert_eq!(Ok("release"), reader.read_name());
+        assert_eq!(Ok(-1000), reader.read_i64());
+        assert_eq!(Ok(&[0; 200][..]), reader.read(200));
+        assert!(reader.is_empty());
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{DecodeError, ExecuteError, TrapKind, ValidateError};
+use core::fmt::{Display, Formatter};
+
+// Any failure of loading, linking or running a module, for embedders that don't need to tell
+// the stages apart in their signatures (`?` converts the error of each stage).
+// The detailed error is kept, and `kind()` tells the stage.
+#[derive(Debug, Clone)]
+pub enum Error {
+    Decode(DecodeError),
+    Validate(ValidateError),
+
+    // Import resolution or initialization of an instance failed
+    // (see `ErrorKind::Link` for the classified `ExecuteError` variants).
+    Link(ExecuteError),
+
+    Trap(TrapKind),
+
+    // Misuse of the API such as invoking an unexported function or passing invalid arguments.
+    Execute(ExecuteError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    Decode,
+    Validate,
+
+    // `ExecuteError::{UnresolvedImport, ExternKindMismatch, ExternCountMismatch,
+    // InvalidImported*, InvalidData, InvalidElem, InvalidGlobal}`
+    Link,
+
+    Trap,
+    Execute,
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Decode(_) => ErrorKind::Decode,
+            Self::Validate(_) => ErrorKind::Validate,
+            Self::Link(_) => ErrorKind::Link,
+            Self::Trap(_) => ErrorKind::Trap,
+            Self::Execute(_) => ErrorKind::Execute,
+        }
+    }
+
+    pub fn as_decode(&self) -> Option<&DecodeError> {
+        match self {
+            Self::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn as_validate(&self) -> Option<ValidateError> {
+        match *self {
+            Self::Validate(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn trap_kind(&self) -> Option<TrapKind> {
+        match *self {
+            Self::Trap(kind) => Some(kind),
+            _ => None,
+        }
+    }
+
+    // Returns the original error for the errors converted from `ExecuteError`.
+    pub fn as_execute(&self) -> Option<ExecuteError> {
+        match *self {
+            Self::Validate(e) => Some(ExecuteError::InvalidModule(e)),
+            Self::Link(e) | Self::Execute(e) => Some(e),
+            Self::Trap(kind) => Some(ExecuteError::Trap(kind)),
+            Self::Decode(_) => None,
+        }
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(e: DecodeError) -> Self {
+        Self::Decode(e)
+    }
+}
+
+impl From<ValidateError> for Error {
+    fn from(e: ValidateError) -> Self {
+        Self::Validate(e)
+    }
+}
+
+impl From<TrapKind> for Error {
+    fn from(kind: TrapKind) -> Self {
+        Self::Trap(kind)
+    }
+}
+
+impl From<ExecuteError> for Error {
+    fn from(e: ExecuteError) -> Self {
+        match e {
+            ExecuteError::InvalidModule(e) => Self::Validate(e),
+            ExecuteError::Trap(kind) => Self::Trap(kind),
+            ExecuteError::UnresolvedImport { .. }
+            | ExecuteError::ExternKindMismatch { .. }
+            | ExecuteError::ExternCountMismatch { .. }
+            | ExecuteError::InvalidImportedMem
+            | ExecuteError::InvalidImportedTable
+            | ExecuteError::InvalidImportedGlobal { .. }
+            | ExecuteError::InvalidData { .. }
+            | ExecuteError::InvalidElem { .. }
+            | ExecuteError::InvalidGlobal { .. } => Self::Link(e),
+            _ => Self::Execute(e),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Decode(e) => write!(f, "{}", e),
+            Self::Validate(e) => write!(f, "{}", e),
+            Self::Link(e) | Self::Execute(e) => write!(f, "{}", e),
+            Self::Trap(kind) => write!(f, "Trapped: {}", kind),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Decode(e) => Some(e),
+            Self::Validate(e) => Some(e),
+            Self::Link(e) | Self::Execute(e) => Some(e),
+            Self::Trap(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Module, StdVectorFactory};
+    use std::error::Error as _;
+
+    fn run(input: &[u8]) -> Result<(), Error> {
+        let module = Module::<StdVectorFactory>::decode(input)?;
+        module.validate()?;
+        let mut instance = module.instantiate(())?;
+        instance.invoke("f", &[])?;
+        Ok(())
+    }
+
+    #[test]
+    fn error_kinds() {
+        let e = run(&[0, 97, 115, 109, 2, 0, 0, 0]).expect_err("decode");
+        assert_eq!(ErrorKind::Decode, e.kind());
+        assert!(e.as_decode().is_some());
+        assert!(e.source().is_some());
+
+        // (module (import "env" "f" (func)))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 2, 9, 1, 3, 101, 110, 118, 1, 102, 0, 0,
+        ];
+        let e = run(&input).expect_err("link");
+        assert_eq!(ErrorKind::Link, e.kind());
+        assert!(matches!(
+            e.as_execute(),
+            Some(ExecuteError::UnresolvedImport { index: 0 })
+        ));
+
+        // (module (func (export "f") unreachable))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 2, 1, 0, 7, 5, 1, 1, 102, 0, 0, 10,
+            5, 1, 3, 0, 0, 11,
+        ];
+        let e = run(&input).expect_err("trap");
+        assert_eq!(ErrorKind::Trap, e.kind());
+        assert_eq!(Some(TrapKind::Unreachable), e.trap_kind());
+        assert_eq!("Trapped: unreachable", e.to_string());
+
+        let e = Error::from(ExecuteError::NotExportedFunction);
+        assert_eq!(ErrorKind::Execute, e.kind());
+        let e = Error::from(ExecuteError::InvalidModule(ValidateError::UnknownMemory));
+        assert_eq!(Some(ValidateError::UnknownMemory), e.as_validate());
+    }
+}
@@ -1,226 +1,732 @@
 use crate::{
-    components::{Blocktype, Funcidx, Functype, Importdesc, Localidx},
-    instance::FuncInst,
+    components::{Funcidx, Labelidx, Localidx, Memarg, Resulttype, Typeidx},
+    instance::{DebugOptions, FuncInst, FuncRef},
     instructions::Instr,
-    Env, GlobalVal, HostFunc, Module, Val, Vector, VectorFactory, PAGE_SIZE,
+    memory::{pages_to_bytes, LinearMemory, VectorMemory},
+    stack_buffers::{Stack, StackBuffers, StackStorage},
+    validate::FrameSize,
+    Env, EnvMem, GlobalVal, HostFunc, Module, Val, ValidateError, Vector, VectorFactory, PAGE_SIZE,
 };
-use core::fmt::{Debug, Display, Formatter};
+use core::fmt::{Debug, DebugStruct, Display, Formatter};
+
+pub(crate) const MAX_PAGES: u32 = 65536;
 
 #[derive(Debug, Clone, Copy)]
 pub enum ExecuteError {
+    InvalidModule(ValidateError),
     NotExportedFunction,
     UnresolvedImport { index: usize },
+    ExternKindMismatch { index: usize },
+    ExternCountMismatch { expected: usize, actual: usize },
     InvalidImportedMem,
     InvalidImportedTable,
+    InvalidImportedGlobal { index: usize },
     InvalidData { index: usize },
     InvalidElem { index: usize },
     InvalidGlobal { index: usize },
+    InvalidConstantExpr,
     InvalidMemidx,
     InvalidFuncidx,
+    InvalidElemidx,
+    InvalidDataidx,
     InvalidTypeidx,
+    InvalidTableIndex { index: usize },
+    StaleFuncHandle,
     InvalidFuncArgs,
-    Trapped, // TODO: Trap
+    InvalidReturnValue,
+    NoPendingCall,
+    CallPending,
+    MemoryAllocationFailed,
+    MemoryResetFailed,
+    UnsupportedInstruction,
+    UnregisteredExtension,
+    Trap(TrapKind),
 }
 
 impl Display for ExecuteError {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         match self {
+            Self::InvalidModule(e) => write!(f, "Invalid module: {}", e),
             Self::NotExportedFunction => write!(f, "Not exported function"),
             Self::UnresolvedImport { index } => write!(f, "Unresolved import: {}", index),
+            Self::ExternKindMismatch { index } => {
+                write!(f, "Extern kind mismatch for import: {}", index)
+            }
+            Self::ExternCountMismatch { expected, actual } => write!(
+                f,
+                "Extern count mismatch: expected {}, actual {}",
+                expected, actual
+            ),
             Self::InvalidImportedMem => write!(f, "Invalid imported memory"),
             Self::InvalidImportedTable => write!(f, "Invalid imported table"),
+            Self::InvalidImportedGlobal { index } => {
+                write!(f, "Invalid imported global: {}", index)
+            }
             Self::InvalidData { index } => write!(f, "Invalid data: {}", index),
             Self::InvalidElem { index } => write!(f, "Invalid elem: {}", index),
             Self::InvalidGlobal { index } => write!(f, "Invalid global: {}", index),
+            Self::InvalidConstantExpr => write!(f, "Invalid constant expression"),
             Self::InvalidMemidx => write!(f, "Invalid memidx"),
             Self::InvalidFuncidx => write!(f, "Invalid funcidx"),
+            Self::InvalidElemidx => write!(f, "Invalid elemidx"),
+            Self::InvalidDataidx => write!(f, "Invalid dataidx"),
             Self::InvalidTypeidx => write!(f, "Invalid typeidx"),
+            Self::InvalidTableIndex { index } => write!(f, "Invalid table index: {}", index),
+            Self::StaleFuncHandle => write!(f, "Stale function handle"),
             Self::InvalidFuncArgs => write!(f, "Invalid function arguments"),
-            Self::Trapped => write!(f, "Trapped"),
+            Self::InvalidReturnValue => write!(f, "Invalid return value"),
+            Self::NoPendingCall => write!(f, "No pending call"),
+            Self::CallPending => write!(f, "Another call is pending"),
+            Self::MemoryAllocationFailed => write!(f, "Memory allocation failed"),
+            Self::MemoryResetFailed => write!(f, "Memory reset failed"),
+            Self::UnsupportedInstruction => write!(f, "Unsupported instruction"),
+            Self::UnregisteredExtension => write!(f, "Unregistered extension instruction"),
+            Self::Trap(kind) => write!(f, "Trapped: {}", kind),
         }
     }
 }
 
 #[cfg(feature = "std")]
-impl std::error::Error for ExecuteError {}
-
-pub struct Executor<V: VectorFactory> {
-    pub mem: V::Vector<u8>,
-    pub table: V::Vector<Option<Funcidx>>,
-    pub globals: V::Vector<GlobalVal>,
-    pub locals: V::Vector<Val>,
-    pub values: V::Vector<Val>,
-    pub current_frame: Frame,
-    pub current_block: Block,
+impl std::error::Error for ExecuteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidModule(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
-impl<V: VectorFactory> Executor<V> {
-    pub fn new(
-        mem: V::Vector<u8>,
-        table: V::Vector<Option<Funcidx>>,
-        globals: V::Vector<GlobalVal>,
-    ) -> Self {
+#[derive(Debug, Clone, Copy)]
+pub struct ExecutorConfig {
+    pub fp_mode: FpMode,
+
+    // Upper bound of the number of values held by the operand stack and the locals of the
+    // active frames. Exceeding it traps with `TrapKind::StackExhausted`.
+    pub max_value_stack: usize,
+
+    // Upper bounds of the number of active frames of module functions and that of the labels
+    // of the blocks entered by them. Exceeding either traps with `TrapKind::StackExhausted`.
+    pub max_call_depth: usize,
+    pub max_labels: usize,
+
+    // Executor of the instructions decoded by `DecodeConfig::extension`.
+    #[cfg(feature = "extension")]
+    pub extension: Option<crate::Extension>,
+
+    // Called when an invocation traps (after the backtrace is recorded and before the error is
+    // returned), e.g. to record failures without wrapping every call site.
+    pub on_trap: Option<fn(&TrapInfo)>,
+
+    // Polled before each instruction of a resumable invocation (`step()`, `resume()` or `run()`
+    // of `ModuleInstance`), which returns `StepResult::Paused` if it returns `true`.
+    // Typically it takes a flag set by an interrupt handler, e.g. `|| PAUSE.swap(false, Relaxed)`
+    // with `static PAUSE: AtomicBool` (or a load followed by a store on targets without
+    // atomic read-modify-write operations), so that the request is consumed.
+    pub pause_requested: Option<fn() -> bool>,
+}
+
+impl Default for ExecutorConfig {
+    fn default() -> Self {
         Self {
-            mem,
-            table,
-            globals,
-            locals: V::create_vector(None),
-            values: V::create_vector(None),
-            current_frame: Frame::default(),
-            current_block: Block::default(),
+            fp_mode: FpMode::default(),
+            max_value_stack: 1024 * 1024,
+            max_call_depth: 64 * 1024,
+            max_labels: 256 * 1024,
+            #[cfg(feature = "extension")]
+            extension: None,
+            on_trap: None,
+            pause_requested: None,
         }
     }
+}
 
-    pub fn enter_frame(&mut self, ty: &Functype<V>) -> Frame {
-        let locals_start = self.locals.len();
-        for _ in 0..ty.params.len() {
-            let v = self.pop_value();
-            self.locals.push(v);
+impl PartialEq for ExecutorConfig {
+    fn eq(&self, other: &Self) -> bool {
+        #[cfg(feature = "extension")]
+        if self.extension != other.extension {
+            return false;
         }
-        self.locals[locals_start..].reverse();
-        let values_start = self.values.len();
+        self.fp_mode == other.fp_mode
+            && self.max_value_stack == other.max_value_stack
+            && self.max_call_depth == other.max_call_depth
+            && self.max_labels == other.max_labels
+            && match (self.on_trap, other.on_trap) {
+                (Some(a), Some(b)) => core::ptr::fn_addr_eq(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+            && match (self.pause_requested, other.pause_requested) {
+                (Some(a), Some(b)) => core::ptr::fn_addr_eq(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+    }
+}
 
-        let prev = self.current_frame;
-        self.current_frame = Frame {
-            arity: ty.result.len(),
-            locals_start,
-            values_start,
-        };
-        prev
+impl Eq for ExecutorConfig {}
+
+// Counters accumulated over the calls made by an instance (including the start function).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ExecStats {
+    pub instrs_executed: u64,
+
+    // Calls of both module and host functions.
+    pub calls: u64,
+
+    // Peak of the number of values held by the operand stack and the locals
+    // (as limited by `ExecutorConfig::max_value_stack`).
+    pub max_value_stack: usize,
+
+    // As limited by `ExecutorConfig::max_call_depth`.
+    pub max_call_depth: usize,
+
+    // Bytes added by `memory.grow`.
+    pub mem_grown: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FpMode {
+    #[default]
+    Strict,
+
+    // Flushes subnormal results of float operations to (signed) zero, which is much cheaper on
+    // targets without an FPU but no longer conforms to the spec.
+    Fast,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrapKind {
+    Unreachable,
+    MemoryOutOfBounds,
+    TableIndexOutOfBounds { index: u32 },
+    UninitializedTableElement { index: u32 },
+    SignatureMismatch { expected: Typeidx, actual: Typeidx },
+    TableOutOfBounds,
+    Aborted,
+    MemoryAccessDenied,
+    StackExhausted,
+    ImmutableGlobal { index: u32 },
+}
+
+impl Display for TrapKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Unreachable => write!(f, "unreachable"),
+            Self::MemoryOutOfBounds => write!(f, "out of bounds memory access"),
+            Self::TableIndexOutOfBounds { index } => {
+                write!(f, "undefined element (table index {})", index)
+            }
+            Self::UninitializedTableElement { index } => {
+                write!(f, "uninitialized element (table index {})", index)
+            }
+            Self::SignatureMismatch { expected, actual } => write!(
+                f,
+                "indirect call type mismatch (expected type {}, actual type {})",
+                expected.get(),
+                actual.get()
+            ),
+            Self::TableOutOfBounds => write!(f, "out of bounds table access"),
+            Self::Aborted => write!(f, "aborted by host"),
+            Self::MemoryAccessDenied => write!(f, "memory access denied by page protection"),
+            Self::StackExhausted => write!(f, "call stack exhausted"),
+            Self::ImmutableGlobal { index } => {
+                write!(f, "global is immutable (global index {index})")
+            }
+        }
     }
+}
 
-    // TODO: delete unused parameter
-    pub fn exit_frame(&mut self, _ty: &Functype<V>, prev: Frame) {
-        let frame = self.current_frame;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceFrame {
+    pub funcidx: Funcidx,
 
-        assert!(frame.locals_start <= self.locals.len());
-        self.locals.truncate(frame.locals_start);
+    // Pre-order index of the instruction within the function body
+    // (nested instructions are counted, `else` and `end` are not).
+    pub instr_offset: usize,
+}
 
-        self.values
-            .remove_range(frame.values_start..self.values.len() - frame.arity);
+// Context of a trap passed to `ExecutorConfig::on_trap`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrapInfo<'a> {
+    pub kind: TrapKind,
+
+    // Same as `ModuleInstance::backtrace().frames()`.
+    pub backtrace: &'a [TraceFrame],
+
+    // Instructions executed by the trapped invocation (i.e., the steps of `step()` and
+    // `resume()` counted from `start_invoke()`).
+    pub fuel_consumed: u64,
+
+    // Same as `ExecStats::instrs_executed`.
+    pub instrs_executed: u64,
+}
+
+pub struct Backtrace<'a, V: VectorFactory> {
+    pub(crate) module: &'a Module<V>,
+    pub(crate) frames: &'a [TraceFrame],
+}
 
-        self.current_frame = prev;
+impl<V: VectorFactory> Backtrace<'_, V> {
+    pub fn frames(&self) -> &[TraceFrame] {
+        self.frames
     }
+}
 
-    pub fn enter_block(&mut self, ty: Blocktype) -> Block {
-        assert!(matches!(ty, Blocktype::Empty)); // TODO
+impl<V: VectorFactory> Display for Backtrace<'_, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        for (i, frame) in self.frames.iter().enumerate() {
+            if i > 0 {
+                write!(f, " <- ")?;
+            }
+            if let Some(name) = self.module.func_name(frame.funcidx) {
+                write!(f, "{}[{}]", name, frame.instr_offset)?;
+            } else {
+                write!(f, "func{}[{}]", frame.funcidx.get(), frame.instr_offset)?;
+            }
+        }
+        Ok(())
+    }
+}
 
-        let prev = self.current_block;
-        self.current_block = Block {
-            arity: ty.arity(),
-            values_start: self.values.len(),
-        };
-        prev
+impl<V: VectorFactory> Debug for Backtrace<'_, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("Backtrace").field(&self.frames).finish()
     }
+}
 
-    // TODO: rename skipped
-    pub fn exit_block(&mut self, ty: Blocktype, skipped: bool, prev: Block) {
-        assert!(matches!(ty, Blocktype::Empty)); // TODO
+pub(crate) struct Executor<
+    V: VectorFactory,
+    M = VectorMemory<V>,
+    S: StackStorage = StackBuffers<'static>,
+> {
+    pub(crate) mem: M,
+    // Size (in pages) that `memory.grow` can't exceed, whether the memory is defined or imported.
+    pub(crate) mem_max_pages: u32,
+    pub(crate) table: V::Vector<Option<FuncRef>>,
+    pub(crate) dropped_elems: V::Vector<bool>,
+    pub(crate) dropped_datas: V::Vector<bool>,
+    pub(crate) globals: V::Vector<GlobalVal>,
+    pub(crate) locals: Stack<V, S, Val>,
+    pub(crate) values: Stack<V, S, Val>,
+    pub(crate) frames: Stack<V, S, Frame>,
+    pub(crate) labels: Stack<V, S, Label>,
+    // Peaks of the stacks used by each function, to check the room of fixed stacks on calls.
+    pub(crate) frame_sizes: Option<V::Vector<FrameSize>>,
+    pub(crate) result_type: Option<Resulttype>,
+    pub(crate) breakpoints: V::Vector<(Funcidx, usize)>,
+    pub(crate) skip_breakpoint: bool,
+    pub(crate) trace: V::Vector<TraceFrame>,
+    pub(crate) config: ExecutorConfig,
+    pub(crate) coverage: Option<V::Vector<V::Vector<u64>>>,
+    pub(crate) stats: ExecStats,
+    // `stats.instrs_executed` at the start of the current invocation.
+    pub(crate) instrs_at_call: u64,
+}
 
-        let block = self.current_block;
+impl<V: VectorFactory, M: LinearMemory, S: StackStorage> Executor<V, M, S> {
+    pub(crate) fn new(
+        mem: M,
+        table: V::Vector<Option<FuncRef>>,
+        globals: V::Vector<GlobalVal>,
+    ) -> Self {
+        Self {
+            mem,
+            mem_max_pages: MAX_PAGES,
+            table,
+            dropped_elems: V::create_vector(None),
+            dropped_datas: V::create_vector(None),
+            globals,
+            locals: Stack::new(),
+            values: Stack::new(),
+            frames: Stack::new(),
+            labels: Stack::new(),
+            frame_sizes: None,
+            result_type: None,
+            breakpoints: V::create_vector(None),
+            skip_breakpoint: false,
+            trace: V::create_vector(None),
+            config: ExecutorConfig::default(),
+            coverage: None,
+            stats: ExecStats::default(),
+            instrs_at_call: 0,
+        }
+    }
 
-        if !skipped {
-            self.values
-                .remove_range(block.values_start..self.values.len() - block.arity);
+    pub(crate) fn reserve_stacks(&mut self, values: usize, frames: usize) {
+        let additional = |len: usize| values.saturating_sub(len);
+        self.values.reserve(additional(self.values.len()));
+        self.locals.reserve(additional(self.locals.len()));
+        self.frames
+            .reserve(frames.saturating_sub(self.frames.len()));
+        self.labels
+            .reserve(frames.saturating_sub(self.labels.len()));
+        self.trace.reserve(frames.saturating_sub(self.trace.len()));
+    }
+
+    // Moves the state other than the stacks to an executor whose stacks are the given buffers
+    // (discarding the pending call, if any). `frame_sizes` are those of the functions of
+    // the module that the instance was created from.
+    pub(crate) fn with_stack_buffers(
+        self,
+        buffers: StackBuffers<'_>,
+        frame_sizes: V::Vector<FrameSize>,
+    ) -> Executor<V, M, StackBuffers<'_>> {
+        Executor {
+            mem: self.mem,
+            mem_max_pages: self.mem_max_pages,
+            table: self.table,
+            dropped_elems: self.dropped_elems,
+            dropped_datas: self.dropped_datas,
+            globals: self.globals,
+            locals: Stack::fixed(buffers.locals),
+            values: Stack::fixed(buffers.values),
+            frames: Stack::fixed(buffers.frames),
+            labels: Stack::fixed(buffers.labels),
+            frame_sizes: Some(frame_sizes),
+            result_type: None,
+            breakpoints: self.breakpoints,
+            skip_breakpoint: false,
+            trace: self.trace,
+            config: self.config,
+            coverage: self.coverage,
+            stats: self.stats,
+            instrs_at_call: self.instrs_at_call,
+        }
+    }
+
+    // Pushes the arguments of an invocation.
+    // Fails while another invocation started by `start_call()` is pending.
+    pub(crate) fn push_args(&mut self, args: &[Val]) -> Result<(), ExecuteError> {
+        if self.result_type.is_some() {
+            return Err(ExecuteError::CallPending);
         }
+        if !self.values.has_room(args.len()) {
+            return Err(ExecuteError::Trap(TrapKind::StackExhausted));
+        }
+        for v in args.iter().copied() {
+            self.push_value(v);
+        }
+        Ok(())
+    }
 
-        self.current_block = prev;
+    // Discards the state of the pending call, if any.
+    pub(crate) fn clear_stacks(&mut self) {
+        self.values.clear();
+        self.locals.clear();
+        self.frames.clear();
+        self.labels.clear();
+        self.trace.clear();
+        self.result_type = None;
+        self.skip_breakpoint = false;
     }
 
-    pub fn set_local(&mut self, i: Localidx, v: Val) {
-        let i = self.current_frame.locals_start + i.get();
+    fn current_frame(&self) -> Frame {
+        *self.frames.last().expect("unreachable")
+    }
+
+    fn current_frame_mut(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("unreachable")
+    }
+
+    pub(crate) fn set_local(&mut self, i: Localidx, v: Val) {
+        let i = self.current_frame().locals_start + i.get();
         self.locals[i] = v;
     }
 
-    pub fn get_local(&self, i: Localidx) -> Val {
-        let i = self.current_frame.locals_start + i.get();
+    pub(crate) fn get_local(&self, i: Localidx) -> Val {
+        let i = self.current_frame().locals_start + i.get();
         self.locals[i]
     }
 
-    pub fn push_value(&mut self, v: Val) {
+    pub(crate) fn push_value(&mut self, v: Val) {
         self.values.push(v);
     }
 
-    pub fn pop_value(&mut self) -> Val {
+    pub(crate) fn pop_value(&mut self) -> Val {
         self.values.pop().expect("unreachable")
     }
 
-    pub fn pop_value_i32(&mut self) -> i32 {
+    pub(crate) fn pop_value_i32(&mut self) -> i32 {
         let Some(Val::I32(v)) = self.values.pop() else {
             unreachable!();
         };
         v
     }
 
-    pub fn pop_value_i64(&mut self) -> i64 {
+    pub(crate) fn pop_value_i64(&mut self) -> i64 {
         let Some(Val::I64(v)) = self.values.pop() else {
             unreachable!();
         };
         v
     }
 
-    pub fn pop_value_u64(&mut self) -> u64 {
+    pub(crate) fn pop_value_u64(&mut self) -> u64 {
         let Some(Val::I64(v)) = self.values.pop() else {
             unreachable!();
         };
         v as u64
     }
 
-    pub fn pop_value_u32(&mut self) -> u32 {
+    pub(crate) fn pop_value_u32(&mut self) -> u32 {
         let Some(Val::I32(v)) = self.values.pop() else {
             unreachable!();
         };
         v as u32
     }
 
-    pub fn pop_value_f32(&mut self) -> f32 {
+    pub(crate) fn pop_value_f32(&mut self) -> f32 {
         let Some(Val::F32(v)) = self.values.pop() else {
             unreachable!();
         };
         v
     }
 
-    pub fn pop_value_f64(&mut self) -> f64 {
+    pub(crate) fn pop_value_f64(&mut self) -> f64 {
         let Some(Val::F64(v)) = self.values.pop() else {
             unreachable!();
         };
         v
     }
 
-    pub fn call_function<H: HostFunc>(
+    // Runs the given function to completion (breakpoints are ignored).
+    pub(crate) fn call_function<H: HostFunc>(
+        &mut self,
+        func_idx: Funcidx,
+        funcs: &mut [FuncInst<H>],
+        module: &Module<V>,
+    ) -> Result<Option<Val>, ExecuteError> {
+        self.start_call(func_idx, funcs, module)?;
+        match self.run(funcs, module, None, false)? {
+            StepResult::Returned(v) => Ok(v),
+            _ => unreachable!(),
+        }
+    }
+
+    // Prepares a call of the given function whose arguments have already been pushed
+    // to the value stack. The call proceeds by `run()`.
+    pub(crate) fn start_call<H: HostFunc>(
+        &mut self,
+        func_idx: Funcidx,
+        funcs: &mut [FuncInst<H>],
+        module: &Module<V>,
+    ) -> Result<(), ExecuteError> {
+        let func_type = funcs
+            .get(func_idx.get())
+            .ok_or(ExecuteError::InvalidFuncidx)?
+            .get_type(module)
+            .ok_or(ExecuteError::InvalidFuncidx)?;
+        self.trace.clear();
+        self.skip_breakpoint = false;
+        self.result_type = Some(func_type.result);
+        self.instrs_at_call = self.stats.instrs_executed;
+        if let Err(e) = self.call(func_idx, funcs, module) {
+            self.unwind(module, e);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn run<H: HostFunc>(
+        &mut self,
+        funcs: &mut [FuncInst<H>],
+        module: &Module<V>,
+        max_steps: Option<usize>,
+        resumable: bool, // Enables the breakpoints and `ExecutorConfig::pause_requested`.
+    ) -> Result<StepResult, ExecuteError> {
+        // The float mode is resolved here so that `FpMode::Strict` pays nothing per instruction.
+        match self.config.fp_mode {
+            FpMode::Strict => self.run_loop::<H, false>(funcs, module, max_steps, resumable),
+            FpMode::Fast => self.run_loop::<H, true>(funcs, module, max_steps, resumable),
+        }
+    }
+
+    fn run_loop<H: HostFunc, const FLUSH_SUBNORMALS: bool>(
+        &mut self,
+        funcs: &mut [FuncInst<H>],
+        module: &Module<V>,
+        max_steps: Option<usize>,
+        resumable: bool,
+    ) -> Result<StepResult, ExecuteError> {
+        let Some(result_type) = self.result_type else {
+            return Err(ExecuteError::NoPendingCall);
+        };
+
+        let mut steps = 0;
+        loop {
+            let Some(&frame) = self.frames.last() else {
+                self.result_type = None;
+                let value = result_type.get().and_then(|_| self.values.pop());
+                if value.map(|v| v.ty()) != result_type.get() || !self.values.is_empty() {
+                    self.values.clear();
+                    return Err(ExecuteError::InvalidReturnValue);
+                }
+                return Ok(StepResult::Returned(value));
+            };
+
+            let body = module.funcs()[frame.funcs_index].body.instrs();
+            let labels = &self.labels[frame.labels_start..];
+            let instrs = current_instrs(body, labels);
+            let Some(instr) = instrs.get(frame.pc) else {
+                // The end of the current block or function.
+                if let Some(label) = labels.last().copied() {
+                    self.labels.pop();
+                    self.current_frame_mut().pc = label.pc + 1;
+                } else {
+                    self.exit_frame();
+                }
+                continue;
+            };
+
+            if resumable && !self.skip_breakpoint && !self.breakpoints.is_empty() {
+                let funcidx = frame.funcidx;
+                if self.breakpoints.iter().any(|(f, _)| *f == funcidx) {
+                    let offset = instr_offset(body, labels, frame.pc);
+                    if self
+                        .breakpoints
+                        .iter()
+                        .any(|(f, o)| *f == funcidx && *o == offset)
+                    {
+                        self.skip_breakpoint = true;
+                        return Ok(StepResult::Breakpoint(TraceFrame {
+                            funcidx,
+                            instr_offset: offset,
+                        }));
+                    }
+                }
+            }
+            if max_steps == Some(steps) {
+                return Ok(StepResult::Running);
+            }
+            if resumable && self.config.pause_requested.is_some_and(|pause| pause()) {
+                return Ok(StepResult::Paused);
+            }
+            steps += 1;
+            self.stats.instrs_executed += 1;
+
+            if let Some(coverage) = &mut self.coverage {
+                let offset = instr_offset(body, labels, frame.pc);
+                coverage[frame.funcs_index][offset / 64] |= 1 << (offset % 64);
+            }
+
+            self.skip_breakpoint = false;
+            self.current_frame_mut().pc += 1;
+            let result = self
+                .execute_instr::<H, FLUSH_SUBNORMALS>(instr, funcs, module)
+                .and_then(|()| self.check_value_stack(0));
+            if let Err(e) = result {
+                trace!("{e} at `{instr}` in function {}", frame.funcidx.get());
+                self.unwind(module, e);
+                return Err(e);
+            }
+        }
+    }
+
+    // The innermost frame points to the next instruction to be executed.
+    pub(crate) fn call_stack<'a>(
+        &'a self,
+        module: &'a Module<V>,
+    ) -> impl 'a + Iterator<Item = TraceFrame> {
+        let mut labels_end = self.labels.len();
+        self.frames.iter().rev().enumerate().map(move |(i, frame)| {
+            let body = module.funcs()[frame.funcs_index].body.instrs();
+            let labels = &self.labels[frame.labels_start..labels_end];
+            labels_end = frame.labels_start;
+            let pc = if i == 0 { frame.pc } else { frame.pc - 1 };
+            TraceFrame {
+                funcidx: frame.funcidx,
+                instr_offset: instr_offset(body, labels, pc),
+            }
+        })
+    }
+
+    // Records the backtrace, notifies `on_trap` and discards the trapped call.
+    fn unwind(&mut self, module: &Module<V>, error: ExecuteError) {
+        let mut labels_end = self.labels.len();
+        for frame in self.frames.iter().rev() {
+            let body = module.funcs()[frame.funcs_index].body.instrs();
+            let labels = &self.labels[frame.labels_start..labels_end];
+            self.trace.push(TraceFrame {
+                funcidx: frame.funcidx,
+                instr_offset: instr_offset(body, labels, frame.pc - 1),
+            });
+            labels_end = frame.labels_start;
+        }
+        if let (ExecuteError::Trap(kind), Some(on_trap)) = (error, self.config.on_trap) {
+            on_trap(&TrapInfo {
+                kind,
+                backtrace: &self.trace,
+                fuel_consumed: self.stats.instrs_executed - self.instrs_at_call,
+                instrs_executed: self.stats.instrs_executed,
+            });
+        }
+
+        self.frames.clear();
+        self.labels.clear();
+        self.locals.clear();
+        self.values.clear();
+        self.result_type = None;
+    }
+
+    fn call<H: HostFunc>(
         &mut self,
         func_idx: Funcidx,
         funcs: &mut [FuncInst<H>],
         module: &Module<V>,
     ) -> Result<(), ExecuteError> {
-        // TODO: Add validation phase
         let func = funcs
             .get_mut(func_idx.get())
             .ok_or(ExecuteError::InvalidFuncidx)?;
         let func_type = func.get_type(module).ok_or(ExecuteError::InvalidFuncidx)?; // TODO: change reason
+        self.stats.calls += 1;
+
+        let callee_locals = match func {
+            FuncInst::Module { funcs_index } => module
+                .funcs()
+                .get(*funcs_index)
+                .map_or(0, |func| func.locals.len()),
+            _ => 0,
+        };
+        if !self.locals.has_room(func_type.params.len() + callee_locals) {
+            return Err(ExecuteError::Trap(TrapKind::StackExhausted));
+        }
 
-        let prev_frame = self.enter_frame(func_type);
-        match func {
-            FuncInst::Imported {
-                imports_index,
-                host_func,
-            } => {
-                let Importdesc::Func(typeidx) = module.imports()[*imports_index].desc else {
-                    unreachable!()
-                };
-                let func_type = &module.types()[typeidx.get()];
-                let args_end = self.locals.len();
-                let args_start = args_end - func_type.params.len();
-                let args = &self.locals[args_start..args_end];
+        let locals_start = self.locals.len();
+        for _ in 0..func_type.params.len() {
+            let v = self.pop_value();
+            self.locals.push(v);
+        }
+        self.locals[locals_start..].reverse();
 
+        match func {
+            FuncInst::Imported { host_func, .. } | FuncInst::Host { host_func, .. } => {
+                let frame = self.frames.last().copied();
+                let labels = &self.labels;
+                let caller = || frame.map(|frame| frame_position(&frame, labels, module));
+                let (caller_locals, args) = self.locals.split_at(locals_start);
                 let mut env = Env {
-                    mem: &mut self.mem,
+                    mem: EnvMem {
+                        mem: &mut self.mem,
+                        max_pages: self.mem_max_pages,
+                        grown: &mut self.stats.mem_grown,
+                    },
                     globals: &mut self.globals,
+                    aborted: false,
+                    caller: &caller,
+                    caller_locals: frame.map_or(&[], |f| &caller_locals[f.locals_start..]),
+                    call_depth: self.frames.len(),
                 };
                 let value = host_func.invoke(args, &mut env);
+                let aborted = env.aborted;
+                self.locals.truncate(locals_start);
+                if aborted {
+                    return Err(ExecuteError::Trap(TrapKind::Aborted));
+                }
 
-                // TODO: check return value type
+                if value.map(|v| v.ty()) != func_type.result.get() {
+                    return Err(ExecuteError::InvalidReturnValue);
+                }
                 if let Some(v) = value {
+                    if !self.values.has_room(1) {
+                        return Err(ExecuteError::Trap(TrapKind::StackExhausted));
+                    }
                     self.values.push(v);
                 }
             }
@@ -229,581 +735,726 @@ impl<V: VectorFactory> Executor<V> {
                     .funcs()
                     .get(*funcs_index)
                     .ok_or(ExecuteError::InvalidFuncidx)?;
+                self.check_value_stack(func.locals.len())?;
+                if self.frames.len() >= self.config.max_call_depth {
+                    return Err(ExecuteError::Trap(TrapKind::StackExhausted));
+                }
+                if let Some(size) = self
+                    .frame_sizes
+                    .as_ref()
+                    .and_then(|sizes| sizes.get(*funcs_index))
+                {
+                    if !self.frames.has_room(1)
+                        || !self.values.has_room(size.operands)
+                        || !self.labels.has_room(size.labels)
+                    {
+                        return Err(ExecuteError::Trap(TrapKind::StackExhausted));
+                    }
+                }
                 for v in func.locals.iter().copied().map(Val::zero) {
                     self.locals.push(v);
                 }
-                self.execute_instrs(func.body.instrs(), 0, funcs, module)?;
+                self.frames.push(Frame {
+                    funcidx: func_idx,
+                    funcs_index: *funcs_index,
+                    arity: func_type.result.len(),
+                    locals_start,
+                    values_start: self.values.len(),
+                    labels_start: self.labels.len(),
+                    pc: 0,
+                });
+                self.stats.max_call_depth = self.stats.max_call_depth.max(self.frames.len());
             }
+        }
+        Ok(())
+    }
+
+    fn check_value_stack(&mut self, additional: usize) -> Result<(), ExecuteError> {
+        let n = (self.values.len() + self.locals.len()).saturating_add(additional);
+        if n > self.config.max_value_stack {
+            return Err(ExecuteError::Trap(TrapKind::StackExhausted));
+        }
+        self.stats.max_value_stack = self.stats.max_value_stack.max(n);
+        Ok(())
+    }
+
+    fn exit_frame(&mut self) {
+        let frame = self.frames.pop().expect("unreachable");
+        self.locals.truncate(frame.locals_start);
+        self.labels.truncate(frame.labels_start);
+        self.values
+            .remove_range(frame.values_start..self.values.len() - frame.arity);
+    }
+
+    fn enter_label(&mut self, kind: LabelKind, arity: usize) -> Result<(), ExecuteError> {
+        if self.labels.len() >= self.config.max_labels {
+            return Err(ExecuteError::Trap(TrapKind::StackExhausted));
+        }
+        let values_start = self.values.len();
+        let frame = self.current_frame_mut();
+        let label = Label {
+            kind,
+            pc: frame.pc - 1,
+            arity,
+            values_start,
         };
-        self.exit_frame(func_type, prev_frame);
+        frame.pc = 0;
+        self.labels.push(label);
         Ok(())
     }
 
-    pub fn execute_instrs<H: HostFunc>(
+    fn branch(&mut self, labelidx: Labelidx) {
+        let frame = self.current_frame();
+        if labelidx.get() == self.labels.len() - frame.labels_start {
+            // Branching to the outermost label is equivalent to `return`.
+            self.return_from_func();
+            return;
+        }
+
+        let i = self.labels.len() - 1 - labelidx.get();
+        let label = self.labels[i];
+        if label.kind == LabelKind::Loop {
+            self.values.truncate(label.values_start);
+            self.labels.truncate(i + 1);
+            self.current_frame_mut().pc = 0;
+        } else {
+            self.values
+                .remove_range(label.values_start..self.values.len() - label.arity);
+            self.labels.truncate(i);
+            self.current_frame_mut().pc = label.pc + 1;
+        }
+    }
+
+    fn return_from_func(&mut self) {
+        // Drop the labels and move to the end of the function body.
+        let frame = self.current_frame();
+        self.labels.truncate(frame.labels_start);
+        self.current_frame_mut().pc = usize::MAX;
+    }
+
+    fn effective_address(&mut self, arg: &Memarg) -> Result<usize, ExecuteError> {
+        let i = self.pop_value_u32() as usize;
+        i.checked_add(arg.offset as usize)
+            .ok_or(ExecuteError::Trap(TrapKind::MemoryOutOfBounds))
+    }
+
+    #[cfg(feature = "memory_protection")]
+    fn check_prot(&self, addr: usize, len: usize, write: bool) -> Result<(), ExecuteError> {
+        let last = addr.saturating_add(len.saturating_sub(1));
+        for page in addr / PAGE_SIZE..=last / PAGE_SIZE {
+            let prot = self.mem.page_prot(page);
+            if !(if write { prot.write } else { prot.read }) {
+                return Err(ExecuteError::Trap(TrapKind::MemoryAccessDenied));
+            }
+        }
+        Ok(())
+    }
+
+    // Checks the bounds (and the protection) of the whole range before a bulk memory
+    // instruction touches any of it. Empty ranges are allowed up to the end of the memory.
+    #[cfg(feature = "bulk_memory")]
+    #[cfg_attr(not(feature = "memory_protection"), allow(unused_variables))]
+    fn check_mem_range(&self, addr: usize, len: usize, write: bool) -> Result<(), ExecuteError> {
+        if addr
+            .checked_add(len)
+            .is_none_or(|end| end > self.mem.size())
+        {
+            return Err(ExecuteError::Trap(TrapKind::MemoryOutOfBounds));
+        }
+        #[cfg(feature = "memory_protection")]
+        if len > 0 {
+            self.check_prot(addr, len, write)?;
+        }
+        Ok(())
+    }
+
+    // `LinearMemory` only copies bytes in and out, so the bytes go through a buffer on the
+    // stack, from the end if the destination overlaps the tail of the source.
+    #[cfg(feature = "bulk_memory")]
+    fn copy_mem(&mut self, dst: usize, src: usize, len: usize) -> Result<(), ExecuteError> {
+        self.check_mem_range(src, len, false)?;
+        self.check_mem_range(dst, len, true)?;
+        let mut buf = [0; 256];
+        let mut copied = 0;
+        while copied < len {
+            let n = (len - copied).min(buf.len());
+            let offset = if dst > src { len - copied - n } else { copied };
+            self.mem.read(src + offset, &mut buf[..n]);
+            self.mem.write(dst + offset, &buf[..n]);
+            copied += n;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "bulk_memory")]
+    fn fill_mem(&mut self, dst: usize, value: u8, len: usize) -> Result<(), ExecuteError> {
+        self.check_mem_range(dst, len, true)?;
+        let buf = [value; 256];
+        let mut filled = 0;
+        while filled < len {
+            let n = (len - filled).min(buf.len());
+            self.mem.write(dst + filled, &buf[..n]);
+            filled += n;
+        }
+        Ok(())
+    }
+
+    fn load<const N: usize>(&mut self, arg: &Memarg) -> Result<[u8; N], ExecuteError> {
+        let addr = self.effective_address(arg)?;
+        let mut buf = [0; N];
+        #[cfg(feature = "memory_protection")]
+        self.check_prot(addr, N, false)?;
+        if !self.mem.read(addr, &mut buf) {
+            return Err(ExecuteError::Trap(TrapKind::MemoryOutOfBounds));
+        }
+        Ok(buf)
+    }
+
+    fn store(&mut self, arg: &Memarg, bytes: &[u8]) -> Result<(), ExecuteError> {
+        let addr = self.effective_address(arg)?;
+        #[cfg(feature = "memory_protection")]
+        self.check_prot(addr, bytes.len(), true)?;
+        if !self.mem.write(addr, bytes) {
+            return Err(ExecuteError::Trap(TrapKind::MemoryOutOfBounds));
+        }
+        Ok(())
+    }
+
+    // Returns the previous size in pages, or `None` if the memory cannot grow by `delta` pages.
+    pub(crate) fn grow_mem(&mut self, delta: u32) -> Option<u32> {
+        grow_mem_pages(
+            &mut self.mem,
+            self.mem_max_pages,
+            delta,
+            &mut self.stats.mem_grown,
+        )
+    }
+
+    fn execute_instr<H: HostFunc, const FLUSH_SUBNORMALS: bool>(
         &mut self,
-        instrs: &[Instr<V>],
-        level: usize, // TODO: label
+        instr: &Instr<V>,
         funcs: &mut [FuncInst<H>],
         module: &Module<V>,
-    ) -> Result<Option<usize>, ExecuteError> {
-        for instr in instrs {
-            match instr {
-                // Control Instructions
-                Instr::Unreachable => return Err(ExecuteError::Trapped),
-                Instr::Nop => {}
-                Instr::Block(block) => {
-                    let prev_block = self.enter_block(block.blocktype);
-                    let return_level =
-                        self.execute_instrs(&block.instrs, level + 1, funcs, module)?;
-                    let skipped = return_level.map_or(false, |return_level| return_level <= level);
-                    self.exit_block(block.blocktype, skipped, prev_block);
-                    if skipped {
-                        return Ok(return_level);
-                    }
-                }
-                Instr::Loop(block) => {
-                    let current_level = level + 1;
-                    let blocktype = Blocktype::Empty;
-                    let prev_block = self.enter_block(blocktype);
-                    loop {
-                        let return_level =
-                            self.execute_instrs(&block.instrs, current_level, funcs, module)?;
-                        if return_level == Some(current_level) {
-                            continue;
-                        }
-                        let skipped =
-                            return_level.map_or(false, |return_level| return_level <= level);
-                        self.exit_block(blocktype, skipped, prev_block);
-                        if skipped {
-                            return Ok(return_level);
-                        }
-                        break;
-                    }
-                }
-                Instr::If(block) => {
-                    let c = self.pop_value_i32();
-                    let prev_block = self.enter_block(block.blocktype);
-                    let return_level = if c != 0 {
-                        self.execute_instrs(&block.then_instrs, level + 1, funcs, module)?
-                    } else {
-                        self.execute_instrs(&block.else_instrs, level + 1, funcs, module)?
-                    };
-                    let skipped = return_level.map_or(false, |return_level| return_level <= level);
-                    self.exit_block(block.blocktype, skipped, prev_block);
-                    if skipped {
-                        return Ok(return_level);
-                    }
-                }
-                Instr::Br(label) => {
-                    return Ok(Some(level - label.get()));
-                }
-                Instr::BrIf(label) => {
-                    let c = self.pop_value_i32();
-                    if c != 0 {
-                        return Ok(Some(level - label.get()));
-                    }
-                }
-                Instr::BrTable(table) => {
-                    let i = self.pop_value_i32() as usize;
-                    let label = table
-                        .labels
-                        .get(i)
-                        .unwrap_or_else(|| table.labels.last().expect("unreachable"));
-                    return Ok(Some(level - label.get()));
-                }
-                Instr::Return => {
-                    return Ok(Some(0));
-                }
-                Instr::Call(funcidx) => {
-                    self.call_function(*funcidx, funcs, module)?;
+    ) -> Result<(), ExecuteError> {
+        match instr {
+            // Control Instructions
+            Instr::Unreachable => return Err(ExecuteError::Trap(TrapKind::Unreachable)),
+            Instr::Nop => {}
+            Instr::Block(block) => {
+                self.enter_label(LabelKind::Block, block.blocktype.arity())?;
+            }
+            Instr::Loop(_) => {
+                self.enter_label(LabelKind::Loop, 0)?;
+            }
+            Instr::If(block) => {
+                let c = self.pop_value_i32();
+                let kind = if c != 0 {
+                    LabelKind::Then
+                } else {
+                    LabelKind::Else
+                };
+                self.enter_label(kind, block.blocktype.arity())?;
+            }
+            Instr::Br(label) => {
+                self.branch(*label);
+            }
+            Instr::BrIf(label) => {
+                let c = self.pop_value_i32();
+                if c != 0 {
+                    self.branch(*label);
                 }
-                Instr::CallIndirect(typeidx) => {
-                    let expect_type = module
-                        .types()
-                        .get(typeidx.get())
-                        .ok_or(ExecuteError::InvalidTypeidx)?;
-
-                    let i = self.pop_value_i32() as usize;
-                    let funcidx = self
-                        .table
-                        .get(i)
-                        .ok_or(ExecuteError::Trapped)?
-                        .ok_or(ExecuteError::Trapped)?;
-                    let func = funcs
-                        .get(funcidx.get())
-                        .ok_or(ExecuteError::InvalidFuncidx)?;
-                    let actual_type = func.get_type(module).ok_or(ExecuteError::InvalidFuncidx)?; // TODO
-                    if expect_type != actual_type {
-                        return Err(ExecuteError::Trapped);
+            }
+            Instr::BrTable(table) => {
+                let i = self.pop_value_i32() as u32 as usize;
+                let label = table
+                    .labels
+                    .get(i)
+                    .unwrap_or_else(|| table.labels.last().expect("unreachable"));
+                self.branch(*label);
+            }
+            Instr::Return => {
+                self.return_from_func();
+            }
+            Instr::Call(funcidx) => {
+                self.call(*funcidx, funcs, module)?;
+            }
+            Instr::CallIndirect(typeidx) => {
+                let expect_type = module
+                    .types()
+                    .get(typeidx.get())
+                    .ok_or(ExecuteError::InvalidTypeidx)?;
+
+                let index = self.pop_value_u32();
+                let funcref = self
+                    .table
+                    .get(index as usize)
+                    .ok_or(ExecuteError::Trap(TrapKind::TableIndexOutOfBounds {
+                        index,
+                    }))?
+                    .ok_or(ExecuteError::Trap(TrapKind::UninitializedTableElement {
+                        index,
+                    }))?;
+                let funcidx = match funcref {
+                    FuncRef::Func(funcidx) => funcidx,
+                    FuncRef::Host(_) => {
+                        funcref.funcidx(module.imported_funcs_len() + module.funcs().len())
                     }
-                    self.call_function(funcidx, funcs, module)?;
+                };
+                let func = funcs
+                    .get(funcidx.get())
+                    .ok_or(ExecuteError::InvalidFuncidx)?;
+                let actual_typeidx = func
+                    .get_typeidx(module)
+                    .ok_or(ExecuteError::InvalidFuncidx)?;
+                let actual_type = module
+                    .types()
+                    .get(actual_typeidx.get())
+                    .ok_or(ExecuteError::InvalidTypeidx)?;
+                // Types are compared structurally, so different indices may still match.
+                if expect_type != actual_type {
+                    return Err(ExecuteError::Trap(TrapKind::SignatureMismatch {
+                        expected: *typeidx,
+                        actual: actual_typeidx,
+                    }));
                 }
+                self.call(funcidx, funcs, module)?;
+            }
 
-                // Parametric Instructions
-                Instr::Drop => {
-                    self.pop_value();
-                }
-                Instr::Select => {
-                    let c = self.pop_value_i32();
-                    let v2 = self.pop_value();
-                    let v1 = self.pop_value();
-                    self.push_value(if c != 0 { v1 } else { v2 });
-                }
+            // Parametric Instructions
+            Instr::Drop => {
+                self.pop_value();
+            }
+            Instr::Select => {
+                let c = self.pop_value_i32();
+                let v2 = self.pop_value();
+                let v1 = self.pop_value();
+                self.push_value(if c != 0 { v1 } else { v2 });
+            }
+            #[cfg(feature = "reference_types")]
+            Instr::SelectTyped(_) => {
+                let c = self.pop_value_i32();
+                let v2 = self.pop_value();
+                let v1 = self.pop_value();
+                self.push_value(if c != 0 { v1 } else { v2 });
+            }
 
-                // Variable Instructions
-                Instr::LocalTee(idx) => {
-                    let v = self.pop_value();
-                    self.set_local(*idx, v);
-                    self.push_value(v);
-                }
-                Instr::LocalGet(idx) => {
-                    let v = self.get_local(*idx);
-                    self.push_value(v);
-                }
-                Instr::LocalSet(idx) => {
-                    let v = self.pop_value();
-                    self.set_local(*idx, v);
-                }
-                Instr::GlobalGet(idx) => {
-                    let v = self.globals[idx.get()].get();
-                    self.push_value(v);
-                }
-                Instr::GlobalSet(idx) => {
-                    let v = self.pop_value();
-                    self.globals[idx.get()].set(v);
+            // Variable Instructions
+            Instr::LocalTee(idx) => {
+                let v = self.pop_value();
+                self.set_local(*idx, v);
+                self.push_value(v);
+            }
+            Instr::LocalGet(idx) => {
+                let v = self.get_local(*idx);
+                self.push_value(v);
+            }
+            Instr::LocalSet(idx) => {
+                let v = self.pop_value();
+                self.set_local(*idx, v);
+            }
+            Instr::GlobalGet(idx) => {
+                let v = self.globals[idx.get()].get();
+                self.push_value(v);
+            }
+            Instr::GlobalSet(idx) => {
+                let v = self.pop_value();
+                // Rejected by the validator, but the globals can be replaced by the embedder.
+                if !self.globals[idx.get()].set(v) {
+                    return Err(ExecuteError::Trap(TrapKind::ImmutableGlobal {
+                        index: idx.get() as u32,
+                    }));
                 }
+            }
+
+            // Memory Instructions
+            Instr::I32Load(arg) => {
+                let v = i32::from_le_bytes(self.load(arg)?);
+                self.push_value(Val::I32(v));
+            }
+            Instr::I64Load(arg) => {
+                let v = i64::from_le_bytes(self.load(arg)?);
+                self.push_value(Val::I64(v));
+            }
+            Instr::F32Load(arg) => {
+                let v = f32::from_le_bytes(self.load(arg)?);
+                self.push_value(Val::F32(v));
+            }
+            Instr::F64Load(arg) => {
+                let v = f64::from_le_bytes(self.load(arg)?);
+                self.push_value(Val::F64(v));
+            }
+            Instr::I32Load8S(arg) => {
+                let v = i8::from_le_bytes(self.load(arg)?);
+                self.push_value(Val::I32(v as i32));
+            }
+            Instr::I32Load8U(arg) => {
+                let v = u8::from_le_bytes(self.load(arg)?);
+                self.push_value(Val::I32(v as i32));
+            }
+            Instr::I32Load16S(arg) => {
+                let v = i16::from_le_bytes(self.load(arg)?);
+                self.push_value(Val::I32(v as i32));
+            }
+            Instr::I32Load16U(arg) => {
+                let v = u16::from_le_bytes(self.load(arg)?);
+                self.push_value(Val::I32(v as i32));
+            }
+            Instr::I64Load8S(arg) => {
+                let v = i8::from_le_bytes(self.load(arg)?);
+                self.push_value(Val::I64(v as i64));
+            }
+            Instr::I64Load8U(arg) => {
+                let v = u8::from_le_bytes(self.load(arg)?);
+                self.push_value(Val::I64(v as i64));
+            }
+            Instr::I64Load16S(arg) => {
+                let v = i16::from_le_bytes(self.load(arg)?);
+                self.push_value(Val::I64(v as i64));
+            }
+            Instr::I64Load16U(arg) => {
+                let v = u16::from_le_bytes(self.load(arg)?);
+                self.push_value(Val::I64(v as i64));
+            }
+            Instr::I64Load32S(arg) => {
+                let v = i32::from_le_bytes(self.load(arg)?);
+                self.push_value(Val::I64(v as i64));
+            }
+            Instr::I64Load32U(arg) => {
+                let v = u32::from_le_bytes(self.load(arg)?);
+                self.push_value(Val::I64(v as i64));
+            }
+            Instr::I32Store(arg) => {
+                let v = self.pop_value_i32();
+                self.store(arg, &v.to_le_bytes())?;
+            }
+            Instr::I64Store(arg) => {
+                let v = self.pop_value_i64();
+                self.store(arg, &v.to_le_bytes())?;
+            }
+            Instr::F32Store(arg) => {
+                let v = self.pop_value_f32();
+                self.store(arg, &v.to_le_bytes())?;
+            }
+            Instr::F64Store(arg) => {
+                let v = self.pop_value_f64();
+                self.store(arg, &v.to_le_bytes())?;
+            }
+            Instr::I32Store8(arg) => {
+                let v = self.pop_value_i32() as u8;
+                self.store(arg, &v.to_le_bytes())?;
+            }
+            Instr::I32Store16(arg) => {
+                let v = self.pop_value_i32() as u16;
+                self.store(arg, &v.to_le_bytes())?;
+            }
+            Instr::I64Store8(arg) => {
+                let v = self.pop_value_i64() as u8;
+                self.store(arg, &v.to_le_bytes())?;
+            }
+            Instr::I64Store16(arg) => {
+                let v = self.pop_value_i64() as u16;
+                self.store(arg, &v.to_le_bytes())?;
+            }
+            Instr::I64Store32(arg) => {
+                let v = self.pop_value_i64() as u32;
+                self.store(arg, &v.to_le_bytes())?;
+            }
+            Instr::MemorySize => {
+                let size = self.mem.size() / PAGE_SIZE;
+                self.push_value(Val::I32(size as i32));
+            }
+            Instr::MemoryGrow => {
+                let delta = self.pop_value_u32();
+                let result = self.grow_mem(delta);
+                self.push_value(Val::I32(result.map_or(-1, |current| current as i32)));
+            }
 
-                // Memory Instructions
-                Instr::I32Load(arg) => {
-                    // TODO: handle alignment
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + 4;
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    let v = i32::from_le_bytes(self.mem[start..end].try_into().unwrap()); // TODO
-                    self.values.push(Val::I32(v));
-                }
-                Instr::I64Load(arg) => {
-                    // TODO: handle alignment
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + 8;
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    let v = i64::from_le_bytes(self.mem[start..end].try_into().unwrap()); // TODO
-                    self.values.push(Val::I64(v));
-                }
-                Instr::F32Load(arg) => {
-                    // TODO: handle alignment
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + 4;
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    let v = f32::from_le_bytes(self.mem[start..end].try_into().unwrap()); // TODO
-                    self.values.push(Val::F32(v));
-                }
-                Instr::F64Load(arg) => {
-                    // TODO: handle alignment
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + 8;
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    let v = f64::from_le_bytes(self.mem[start..end].try_into().unwrap()); // TODO
-                    self.values.push(Val::F64(v));
-                }
-                Instr::I32Load8S(arg) => {
-                    // TODO: handle alignment
-                    let i = self.pop_value_i32();
-                    let i = (i + arg.offset as i32) as usize;
-                    if self.mem.len() < i {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    let v = self.mem[i] as i8 as i32;
-                    self.values.push(Val::I32(v));
-                }
-                Instr::I32Load8U(arg) => {
-                    // TODO: handle alignment
-                    let i = self.pop_value_i32();
-                    let i = (i + arg.offset as i32) as usize;
-                    if self.mem.len() < i {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    let v = self.mem[i] as i32;
-                    self.values.push(Val::I32(v));
-                }
-                Instr::I32Load16S(arg) => {
-                    // TODO: handle alignment
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + 2;
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    let v = i16::from_le_bytes(self.mem[start..end].try_into().unwrap()); // TODO
-                    self.values.push(Val::I32(v as i32));
-                }
-                Instr::I32Load16U(arg) => {
-                    // TODO: handle alignment
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + 2;
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    let v = u16::from_le_bytes(self.mem[start..end].try_into().unwrap()); // TODO
-                    self.values.push(Val::I32(v as i32));
-                }
-                Instr::I64Load8S(arg) => {
-                    // TODO: handle alignment
-                    let i = self.pop_value_i32();
-                    let i = (i + arg.offset as i32) as usize;
-                    if self.mem.len() < i {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    let v = self.mem[i] as i8 as i64;
-                    self.values.push(Val::I64(v));
-                }
-                Instr::I64Load8U(arg) => {
-                    // TODO: handle alignment
-                    let i = self.pop_value_i32();
-                    let i = (i + arg.offset as i32) as usize;
-                    if self.mem.len() < i {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    let v = self.mem[i] as i64;
-                    self.values.push(Val::I64(v));
+            // Numeric Instructions
+            Instr::I32Const(v) => self.push_value(Val::I32(*v)),
+            Instr::I64Const(v) => self.push_value(Val::I64(*v)),
+            Instr::F32Const(v) => self.push_value(Val::F32(f32::from_bits(*v))),
+            Instr::F64Const(v) => self.push_value(Val::F64(f64::from_bits(*v))),
+            Instr::I32Eqz => self.apply_unop_cmp_i32(|v| v == 0),
+            Instr::I32Eq => self.apply_binop_cmp_i32(|v0, v1| v0 == v1),
+            Instr::I32Ne => self.apply_binop_cmp_i32(|v0, v1| v0 != v1),
+            Instr::I32LtS => self.apply_binop_cmp_i32(|v0, v1| v0 < v1),
+            Instr::I32LtU => self.apply_binop_cmp_u32(|v0, v1| v0 < v1),
+            Instr::I32GtS => self.apply_binop_cmp_i32(|v0, v1| v0 > v1),
+            Instr::I32GtU => self.apply_binop_cmp_u32(|v0, v1| v0 > v1),
+            Instr::I32LeS => self.apply_binop_cmp_i32(|v0, v1| v0 <= v1),
+            Instr::I32LeU => self.apply_binop_cmp_u32(|v0, v1| v0 <= v1),
+            Instr::I32GeS => self.apply_binop_cmp_i32(|v0, v1| v0 >= v1),
+            Instr::I32GeU => self.apply_binop_cmp_u32(|v0, v1| v0 >= v1),
+            Instr::I64Eqz => self.apply_unop_cmp_i64(|v| v == 0),
+            Instr::I64Eq => self.apply_binop_cmp_i64(|v0, v1| v0 == v1),
+            Instr::I64Ne => self.apply_binop_cmp_i64(|v0, v1| v0 != v1),
+            Instr::I64LtS => self.apply_binop_cmp_i64(|v0, v1| v0 < v1),
+            Instr::I64LtU => self.apply_binop_cmp_u64(|v0, v1| v0 < v1),
+            Instr::I64GtS => self.apply_binop_cmp_i64(|v0, v1| v0 > v1),
+            Instr::I64GtU => self.apply_binop_cmp_u64(|v0, v1| v0 > v1),
+            Instr::I64LeS => self.apply_binop_cmp_i64(|v0, v1| v0 <= v1),
+            Instr::I64LeU => self.apply_binop_cmp_u64(|v0, v1| v0 <= v1),
+            Instr::I64GeS => self.apply_binop_cmp_i64(|v0, v1| v0 >= v1),
+            Instr::I64GeU => self.apply_binop_cmp_u64(|v0, v1| v0 >= v1),
+            Instr::F32Eq => self.apply_binop_cmp_f32(|v0, v1| v0 == v1),
+            Instr::F32Ne => self.apply_binop_cmp_f32(|v0, v1| v0 != v1),
+            Instr::F32Lt => self.apply_binop_cmp_f32(|v0, v1| v0 < v1),
+            Instr::F32Gt => self.apply_binop_cmp_f32(|v0, v1| v0 > v1),
+            Instr::F32Le => self.apply_binop_cmp_f32(|v0, v1| v0 <= v1),
+            Instr::F32Ge => self.apply_binop_cmp_f32(|v0, v1| v0 >= v1),
+            Instr::F64Eq => self.apply_binop_cmp_f64(|v0, v1| v0 == v1),
+            Instr::F64Ne => self.apply_binop_cmp_f64(|v0, v1| v0 != v1),
+            Instr::F64Lt => self.apply_binop_cmp_f64(|v0, v1| v0 < v1),
+            Instr::F64Gt => self.apply_binop_cmp_f64(|v0, v1| v0 > v1),
+            Instr::F64Le => self.apply_binop_cmp_f64(|v0, v1| v0 <= v1),
+            Instr::F64Ge => self.apply_binop_cmp_f64(|v0, v1| v0 >= v1),
+            Instr::I32Clz => self.apply_unop_i32(|v| v.leading_zeros() as i32),
+            Instr::I32Ctz => self.apply_unop_i32(|v| v.trailing_zeros() as i32),
+            Instr::I32Popcnt => self.apply_unop_i32(|v| v.count_ones() as i32),
+            Instr::I32Add => self.apply_binop_i32(|v0, v1| v0.wrapping_add(v1)),
+            Instr::I32Sub => self.apply_binop_i32(|v0, v1| v0.wrapping_sub(v1)),
+            Instr::I32Mul => self.apply_binop_i32(|v0, v1| v0.wrapping_mul(v1)),
+            Instr::I32DivS => self.apply_binop_i32(|v0, v1| v0.wrapping_div(v1)), // TODO: wrapping?
+            Instr::I32DivU => self.apply_binop_u32(|v0, v1| v0.wrapping_div(v1)), // TODO: wrapping?
+            Instr::I32RemS => self.apply_binop_i32(|v0, v1| v0.wrapping_rem(v1)), // TODO: wrapping?
+            Instr::I32RemU => self.apply_binop_u32(|v0, v1| v0.wrapping_rem(v1)), // TODO: wrapping?
+            Instr::I32And => self.apply_binop_i32(|v0, v1| v0 & v1),
+            Instr::I32Or => self.apply_binop_i32(|v0, v1| v0 | v1),
+            Instr::I32Xor => self.apply_binop_i32(|v0, v1| v0 ^ v1),
+            // Shift counts are taken modulo the bit width.
+            Instr::I32Shl => self.apply_binop_i32(|v0, v1| v0 << (v1 & 31)),
+            Instr::I32ShrS => self.apply_binop_i32(|v0, v1| v0 >> (v1 & 31)),
+            Instr::I32ShrU => self.apply_binop_u32(|v0, v1| v0 >> (v1 & 31)),
+            Instr::I32Rotl => self.apply_binop_i32(|v0, v1| v0.rotate_left(v1 as u32)),
+            Instr::I32Rotr => self.apply_binop_i32(|v0, v1| v0.rotate_right(v1 as u32)),
+            Instr::I64Clz => self.apply_unop_i64(|v| v.leading_zeros() as i64),
+            Instr::I64Ctz => self.apply_unop_i64(|v| v.trailing_zeros() as i64),
+            Instr::I64Popcnt => self.apply_unop_i64(|v| v.count_ones() as i64),
+            Instr::I64Add => self.apply_binop_i64(|v0, v1| v0.wrapping_add(v1)),
+            Instr::I64Sub => self.apply_binop_i64(|v0, v1| v0.wrapping_sub(v1)),
+            Instr::I64Mul => self.apply_binop_i64(|v0, v1| v0.wrapping_mul(v1)),
+            Instr::I64DivS => self.apply_binop_i64(|v0, v1| v0.wrapping_div(v1)), // TODO: wrapping?
+            Instr::I64DivU => self.apply_binop_u64(|v0, v1| v0.wrapping_div(v1)), // TODO: wrapping?
+            Instr::I64RemS => self.apply_binop_i64(|v0, v1| v0.wrapping_rem(v1)), // TODO: wrapping?
+            Instr::I64RemU => self.apply_binop_u64(|v0, v1| v0.wrapping_rem(v1)), // TODO: wrapping?
+            Instr::I64And => self.apply_binop_i64(|v0, v1| v0 & v1),
+            Instr::I64Or => self.apply_binop_i64(|v0, v1| v0 | v1),
+            Instr::I64Xor => self.apply_binop_i64(|v0, v1| v0 ^ v1),
+            Instr::I64Shl => self.apply_binop_i64(|v0, v1| v0 << (v1 & 63)),
+            Instr::I64ShrS => self.apply_binop_i64(|v0, v1| v0 >> (v1 & 63)),
+            Instr::I64ShrU => self.apply_binop_u64(|v0, v1| v0 >> (v1 & 63)),
+            // `rotate_*()` take the count modulo 64, which truncating it to `u32` doesn't change.
+            Instr::I64Rotl => self.apply_binop_i64(|v0, v1| v0.rotate_left(v1 as u32)),
+            Instr::I64Rotr => self.apply_binop_i64(|v0, v1| v0.rotate_right(v1 as u32)),
+            Instr::F32Abs => self.apply_unop_f32::<FLUSH_SUBNORMALS, _>(|v| v.abs()),
+            Instr::F32Neg => self.apply_unop_f32::<FLUSH_SUBNORMALS, _>(|v| -v),
+            Instr::F32Ceil => self.apply_unop_f32::<FLUSH_SUBNORMALS, _>(|v| v.ceil()),
+            Instr::F32Floor => self.apply_unop_f32::<FLUSH_SUBNORMALS, _>(|v| v.floor()),
+            Instr::F32Trunc => self.apply_unop_f32::<FLUSH_SUBNORMALS, _>(|v| v.trunc()),
+            Instr::F32Nearest => self.apply_unop_f32::<FLUSH_SUBNORMALS, _>(|v| v.round()), // TODO: round?
+            Instr::F32Sqrt => self.apply_unop_f32::<FLUSH_SUBNORMALS, _>(|v| v.sqrt()),
+            Instr::F32Add => self.apply_binop_f32::<FLUSH_SUBNORMALS, _>(|v0, v1| v0 + v1),
+            Instr::F32Sub => self.apply_binop_f32::<FLUSH_SUBNORMALS, _>(|v0, v1| v0 - v1),
+            Instr::F32Mul => self.apply_binop_f32::<FLUSH_SUBNORMALS, _>(|v0, v1| v0 * v1),
+            Instr::F32Div => self.apply_binop_f32::<FLUSH_SUBNORMALS, _>(|v0, v1| v0 / v1),
+            Instr::F32Min => self.apply_binop_f32::<FLUSH_SUBNORMALS, _>(|v0, v1| v0.min(v1)),
+            Instr::F32Max => self.apply_binop_f32::<FLUSH_SUBNORMALS, _>(|v0, v1| v0.max(v1)),
+            Instr::F32Copysign => {
+                self.apply_binop_f32::<FLUSH_SUBNORMALS, _>(|v0, v1| v0.copysign(v1))
+            }
+            Instr::F64Abs => self.apply_unop_f64::<FLUSH_SUBNORMALS, _>(|v| v.abs()),
+            Instr::F64Neg => self.apply_unop_f64::<FLUSH_SUBNORMALS, _>(|v| -v),
+            Instr::F64Ceil => self.apply_unop_f64::<FLUSH_SUBNORMALS, _>(|v| v.ceil()),
+            Instr::F64Floor => self.apply_unop_f64::<FLUSH_SUBNORMALS, _>(|v| v.floor()),
+            Instr::F64Trunc => self.apply_unop_f64::<FLUSH_SUBNORMALS, _>(|v| v.trunc()),
+            Instr::F64Nearest => self.apply_unop_f64::<FLUSH_SUBNORMALS, _>(|v| v.round()), // TODO: round?
+            Instr::F64Sqrt => self.apply_unop_f64::<FLUSH_SUBNORMALS, _>(|v| v.sqrt()),
+            Instr::F64Add => self.apply_binop_f64::<FLUSH_SUBNORMALS, _>(|v0, v1| v0 + v1),
+            Instr::F64Sub => self.apply_binop_f64::<FLUSH_SUBNORMALS, _>(|v0, v1| v0 - v1),
+            Instr::F64Mul => self.apply_binop_f64::<FLUSH_SUBNORMALS, _>(|v0, v1| v0 * v1),
+            Instr::F64Div => self.apply_binop_f64::<FLUSH_SUBNORMALS, _>(|v0, v1| v0 / v1),
+            Instr::F64Min => self.apply_binop_f64::<FLUSH_SUBNORMALS, _>(|v0, v1| v0.min(v1)),
+            Instr::F64Max => self.apply_binop_f64::<FLUSH_SUBNORMALS, _>(|v0, v1| v0.max(v1)),
+            Instr::F64Copysign => {
+                self.apply_binop_f64::<FLUSH_SUBNORMALS, _>(|v0, v1| v0.copysign(v1))
+            }
+            Instr::I32WrapI64 => self.convert_from_i64(|v| Val::I32(v as i32)),
+            Instr::I32TruncF32S => self.convert_from_f32(|v| Val::I32(v.trunc() as i32)), // TODO: NaN, etc
+            Instr::I32TruncF32U => self.convert_from_f32(|v| Val::I32(v.trunc() as i32)), // TODO: NaN, etc
+            Instr::I32TruncF64S => self.convert_from_f64(|v| Val::I32(v.trunc() as i32)), // TODO: NaN, etc
+            Instr::I32TruncF64U => self.convert_from_f64(|v| Val::I32(v.trunc() as i32)), // TODO: NaN, etc
+            Instr::I64ExtendI32S => self.convert_from_i32(|v| Val::I64(v as i64)),
+            Instr::I64ExtendI32U => self.convert_from_i32(|v| Val::I64(v as u32 as i64)),
+            Instr::I64TruncF32S => self.convert_from_f32(|v| Val::I64(v.trunc() as i64)), // TODO: NaN, etc
+            Instr::I64TruncF32U => self.convert_from_f32(|v| Val::I64(v.trunc() as i64)), // TODO: NaN, etc
+            Instr::I64TruncF64S => self.convert_from_f64(|v| Val::I64(v.trunc() as i64)), // TODO: NaN, etc
+            Instr::I64TruncF64U => self.convert_from_f64(|v| Val::I64(v.trunc() as i64)), // TODO: NaN, etc
+            Instr::F32ConvertI32S => self.convert_from_i32(|v| Val::F32(v as f32)),       // TODO
+            Instr::F32ConvertI32U => self.convert_from_i32(|v| Val::F32(v as u32 as f32)), // TODO
+            Instr::F32ConvertI64S => self.convert_from_i64(|v| Val::F32(v as f32)),       // TODO
+            Instr::F32ConvertI64U => self.convert_from_i64(|v| Val::F32(v as u64 as f32)), // TODO
+            Instr::F32DemoteF64 => self.convert_from_f64(|v| Val::F32(v as f32)),         // TODO
+            Instr::F64ConvertI32S => self.convert_from_i32(|v| Val::F64(v as f64)),       // TODO
+            Instr::F64ConvertI32U => self.convert_from_i32(|v| Val::F64(v as u32 as f64)), // TODO
+            Instr::F64ConvertI64S => self.convert_from_i64(|v| Val::F64(v as f64)),       // TODO
+            Instr::F64ConvertI64U => self.convert_from_i64(|v| Val::F64(v as u64 as f64)), // TODO
+            Instr::F64PromoteF32 => self.convert_from_f32(|v| Val::F64(v as f64)),
+            Instr::I32ReinterpretF32 => self.convert_from_f32(|v| Val::I32(v.to_bits() as i32)),
+            Instr::I64ReinterpretF64 => self.convert_from_f64(|v| Val::I64(v.to_bits() as i64)),
+            Instr::F32ReinterpretI32 => {
+                self.convert_from_i32(|v| Val::F32(f32::from_bits(v as u32)))
+            }
+            Instr::F64ReinterpretI64 => {
+                self.convert_from_i64(|v| Val::F64(f64::from_bits(v as u64)))
+            }
+
+            // Sign Extension
+            #[cfg(feature = "sign_extension")]
+            Instr::SignExtension(instr) => match instr {
+                crate::sign_extension::SignExtensionInstr::I32Extend8S => {
+                    self.convert_from_i32(|v| Val::I32(v as i8 as i32))
                 }
-                Instr::I64Load16S(arg) => {
-                    // TODO: handle alignment
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + 2;
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    let v = i16::from_le_bytes(self.mem[start..end].try_into().unwrap()); // TODO
-                    self.values.push(Val::I64(v as i64));
+                crate::sign_extension::SignExtensionInstr::I32Extend16S => {
+                    self.convert_from_i32(|v| Val::I32(v as i16 as i32))
                 }
-                Instr::I64Load16U(arg) => {
-                    // TODO: handle alignment
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + 2;
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    let v = u16::from_le_bytes(self.mem[start..end].try_into().unwrap()); // TODO
-                    self.values.push(Val::I64(v as i64));
+                crate::sign_extension::SignExtensionInstr::I64Extend8S => {
+                    self.convert_from_i64(|v| Val::I64(v as i8 as i64))
                 }
-                Instr::I64Load32S(arg) => {
-                    // TODO: handle alignment
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + 4;
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    let v = i32::from_le_bytes(self.mem[start..end].try_into().unwrap()); // TODO
-                    self.values.push(Val::I64(v as i64));
+                crate::sign_extension::SignExtensionInstr::I64Extend16S => {
+                    self.convert_from_i64(|v| Val::I64(v as i16 as i64))
                 }
-                Instr::I64Load32U(arg) => {
-                    // TODO: handle alignment
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + 4;
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    let v = u32::from_le_bytes(self.mem[start..end].try_into().unwrap()); // TODO
-                    self.values.push(Val::I64(v as i64));
+                crate::sign_extension::SignExtensionInstr::I64Extend32S => {
+                    self.convert_from_i64(|v| Val::I64(v as i32 as i64))
                 }
-                Instr::I32Store(arg) => {
-                    // TODO: handle alignment
-                    let v = self.pop_value();
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + v.byte_size();
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
+            },
+
+            // Bulk Memory Instructions
+            #[cfg(feature = "bulk_memory")]
+            Instr::BulkMemory(instr) => match instr {
+                crate::bulk_memory::BulkMemoryInstr::MemoryInit(idx) => {
+                    let n = self.pop_value_i32() as u32 as usize;
+                    let s = self.pop_value_i32() as u32 as usize;
+                    let d = self.pop_value_i32() as u32 as usize;
+                    let data = module
+                        .datas()
+                        .get(idx.get())
+                        .ok_or(ExecuteError::InvalidDataidx)?;
+                    let init: &[u8] = if self.dropped_datas[idx.get()] {
+                        &[]
+                    } else {
+                        &data.init
+                    };
+                    if s.saturating_add(n) > init.len() {
+                        return Err(ExecuteError::Trap(TrapKind::MemoryOutOfBounds));
                     }
-                    v.copy_to(&mut self.mem[start..end]);
+                    self.check_mem_range(d, n, true)?;
+                    self.mem.write(d, &init[s..s + n]);
                 }
-                Instr::I64Store(arg) => {
-                    // TODO: handle alignment
-                    let v = self.pop_value();
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + v.byte_size();
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    v.copy_to(&mut self.mem[start..end]);
+                crate::bulk_memory::BulkMemoryInstr::DataDrop(idx) => {
+                    *self
+                        .dropped_datas
+                        .get_mut(idx.get())
+                        .ok_or(ExecuteError::InvalidDataidx)? = true;
                 }
-                Instr::F32Store(arg) => {
-                    // TODO: handle alignment
-                    let v = self.pop_value();
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + v.byte_size();
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    v.copy_to(&mut self.mem[start..end]);
+                crate::bulk_memory::BulkMemoryInstr::MemoryCopy => {
+                    let n = self.pop_value_u32() as usize;
+                    let s = self.pop_value_u32() as usize;
+                    let d = self.pop_value_u32() as usize;
+                    self.copy_mem(d, s, n)?;
                 }
-                Instr::F64Store(arg) => {
-                    // TODO: handle alignment
-                    let v = self.pop_value();
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + v.byte_size();
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    v.copy_to(&mut self.mem[start..end]);
+                crate::bulk_memory::BulkMemoryInstr::MemoryFill => {
+                    let n = self.pop_value_u32() as usize;
+                    let v = self.pop_value_i32() as u8;
+                    let d = self.pop_value_u32() as usize;
+                    self.fill_mem(d, v, n)?;
                 }
-                Instr::I32Store8(arg) => {
-                    // TODO: handle alignment
-                    let v = self.pop_value();
-                    let i = self.pop_value_i32();
-                    let i = (i + arg.offset as i32) as usize;
-                    if self.mem.len() < i {
-                        return Err(ExecuteError::Trapped);
+                crate::bulk_memory::BulkMemoryInstr::TableInit(idx) => {
+                    let n = self.pop_value_i32() as u32 as usize;
+                    let s = self.pop_value_i32() as u32 as usize;
+                    let d = self.pop_value_i32() as u32 as usize;
+                    let elem = module
+                        .elems()
+                        .get(idx.get())
+                        .ok_or(ExecuteError::InvalidElemidx)?;
+                    let init: &[Option<Funcidx>] = if self.dropped_elems[idx.get()] {
+                        &[]
+                    } else {
+                        &elem.init
+                    };
+                    if s.saturating_add(n) > init.len() || d.saturating_add(n) > self.table.len() {
+                        return Err(ExecuteError::Trap(TrapKind::TableOutOfBounds));
                     }
-                    let v = v.as_i32().ok_or(ExecuteError::Trapped)? as u8; // TODO:
-                    self.mem[i] = v;
-                }
-                Instr::I32Store16(arg) => {
-                    // TODO: handle alignment
-                    let v = self.pop_value();
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + 2;
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
+                    for (elem, funcidx) in self.table[d..d + n].iter_mut().zip(&init[s..s + n]) {
+                        *elem = funcidx.map(FuncRef::Func);
                     }
-
-                    let v = v.as_i32().ok_or(ExecuteError::Trapped)? as i16; // TODO:
-                    self.mem[start..end].copy_from_slice(&v.to_le_bytes());
                 }
-                Instr::I64Store8(arg) => {
-                    // TODO: handle alignment
-                    let v = self.pop_value();
-                    let i = self.pop_value_i32();
-                    let i = (i + arg.offset as i32) as usize;
-                    if self.mem.len() < i {
-                        return Err(ExecuteError::Trapped);
-                    }
-                    let v = v.as_i64().ok_or(ExecuteError::Trapped)? as u8; // TODO:
-                    self.mem[i] = v;
+                crate::bulk_memory::BulkMemoryInstr::ElemDrop(idx) => {
+                    *self
+                        .dropped_elems
+                        .get_mut(idx.get())
+                        .ok_or(ExecuteError::InvalidElemidx)? = true;
                 }
-                Instr::I64Store16(arg) => {
-                    // TODO: handle alignment
-                    let v = self.pop_value();
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + 2;
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
+                crate::bulk_memory::BulkMemoryInstr::TableCopy => {
+                    let n = self.pop_value_u32() as usize;
+                    let s = self.pop_value_u32() as usize;
+                    let d = self.pop_value_u32() as usize;
+                    let len = self.table.len();
+                    if s.saturating_add(n) > len || d.saturating_add(n) > len {
+                        return Err(ExecuteError::Trap(TrapKind::TableOutOfBounds));
                     }
-
-                    let v = v.as_i64().ok_or(ExecuteError::Trapped)? as i16; // TODO:
-                    self.mem[start..end].copy_from_slice(&v.to_le_bytes());
+                    self.table.copy_within(s..s + n, d);
                 }
-                Instr::I64Store32(arg) => {
-                    // TODO: handle alignment
-                    let v = self.pop_value();
-                    let i = self.pop_value_i32();
-                    let start = (i + arg.offset as i32) as usize;
-                    let end = start + 4;
-                    if self.mem.len() < end {
-                        return Err(ExecuteError::Trapped);
-                    }
+            },
 
-                    let v = v.as_i64().ok_or(ExecuteError::Trapped)? as i32; // TODO:
-                    self.mem[start..end].copy_from_slice(&v.to_le_bytes());
-                }
-                Instr::MemorySize => {
-                    let size = self.mem.len() / PAGE_SIZE;
-                    self.push_value(Val::I32(size as i32));
-                }
-                Instr::MemoryGrow => {
-                    let delta = self.pop_value_i32();
-                    let max = module.mem().and_then(|m| m.limits.max).unwrap_or(u32::MAX);
-                    let current = self.mem.len() / PAGE_SIZE;
-                    let new = current + delta as usize;
-                    if new <= max as usize {
-                        // TODO: use resize()
-                        for _ in 0..delta as usize * PAGE_SIZE {
-                            self.mem.push(0);
-                        }
-                        self.push_value(Val::I32(current as i32));
-                    } else {
-                        self.push_value(Val::I32(-1));
-                    };
-                }
+            // GC Instructions
+            #[cfg(feature = "gc")]
+            Instr::Gc(_) => return Err(ExecuteError::UnsupportedInstruction),
 
-                // Numeric Instructions
-                Instr::I32Const(v) => self.push_value(Val::I32(*v)),
-                Instr::I64Const(v) => self.push_value(Val::I64(*v)),
-                Instr::F32Const(v) => self.push_value(Val::F32(*v)),
-                Instr::F64Const(v) => self.push_value(Val::F64(*v)),
-                Instr::I32Eqz => self.apply_unop_cmp_i32(|v| v == 0),
-                Instr::I32Eq => self.apply_binop_cmp_i32(|v0, v1| v0 == v1),
-                Instr::I32Ne => self.apply_binop_cmp_i32(|v0, v1| v0 != v1),
-                Instr::I32LtS => self.apply_binop_cmp_i32(|v0, v1| v0 < v1),
-                Instr::I32LtU => self.apply_binop_cmp_u32(|v0, v1| v0 < v1),
-                Instr::I32GtS => self.apply_binop_cmp_i32(|v0, v1| v0 > v1),
-                Instr::I32GtU => self.apply_binop_cmp_u32(|v0, v1| v0 > v1),
-                Instr::I32LeS => self.apply_binop_cmp_i32(|v0, v1| v0 <= v1),
-                Instr::I32LeU => self.apply_binop_cmp_u32(|v0, v1| v0 <= v1),
-                Instr::I32GeS => self.apply_binop_cmp_i32(|v0, v1| v0 >= v1),
-                Instr::I32GeU => self.apply_binop_cmp_u32(|v0, v1| v0 >= v1),
-                Instr::I64Eqz => self.apply_unop_cmp_i64(|v| v == 0),
-                Instr::I64Eq => self.apply_binop_cmp_i64(|v0, v1| v0 == v1),
-                Instr::I64Ne => self.apply_binop_cmp_i64(|v0, v1| v0 != v1),
-                Instr::I64LtS => self.apply_binop_cmp_i64(|v0, v1| v0 < v1),
-                Instr::I64LtU => self.apply_binop_cmp_u64(|v0, v1| v0 < v1),
-                Instr::I64GtS => self.apply_binop_cmp_i64(|v0, v1| v0 > v1),
-                Instr::I64GtU => self.apply_binop_cmp_u64(|v0, v1| v0 > v1),
-                Instr::I64LeS => self.apply_binop_cmp_i64(|v0, v1| v0 <= v1),
-                Instr::I64LeU => self.apply_binop_cmp_u64(|v0, v1| v0 <= v1),
-                Instr::I64GeS => self.apply_binop_cmp_i64(|v0, v1| v0 >= v1),
-                Instr::I64GeU => self.apply_binop_cmp_u64(|v0, v1| v0 >= v1),
-                Instr::F32Eq => self.apply_binop_cmp_f32(|v0, v1| v0 == v1),
-                Instr::F32Ne => self.apply_binop_cmp_f32(|v0, v1| v0 != v1),
-                Instr::F32Lt => self.apply_binop_cmp_f32(|v0, v1| v0 < v1),
-                Instr::F32Gt => self.apply_binop_cmp_f32(|v0, v1| v0 > v1),
-                Instr::F32Le => self.apply_binop_cmp_f32(|v0, v1| v0 <= v1),
-                Instr::F32Ge => self.apply_binop_cmp_f32(|v0, v1| v0 >= v1),
-                Instr::F64Eq => self.apply_binop_cmp_f64(|v0, v1| v0 == v1),
-                Instr::F64Ne => self.apply_binop_cmp_f64(|v0, v1| v0 != v1),
-                Instr::F64Lt => self.apply_binop_cmp_f64(|v0, v1| v0 < v1),
-                Instr::F64Gt => self.apply_binop_cmp_f64(|v0, v1| v0 > v1),
-                Instr::F64Le => self.apply_binop_cmp_f64(|v0, v1| v0 <= v1),
-                Instr::F64Ge => self.apply_binop_cmp_f64(|v0, v1| v0 >= v1),
-                Instr::I32Clz => self.apply_unop_i32(|v| v.leading_zeros() as i32),
-                Instr::I32Ctz => self.apply_unop_i32(|v| v.trailing_zeros() as i32),
-                Instr::I32Popcnt => self.apply_unop_i32(|v| v.count_ones() as i32),
-                Instr::I32Add => self.apply_binop_i32(|v0, v1| v0 + v1),
-                Instr::I32Sub => self.apply_binop_i32(|v0, v1| v0 - v1),
-                Instr::I32Mul => self.apply_binop_i32(|v0, v1| v0 * v1),
-                Instr::I32DivS => self.apply_binop_i32(|v0, v1| v0.wrapping_div(v1)), // TODO: wrapping?
-                Instr::I32DivU => self.apply_binop_u32(|v0, v1| v0.wrapping_div(v1)), // TODO: wrapping?
-                Instr::I32RemS => self.apply_binop_i32(|v0, v1| v0.wrapping_rem(v1)), // TODO: wrapping?
-                Instr::I32RemU => self.apply_binop_u32(|v0, v1| v0.wrapping_rem(v1)), // TODO: wrapping?
-                Instr::I32And => self.apply_binop_i32(|v0, v1| v0 & v1),
-                Instr::I32Or => self.apply_binop_i32(|v0, v1| v0 | v1),
-                Instr::I32Xor => self.apply_binop_i32(|v0, v1| v0 ^ v1),
-                Instr::I32Shl => self.apply_binop_i32(|v0, v1| v0.wrapping_shl(v1 as u32)), // TODO: wrapping?
-                Instr::I32ShrS => self.apply_binop_i32(|v0, v1| v0.wrapping_shr(v1 as u32)), // TODO: wrapping?
-                Instr::I32ShrU => self.apply_binop_u32(|v0, v1| v0.wrapping_shr(v1)), // TODO: wrapping?
-                Instr::I32Rotl => self.apply_binop_i32(|v0, v1| v0.rotate_left(v1 as u32)),
-                Instr::I32Rotr => self.apply_binop_i32(|v0, v1| v0.rotate_right(v1 as u32)),
-                Instr::I64Clz => self.apply_unop_i64(|v| v.leading_zeros() as i64),
-                Instr::I64Ctz => self.apply_unop_i64(|v| v.trailing_zeros() as i64),
-                Instr::I64Popcnt => self.apply_unop_i64(|v| v.count_ones() as i64),
-                Instr::I64Add => self.apply_binop_i64(|v0, v1| v0 + v1),
-                Instr::I64Sub => self.apply_binop_i64(|v0, v1| v0 - v1),
-                Instr::I64Mul => self.apply_binop_i64(|v0, v1| v0 * v1),
-                Instr::I64DivS => self.apply_binop_i64(|v0, v1| v0.wrapping_div(v1)), // TODO: wrapping?
-                Instr::I64DivU => self.apply_binop_u64(|v0, v1| v0.wrapping_div(v1)), // TODO: wrapping?
-                Instr::I64RemS => self.apply_binop_i64(|v0, v1| v0.wrapping_rem(v1)), // TODO: wrapping?
-                Instr::I64RemU => self.apply_binop_u64(|v0, v1| v0.wrapping_rem(v1)), // TODO: wrapping?
-                Instr::I64And => self.apply_binop_i64(|v0, v1| v0 & v1),
-                Instr::I64Or => self.apply_binop_i64(|v0, v1| v0 | v1),
-                Instr::I64Xor => self.apply_binop_i64(|v0, v1| v0 ^ v1),
-                Instr::I64Shl => self.apply_binop_i64(|v0, v1| v0.wrapping_shl(v1 as u32)), // TODO: wrapping?
-                Instr::I64ShrS => self.apply_binop_i64(|v0, v1| v0.wrapping_shr(v1 as u32)), // TODO: wrapping?
-                Instr::I64ShrU => self.apply_binop_u64(|v0, v1| v0.wrapping_shr(v1 as u32)), // TODO: wrapping?
-                Instr::I64Rotl => self.apply_binop_i64(|v0, v1| v0.rotate_left(v1 as u32)),
-                Instr::I64Rotr => self.apply_binop_i64(|v0, v1| v0.rotate_right(v1 as u32)),
-                Instr::F32Abs => self.apply_unop_f32(|v| v.abs()),
-                Instr::F32Neg => self.apply_unop_f32(|v| -v),
-                Instr::F32Ceil => self.apply_unop_f32(|v| v.ceil()),
-                Instr::F32Floor => self.apply_unop_f32(|v| v.floor()),
-                Instr::F32Trunc => self.apply_unop_f32(|v| v.trunc()),
-                Instr::F32Nearest => self.apply_unop_f32(|v| v.round()), // TODO: round?
-                Instr::F32Sqrt => self.apply_unop_f32(|v| v.sqrt()),
-                Instr::F32Add => self.apply_binop_f32(|v0, v1| v0 + v1),
-                Instr::F32Sub => self.apply_binop_f32(|v0, v1| v0 - v1),
-                Instr::F32Mul => self.apply_binop_f32(|v0, v1| v0 * v1),
-                Instr::F32Div => self.apply_binop_f32(|v0, v1| v0 / v1),
-                Instr::F32Min => self.apply_binop_f32(|v0, v1| v0.min(v1)),
-                Instr::F32Max => self.apply_binop_f32(|v0, v1| v0.max(v1)),
-                Instr::F32Copysign => self.apply_binop_f32(|v0, v1| v0.copysign(v1)),
-                Instr::F64Abs => self.apply_unop_f64(|v| v.abs()),
-                Instr::F64Neg => self.apply_unop_f64(|v| -v),
-                Instr::F64Ceil => self.apply_unop_f64(|v| v.ceil()),
-                Instr::F64Floor => self.apply_unop_f64(|v| v.floor()),
-                Instr::F64Trunc => self.apply_unop_f64(|v| v.trunc()),
-                Instr::F64Nearest => self.apply_unop_f64(|v| v.round()), // TODO: round?
-                Instr::F64Sqrt => self.apply_unop_f64(|v| v.sqrt()),
-                Instr::F64Add => self.apply_binop_f64(|v0, v1| v0 + v1),
-                Instr::F64Sub => self.apply_binop_f64(|v0, v1| v0 - v1),
-                Instr::F64Mul => self.apply_binop_f64(|v0, v1| v0 * v1),
-                Instr::F64Div => self.apply_binop_f64(|v0, v1| v0 / v1),
-                Instr::F64Min => self.apply_binop_f64(|v0, v1| v0.min(v1)),
-                Instr::F64Max => self.apply_binop_f64(|v0, v1| v0.max(v1)),
-                Instr::F64Copysign => self.apply_binop_f64(|v0, v1| v0.copysign(v1)),
-                Instr::I32WrapI64 => self.convert_from_i64(|v| Val::I32(v as i32)),
-                Instr::I32TruncF32S => self.convert_from_f32(|v| Val::I32(v.trunc() as i32)), // TODO: NaN, etc
-                Instr::I32TruncF32U => self.convert_from_f32(|v| Val::I32(v.trunc() as i32)), // TODO: NaN, etc
-                Instr::I32TruncF64S => self.convert_from_f64(|v| Val::I32(v.trunc() as i32)), // TODO: NaN, etc
-                Instr::I32TruncF64U => self.convert_from_f64(|v| Val::I32(v.trunc() as i32)), // TODO: NaN, etc
-                Instr::I64ExtendI32S => self.convert_from_i32(|v| Val::I64(v as i64)),
-                Instr::I64ExtendI32U => self.convert_from_i32(|v| Val::I64(v as u32 as i64)),
-                Instr::I64TruncF32S => self.convert_from_f32(|v| Val::I64(v.trunc() as i64)), // TODO: NaN, etc
-                Instr::I64TruncF32U => self.convert_from_f32(|v| Val::I64(v.trunc() as i64)), // TODO: NaN, etc
-                Instr::I64TruncF64S => self.convert_from_f64(|v| Val::I64(v.trunc() as i64)), // TODO: NaN, etc
-                Instr::I64TruncF64U => self.convert_from_f64(|v| Val::I64(v.trunc() as i64)), // TODO: NaN, etc
-                Instr::F32ConvertI32S => self.convert_from_i32(|v| Val::F32(v as f32)), // TODO
-                Instr::F32ConvertI32U => self.convert_from_i32(|v| Val::F32(v as u32 as f32)), // TODO
-                Instr::F32ConvertI64S => self.convert_from_i64(|v| Val::F32(v as f32)), // TODO
-                Instr::F32ConvertI64U => self.convert_from_i64(|v| Val::F32(v as u64 as f32)), // TODO
-                Instr::F32DemoteF64 => self.convert_from_f64(|v| Val::F32(v as f32)), // TODO
-                Instr::F64ConvertI32S => self.convert_from_i32(|v| Val::F64(v as f64)), // TODO
-                Instr::F64ConvertI32U => self.convert_from_i32(|v| Val::F64(v as u32 as f64)), // TODO
-                Instr::F64ConvertI64S => self.convert_from_i64(|v| Val::F64(v as f64)), // TODO
-                Instr::F64ConvertI64U => self.convert_from_i64(|v| Val::F64(v as u64 as f64)), // TODO
-                Instr::F64PromoteF32 => self.convert_from_f32(|v| Val::F64(v as f64)),
-                Instr::I32ReinterpretF32 => self.convert_from_f32(|v| Val::I32(v.to_bits() as i32)),
-                Instr::I64ReinterpretF64 => self.convert_from_f64(|v| Val::I64(v.to_bits() as i64)),
-                Instr::F32ReinterpretI32 => {
-                    self.convert_from_i32(|v| Val::F32(f32::from_bits(v as u32)))
-                }
-                Instr::F64ReinterpretI64 => {
-                    self.convert_from_i64(|v| Val::F64(f64::from_bits(v as u64)))
-                }
+            // Embedder-defined Instructions
+            #[cfg(feature = "extension")]
+            Instr::Extension(instr) => self.execute_extension(instr, module)?,
+        }
+        Ok(())
+    }
 
-                // Sign Extension
-                #[cfg(feature = "sign_extension")]
-                Instr::SignExtension(instr) => match instr {
-                    crate::sign_extension::SignExtensionInstr::I32Extend8S => {
-                        self.convert_from_i32(|v| Val::I32(v as i8 as i32))
-                    }
-                    crate::sign_extension::SignExtensionInstr::I32Extend16S => {
-                        self.convert_from_i32(|v| Val::I32(v as i16 as i32))
-                    }
-                    crate::sign_extension::SignExtensionInstr::I64Extend8S => {
-                        self.convert_from_i64(|v| Val::I64(v as i8 as i64))
-                    }
-                    crate::sign_extension::SignExtensionInstr::I64Extend16S => {
-                        self.convert_from_i64(|v| Val::I64(v as i16 as i64))
-                    }
-                    crate::sign_extension::SignExtensionInstr::I64Extend32S => {
-                        self.convert_from_i64(|v| Val::I64(v as i32 as i64))
-                    }
-                },
-            }
+    #[cfg(feature = "extension")]
+    fn execute_extension(
+        &mut self,
+        instr: &crate::extension::ExtensionInstr,
+        module: &Module<V>,
+    ) -> Result<(), ExecuteError> {
+        let extension = self
+            .config
+            .extension
+            .ok_or(ExecuteError::UnregisteredExtension)?;
+        let frame = self.current_frame();
+        let labels = &self.labels;
+        let caller = || Some(frame_position(&frame, labels, module));
+        let args_start = self.values.len() - instr.params.len();
+        let mut env = Env {
+            mem: EnvMem {
+                mem: &mut self.mem,
+                max_pages: self.mem_max_pages,
+                grown: &mut self.stats.mem_grown,
+            },
+            globals: &mut self.globals,
+            aborted: false,
+            caller: &caller,
+            caller_locals: &self.locals[frame.locals_start..],
+            call_depth: self.frames.len(),
+        };
+        let value = (extension.execute)(instr, &self.values[args_start..], &mut env);
+        if env.aborted {
+            return Err(ExecuteError::Trap(TrapKind::Aborted));
         }
-        Ok(None)
+        if value.map(|v| v.ty()) != instr.result {
+            return Err(ExecuteError::InvalidReturnValue);
+        }
+        self.values.truncate(args_start);
+        if let Some(v) = value {
+            self.values.push(v);
+        }
+        Ok(())
     }
 
     fn convert_from_i32<F>(&mut self, f: F)
@@ -838,38 +1489,56 @@ impl<V: VectorFactory> Executor<V> {
         self.push_value(f(v));
     }
 
-    fn apply_unop_f32<F>(&mut self, f: F)
+    fn flush_subnormal_f32<const FLUSH_SUBNORMALS: bool>(v: f32) -> f32 {
+        if FLUSH_SUBNORMALS && v.is_subnormal() {
+            f32::from_bits(v.to_bits() & 0x8000_0000)
+        } else {
+            v
+        }
+    }
+
+    fn flush_subnormal_f64<const FLUSH_SUBNORMALS: bool>(v: f64) -> f64 {
+        if FLUSH_SUBNORMALS && v.is_subnormal() {
+            f64::from_bits(v.to_bits() & 0x8000_0000_0000_0000)
+        } else {
+            v
+        }
+    }
+
+    fn apply_unop_f32<const FLUSH_SUBNORMALS: bool, F>(&mut self, f: F)
     where
         F: FnOnce(f32) -> f32,
     {
-        let v = self.pop_value_f32();
-        self.push_value(Val::F32(f(v)));
+        let v = f(self.pop_value_f32());
+        self.push_value(Val::F32(Self::flush_subnormal_f32::<FLUSH_SUBNORMALS>(v)));
     }
 
-    fn apply_binop_f32<F>(&mut self, f: F)
+    fn apply_binop_f32<const FLUSH_SUBNORMALS: bool, F>(&mut self, f: F)
     where
         F: FnOnce(f32, f32) -> f32,
     {
         let v0 = self.pop_value_f32();
         let v1 = self.pop_value_f32();
-        self.push_value(Val::F32(f(v1, v0)));
+        let v = f(v1, v0);
+        self.push_value(Val::F32(Self::flush_subnormal_f32::<FLUSH_SUBNORMALS>(v)));
     }
 
-    fn apply_unop_f64<F>(&mut self, f: F)
+    fn apply_unop_f64<const FLUSH_SUBNORMALS: bool, F>(&mut self, f: F)
     where
         F: FnOnce(f64) -> f64,
     {
-        let v = self.pop_value_f64();
-        self.push_value(Val::F64(f(v)));
+        let v = f(self.pop_value_f64());
+        self.push_value(Val::F64(Self::flush_subnormal_f64::<FLUSH_SUBNORMALS>(v)));
     }
 
-    fn apply_binop_f64<F>(&mut self, f: F)
+    fn apply_binop_f64<const FLUSH_SUBNORMALS: bool, F>(&mut self, f: F)
     where
         F: FnOnce(f64, f64) -> f64,
     {
         let v0 = self.pop_value_f64();
         let v1 = self.pop_value_f64();
-        self.push_value(Val::F64(f(v1, v0)));
+        let v = f(v1, v0);
+        self.push_value(Val::F64(Self::flush_subnormal_f64::<FLUSH_SUBNORMALS>(v)));
     }
 
     fn apply_unop_i32<F>(&mut self, f: F)
@@ -995,25 +1664,240 @@ impl<V: VectorFactory> Executor<V> {
     }
 }
 
-impl<V: VectorFactory> Debug for Executor<V> {
+// Read-only view of the execution state of an instance.
+//
+// This is the stable public facade of the executor, whose internal layout is not exposed.
+pub struct Runtime<'a, V: VectorFactory, M, S: StackStorage = StackBuffers<'static>> {
+    executor: &'a Executor<V, M, S>,
+}
+
+impl<'a, V: VectorFactory, M, S: StackStorage> Runtime<'a, V, M, S> {
+    pub(crate) fn new(executor: &'a Executor<V, M, S>) -> Self {
+        Self { executor }
+    }
+
+    pub fn mem(&self) -> &'a M {
+        &self.executor.mem
+    }
+
+    pub fn globals(&self) -> &'a [GlobalVal] {
+        &self.executor.globals
+    }
+
+    pub fn table(&self) -> &'a [Option<FuncRef>] {
+        &self.executor.table
+    }
+
+    // Operand stack of the pending call, bottom first.
+    pub fn values(&self) -> &'a [Val] {
+        &self.executor.values
+    }
+
+    // Parameters and locals of the innermost frame, or `None` if no call is pending.
+    pub fn frame_locals(&self) -> Option<&'a [Val]> {
+        let frame = self.executor.frames.last()?;
+        Some(&self.executor.locals[frame.locals_start..])
+    }
+
+    // Number of frames of the pending call.
+    pub fn call_depth(&self) -> usize {
+        self.executor.frames.len()
+    }
+}
+
+impl<V: VectorFactory, M, S: StackStorage> Debug for Runtime<'_, V, M, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Runtime")
+            .field("call_depth", &self.call_depth())
+            .field("values", &self.values())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<V: VectorFactory, M: LinearMemory, S: StackStorage> Executor<V, M, S> {
+    pub(crate) fn debug_fields(&self, s: &mut DebugStruct<'_, '_>, options: DebugOptions) {
+        s.field("mem_size", &self.mem.size());
+        if options.memory_bytes > 0 {
+            s.field(
+                "mem",
+                &MemoryHead {
+                    mem: &self.mem,
+                    len: options.memory_bytes.min(self.mem.size()),
+                },
+            );
+        }
+        s.field("table_len", &self.table.len());
+        if options.globals {
+            s.field("globals", &self.globals.as_ref());
+        }
+        if options.stacks {
+            s.field("values", &self.values.len())
+                .field("locals", &self.locals.len())
+                .field("frames", &self.frames.len())
+                .field("labels", &self.labels.len())
+                .field("current_frame", &self.frames.last());
+        }
+        s.field("config", &self.config).field("stats", &self.stats);
+    }
+}
+
+impl<V: VectorFactory, M: LinearMemory, S: StackStorage> Debug for Executor<V, M, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let mut s = f.debug_struct("Executor");
+        self.debug_fields(&mut s, DebugOptions::default());
+        s.finish_non_exhaustive()
+    }
+}
+
+// Hex dump of the leading bytes of a memory.
+struct MemoryHead<'a, M> {
+    mem: &'a M,
+    len: usize,
+}
+
+impl<M: LinearMemory> Debug for MemoryHead<'_, M> {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        // TODO
-        f.debug_struct("Executor").finish_non_exhaustive()
+        let mut buf = [0; 16];
+        for offset in (0..self.len).step_by(buf.len()) {
+            let chunk = &mut buf[..(self.len - offset).min(16)];
+            self.mem.read(offset, chunk);
+            for (i, b) in chunk.iter().enumerate() {
+                if offset + i > 0 {
+                    write!(f, " ")?;
+                }
+                write!(f, "{:02x}", b)?;
+            }
+        }
+        Ok(())
     }
 }
 
 // TODO: Activation(?)
-#[derive(Debug, Default, Clone, Copy)]
+//
+// The fields are private to the executor, so this is public only as the item type of
+// `StackBuffers::frames`.
+#[derive(Debug, Clone, Copy)]
 pub struct Frame {
-    pub arity: usize,
-    pub locals_start: usize,
-    pub values_start: usize,
+    pub(crate) funcidx: Funcidx,
+    pub(crate) funcs_index: usize,
+    pub(crate) arity: usize,
+    pub(crate) locals_start: usize,
+    pub(crate) values_start: usize,
+    pub(crate) labels_start: usize,
+    pub(crate) pc: usize,
+}
+
+impl Frame {
+    // Initial value of the items of `StackBuffers::frames`.
+    pub const EMPTY: Self = Self {
+        funcidx: Funcidx::new(0),
+        funcs_index: 0,
+        arity: 0,
+        locals_start: 0,
+        values_start: 0,
+        labels_start: 0,
+        pc: 0,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LabelKind {
+    Block,
+    Loop,
+    Then,
+    Else,
+}
+
+// Same as `Frame`, this is public only as the item type of `StackBuffers::labels`.
+#[derive(Debug, Clone, Copy)]
+pub struct Label {
+    pub(crate) kind: LabelKind,
+    pub(crate) pc: usize, // Position of the block instruction in the enclosing instructions
+    pub(crate) arity: usize,
+    pub(crate) values_start: usize,
+}
+
+impl Label {
+    // Initial value of the items of `StackBuffers::labels`.
+    pub const EMPTY: Self = Self {
+        kind: LabelKind::Block,
+        pc: 0,
+        arity: 0,
+        values_start: 0,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepResult {
+    Running,
+    Breakpoint(TraceFrame),
+    Paused, // See `ExecutorConfig::pause_requested`.
+    Returned(Option<Val>),
+}
+
+fn current_instrs<'a, V: VectorFactory>(
+    instrs: &'a [Instr<V>],
+    labels: &[Label],
+) -> &'a [Instr<V>] {
+    let Some((label, labels)) = labels.split_first() else {
+        return instrs;
+    };
+    match (&instrs[label.pc], label.kind) {
+        (Instr::Block(block), _) => current_instrs(&block.instrs, labels),
+        (Instr::Loop(block), _) => current_instrs(&block.instrs, labels),
+        (Instr::If(block), LabelKind::Then) => current_instrs(&block.then_instrs, labels),
+        (Instr::If(block), _) => current_instrs(&block.else_instrs, labels),
+        _ => unreachable!(),
+    }
+}
+
+pub(crate) fn instr_count<V: VectorFactory>(instr: &Instr<V>) -> usize {
+    let children = match instr {
+        Instr::Block(block) => block.instrs.iter().map(instr_count).sum(),
+        Instr::Loop(block) => block.instrs.iter().map(instr_count).sum(),
+        Instr::If(block) => block
+            .then_instrs
+            .iter()
+            .chain(block.else_instrs.iter())
+            .map(instr_count)
+            .sum(),
+        _ => 0,
+    };
+    1 + children
 }
 
-#[derive(Debug, Default, Clone, Copy)]
-pub struct Block {
-    pub arity: usize,
-    pub values_start: usize,
+// Converts a position (the enclosing labels and `pc` in the innermost block) to
+// the pre-order index of the instruction within the function body.
+// Position of the instruction being executed in `frame`.
+fn frame_position<V: VectorFactory>(
+    frame: &Frame,
+    labels: &[Label],
+    module: &Module<V>,
+) -> TraceFrame {
+    let body = module.funcs()[frame.funcs_index].body.instrs();
+    TraceFrame {
+        funcidx: frame.funcidx,
+        instr_offset: instr_offset(body, &labels[frame.labels_start..], frame.pc - 1),
+    }
+}
+
+fn instr_offset<V: VectorFactory>(instrs: &[Instr<V>], labels: &[Label], pc: usize) -> usize {
+    let Some((label, labels)) = labels.split_first() else {
+        return instrs[..pc].iter().map(instr_count).sum();
+    };
+    let offset = instrs[..label.pc].iter().map(instr_count).sum::<usize>() + 1;
+    match (&instrs[label.pc], label.kind) {
+        (Instr::Block(block), _) => offset + instr_offset(&block.instrs, labels, pc),
+        (Instr::Loop(block), _) => offset + instr_offset(&block.instrs, labels, pc),
+        (Instr::If(block), LabelKind::Then) => {
+            offset + instr_offset(&block.then_instrs, labels, pc)
+        }
+        (Instr::If(block), _) => {
+            let then_count = block.then_instrs.iter().map(instr_count).sum::<usize>();
+            offset + then_count + instr_offset(&block.else_instrs, labels, pc)
+        }
+        _ => unreachable!(),
+    }
 }
 
 #[cfg(not(feature = "std"))]
@@ -1198,9 +2082,311 @@ impl FloatExt for f64 {
     }
 }
 
+// Same as `memory.grow` (also used by host functions via `EnvMem::grow()`).
+// `grown` accumulates the number of bytes added.
+pub(crate) fn grow_mem_pages<M: LinearMemory + ?Sized>(
+    mem: &mut M,
+    max_pages: u32,
+    delta: u32,
+    grown: &mut usize,
+) -> Option<u32> {
+    let current = (mem.size() / PAGE_SIZE) as u32;
+    let Some(new) = current.checked_add(delta).filter(|n| *n <= max_pages) else {
+        trace!("memory growth from {current} pages by {delta} pages exceeds the limit");
+        return None;
+    };
+    // `delta` is converted only after the new size is known to be addressable.
+    let additional = pages_to_bytes(new).and_then(|_| pages_to_bytes(delta));
+    let Some(additional) = additional.filter(|&n| mem.grow(n)) else {
+        trace!("memory growth from {current} pages by {delta} pages failed");
+        return None;
+    };
+    trace!("memory grown from {current} to {new} pages");
+    *grown = grown.saturating_add(additional);
+    Some(current)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{Env, FuncInst, HostFunc, Module, Resolve, StdVectorFactory, Val};
+    use crate::{
+        components::{Funcidx, Typeidx},
+        Env, ExecStats, ExecuteError, ExecutorConfig, Extern, FpMode, FuncInst, FuncRef, HostFunc,
+        LinearMemory, Module, ModuleInstance, ReservedMemory, Resolve, SharedMemory,
+        StdVectorFactory, StepResult, TraceFrame, TrapInfo, TrapKind, Val, PAGE_SIZE,
+    };
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    #[cfg(feature = "extended_const")]
+    #[test]
+    fn extended_const_test() {
+        // (module
+        //   (import "env" "base" (global i32))
+        //   (memory 1)
+        //   (global i64 (i64.sub (i64.mul (i64.const 6) (i64.const 7)) (i64.const 2)))
+        //   (data (i32.add (global.get 0) (i32.const 16)) "hi"))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 2, 13, 1, 3, 101, 110, 118, 4, 98, 97, 115, 101, 3, 127,
+            0, 5, 3, 1, 0, 1, 6, 12, 1, 126, 0, 66, 6, 66, 7, 126, 66, 2, 125, 11, 11, 11, 1, 0,
+            35, 0, 65, 16, 106, 11, 2, 104, 105,
+        ];
+
+        struct Resolver;
+        impl Resolve for Resolver {
+            type HostFunc = ();
+
+            fn resolve_global(&self, _module: &str, _name: &str) -> Option<Val> {
+                Some(Val::I32(8))
+            }
+        }
+
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let instance = module.instantiate(Resolver).expect("instantiate");
+        assert_eq!(Val::I64(40), instance.globals()[1].get());
+        assert_eq!(b"hi", &instance.mem().as_slice()[24..26]);
+    }
+
+    #[cfg(feature = "bulk_memory")]
+    #[test]
+    fn table_init_test() {
+        // (module
+        //   (type $t (func (result i32)))
+        //   (table 4 funcref)
+        //   (func $f0 (type $t) i32.const 1)
+        //   (func $f1 (type $t) i32.const 2)
+        //   (func (export "init") (param i32)
+        //     (table.init 0 (local.get 0) (i32.const 0) (i32.const 2))
+        //     (elem.drop 0))
+        //   (func (export "call") (param i32) (result i32)
+        //     (call_indirect (type $t) (local.get 0)))
+        //   (func (export "init_exprs") (param i32)
+        //     (table.init 1 (local.get 0) (i32.const 0) (i32.const 2)))
+        //   (elem func $f0 $f1)
+        //   (elem funcref (ref.func $f1) (ref.null func))
+        //   (elem declare func 3))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 14, 3, 96, 0, 1, 127, 96, 1, 127, 0, 96, 1, 127, 1,
+            127, 3, 6, 5, 0, 0, 1, 2, 1, 4, 4, 1, 112, 0, 4, 7, 28, 3, 4, 105, 110, 105, 116, 0, 2,
+            4, 99, 97, 108, 108, 0, 3, 10, 105, 110, 105, 116, 95, 101, 120, 112, 114, 115, 0, 4,
+            9, 19, 3, 1, 0, 2, 0, 1, 5, 112, 2, 210, 1, 11, 208, 112, 11, 3, 0, 1, 3, 10, 48, 5, 4,
+            0, 65, 1, 11, 4, 0, 65, 2, 11, 15, 0, 32, 0, 65, 0, 65, 2, 252, 12, 0, 0, 252, 13, 0,
+            11, 7, 0, 32, 0, 17, 0, 0, 11, 12, 0, 32, 0, 65, 0, 65, 2, 252, 12, 1, 0, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(Resolver).expect("instantiate");
+
+        instance.invoke("init", &[Val::I32(0)]).expect("invoke");
+        let val = instance.invoke("call", &[Val::I32(1)]).expect("invoke");
+        assert_eq!(Some(Val::I32(2)), val);
+
+        // The segment has been dropped.
+        let result = instance.invoke("init", &[Val::I32(0)]);
+        assert!(matches!(
+            result,
+            Err(ExecuteError::Trap(TrapKind::TableOutOfBounds))
+        ));
+
+        instance
+            .invoke("init_exprs", &[Val::I32(2)])
+            .expect("invoke");
+        let val = instance.invoke("call", &[Val::I32(2)]).expect("invoke");
+        assert_eq!(Some(Val::I32(2)), val);
+        let result = instance.invoke("call", &[Val::I32(3)]);
+        assert!(matches!(
+            result,
+            Err(ExecuteError::Trap(TrapKind::UninitializedTableElement {
+                index: 3
+            }))
+        ));
+    }
+
+    #[cfg(feature = "bulk_memory")]
+    #[test]
+    fn memory_init_test() {
+        // (module
+        //   (memory 1)
+        //   (func (export "init") (param i32)
+        //     (memory.init 0 (local.get 0) (i32.const 1) (i32.const 2))
+        //     (data.drop 0))
+        //   (data "abc")
+        //   (data (i32.const 0) "x"))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 1, 127, 0, 3, 2, 1, 0, 5, 3, 1, 0, 1, 7, 8,
+            1, 4, 105, 110, 105, 116, 0, 0, 12, 1, 2, 10, 17, 1, 15, 0, 32, 0, 65, 1, 65, 2, 252,
+            8, 0, 0, 252, 9, 0, 11, 11, 12, 2, 1, 3, 97, 98, 99, 0, 65, 0, 11, 1, 120,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        assert_eq!(Some(2), module.data_count());
+
+        let mut instance = module.instantiate(Resolver).expect("instantiate");
+        instance.invoke("init", &[Val::I32(10)]).expect("invoke");
+        assert_eq!(b"x", &instance.mem().as_slice()[..1]);
+        assert_eq!(b"bc", &instance.mem().as_slice()[10..12]);
+
+        // The segment has been dropped.
+        let result = instance.invoke("init", &[Val::I32(10)]);
+        assert!(matches!(
+            result,
+            Err(ExecuteError::Trap(TrapKind::MemoryOutOfBounds))
+        ));
+    }
+
+    #[cfg(feature = "bulk_memory")]
+    #[test]
+    fn memory_copy_fill_test() {
+        // (module
+        //   (memory 1)
+        //   (func (export "copy") (param i32 i32 i32)
+        //     (memory.copy (local.get 0) (local.get 1) (local.get 2)))
+        //   (func (export "fill") (param i32 i32 i32)
+        //     (memory.fill (local.get 0) (local.get 1) (local.get 2)))
+        //   (data (i32.const 0) "abcdef"))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 7, 1, 96, 3, 127, 127, 127, 0, 3, 3, 2, 0, 0, 5, 3, 1,
+            0, 1, 7, 15, 2, 4, 99, 111, 112, 121, 0, 0, 4, 102, 105, 108, 108, 0, 1, 10, 26, 2, 12,
+            0, 32, 0, 32, 1, 32, 2, 252, 10, 0, 0, 11, 11, 0, 32, 0, 32, 1, 32, 2, 252, 11, 0, 11,
+            11, 12, 1, 0, 65, 0, 11, 6, 97, 98, 99, 100, 101, 102,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(Resolver).expect("instantiate");
+        let mut call = |name, args: [i32; 3]| {
+            let result = instance.invoke(name, &args.map(Val::I32));
+            (result, instance.mem().as_slice().to_vec())
+        };
+
+        // Overlapping copies in both directions.
+        call("copy", [2, 0, 4]).0.expect("invoke");
+        call("copy", [10, 2, 4]).0.expect("invoke");
+        call("copy", [9, 10, 4]).0.expect("invoke");
+        let (result, mem) = call("fill", [20, 0x7a, 3]);
+        result.expect("invoke");
+        assert_eq!(b"ababcd", &mem[..6]);
+        assert_eq!(b"\0abcdd", &mem[8..14]);
+        assert_eq!(b"zzz\0", &mem[20..24]);
+
+        // Ranges larger than the internal buffer.
+        call("fill", [100, 1, 1000]).0.expect("invoke");
+        call("fill", [600, 2, 1000]).0.expect("invoke");
+        let (result, mem) = call("copy", [300, 100, 1000]);
+        result.expect("invoke");
+        assert!(mem[300..800].iter().all(|&b| b == 1));
+        assert!(mem[800..1300].iter().all(|&b| b == 2));
+        let (result, copied) = call("copy", [100, 300, 1000]);
+        result.expect("invoke");
+        assert_eq!(&mem[300..1300], &copied[100..1100]);
+
+        // Out of bounds ranges trap without writing anything.
+        let page = PAGE_SIZE as i32;
+        for (name, args) in [
+            ("copy", [page - 2, 0, 4]),
+            ("copy", [0, page - 2, 4]),
+            ("fill", [page - 2, 9, 4]),
+            ("fill", [-1, 9, 2]),
+        ] {
+            let (result, mem) = call(name, args);
+            assert!(matches!(
+                result,
+                Err(ExecuteError::Trap(TrapKind::MemoryOutOfBounds))
+            ));
+            assert!(mem[PAGE_SIZE - 2..].iter().all(|&b| b == 0));
+        }
+        call("copy", [page, 0, 0]).0.expect("invoke");
+        call("fill", [page, 9, 0]).0.expect("invoke");
+    }
+
+    #[cfg(feature = "bulk_memory")]
+    #[test]
+    fn table_copy_test() {
+        // (module
+        //   (type $t (func (result i32)))
+        //   (table 3 funcref)
+        //   (func $f0 (type $t) i32.const 1)
+        //   (func $f1 (type $t) i32.const 2)
+        //   (func (export "copy") (param i32 i32 i32)
+        //     (table.copy (local.get 0) (local.get 1) (local.get 2)))
+        //   (func (export "call") (param i32) (result i32)
+        //     (call_indirect (type $t) (local.get 0)))
+        //   (elem (i32.const 0) $f0 $f1))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 16, 3, 96, 0, 1, 127, 96, 3, 127, 127, 127, 0, 96, 1,
+            127, 1, 127, 3, 5, 4, 0, 0, 1, 2, 4, 4, 1, 112, 0, 3, 7, 15, 2, 4, 99, 111, 112, 121,
+            0, 2, 4, 99, 97, 108, 108, 0, 3, 9, 8, 1, 0, 65, 0, 11, 2, 0, 1, 10, 32, 4, 4, 0, 65,
+            1, 11, 4, 0, 65, 2, 11, 12, 0, 32, 0, 32, 1, 32, 2, 252, 14, 0, 0, 11, 7, 0, 32, 0, 17,
+            0, 0, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(Resolver).expect("instantiate");
+
+        instance
+            .invoke("copy", &[Val::I32(1), Val::I32(0), Val::I32(2)])
+            .expect("invoke");
+        for (i, expected) in [(0, 1), (1, 1), (2, 2)] {
+            let val = instance.invoke("call", &[Val::I32(i)]).expect("invoke");
+            assert_eq!(Some(Val::I32(expected)), val);
+        }
+
+        let result = instance.invoke("copy", &[Val::I32(0), Val::I32(2), Val::I32(2)]);
+        assert!(matches!(
+            result,
+            Err(ExecuteError::Trap(TrapKind::TableOutOfBounds))
+        ));
+        instance
+            .invoke("copy", &[Val::I32(3), Val::I32(0), Val::I32(0)])
+            .expect("invoke");
+    }
+
+    #[cfg(all(feature = "bulk_memory", feature = "memory_protection"))]
+    #[test]
+    fn bulk_memory_protection() {
+        use crate::{Prot, ProtectedMemory, VectorMemory};
+
+        // (module
+        //   (memory 2)
+        //   (func (export "init") (param i32)
+        //     (memory.init 0 (local.get 0) (i32.const 0) (i32.const 4)))
+        //   (func (export "copy") (param i32)
+        //     (memory.copy (local.get 0) (i32.const 0) (i32.const 4)))
+        //   (func (export "fill") (param i32)
+        //     (memory.fill (local.get 0) (i32.const 7) (i32.const 4)))
+        //   (data "abcd"))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 1, 127, 0, 3, 4, 3, 0, 0, 0, 5, 3, 1, 0, 2,
+            7, 22, 3, 4, 105, 110, 105, 116, 0, 0, 4, 99, 111, 112, 121, 0, 1, 4, 102, 105, 108,
+            108, 0, 2, 12, 1, 1, 10, 39, 3, 12, 0, 32, 0, 65, 0, 65, 4, 252, 8, 0, 0, 11, 12, 0,
+            32, 0, 65, 0, 65, 4, 252, 10, 0, 0, 11, 11, 0, 32, 0, 65, 7, 65, 4, 252, 11, 0, 11, 11,
+            7, 1, 1, 4, 97, 98, 99, 100,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mem = ProtectedMemory::<StdVectorFactory>::new(VectorMemory::new());
+        let mut instance = module
+            .instantiate_with_memory(Resolver, mem)
+            .expect("instantiate");
+        instance
+            .mem_mut()
+            .protect(PAGE_SIZE..PAGE_SIZE + 1, Prot::READ_ONLY);
+
+        let page = PAGE_SIZE as i32;
+        for name in ["init", "copy", "fill"] {
+            instance
+                .invoke(name, &[Val::I32(page - 4)])
+                .expect("invoke");
+            let result = instance.invoke(name, &[Val::I32(page - 2)]);
+            assert!(matches!(
+                result,
+                Err(ExecuteError::Trap(TrapKind::MemoryAccessDenied))
+            ));
+        }
+        // Nothing has been written to the page below the protected one either.
+        let mut buf = [0; 4];
+        assert!(instance.mem().read(PAGE_SIZE - 4, &mut buf));
+        assert_eq!([7; 4], buf);
+
+        instance.mem_mut().protect(0..PAGE_SIZE, Prot::NONE);
+        let result = instance.invoke("copy", &[Val::I32(page)]);
+        assert!(matches!(
+            result,
+            Err(ExecuteError::Trap(TrapKind::MemoryAccessDenied))
+        ));
+    }
 
     #[test]
     fn control_flow_br_test() {
@@ -1246,15 +2432,194 @@ mod tests {
         let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
         let instance = module.instantiate(Resolver).expect("instantiate");
 
-        let FuncInst::Imported { host_func, .. } = &instance.funcs()[0] else {
-            panic!()
-        };
+        let (_, host_func) = instance.host_funcs().next().expect("host_func");
         assert_eq!(10, host_func.messages.len());
         for (i, m) in host_func.messages.iter().enumerate() {
             assert_eq!(Val::I32((i + 1) as i32), *m);
         }
     }
 
+    #[test]
+    fn trap_backtrace_test() {
+        // (module
+        //   (func $fib
+        //     i32.const 0
+        //     drop
+        //     block
+        //       nop
+        //       unreachable
+        //     end)
+        //   (func $main (export "main")
+        //     nop
+        //     call $fib))
+        //
+        // (with a "name" custom section)
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 3, 2, 0, 0, 7, 8, 1, 4, 109, 97,
+            105, 110, 0, 1, 10, 18, 2, 10, 0, 65, 0, 26, 2, 64, 1, 0, 11, 11, 5, 0, 1, 16, 0, 11,
+            0, 19, 4, 110, 97, 109, 101, 1, 12, 2, 0, 3, 102, 105, 98, 1, 4, 109, 97, 105, 110,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+
+        let e = instance.invoke("main", &[]).expect_err("trap");
+        assert!(matches!(e, ExecuteError::Trap(TrapKind::Unreachable)));
+        assert_eq!(
+            "Trapped: unreachable at fib[4] <- main[1]",
+            format!("{} at {}", e, instance.backtrace())
+        );
+    }
+
+    // (module
+    //   (func $fib (export "fib") (param i32) (result i32)
+    //     local.get 0
+    //     i32.const 2
+    //     i32.lt_s
+    //     if (result i32)
+    //       local.get 0
+    //     else
+    //       local.get 0
+    //       i32.const 1
+    //       i32.sub
+    //       call $fib
+    //       local.get 0
+    //       i32.const 2
+    //       i32.sub
+    //       call $fib
+    //       i32.add
+    //     end)
+    //   (func $sum (export "sum") (param i32) (result i32) (local i32)
+    //     block (result i32)
+    //       loop
+    //         local.get 1
+    //         local.get 0
+    //         i32.add
+    //         local.set 1
+    //         local.get 0
+    //         i32.const 1
+    //         i32.sub
+    //         local.tee 0
+    //         br_if 0
+    //       end
+    //       local.get 1
+    //     end))
+    const FIB_SUM_WASM: &[u8] = &[
+        0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 3, 3, 2, 0, 0, 7, 13, 2, 3, 102,
+        105, 98, 0, 0, 3, 115, 117, 109, 0, 1, 10, 59, 2, 28, 0, 32, 0, 65, 2, 72, 4, 127, 32, 0,
+        5, 32, 0, 65, 1, 107, 16, 0, 32, 0, 65, 2, 107, 16, 0, 106, 11, 11, 28, 1, 1, 127, 2, 127,
+        3, 64, 32, 1, 32, 0, 106, 33, 1, 32, 0, 65, 1, 107, 34, 0, 13, 0, 11, 32, 1, 11, 11,
+    ];
+
+    #[test]
+    fn control_flow_nested_blocks_test() {
+        let module = Module::<StdVectorFactory>::decode(FIB_SUM_WASM).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+        assert_eq!(
+            Some(Val::I32(55)),
+            instance.invoke("fib", &[Val::I32(10)]).ok().flatten()
+        );
+        assert_eq!(
+            Some(Val::I32(10)),
+            instance.invoke("sum", &[Val::I32(4)]).ok().flatten()
+        );
+    }
+
+    #[test]
+    fn breakpoint_test() {
+        let module = Module::<StdVectorFactory>::decode(FIB_SUM_WASM).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+
+        // `i32.add` in the else branch of $fib.
+        let bp = TraceFrame {
+            funcidx: Funcidx::new(0),
+            instr_offset: 13,
+        };
+        instance.set_breakpoint(bp.funcidx, bp.instr_offset);
+        instance.start_invoke("fib", &[Val::I32(3)]).expect("start");
+        assert_eq!(
+            StepResult::Breakpoint(bp),
+            instance.resume().expect("resume")
+        );
+        assert_eq!(
+            StepResult::Breakpoint(bp),
+            instance.resume().expect("resume")
+        );
+        assert_eq!(
+            StepResult::Returned(Some(Val::I32(2))),
+            instance.resume().expect("resume")
+        );
+        assert!(matches!(
+            instance.resume(),
+            Err(ExecuteError::NoPendingCall)
+        ));
+
+        // Breakpoints are ignored by `invoke()`.
+        assert_eq!(
+            Some(Val::I32(2)),
+            instance.invoke("fib", &[Val::I32(3)]).ok().flatten()
+        );
+    }
+
+    #[test]
+    fn invoke_while_pending_test() {
+        let module = Module::<StdVectorFactory>::decode(FIB_SUM_WASM).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+
+        instance.start_invoke("fib", &[Val::I32(5)]).expect("start");
+        instance.run(10).expect("run");
+        assert!(matches!(
+            instance.invoke("fib", &[Val::I32(3)]),
+            Err(ExecuteError::CallPending)
+        ));
+        assert!(matches!(
+            instance.start_invoke("fib", &[Val::I32(3)]),
+            Err(ExecuteError::CallPending)
+        ));
+
+        // The pending call is not disturbed.
+        assert_eq!(
+            StepResult::Returned(Some(Val::I32(5))),
+            instance.resume().expect("resume")
+        );
+        assert_eq!(
+            Some(Val::I32(2)),
+            instance.invoke("fib", &[Val::I32(3)]).ok().flatten()
+        );
+    }
+
+    #[test]
+    fn introspection_test() {
+        let module = Module::<StdVectorFactory>::decode(FIB_SUM_WASM).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+        assert_eq!(None, instance.runtime().frame_locals());
+
+        // `i32.add` in the else branch of $fib.
+        instance.set_breakpoint(Funcidx::new(0), 13);
+        instance.start_invoke("fib", &[Val::I32(3)]).expect("start");
+        instance.resume().expect("resume");
+
+        // Stopped in the call of `fib(2)` made by `fib(3)`.
+        let runtime = instance.runtime();
+        assert_eq!(2, runtime.call_depth());
+        assert_eq!(Some(&[Val::I32(2)][..]), runtime.frame_locals());
+        assert_eq!(&[Val::I32(1), Val::I32(0)], runtime.values());
+    }
+
+    #[test]
+    fn step_test() {
+        let module = Module::<StdVectorFactory>::decode(FIB_SUM_WASM).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+
+        instance.start_invoke("fib", &[Val::I32(1)]).expect("start");
+        for _ in 0..4 {
+            assert_eq!(StepResult::Running, instance.step().expect("step"));
+        }
+        assert_eq!(
+            StepResult::Returned(Some(Val::I32(1))),
+            instance.step().expect("step")
+        );
+    }
+
     #[test]
     fn control_flow_drop_test() {
         // From: https://developer.mozilla.org/en-US/docs/WebAssembly/Reference/Control_flow/Drop
@@ -1281,9 +2646,7 @@ mod tests {
         let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
         let instance = module.instantiate(Resolver).expect("instantiate");
 
-        let FuncInst::Imported { host_func, .. } = &instance.funcs()[0] else {
-            panic!()
-        };
+        let (_, host_func) = instance.host_funcs().next().expect("host_func");
         assert_eq!(&[Val::I32(10)][..], &host_func.messages);
     }
 
@@ -1324,6 +2687,38 @@ mod tests {
         assert_eq!(Val::I32(20), val);
     }
 
+    #[cfg(feature = "reference_types")]
+    #[test]
+    fn control_flow_select_typed_test() {
+        // (module
+        //   (func (export "select_typed") (param i32) (result i64)
+        //     i64.const 10
+        //     i64.const 20
+        //     local.get 0
+        //     select (result i64)
+        //   )
+        // )
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 126, 3, 2, 1, 0, 7, 16, 1, 12,
+            115, 101, 108, 101, 99, 116, 95, 116, 121, 112, 101, 100, 0, 0, 10, 13, 1, 11, 0, 66,
+            10, 66, 20, 32, 0, 28, 1, 126, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(Resolver).expect("instantiate");
+
+        let val = instance
+            .invoke("select_typed", &[Val::I32(1)])
+            .expect("invoke")
+            .expect("result");
+        assert_eq!(Val::I64(10), val);
+
+        let val = instance
+            .invoke("select_typed", &[Val::I32(0)])
+            .expect("invoke")
+            .expect("result");
+        assert_eq!(Val::I64(20), val);
+    }
+
     #[test]
     fn control_flow_block_test() {
         // From: https://developer.mozilla.org/en-US/docs/WebAssembly/Reference/Control_flow/block
@@ -1381,10 +2776,8 @@ mod tests {
             .invoke("log_if_not_100", &[Val::I32(101)])
             .expect("invoke")
             .is_none());
-
-        let FuncInst::Imported { host_func, .. } = &instance.funcs()[0] else {
-            panic!()
-        };
+
+        let (_, host_func) = instance.host_funcs().next().expect("host_func");
         assert_eq!(&[Val::I32(99), Val::I32(101)][..], &host_func.messages);
     }
 
@@ -1411,9 +2804,7 @@ mod tests {
         let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
         let instance = module.instantiate(Resolver).expect("instantiate");
 
-        let FuncInst::Imported { host_func, .. } = &instance.funcs()[0] else {
-            panic!()
-        };
+        let (_, host_func) = instance.host_funcs().next().expect("host_func");
         assert_eq!(&[Val::I32(2)][..], &host_func.messages);
     }
 
@@ -1445,10 +2836,425 @@ mod tests {
         let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
         let instance = module.instantiate(Resolver).expect("instantiate");
 
-        let FuncInst::Imported { host_func, .. } = &instance.funcs()[0] else {
+        let (_, host_func) = instance.host_funcs().next().expect("host_func");
+        assert_eq!(&[Val::I32(1), Val::I32(-1)][..], &host_func.messages);
+    }
+
+    #[test]
+    fn memory_grow_reserved_test() {
+        // (module
+        //   (memory (export "mem") 1)
+        //   (func (export "grow") (param i32) (result i32)
+        //     local.get 0
+        //     memory.grow))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 3, 2, 1, 0, 5, 3, 1, 0, 1, 7,
+            14, 2, 3, 109, 101, 109, 2, 0, 4, 103, 114, 111, 119, 0, 0, 10, 8, 1, 6, 0, 32, 0, 64,
+            0, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mem = ReservedMemory::new(2 * PAGE_SIZE);
+        let mut instance = module
+            .instantiate_with_memory((), mem)
+            .expect("instantiate");
+        assert_eq!(PAGE_SIZE, instance.mem().size());
+
+        let grow = |instance: &mut ModuleInstance<_, _, _>, delta| {
+            instance.invoke("grow", &[Val::I32(delta)]).expect("invoke")
+        };
+        assert_eq!(Some(Val::I32(-1)), grow(&mut instance, 2));
+        assert_eq!(Some(Val::I32(-1)), grow(&mut instance, -1)); // u32::MAX pages
+        assert_eq!(Some(Val::I32(1)), grow(&mut instance, 1));
+        assert_eq!(2 * PAGE_SIZE, instance.mem().as_slice().len());
+    }
+
+    #[test]
+    fn memory_grow_imported_test() {
+        // (module
+        //   (import "env" "mem" (memory 1))
+        //   (func (export "grow") (param i32) (result i32)
+        //     local.get 0
+        //     memory.grow))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 2, 12, 1, 3, 101, 110, 118,
+            3, 109, 101, 109, 2, 0, 1, 3, 2, 1, 0, 7, 8, 1, 4, 103, 114, 111, 119, 0, 0, 10, 8, 1,
+            6, 0, 32, 0, 64, 0, 11,
+        ];
+        // Same as above except for the limits of the import (memory 1 2).
+        let input_with_max = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 2, 13, 1, 3, 101, 110, 118,
+            3, 109, 101, 109, 2, 1, 1, 2, 3, 2, 1, 0, 7, 8, 1, 4, 103, 114, 111, 119, 0, 0, 10, 8,
+            1, 6, 0, 32, 0, 64, 0, 11,
+        ];
+
+        struct Resolver(Option<u32>);
+        impl Resolve for Resolver {
+            type HostFunc = ();
+
+            fn resolve_mem(&self, _module: &str, _name: &str) -> Option<&[u8]> {
+                Some(&[0; PAGE_SIZE])
+            }
+
+            fn mem_max_pages(&self, _module: &str, _name: &str) -> Option<u32> {
+                self.0
+            }
+        }
+
+        let grow = |instance: &mut ModuleInstance<_, _, _>, delta| {
+            instance.invoke("grow", &[Val::I32(delta)]).expect("invoke")
+        };
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module
+            .clone()
+            .instantiate(Resolver(Some(2)))
+            .expect("instantiate");
+        assert_eq!(Some(Val::I32(-1)), grow(&mut instance, 2));
+        assert_eq!(Some(Val::I32(1)), grow(&mut instance, 1));
+        assert_eq!(Some(Val::I32(-1)), grow(&mut instance, 1));
+        assert!(matches!(
+            module.instantiate(Resolver(Some(0))),
+            Err(ExecuteError::InvalidImportedMem)
+        ));
+
+        let module = Module::<StdVectorFactory>::decode(&input_with_max).expect("decode");
+        let mut instance = module
+            .clone()
+            .instantiate(Resolver(None))
+            .expect("instantiate");
+        assert_eq!(Some(Val::I32(1)), grow(&mut instance, 1));
+        assert_eq!(Some(Val::I32(-1)), grow(&mut instance, 1));
+        let mem = [0; PAGE_SIZE];
+        let mut instance = module
+            .instantiate_with::<()>(&[Extern::Memory(&mem)])
+            .expect("instantiate");
+        assert_eq!(Some(Val::I32(-1)), grow(&mut instance, 2));
+        assert_eq!(Some(Val::I32(1)), grow(&mut instance, 1));
+    }
+
+    #[test]
+    fn set_immutable_global_test() {
+        // (module
+        //   (global i32 (i32.const 7))
+        //   (global (mut i32) (i32.const 0))
+        //   (func (export "set") (global.set 1 (i32.const 1))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 2, 1, 0, 6, 11, 2, 127, 0, 65, 7,
+            11, 127, 1, 65, 0, 11, 7, 7, 1, 3, 115, 101, 116, 0, 0, 10, 8, 1, 6, 0, 65, 1, 36, 1,
+            11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+        instance.invoke("set", &[]).expect("invoke");
+        assert_eq!(Val::I32(1), instance.globals()[1].get());
+
+        // Only host functions can replace a global wholesale (through `Env::globals`).
+        let immutable = instance.globals()[0];
+        instance.executor.globals[1] = immutable;
+        assert!(matches!(
+            instance.invoke("set", &[]),
+            Err(ExecuteError::Trap(TrapKind::ImmutableGlobal { index: 1 }))
+        ));
+        assert_eq!(Val::I32(7), instance.globals()[1].get());
+    }
+
+    #[test]
+    fn on_trap_test() {
+        // (module
+        //   (func $inner nop unreachable)
+        //   (func (export "f") (drop (i32.const 1)) (call $inner)))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 3, 2, 0, 0, 7, 5, 1, 1, 102, 0, 1,
+            10, 14, 2, 4, 0, 1, 0, 11, 7, 0, 65, 1, 26, 16, 0, 11,
+        ];
+        type Trapped = (TrapKind, Vec<TraceFrame>, u64, u64);
+        static TRAPPED: std::sync::Mutex<Vec<Trapped>> = std::sync::Mutex::new(Vec::new());
+        fn on_trap(info: &TrapInfo) {
+            let trapped = (
+                info.kind,
+                info.backtrace.to_vec(),
+                info.fuel_consumed,
+                info.instrs_executed,
+            );
+            TRAPPED.lock().unwrap().push(trapped);
+        }
+
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+        instance.set_executor_config(ExecutorConfig {
+            on_trap: Some(on_trap),
+            ..Default::default()
+        });
+        for _ in 0..2 {
+            assert!(matches!(
+                instance.invoke("f", &[]),
+                Err(ExecuteError::Trap(TrapKind::Unreachable))
+            ));
+        }
+
+        let backtrace = vec![
+            TraceFrame {
+                funcidx: Funcidx::new(0),
+                instr_offset: 1,
+            },
+            TraceFrame {
+                funcidx: Funcidx::new(1),
+                instr_offset: 2,
+            },
+        ];
+        assert_eq!(
+            *TRAPPED.lock().unwrap(),
+            [
+                (TrapKind::Unreachable, backtrace.clone(), 5, 5),
+                (TrapKind::Unreachable, backtrace, 5, 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn pause_requested_test() {
+        // (module
+        //   (func (export "count") (param i32) (result i32) (local i32)
+        //     (loop
+        //       (local.set 1 (i32.add (local.get 1) (i32.const 1)))
+        //       (br_if 0 (i32.lt_u (local.get 1) (local.get 0))))
+        //     (local.get 1)))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 3, 2, 1, 0, 7, 9, 1, 5, 99,
+            111, 117, 110, 116, 0, 0, 10, 25, 1, 23, 1, 1, 127, 3, 64, 32, 1, 65, 1, 106, 33, 1,
+            32, 1, 32, 0, 73, 13, 0, 11, 32, 1, 11,
+        ];
+        static PAUSE: AtomicBool = AtomicBool::new(false);
+        fn pause_requested() -> bool {
+            PAUSE.swap(false, Ordering::Relaxed)
+        }
+
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+        instance.set_executor_config(ExecutorConfig {
+            pause_requested: Some(pause_requested),
+            ..Default::default()
+        });
+
+        instance
+            .start_invoke("count", &[Val::I32(10)])
+            .expect("start_invoke");
+        assert_eq!(StepResult::Running, instance.run(20).expect("run"));
+        PAUSE.store(true, Ordering::Relaxed);
+        assert_eq!(StepResult::Paused, instance.resume().expect("resume"));
+        assert_eq!(20, instance.stats().instrs_executed);
+        assert_eq!(
+            StepResult::Returned(Some(Val::I32(10))),
+            instance.resume().expect("resume")
+        );
+
+        // Invocations that can't be resumed run to completion.
+        PAUSE.store(true, Ordering::Relaxed);
+        assert_eq!(
+            Some(Val::I32(3)),
+            instance.invoke("count", &[Val::I32(3)]).expect("invoke")
+        );
+        assert!(PAUSE.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn float_const_bits_test() {
+        // (module
+        //   (global (export "g") f64 (f64.const nan:0x4000000000001))
+        //   (func (export "f") (result f32) (f32.const nan:0x200001)))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 125, 3, 2, 1, 0, 6, 13, 1, 124, 0, 68,
+            1, 0, 0, 0, 0, 0, 244, 127, 11, 7, 9, 2, 1, 102, 0, 0, 1, 103, 3, 0, 10, 9, 1, 7, 0,
+            67, 1, 0, 160, 127, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+        let Some(Val::F32(v)) = instance.invoke("f", &[]).expect("invoke") else {
             panic!()
         };
-        assert_eq!(&[Val::I32(1), Val::I32(-1)][..], &host_func.messages);
+        assert_eq!(0x7fa0_0001, v.to_bits());
+        let Val::F64(v) = instance.globals()[0].get() else {
+            panic!()
+        };
+        assert_eq!(0x7ff4_0000_0000_0001, v.to_bits());
+    }
+
+    #[test]
+    fn shared_memory_test() {
+        // (module
+        //   (memory (export "memory") 1 2)
+        //   (func (export "store") (param i32 i32)
+        //     (i32.store (local.get 0) (local.get 1)))
+        //   (func (export "grow") (param i32) (result i32)
+        //     (memory.grow (local.get 0))))
+        let exporter = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 11, 2, 96, 2, 127, 127, 0, 96, 1, 127, 1, 127, 3, 3, 2,
+            0, 1, 5, 4, 1, 1, 1, 2, 7, 25, 3, 6, 109, 101, 109, 111, 114, 121, 2, 0, 5, 115, 116,
+            111, 114, 101, 0, 0, 4, 103, 114, 111, 119, 0, 1, 10, 18, 2, 9, 0, 32, 0, 32, 1, 54, 2,
+            0, 11, 6, 0, 32, 0, 64, 0, 11,
+        ];
+        // (module
+        //   (import "env" "memory" (memory 1))
+        //   (func (export "load") (param i32) (result i32)
+        //     (i32.load (local.get 0)))
+        //   (func (export "size") (result i32)
+        //     memory.size)
+        //   (data (i32.const 16) "hi"))
+        let importer = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 10, 2, 96, 1, 127, 1, 127, 96, 0, 1, 127, 2, 15, 1, 3,
+            101, 110, 118, 6, 109, 101, 109, 111, 114, 121, 2, 0, 1, 3, 3, 2, 0, 1, 7, 15, 2, 4,
+            108, 111, 97, 100, 0, 0, 4, 115, 105, 122, 101, 0, 1, 10, 14, 2, 7, 0, 32, 0, 40, 2, 0,
+            11, 4, 0, 63, 0, 11, 11, 8, 1, 0, 65, 16, 11, 2, 104, 105,
+        ];
+        // (module
+        //   (import "env" "memory" (memory 1))
+        //   (data (i32.const 0) "x")
+        //   (data (i32.const 0x20000) "y"))
+        let failing_importer = [
+            0, 97, 115, 109, 1, 0, 0, 0, 2, 15, 1, 3, 101, 110, 118, 6, 109, 101, 109, 111, 114,
+            121, 2, 0, 1, 11, 15, 2, 0, 65, 0, 11, 1, 120, 0, 65, 128, 128, 8, 11, 1, 121,
+        ];
+
+        struct Resolver;
+        impl Resolve for Resolver {
+            type HostFunc = ();
+
+            fn alias_mem(&self, module: &str, name: &str) -> bool {
+                module == "env" && name == "memory"
+            }
+        }
+
+        let shared = SharedMemory::new();
+        let module = Module::<StdVectorFactory>::decode(&exporter).expect("decode");
+        let mut exporter = module
+            .instantiate_with_memory((), shared.clone())
+            .expect("instantiate");
+        let module = Module::<StdVectorFactory>::decode(&importer).expect("decode");
+        let mut importer = module
+            .instantiate_with_memory(Resolver, shared.clone())
+            .expect("instantiate");
+        assert!(exporter.mem().ptr_eq(importer.mem()));
+
+        // Data segments of the importer are written to the shared memory.
+        assert_eq!(b"hi", &shared.bytes()[16..18]);
+
+        // Stores and growth made by one instance are visible to the other.
+        exporter
+            .invoke("store", &[Val::I32(0), Val::I32(42)])
+            .expect("invoke");
+        let loaded = importer.invoke("load", &[Val::I32(0)]).expect("invoke");
+        assert_eq!(Some(Val::I32(42)), loaded);
+        let grows = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let grows_clone = grows.clone();
+        shared.on_memory_grow(move |old, new| grows_clone.borrow_mut().push((old, new)));
+        let grown = exporter.invoke("grow", &[Val::I32(1)]).expect("invoke");
+        assert_eq!(Some(Val::I32(1)), grown);
+        assert_eq!(&[(PAGE_SIZE, PAGE_SIZE * 2)][..], &grows.borrow()[..]);
+        let size = importer.invoke("size", &[]).expect("invoke");
+        assert_eq!(Some(Val::I32(2)), size);
+
+        // Resetting the importer doesn't overwrite the shared memory with its data segments.
+        exporter
+            .invoke("store", &[Val::I32(16), Val::I32(7)])
+            .expect("invoke");
+        importer.reset(false).expect("reset");
+        assert_eq!(2 * PAGE_SIZE, shared.bytes().len());
+        let loaded = importer.invoke("load", &[Val::I32(16)]).expect("invoke");
+        assert_eq!(Some(Val::I32(7)), loaded);
+
+        // As in the spec's linking tests, the segments written before
+        // a failing one remain visible after the instantiation fails.
+        let module = Module::<StdVectorFactory>::decode(&failing_importer).expect("decode");
+        let err = module
+            .instantiate_with_memory(Resolver, shared.clone())
+            .expect_err("out of bounds data");
+        assert!(matches!(err, ExecuteError::InvalidData { index: 1 }));
+        assert_eq!(b'x', shared.bytes()[0]);
+    }
+
+    #[test]
+    fn table_get_test() {
+        // (module
+        //   (table 3 funcref)
+        //   (func $double (param i32) (result i32)
+        //     (i32.add (local.get 0) (local.get 0)))
+        //   (func $square (param i32) (result i32)
+        //     (i32.mul (local.get 0) (local.get 0)))
+        //   (func (export "callback") (result i32)
+        //     i32.const 1)
+        //   (elem (i32.const 0) $double $square))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 10, 2, 96, 1, 127, 1, 127, 96, 0, 1, 127, 3, 4, 3, 0,
+            0, 1, 4, 4, 1, 112, 0, 3, 7, 12, 1, 8, 99, 97, 108, 108, 98, 97, 99, 107, 0, 2, 9, 8,
+            1, 0, 65, 0, 11, 2, 0, 1, 10, 22, 3, 7, 0, 32, 0, 32, 0, 106, 11, 7, 0, 32, 0, 32, 0,
+            108, 11, 4, 0, 65, 1, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+
+        // The guest registers a callback by returning its table slot.
+        let Some(Val::I32(slot)) = instance.invoke("callback", &[]).expect("invoke") else {
+            panic!("unexpected result");
+        };
+        let handle = instance.table_get(slot as usize).expect("funcref");
+        assert_eq!(Funcidx::new(1), handle.funcidx());
+        let result = instance
+            .invoke_handle(handle, &[Val::I32(7)])
+            .expect("invoke");
+        assert_eq!(Some(Val::I32(49)), result);
+
+        assert!(instance.table_get(2).is_none());
+        assert!(instance.table_get(3).is_none());
+        assert!(matches!(
+            instance.invoke_handle(handle, &[]),
+            Err(ExecuteError::InvalidFuncArgs)
+        ));
+    }
+
+    #[test]
+    fn call_indirect_trap_test() {
+        // (module
+        //   (type $t0 (func (result i32)))
+        //   (type $t1 (func (param i32) (result i32)))
+        //   (table 3 funcref)
+        //   (func $f0 (type $t0) i32.const 5)
+        //   (func $f1 (type $t1) local.get 0)
+        //   (func (export "call") (type $t1)
+        //     (call_indirect (type $t0) (local.get 0)))
+        //   (elem (i32.const 0) $f0)
+        //   (elem (i32.const 2) $f1))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 10, 2, 96, 0, 1, 127, 96, 1, 127, 1, 127, 3, 4, 3, 0,
+            1, 1, 4, 4, 1, 112, 0, 3, 7, 8, 1, 4, 99, 97, 108, 108, 0, 2, 9, 13, 2, 0, 65, 0, 11,
+            1, 0, 0, 65, 2, 11, 1, 1, 10, 19, 3, 4, 0, 65, 5, 11, 4, 0, 32, 0, 11, 7, 0, 32, 0, 17,
+            0, 0, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+
+        let result = instance.invoke("call", &[Val::I32(0)]).expect("invoke");
+        assert_eq!(Some(Val::I32(5)), result);
+
+        let trap = |instance: &mut ModuleInstance<_, _, _>, i| match instance
+            .invoke("call", &[Val::I32(i)])
+        {
+            Err(ExecuteError::Trap(kind)) => kind,
+            other => panic!("unexpected result: {other:?}"),
+        };
+        assert_eq!(
+            TrapKind::UninitializedTableElement { index: 1 },
+            trap(&mut instance, 1)
+        );
+        assert_eq!(
+            TrapKind::SignatureMismatch {
+                expected: Typeidx::new(0),
+                actual: Typeidx::new(1)
+            },
+            trap(&mut instance, 2)
+        );
+        assert_eq!(
+            TrapKind::TableIndexOutOfBounds { index: 3 },
+            trap(&mut instance, 3)
+        );
     }
 
     #[test]
@@ -1474,9 +3280,7 @@ mod tests {
         let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
         let instance = module.instantiate(Resolver).expect("instantiate");
 
-        let FuncInst::Imported { host_func, .. } = &instance.funcs()[0] else {
-            panic!()
-        };
+        let (_, host_func) = instance.host_funcs().next().expect("host_func");
         assert_eq!(&[Val::I32(10), Val::I32(-3)][..], &host_func.messages);
     }
 
@@ -1506,9 +3310,7 @@ mod tests {
         let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
         let instance = module.instantiate(Resolver).expect("instantiate");
 
-        let FuncInst::Imported { host_func, .. } = &instance.funcs()[0] else {
-            panic!()
-        };
+        let (_, host_func) = instance.host_funcs().next().expect("host_func");
         assert_eq!(&[Val::I32(-2147483648)][..], &host_func.messages);
     }
 
@@ -1537,9 +3339,7 @@ mod tests {
         let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
         let instance = module.instantiate(Resolver).expect("instantiate");
 
-        let FuncInst::Imported { host_func, .. } = &instance.funcs()[0] else {
-            panic!()
-        };
+        let (_, host_func) = instance.host_funcs().next().expect("host_func");
         assert_eq!(&[Val::I32(10)][..], &host_func.messages);
     }
 
@@ -1567,9 +3367,7 @@ mod tests {
         let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
         let instance = module.instantiate(Resolver).expect("instantiate");
 
-        let FuncInst::Imported { host_func, .. } = &instance.funcs()[0] else {
-            panic!()
-        };
+        let (_, host_func) = instance.host_funcs().next().expect("host_func");
         assert_eq!(&[Val::F32(10.0)][..], &host_func.messages);
     }
 
@@ -1598,9 +3396,7 @@ mod tests {
         let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
         let instance = module.instantiate(Resolver).expect("instantiate");
 
-        let FuncInst::Imported { host_func, .. } = &instance.funcs()[0] else {
-            panic!()
-        };
+        let (_, host_func) = instance.host_funcs().next().expect("host_func");
         assert_eq!(&[Val::F32(10.5)][..], &host_func.messages);
     }
 
@@ -1629,9 +3425,7 @@ mod tests {
         let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
         let instance = module.instantiate(Resolver).expect("instantiate");
 
-        let FuncInst::Imported { host_func, .. } = &instance.funcs()[0] else {
-            panic!()
-        };
+        let (_, host_func) = instance.host_funcs().next().expect("host_func");
         assert_eq!(&[Val::F64(10.5)][..], &host_func.messages);
     }
 
@@ -1659,9 +3453,7 @@ mod tests {
         let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
         let instance = module.instantiate(Resolver).expect("instantiate");
 
-        let FuncInst::Imported { host_func, .. } = &instance.funcs()[0] else {
-            panic!()
-        };
+        let (_, host_func) = instance.host_funcs().next().expect("host_func");
         assert_eq!(&[Val::I32(10)][..], &host_func.messages);
     }
 
@@ -1690,10 +3482,657 @@ mod tests {
         let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
         let instance = module.instantiate(Resolver).expect("instantiate");
 
-        let FuncInst::Imported { host_func, .. } = &instance.funcs()[0] else {
+        let (_, host_func) = instance.host_funcs().next().expect("host_func");
+        assert_eq!(&[Val::I64(10)][..], &host_func.messages);
+    }
+
+    #[cfg(feature = "memory_protection")]
+    #[test]
+    fn memory_protection() {
+        use crate::{Prot, ProtectedMemory, VectorMemory};
+
+        // (module
+        //   (memory 2)
+        //   (func (export "store") (param i32) (i32.store (local.get 0) (i32.const 1)))
+        //   (func (export "load") (param i32) (result i32) (i32.load (local.get 0))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 10, 2, 96, 1, 127, 0, 96, 1, 127, 1, 127, 3, 3, 2, 0,
+            1, 5, 3, 1, 0, 2, 7, 16, 2, 5, 115, 116, 111, 114, 101, 0, 0, 4, 108, 111, 97, 100, 0,
+            1, 10, 19, 2, 9, 0, 32, 0, 65, 1, 54, 2, 0, 11, 7, 0, 32, 0, 40, 2, 0, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mem = ProtectedMemory::<StdVectorFactory>::new(VectorMemory::new());
+        let mut instance = module
+            .instantiate_with_memory(Resolver, mem)
+            .expect("instantiate");
+        instance
+            .mem_mut()
+            .protect(PAGE_SIZE..PAGE_SIZE + 1, Prot::READ_ONLY);
+
+        let page = PAGE_SIZE as i32;
+        instance
+            .invoke("store", &[Val::I32(page - 4)])
+            .expect("invoke");
+        let result = instance.invoke("store", &[Val::I32(page - 2)]);
+        assert!(matches!(
+            result,
+            Err(ExecuteError::Trap(TrapKind::MemoryAccessDenied))
+        ));
+        let result = instance.invoke("load", &[Val::I32(page)]).expect("invoke");
+        assert_eq!(Some(Val::I32(0)), result);
+
+        instance.mem_mut().protect(0..PAGE_SIZE * 2, Prot::NONE);
+        let result = instance.invoke("load", &[Val::I32(0)]);
+        assert!(matches!(
+            result,
+            Err(ExecuteError::Trap(TrapKind::MemoryAccessDenied))
+        ));
+        // The host can still access the memory.
+        let mut buf = [0; 4];
+        assert!(instance.mem().read(page as usize - 4, &mut buf));
+        assert_eq!([1, 0, 0, 0], buf);
+    }
+
+    #[test]
+    fn host_func_caller() {
+        // (module
+        //   (import "env" "log" (func $log))
+        //   (func $inner (call $log))
+        //   (func (export "main") (local i32)
+        //     (local.set 0 (i32.const 7))
+        //     (call $inner)
+        //     (call $log)))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 2, 11, 1, 3, 101, 110, 118, 3, 108,
+            111, 103, 0, 0, 3, 3, 2, 0, 0, 7, 8, 1, 4, 109, 97, 105, 110, 0, 2, 10, 19, 2, 4, 0,
+            16, 0, 11, 12, 1, 1, 127, 65, 7, 33, 0, 16, 1, 16, 0, 11,
+        ];
+
+        #[derive(Debug, Default)]
+        struct Callers(Vec<(Option<TraceFrame>, usize, Vec<Val>)>);
+
+        impl HostFunc for Callers {
+            fn invoke(&mut self, _args: &[Val], env: &mut Env) -> Option<Val> {
+                let locals = env.caller_locals().to_vec();
+                self.0.push((env.caller(), env.call_depth(), locals));
+                None
+            }
+        }
+
+        struct CallersResolver;
+
+        impl Resolve for CallersResolver {
+            type HostFunc = Callers;
+
+            fn resolve_func(&self, _module: &str, _name: &str) -> Option<Self::HostFunc> {
+                Some(Callers::default())
+            }
+        }
+
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(CallersResolver).expect("instantiate");
+        instance.invoke("main", &[]).expect("invoke");
+
+        let (_, host_func) = instance.host_funcs().next().expect("host_func");
+        let frame = |funcidx, instr_offset| {
+            Some(TraceFrame {
+                funcidx: Funcidx::new(funcidx),
+                instr_offset,
+            })
+        };
+        assert_eq!(
+            &[
+                (frame(1, 0), 2, vec![]),
+                (frame(2, 3), 1, vec![Val::I32(7)]),
+            ][..],
+            &host_func.0[..]
+        );
+    }
+
+    #[test]
+    fn table_set_host_test() {
+        // (module
+        //   (type $t (func (param i32)))
+        //   (table 1 funcref)
+        //   (func (export "call") (param i32)
+        //     (call_indirect (type $t) (local.get 0) (i32.const 0))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 1, 127, 0, 3, 2, 1, 0, 4, 4, 1, 112, 0, 1, 7,
+            8, 1, 4, 99, 97, 108, 108, 0, 0, 10, 11, 1, 9, 0, 32, 0, 65, 0, 17, 0, 0, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(Resolver).expect("instantiate");
+        let result = instance.invoke("call", &[Val::I32(1)]);
+        assert!(matches!(
+            result,
+            Err(ExecuteError::Trap(TrapKind::UninitializedTableElement {
+                index: 0
+            }))
+        ));
+
+        let handle = instance
+            .table_set_host(0, Typeidx::new(0), Log::default())
+            .expect("table_set_host");
+        assert_eq!(Some(handle), instance.table_get(0));
+        instance.invoke("call", &[Val::I32(2)]).expect("invoke");
+        instance
+            .invoke_handle(handle, &[Val::I32(3)])
+            .expect("invoke");
+
+        let FuncInst::Host { host_func, .. } = &instance.funcs()[1] else {
             panic!()
         };
-        assert_eq!(&[Val::I64(10)][..], &host_func.messages);
+        assert_eq!(&[Val::I32(2), Val::I32(3)][..], &host_func.messages);
+
+        // The replaced host function is reused instead of adding another one.
+        for _ in 0..3 {
+            instance
+                .table_set_host(0, Typeidx::new(0), Log::default())
+                .expect("table_set_host");
+        }
+        assert_eq!(2, instance.funcs().len());
+        assert_eq!(&[Some(FuncRef::Host(0))][..], instance.table());
+        instance.invoke("call", &[Val::I32(4)]).expect("invoke");
+        let FuncInst::Host { host_func, .. } = &instance.funcs()[1] else {
+            panic!()
+        };
+        assert_eq!(&[Val::I32(4)][..], &host_func.messages);
+
+        // Handles to the replaced host functions are stale (as are all of them after a reset).
+        assert!(matches!(
+            instance.invoke_handle(handle, &[Val::I32(5)]),
+            Err(ExecuteError::StaleFuncHandle)
+        ));
+        assert!(!instance.table_mut().set(0, Some(handle)));
+        let current = instance.table_get(0).expect("table_get");
+        assert_ne!(handle, current);
+        assert!(instance.table_mut().set(0, Some(current)));
+        instance
+            .invoke_handle(current, &[Val::I32(5)])
+            .expect("invoke");
+        instance.reset(false).expect("reset");
+        instance
+            .table_set_host(0, Typeidx::new(0), Log::default())
+            .expect("table_set_host");
+        assert!(matches!(
+            instance.invoke_handle(current, &[Val::I32(6)]),
+            Err(ExecuteError::StaleFuncHandle)
+        ));
+
+        assert!(matches!(
+            instance.table_set_host(1, Typeidx::new(0), Log::default()),
+            Err(ExecuteError::InvalidTableIndex { index: 1 })
+        ));
+        assert!(matches!(
+            instance.table_set_host(0, Typeidx::new(1), Log::default()),
+            Err(ExecuteError::InvalidTypeidx)
+        ));
+    }
+
+    #[test]
+    fn invalid_return_value() {
+        // (module
+        //   (import "env" "f" (func $f (result i32)))
+        //   (export "f" (func $f))
+        //   (func (export "g") (result i32) (call $f)))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 127, 2, 9, 1, 3, 101, 110, 118, 1, 102,
+            0, 0, 3, 2, 1, 0, 7, 9, 2, 1, 102, 0, 0, 1, 103, 0, 1, 10, 6, 1, 4, 0, 16, 0, 11,
+        ];
+
+        #[derive(Debug)]
+        struct WrongType;
+
+        impl HostFunc for WrongType {
+            fn invoke(&mut self, _args: &[Val], _env: &mut Env) -> Option<Val> {
+                Some(Val::I64(1))
+            }
+        }
+
+        struct WrongTypeResolver;
+
+        impl Resolve for WrongTypeResolver {
+            type HostFunc = WrongType;
+
+            fn resolve_func(&self, _module: &str, _name: &str) -> Option<Self::HostFunc> {
+                Some(WrongType)
+            }
+        }
+
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(WrongTypeResolver).expect("instantiate");
+        for name in ["f", "g"] {
+            let result = instance.invoke(name, &[]);
+            assert!(matches!(result, Err(ExecuteError::InvalidReturnValue)));
+        }
+    }
+
+    #[test]
+    fn value_stack_limit() {
+        // (module (func $f (export "f") (param i32) (call $f (local.get 0))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 1, 127, 0, 3, 2, 1, 0, 7, 5, 1, 1, 102, 0, 0,
+            10, 8, 1, 6, 0, 32, 0, 16, 0, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(Resolver).expect("instantiate");
+        instance.set_executor_config(ExecutorConfig {
+            max_value_stack: 1000,
+            ..Default::default()
+        });
+
+        let result = instance.invoke("f", &[Val::I32(0)]);
+        assert!(matches!(
+            result,
+            Err(ExecuteError::Trap(TrapKind::StackExhausted))
+        ));
+        assert!(instance.backtrace().frames().len() > 900);
+    }
+
+    #[test]
+    fn call_depth_limit() {
+        // (module (func $f (export "f") (call $f)))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 2, 1, 0, 7, 5, 1, 1, 102, 0, 0, 10,
+            6, 1, 4, 0, 16, 0, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(Resolver).expect("instantiate");
+        let result = instance.invoke("f", &[]);
+        assert!(matches!(
+            result,
+            Err(ExecuteError::Trap(TrapKind::StackExhausted))
+        ));
+        let config = ExecutorConfig::default();
+        assert_eq!(config.max_call_depth, instance.stats().max_call_depth);
+
+        // (module (func $f (export "f") (block (call $f))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 2, 1, 0, 7, 5, 1, 1, 102, 0, 0, 10,
+            9, 1, 7, 0, 2, 64, 16, 0, 11, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(Resolver).expect("instantiate");
+        instance.set_executor_config(ExecutorConfig {
+            max_labels: 100,
+            ..config
+        });
+        let result = instance.invoke("f", &[]);
+        assert!(matches!(
+            result,
+            Err(ExecuteError::Trap(TrapKind::StackExhausted))
+        ));
+        assert_eq!(101, instance.stats().max_call_depth);
+    }
+
+    #[test]
+    fn exec_stats() {
+        // (module
+        //   (memory 0)
+        //   (func $f (export "f") (param i32) (result i32)
+        //     (if (result i32) (local.get 0)
+        //       (then (call $f (i32.sub (local.get 0) (i32.const 1))))
+        //       (else (memory.grow (i32.const 1))))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 3, 2, 1, 0, 5, 3, 1, 0, 0, 7,
+            5, 1, 1, 102, 0, 0, 10, 21, 1, 19, 0, 32, 0, 4, 127, 32, 0, 65, 1, 107, 16, 0, 5, 65,
+            1, 64, 0, 11, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(Resolver).expect("instantiate");
+        assert_eq!(ExecStats::default(), instance.stats());
+
+        instance.invoke("f", &[Val::I32(2)]).expect("invoke");
+        assert_eq!(
+            ExecStats {
+                instrs_executed: 16,
+                calls: 3,
+                max_value_stack: 4,
+                max_call_depth: 3,
+                mem_grown: PAGE_SIZE,
+            },
+            instance.stats()
+        );
+
+        instance.reset_stats();
+        instance.invoke("f", &[Val::I32(0)]).expect("invoke");
+        assert_eq!(1, instance.stats().max_call_depth);
+    }
+
+    #[test]
+    fn fast_fp_mode() {
+        // (module
+        //   (func (export "mul") (param f32 f32) (result f32)
+        //     (f32.mul (local.get 0) (local.get 1)))
+        //   (func (export "sqrt") (param f64) (result f64)
+        //     (f64.sqrt (local.get 0))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 12, 2, 96, 2, 125, 125, 1, 125, 96, 1, 124, 1, 124, 3,
+            3, 2, 0, 1, 7, 14, 2, 3, 109, 117, 108, 0, 0, 4, 115, 113, 114, 116, 0, 1, 10, 15, 2,
+            7, 0, 32, 0, 32, 1, 148, 11, 5, 0, 32, 0, 159, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(Resolver).expect("instantiate");
+        let args = [Val::F32(-f32::MIN_POSITIVE), Val::F32(0.5)];
+
+        let result = instance.invoke("mul", &args).expect("invoke");
+        assert_eq!(Some(Val::F32(-f32::MIN_POSITIVE * 0.5)), result);
+
+        instance.set_executor_config(ExecutorConfig {
+            fp_mode: FpMode::Fast,
+            ..Default::default()
+        });
+        let Some(Val::F32(v)) = instance.invoke("mul", &args).expect("invoke") else {
+            panic!()
+        };
+        assert_eq!((-0.0f32).to_bits(), v.to_bits());
+
+        let Some(Val::F64(v)) = instance.invoke("sqrt", &[Val::F64(2.0)]).expect("invoke") else {
+            panic!()
+        };
+        assert_eq!(core::f64::consts::SQRT_2, v);
+    }
+
+    #[test]
+    fn integer_overflow_test() {
+        // From the `i32.wast` and `i64.wast` of the spec test suite.
+        let i32_cases: &[(u8, i32, i32, i32)] = &[
+            (0x6a, 0x7fff_ffff, 1, i32::MIN),
+            (0x6a, i32::MIN, -1, 0x7fff_ffff),
+            (0x6a, i32::MIN, i32::MIN, 0),
+            (0x6a, -1, -1, -2),
+            (0x6b, 0x7fff_ffff, -1, i32::MIN),
+            (0x6b, i32::MIN, 1, 0x7fff_ffff),
+            (0x6b, i32::MIN, i32::MIN, 0),
+            (0x6c, 0x1000_0000, 4096, 0),
+            (0x6c, i32::MIN, -1, i32::MIN),
+            (0x6c, 0x7fff_ffff, -1, -0x7fff_ffff),
+            (0x6c, 0x0123_4567, 0x7654_3210, 0x358e_7470),
+            (0x6c, 0x7fff_ffff, 0x7fff_ffff, 1),
+        ];
+        for &(opcode, a, b, expected) in i32_cases {
+            let mut instance = binop_instance(0x7f, opcode);
+            let result = instance.invoke("f", &[Val::I32(a), Val::I32(b)]);
+            assert_eq!(Some(Val::I32(expected)), result.expect("invoke"));
+        }
+
+        let i64_cases: &[(u8, i64, i64, i64)] = &[
+            (0x7c, 0x7fff_ffff_ffff_ffff, 1, i64::MIN),
+            (0x7c, i64::MIN, -1, 0x7fff_ffff_ffff_ffff),
+            (0x7c, i64::MIN, i64::MIN, 0),
+            (0x7d, 0x7fff_ffff_ffff_ffff, -1, i64::MIN),
+            (0x7d, i64::MIN, 1, 0x7fff_ffff_ffff_ffff),
+            (0x7e, 0x1000_0000_0000_0000, 4096, 0),
+            (0x7e, i64::MIN, -1, i64::MIN),
+            (0x7e, 0x7fff_ffff_ffff_ffff, -1, -0x7fff_ffff_ffff_ffff),
+            (
+                0x7e,
+                0x0123_4567_89ab_cdef,
+                0xfedc_ba98_7654_3210u64 as i64,
+                0x2236_d88f_e561_8cf0,
+            ),
+            (0x7e, 0x7fff_ffff_ffff_ffff, 0x7fff_ffff_ffff_ffff, 1),
+        ];
+        for &(opcode, a, b, expected) in i64_cases {
+            let mut instance = binop_instance(0x7e, opcode);
+            let result = instance.invoke("f", &[Val::I64(a), Val::I64(b)]);
+            assert_eq!(Some(Val::I64(expected)), result.expect("invoke"));
+        }
+    }
+
+    #[test]
+    fn shift_count_masking_test() {
+        // From the `i32.wast` and `i64.wast` of the spec test suite.
+        let i32_cases: &[(u8, i32, i32, i32)] = &[
+            (0x74, 1, 31, i32::MIN),
+            (0x74, 1, 32, 1),
+            (0x74, 1, -1, i32::MIN),
+            (0x74, 1, 0x7fff_ffff, i32::MIN),
+            (0x75, i32::MIN, 31, -1),
+            (0x75, 1, 32, 1),
+            (0x75, 1, 33, 0),
+            (0x75, 1, -1, 0),
+            (0x75, i32::MIN, i32::MIN, i32::MIN),
+            (0x76, -1, 31, 1),
+            (0x76, -1, 32, -1),
+            (0x76, 1, 33, 0),
+            (0x76, -1, -1, 1),
+            (0x76, i32::MIN, 0x7fff_ffff, 1),
+        ];
+        for &(opcode, a, b, expected) in i32_cases {
+            let mut instance = binop_instance(0x7f, opcode);
+            let result = instance.invoke("f", &[Val::I32(a), Val::I32(b)]);
+            assert_eq!(Some(Val::I32(expected)), result.expect("invoke"));
+        }
+
+        let i64_cases: &[(u8, i64, i64, i64)] = &[
+            (0x86, 1, 63, i64::MIN),
+            (0x86, 1, 64, 1),
+            (0x86, 1, -1, i64::MIN),
+            (0x86, 1, 0x7fff_ffff_ffff_ffff, i64::MIN),
+            (0x87, i64::MIN, 63, -1),
+            (0x87, 1, 64, 1),
+            (0x87, 1, 65, 0),
+            (0x87, 1, -1, 0),
+            (0x88, -1, 63, 1),
+            (0x88, -1, 64, -1),
+            (0x88, -1, -1, 1),
+            (
+                0x88,
+                i64::MIN,
+                0x8000_0000_0000_0001u64 as i64,
+                0x4000_0000_0000_0000,
+            ),
+        ];
+        for &(opcode, a, b, expected) in i64_cases {
+            let mut instance = binop_instance(0x7e, opcode);
+            let result = instance.invoke("f", &[Val::I64(a), Val::I64(b)]);
+            assert_eq!(Some(Val::I64(expected)), result.expect("invoke"));
+        }
+    }
+
+    #[test]
+    fn rotate_test() {
+        // From the `i32.wast` and `i64.wast` of the spec test suite
+        // (operands and results as unsigned for readability).
+        let i32_cases: &[(u8, u32, u32, u32)] = &[
+            (0x77, 1, 1, 2),
+            (0x77, 1, 0, 1),
+            (0x77, u32::MAX, 1, u32::MAX),
+            (0x77, 1, 32, 1),
+            (0x77, 0xabcd_9876, 1, 0x579b_30ed),
+            (0x77, 0xfe00_dc00, 4, 0xe00d_c00f),
+            (0x77, 0xb0c1_d2e3, 5, 0x183a_5c76),
+            (0x77, 0x0000_8000, 37, 0x0010_0000),
+            (0x77, 0xb0c1_d2e3, 0xff05, 0x183a_5c76),
+            (0x77, 0x769a_bcdf, 0xffff_ffed, 0x579b_eed3),
+            (0x77, 0x769a_bcdf, 0x8000_000d, 0x579b_eed3),
+            (0x77, 1, 31, 0x8000_0000),
+            (0x77, 0x8000_0000, 1, 1),
+            (0x78, 1, 1, 0x8000_0000),
+            (0x78, 1, 0, 1),
+            (0x78, u32::MAX, 1, u32::MAX),
+            (0x78, 1, 32, 1),
+            (0x78, 0xff00_cc00, 1, 0x7f80_6600),
+            (0x78, 0x0008_0000, 4, 0x0000_8000),
+            (0x78, 0xb0c1_d2e3, 5, 0x1d86_0e97),
+            (0x78, 0x0000_8000, 37, 0x0000_0400),
+            (0x78, 0xb0c1_d2e3, 0xff05, 0x1d86_0e97),
+            (0x78, 0x769a_bcdf, 0xffff_ffed, 0xe6fb_b4d5),
+            (0x78, 0x769a_bcdf, 0x8000_000d, 0xe6fb_b4d5),
+            (0x78, 1, 31, 2),
+            (0x78, 0x8000_0000, 31, 1),
+        ];
+        for &(opcode, a, b, expected) in i32_cases {
+            let mut instance = binop_instance(0x7f, opcode);
+            let result = instance.invoke("f", &[Val::I32(a as i32), Val::I32(b as i32)]);
+            assert_eq!(Some(Val::I32(expected as i32)), result.expect("invoke"));
+        }
+
+        let i64_cases: &[(u8, u64, u64, u64)] = &[
+            (0x89, 1, 1, 2),
+            (0x89, 1, 0, 1),
+            (0x89, u64::MAX, 1, u64::MAX),
+            (0x89, 1, 64, 1),
+            (0x89, 0xabcd_9876_0246_8ace, 1, 0x579b_30ec_048d_159d),
+            (0x89, 0xfe00_0000_dc00_0000, 4, 0xe000_000d_c000_000f),
+            (0x89, 0xabcd_1234_ef56_7809, 53, 0x0135_79a2_469d_eacf),
+            (0x89, 0xabd1_234e_f567_809c, 63, 0x55e8_91a7_7ab3_c04e),
+            (0x89, 0xabcd_1234_ef56_7809, 0xf5, 0x0135_79a2_469d_eacf),
+            (
+                0x89,
+                0xabcd_7294_ef56_7809,
+                u64::MAX - 0x12,
+                0xcf01_3579_ae52_9dea,
+            ),
+            (
+                0x89,
+                0xabd1_234e_f567_809c,
+                0x8000_0000_0000_003f,
+                0x55e8_91a7_7ab3_c04e,
+            ),
+            (0x89, 1, 63, 0x8000_0000_0000_0000),
+            (0x89, 0x8000_0000_0000_0000, 1, 1),
+            (0x8a, 1, 1, 0x8000_0000_0000_0000),
+            (0x8a, 0xabcd_9876_0246_8ace, 1, 0x55e6_cc3b_0123_4567),
+            (0x8a, 0xfe00_0000_dc00_0000, 4, 0x0fe0_0000_0dc0_0000),
+            (0x8a, 0xabcd_1234_ef56_7809, 53, 0x6891_a77a_b3c0_4d5e),
+            (0x8a, 0xabd1_234e_f567_809c, 63, 0x57a2_469d_eacf_0139),
+            (
+                0x8a,
+                0xabcd_7294_ef56_7809,
+                u64::MAX - 0x12,
+                0x94a7_7ab3_c04d_5e6b,
+            ),
+            (0x8a, 1, 63, 2),
+            (0x8a, 0x8000_0000_0000_0000, 63, 1),
+        ];
+        for &(opcode, a, b, expected) in i64_cases {
+            let mut instance = binop_instance(0x7e, opcode);
+            let result = instance.invoke("f", &[Val::I64(a as i64), Val::I64(b as i64)]);
+            assert_eq!(Some(Val::I64(expected as i64)), result.expect("invoke"));
+        }
+    }
+
+    #[test]
+    fn binop_operand_order_test() {
+        // The operands are chosen so that swapping them changes the result of every
+        // non-commutative operation (e.g., `7 - -3` vs `-3 - 7` and `lt_s` vs `lt_u`).
+        let i32_cases: &[(u8, i32)] = &[
+            (0x46, 0),            // i32.eq
+            (0x47, 1),            // i32.ne
+            (0x48, 0),            // i32.lt_s
+            (0x49, 1),            // i32.lt_u
+            (0x4a, 1),            // i32.gt_s
+            (0x4b, 0),            // i32.gt_u
+            (0x4c, 0),            // i32.le_s
+            (0x4d, 1),            // i32.le_u
+            (0x4e, 1),            // i32.ge_s
+            (0x4f, 0),            // i32.ge_u
+            (0x6a, 4),            // i32.add
+            (0x6b, 10),           // i32.sub
+            (0x6c, -21),          // i32.mul
+            (0x6d, -2),           // i32.div_s
+            (0x6e, 0),            // i32.div_u
+            (0x6f, 1),            // i32.rem_s
+            (0x70, 7),            // i32.rem_u
+            (0x71, 5),            // i32.and
+            (0x72, -1),           // i32.or
+            (0x73, -6),           // i32.xor
+            (0x74, -0x2000_0000), // i32.shl
+            (0x75, 0),            // i32.shr_s
+            (0x76, 0),            // i32.shr_u
+            (0x77, -0x2000_0000), // i32.rotl
+            (0x78, 56),           // i32.rotr
+        ];
+        for &(opcode, expected) in i32_cases {
+            let mut instance = binop_instance(0x7f, opcode);
+            let result = instance.invoke("f", &[Val::I32(7), Val::I32(-3)]);
+            assert_eq!(
+                Some(Val::I32(expected)),
+                result.expect("invoke"),
+                "{opcode:#x}"
+            );
+        }
+
+        let i64_cases: &[(u8, Val)] = &[
+            (0x51, Val::I32(0)),                      // i64.eq
+            (0x52, Val::I32(1)),                      // i64.ne
+            (0x53, Val::I32(0)),                      // i64.lt_s
+            (0x54, Val::I32(1)),                      // i64.lt_u
+            (0x55, Val::I32(1)),                      // i64.gt_s
+            (0x56, Val::I32(0)),                      // i64.gt_u
+            (0x57, Val::I32(0)),                      // i64.le_s
+            (0x58, Val::I32(1)),                      // i64.le_u
+            (0x59, Val::I32(1)),                      // i64.ge_s
+            (0x5a, Val::I32(0)),                      // i64.ge_u
+            (0x7c, Val::I64(4)),                      // i64.add
+            (0x7d, Val::I64(10)),                     // i64.sub
+            (0x7e, Val::I64(-21)),                    // i64.mul
+            (0x7f, Val::I64(-2)),                     // i64.div_s
+            (0x80, Val::I64(0)),                      // i64.div_u
+            (0x81, Val::I64(1)),                      // i64.rem_s
+            (0x82, Val::I64(7)),                      // i64.rem_u
+            (0x83, Val::I64(5)),                      // i64.and
+            (0x84, Val::I64(-1)),                     // i64.or
+            (0x85, Val::I64(-6)),                     // i64.xor
+            (0x86, Val::I64(-0x2000_0000_0000_0000)), // i64.shl
+            (0x87, Val::I64(0)),                      // i64.shr_s
+            (0x88, Val::I64(0)),                      // i64.shr_u
+            (0x89, Val::I64(-0x2000_0000_0000_0000)), // i64.rotl
+            (0x8a, Val::I64(56)),                     // i64.rotr
+        ];
+        for &(opcode, expected) in i64_cases {
+            let mut instance = binop_instance(0x7e, opcode);
+            let result = instance.invoke("f", &[Val::I64(7), Val::I64(-3)]);
+            assert_eq!(Some(expected), result.expect("invoke"), "{opcode:#x}");
+        }
+
+        // (opcode for f32, opcode for f64, result of f64)
+        let float_cases: &[(u8, u8, Val)] = &[
+            (0x5b, 0x61, Val::I32(0)),     // eq
+            (0x5c, 0x62, Val::I32(1)),     // ne
+            (0x5d, 0x63, Val::I32(0)),     // lt
+            (0x5e, 0x64, Val::I32(1)),     // gt
+            (0x5f, 0x65, Val::I32(0)),     // le
+            (0x60, 0x66, Val::I32(1)),     // ge
+            (0x92, 0xa0, Val::F64(5.5)),   // add
+            (0x93, 0xa1, Val::F64(9.5)),   // sub
+            (0x94, 0xa2, Val::F64(-15.0)), // mul
+            (0x95, 0xa3, Val::F64(-3.75)), // div
+            (0x96, 0xa4, Val::F64(-2.0)),  // min
+            (0x97, 0xa5, Val::F64(7.5)),   // max
+            (0x98, 0xa6, Val::F64(-7.5)),  // copysign
+        ];
+        for &(f32_opcode, f64_opcode, expected) in float_cases {
+            let mut instance = binop_instance(0x7c, f64_opcode);
+            let result = instance.invoke("f", &[Val::F64(7.5), Val::F64(-2.0)]);
+            assert_eq!(Some(expected), result.expect("invoke"), "{f64_opcode:#x}");
+
+            let expected = match expected {
+                Val::F64(v) => Val::F32(v as f32),
+                v => v,
+            };
+            let mut instance = binop_instance(0x7d, f32_opcode);
+            let result = instance.invoke("f", &[Val::F32(7.5), Val::F32(-2.0)]);
+            assert_eq!(Some(expected), result.expect("invoke"), "{f32_opcode:#x}");
+        }
+    }
+
+    // Instantiates `(module (func (export "f") (param ty ty) (result ty)
+    // (<opcode> (local.get 0) (local.get 1))))` (the result is `i32` for comparisons).
+    fn binop_instance(ty: u8, opcode: u8) -> ModuleInstance<StdVectorFactory, Log> {
+        let result = if (0x46..=0x66).contains(&opcode) {
+            0x7f
+        } else {
+            ty
+        };
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 7, 1, 96, 2, ty, ty, 1, result, 3, 2, 1, 0, 7, 5, 1, 1,
+            102, 0, 0, 10, 9, 1, 7, 0, 32, 0, 32, 1, opcode, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        module.instantiate(Resolver).expect("instantiate")
     }
 
     #[derive(Debug)]
@@ -0,0 +1,131 @@
+use crate::{components::Valtype, reader::Reader, DecodeError, Env, Val};
+use core::fmt::{Display, Formatter};
+
+// An instruction of an embedder-defined extension.
+//
+// The decoder of the extension determines `opcode` and `imm` (their meanings are up to the
+// extension) and the stack effect of the instruction, which is checked by the validator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtensionInstr {
+    pub opcode: u32,
+    pub imm: u64,
+    pub params: &'static [Valtype],
+    pub result: Option<Valtype>,
+}
+
+// The prefix byte isn't known here, so the instruction is printed as `ext.<opcode> <imm>`.
+impl Display for ExtensionInstr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "ext.{} {}", self.opcode, self.imm)
+    }
+}
+
+// Decoder and executor of the instructions following a prefix byte that isn't used by the
+// enabled proposals (e.g. 0xFF). Set it to both `DecodeConfig::extension` and
+// `ExecutorConfig::extension`.
+#[derive(Debug, Clone, Copy)]
+pub struct Extension {
+    pub prefix: u8,
+
+    // Reads the rest of the instruction following the prefix byte.
+    pub decode: fn(&mut Reader) -> Result<ExtensionInstr, DecodeError>,
+
+    // Same as `HostFunc::invoke()`. `args` are typed as `ExtensionInstr::params`
+    // and the returned value must be typed as `ExtensionInstr::result`.
+    pub execute: fn(&ExtensionInstr, &[Val], &mut Env) -> Option<Val>,
+}
+
+impl PartialEq for Extension {
+    fn eq(&self, other: &Self) -> bool {
+        self.prefix == other.prefix
+            && core::ptr::fn_addr_eq(self.decode, other.decode)
+            && core::ptr::fn_addr_eq(self.execute, other.execute)
+    }
+}
+
+impl Eq for Extension {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecodeConfig, ExecuteError, ExecutorConfig, Module, StdVectorFactory, TrapKind};
+
+    // 0xFF 0x00 <u32 imm>: (i32) -> i32, rotates the argument left by `imm` bits.
+    // 0xFF 0x01: () -> (), aborts.
+    fn decode(reader: &mut Reader) -> Result<ExtensionInstr, DecodeError> {
+        match reader.read_u32()? {
+            0 => Ok(ExtensionInstr {
+                opcode: 0,
+                imm: reader.read_u32()? as u64,
+                params: &[Valtype::I32],
+                result: Some(Valtype::I32),
+            }),
+            1 => Ok(ExtensionInstr {
+                opcode: 1,
+                imm: 0,
+                params: &[],
+                result: None,
+            }),
+            _ => Err(DecodeError::InvalidOpcode { value: 0xff }),
+        }
+    }
+
+    fn execute(instr: &ExtensionInstr, args: &[Val], env: &mut Env) -> Option<Val> {
+        match (instr.opcode, args) {
+            (0, [Val::I32(v)]) => Some(Val::I32(v.rotate_left(instr.imm as u32))),
+            _ => {
+                env.abort();
+                None
+            }
+        }
+    }
+
+    const EXTENSION: Extension = Extension {
+        prefix: 0xff,
+        decode,
+        execute,
+    };
+
+    #[test]
+    fn custom_opcodes() {
+        // (module
+        //   (func (export "rotl4") (param i32) (result i32)
+        //     (local.get 0) (ext.rotl 4))
+        //   (func (export "abort") (ext.abort)))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 9, 2, 96, 1, 127, 1, 127, 96, 0, 0, 3, 3, 2, 0, 1, 7,
+            17, 2, 5, 114, 111, 116, 108, 52, 0, 0, 5, 97, 98, 111, 114, 116, 0, 1, 10, 14, 2, 7,
+            0, 32, 0, 255, 0, 4, 11, 4, 0, 255, 1, 11,
+        ];
+        assert!(matches!(
+            Module::<StdVectorFactory>::decode(&input),
+            Err(DecodeError::InvalidOpcode { value: 0xff })
+        ));
+
+        let config = DecodeConfig {
+            extension: Some(EXTENSION),
+            ..Default::default()
+        };
+        let module =
+            Module::<StdVectorFactory>::decode_with_config(&input, config).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+        let args = [Val::I32(0x1234_5678)];
+        assert!(matches!(
+            instance.invoke("rotl4", &args),
+            Err(ExecuteError::UnregisteredExtension)
+        ));
+
+        instance.set_executor_config(ExecutorConfig {
+            extension: Some(EXTENSION),
+            ..Default::default()
+        });
+        assert_eq!(
+            Some(Val::I32(0x2345_6781)),
+            instance.invoke("rotl4", &args).expect("invoke")
+        );
+        assert!(matches!(
+            instance.invoke("abort", &[]),
+            Err(ExecuteError::Trap(TrapKind::Aborted))
+        ));
+    }
+}
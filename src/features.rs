@@ -0,0 +1,204 @@
+use crate::DecodeError;
+use core::fmt::{Display, Formatter};
+use core::ops::{BitOr, BitOrAssign};
+
+// Set of post-MVP WebAssembly proposals.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Features(u32);
+
+impl Features {
+    pub const SIGN_EXTENSION: Self = Self(1 << 0);
+    pub const REFERENCE_TYPES: Self = Self(1 << 1);
+    pub const EXTENDED_CONST: Self = Self(1 << 2);
+    pub const BULK_MEMORY: Self = Self(1 << 3);
+    pub const GC: Self = Self(1 << 4);
+    pub const MULTI_VALUE: Self = Self(1 << 5);
+    pub const MULTI_MEMORY: Self = Self(1 << 6);
+    pub const MEMORY64: Self = Self(1 << 7);
+    pub const NONTRAPPING_FLOAT_TO_INT: Self = Self(1 << 8);
+    pub const SIMD: Self = Self(1 << 9);
+    pub const RELAXED_SIMD: Self = Self(1 << 10);
+    pub const THREADS: Self = Self(1 << 11);
+    pub const EXCEPTIONS: Self = Self(1 << 12);
+    pub const TAIL_CALL: Self = Self(1 << 13);
+    pub const FUNCTION_REFERENCES: Self = Self(1 << 14);
+
+    const NAMES: [(Self, &'static str); 15] = [
+        (Self::SIGN_EXTENSION, "sign_extension"),
+        (Self::REFERENCE_TYPES, "reference_types"),
+        (Self::EXTENDED_CONST, "extended_const"),
+        (Self::BULK_MEMORY, "bulk_memory"),
+        (Self::GC, "gc"),
+        (Self::MULTI_VALUE, "multi_value"),
+        (Self::MULTI_MEMORY, "multi_memory"),
+        (Self::MEMORY64, "memory64"),
+        (Self::NONTRAPPING_FLOAT_TO_INT, "nontrapping_float_to_int"),
+        (Self::SIMD, "simd"),
+        (Self::RELAXED_SIMD, "relaxed_simd"),
+        (Self::THREADS, "threads"),
+        (Self::EXCEPTIONS, "exceptions"),
+        (Self::TAIL_CALL, "tail_call"),
+        (Self::FUNCTION_REFERENCES, "function_references"),
+    ];
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        Self::NAMES
+            .into_iter()
+            .filter(move |(f, _)| self.contains(*f))
+            .map(|(_, name)| name)
+    }
+
+    // Returns the proposal that introduced `opcode`
+    // (`sub` is the sub-opcode following a `0xFB..=0xFE` prefix).
+    pub(crate) fn of_opcode(opcode: u8, sub: u32) -> Option<Self> {
+        match (opcode, sub) {
+            (0x06..=0x0a | 0x18 | 0x19 | 0x1f, _) => Some(Self::EXCEPTIONS),
+            (0x12 | 0x13, _) => Some(Self::TAIL_CALL),
+            (0x14 | 0x15 | 0xd4..=0xd6, _) => Some(Self::FUNCTION_REFERENCES),
+            (0x1c | 0x25 | 0x26 | 0xd0..=0xd2, _) => Some(Self::REFERENCE_TYPES),
+            (0xc0..=0xc4, _) => Some(Self::SIGN_EXTENSION),
+            (0xd3 | 0xfb, _) => Some(Self::GC),
+            (0xfc, 0..=7) => Some(Self::NONTRAPPING_FLOAT_TO_INT),
+            (0xfc, 8..=14) => Some(Self::BULK_MEMORY),
+            (0xfc, 15..=17) => Some(Self::REFERENCE_TYPES),
+            (0xfd, 0..=0xff) => Some(Self::SIMD),
+            (0xfd, 0x100..=0x113) => Some(Self::RELAXED_SIMD),
+            (0xfe, _) => Some(Self::THREADS),
+            _ => None,
+        }
+    }
+
+    // Returns the proposal that introduced the value type encoded as `code`.
+    pub(crate) fn of_valtype(code: u8) -> Option<Self> {
+        match code {
+            0x7b => Some(Self::SIMD),
+            0x6f | 0x70 => Some(Self::REFERENCE_TYPES),
+            0x63 | 0x64 => Some(Self::FUNCTION_REFERENCES),
+            0x69..=0x6e | 0x71..=0x74 => Some(Self::GC),
+            _ => None,
+        }
+    }
+}
+
+impl BitOr for Features {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+impl BitOrAssign for Features {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = self.union(rhs);
+    }
+}
+
+impl Display for Features {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "none");
+        }
+        for (i, name) in self.names().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{name}")?;
+        }
+        Ok(())
+    }
+}
+
+// Returns the proposals enabled in this build (by the corresponding cargo features).
+pub const fn supported_features() -> Features {
+    let mut features = Features::empty();
+    if cfg!(feature = "sign_extension") {
+        features = features.union(Features::SIGN_EXTENSION);
+    }
+    if cfg!(feature = "reference_types") {
+        features = features.union(Features::REFERENCE_TYPES);
+    }
+    if cfg!(feature = "extended_const") {
+        features = features.union(Features::EXTENDED_CONST);
+    }
+    if cfg!(feature = "bulk_memory") {
+        features = features.union(Features::BULK_MEMORY);
+    }
+    if cfg!(feature = "gc") {
+        features = features.union(Features::GC);
+    }
+    features
+}
+
+// Error for an opcode that isn't decoded, naming the proposal it belongs to if it's known.
+pub(crate) fn unknown_opcode_error(opcode: u8, sub: u32) -> DecodeError {
+    match Features::of_opcode(opcode, sub) {
+        Some(feature) => DecodeError::UnsupportedFeature { feature },
+        None => DecodeError::InvalidOpcode { value: opcode },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Module, StdVectorFactory};
+
+    #[test]
+    fn report_unsupported_feature() {
+        // (module (func (drop (v128.const i64x2 0 0))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 2, 1, 0, 10, 23, 1, 21, 0, 253, 12,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 26, 11,
+        ];
+        let e = Module::<StdVectorFactory>::decode(&input).expect_err("decode");
+        assert_eq!(
+            DecodeError::UnsupportedFeature {
+                feature: Features::SIMD
+            },
+            e
+        );
+        assert_eq!(
+            "Module requires simd, which is not supported",
+            e.to_string()
+        );
+
+        // (module (type $t (func)) (func (block (type $t))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 2, 1, 0, 10, 7, 1, 5, 0, 2, 0, 11,
+            11,
+        ];
+        assert_eq!(
+            Err(DecodeError::UnsupportedFeature {
+                feature: Features::MULTI_VALUE
+            }),
+            Module::<StdVectorFactory>::decode(&input).map(|_| ())
+        );
+
+        assert!(supported_features().contains(Features::SIGN_EXTENSION));
+        assert!(!supported_features().contains(Features::SIMD));
+        assert_eq!(
+            "simd, relaxed_simd",
+            (Features::RELAXED_SIMD | Features::SIMD).to_string()
+        );
+    }
+}
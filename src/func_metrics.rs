@@ -0,0 +1,86 @@
+use crate::{components::Funcidx, instructions::Instr, Module, VectorFactory};
+
+// Size metrics of a function defined in a module, which help find the functions that bloat it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuncMetrics {
+    pub idx: Funcidx,
+
+    // Instructions including those in nested blocks (`else` and `end` are not counted).
+    pub instrs: usize,
+
+    // Size of the code entry (the local declarations and the body) in the module binary.
+    pub code_size: usize,
+
+    // Declared locals excluding the parameters.
+    pub locals: usize,
+
+    // Depth of the most deeply nested `block`, `loop` or `if` (0 if there are none).
+    pub max_nesting: usize,
+}
+
+impl FuncMetrics {
+    fn measure<V: VectorFactory>(&mut self, instrs: &[Instr<V>], depth: usize) {
+        self.instrs += instrs.len();
+        self.max_nesting = self.max_nesting.max(depth);
+        for instr in instrs {
+            match instr {
+                Instr::Block(block) => self.measure(&block.instrs, depth + 1),
+                Instr::Loop(block) => self.measure(&block.instrs, depth + 1),
+                Instr::If(block) => {
+                    self.measure(&block.then_instrs, depth + 1);
+                    self.measure(&block.else_instrs, depth + 1);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<V: VectorFactory> Module<V> {
+    // Returns the metrics of the functions defined in the module (in the order of their indices).
+    pub fn func_metrics(&self) -> impl '_ + Iterator<Item = FuncMetrics> {
+        let imported_funcs = self.imported_funcs_len();
+        self.funcs.iter().enumerate().map(move |(i, func)| {
+            let mut metrics = FuncMetrics {
+                idx: Funcidx::new((imported_funcs + i) as u32),
+                instrs: 0,
+                code_size: func.code_size(),
+                locals: func.locals.len(),
+                max_nesting: 0,
+            };
+            metrics.measure(func.body.instrs(), 0);
+            metrics
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StdVectorFactory;
+
+    #[test]
+    fn func_metrics() {
+        // (module
+        //   (import "env" "f" (func))
+        //   (func (local i32 i64)
+        //     (block (loop (if (local.get 0) (then nop) (else (block nop)))))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 2, 9, 1, 3, 101, 110, 118, 1, 102, 0,
+            0, 3, 2, 1, 0, 10, 25, 1, 23, 2, 1, 127, 1, 126, 2, 64, 3, 64, 32, 0, 4, 64, 1, 5, 2,
+            64, 1, 11, 11, 11, 11, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let metrics = module.func_metrics().collect::<Vec<_>>();
+        assert_eq!(
+            vec![FuncMetrics {
+                idx: Funcidx::new(1),
+                instrs: 7,
+                code_size: 23,
+                locals: 2,
+                max_nesting: 4,
+            }],
+            metrics
+        );
+    }
+}
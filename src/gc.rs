@@ -0,0 +1,567 @@
+// Decoding of the GC proposal (https://github.com/WebAssembly/gc).
+//
+// Only the type section extensions and the instructions with the 0xFB prefix are decoded, so that
+// tools can introspect such modules. Reference types elsewhere (e.g., in locals or globals) are
+// still rejected by the decoder, and modules using the GC types or instructions fail to validate.
+use crate::{
+    components::{Dataidx, Elemidx, Functype, Labelidx, Resulttype, Typeidx, Valtype},
+    decode::Decode,
+    reader::Reader,
+    vector::Vector,
+    DecodeError, VectorFactory,
+};
+use core::fmt::{Debug, Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heaptype {
+    Func,
+    NoFunc,
+    Extern,
+    NoExtern,
+    Any,
+    Eq,
+    I31,
+    Struct,
+    Array,
+    None,
+    Concrete(Typeidx),
+}
+
+impl Heaptype {
+    fn from_abstract_code(code: u8) -> Option<Self> {
+        Some(match code {
+            0x70 => Self::Func,
+            0x73 => Self::NoFunc,
+            0x6f => Self::Extern,
+            0x72 => Self::NoExtern,
+            0x6e => Self::Any,
+            0x6d => Self::Eq,
+            0x6c => Self::I31,
+            0x6b => Self::Struct,
+            0x6a => Self::Array,
+            0x71 => Self::None,
+            _ => return None,
+        })
+    }
+}
+
+impl Display for Heaptype {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Func => f.write_str("func"),
+            Self::NoFunc => f.write_str("nofunc"),
+            Self::Extern => f.write_str("extern"),
+            Self::NoExtern => f.write_str("noextern"),
+            Self::Any => f.write_str("any"),
+            Self::Eq => f.write_str("eq"),
+            Self::I31 => f.write_str("i31"),
+            Self::Struct => f.write_str("struct"),
+            Self::Array => f.write_str("array"),
+            Self::None => f.write_str("none"),
+            Self::Concrete(idx) => write!(f, "{}", idx.get()),
+        }
+    }
+}
+
+impl<V: VectorFactory> Decode<V> for Heaptype {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let code = reader.peek_u8()?;
+        if let Some(ty) = Self::from_abstract_code(code) {
+            reader.read_u8()?;
+            return Ok(ty);
+        }
+        let idx = reader.read_integer_s(33)?;
+        let idx = u32::try_from(idx).map_err(|_| DecodeError::InvalidValType { value: code })?;
+        Ok(Self::Concrete(Typeidx::new(idx)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reftype {
+    pub nullable: bool,
+    pub heaptype: Heaptype,
+}
+
+impl Display for Reftype {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        if self.nullable {
+            write!(f, "(ref null {})", self.heaptype)
+        } else {
+            write!(f, "(ref {})", self.heaptype)
+        }
+    }
+}
+
+impl<V: VectorFactory> Decode<V> for Reftype {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let nullable = match reader.read_u8()? {
+            0x63 => true,
+            0x64 => false,
+            code => {
+                // Shorthands such as `funcref` for `(ref null func)`.
+                let heaptype = Heaptype::from_abstract_code(code)
+                    .ok_or(DecodeError::InvalidValType { value: code })?;
+                return Ok(Self {
+                    nullable: true,
+                    heaptype,
+                });
+            }
+        };
+        let heaptype = Decode::<V>::decode(reader)?;
+        Ok(Self { nullable, heaptype })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Storagetype {
+    Val(Valtype),
+    Ref(Reftype),
+    I8,
+    I16,
+}
+
+impl Storagetype {
+    fn decode_valtype<V: VectorFactory>(reader: &mut Reader) -> Result<Self, DecodeError> {
+        match reader.peek_u8()? {
+            0x7c..=0x7f => Ok(Self::Val(Decode::<V>::decode(reader)?)),
+            _ => Ok(Self::Ref(Decode::<V>::decode(reader)?)),
+        }
+    }
+}
+
+impl<V: VectorFactory> Decode<V> for Storagetype {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        match reader.peek_u8()? {
+            0x78 => {
+                reader.read_u8()?;
+                Ok(Self::I8)
+            }
+            0x77 => {
+                reader.read_u8()?;
+                Ok(Self::I16)
+            }
+            _ => Self::decode_valtype::<V>(reader),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fieldtype {
+    pub storage: Storagetype,
+    pub mutable: bool,
+}
+
+impl<V: VectorFactory> Decode<V> for Fieldtype {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let storage = Decode::<V>::decode(reader)?;
+        let mutable = match reader.read_u8()? {
+            0x00 => false,
+            0x01 => true,
+            value => return Err(DecodeError::InvalidMutabilityFlag { value }),
+        };
+        Ok(Self { storage, mutable })
+    }
+}
+
+pub enum Comptype<V: VectorFactory> {
+    Func {
+        params: V::Vector<Storagetype>,
+        results: V::Vector<Storagetype>,
+    },
+    Struct(V::Vector<Fieldtype>),
+    Array(Fieldtype),
+}
+
+impl<V: VectorFactory> Comptype<V> {
+    // Converts to the function type supported by the interpreter (i.e., numeric types only).
+    pub fn to_functype(&self) -> Option<Functype<V>> {
+        let Self::Func { params, results } = self else {
+            return None;
+        };
+        let numeric = |ty: &Storagetype| match *ty {
+            Storagetype::Val(ty) => Some(ty),
+            _ => None,
+        };
+        let mut valtypes = V::create_vector(Some(params.len()));
+        for param in params.iter() {
+            valtypes.push(numeric(param)?);
+        }
+        let result = match &results[..] {
+            [] => None,
+            [result] => Some(numeric(result)?),
+            _ => return None,
+        };
+        Some(Functype {
+            params: valtypes,
+            result: Resulttype::new(result),
+        })
+    }
+}
+
+impl<V: VectorFactory> Decode<V> for Comptype<V> {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        match reader.read_u8()? {
+            0x60 => {
+                let mut types = [V::create_vector(None), V::create_vector(None)];
+                for types in &mut types {
+                    let n = reader.read_usize()?;
+                    for _ in 0..n {
+                        types.push(Storagetype::decode_valtype::<V>(reader)?);
+                    }
+                }
+                let [params, results] = types;
+                Ok(Self::Func { params, results })
+            }
+            0x5f => Ok(Self::Struct(Decode::<V>::decode_vector(reader)?)),
+            0x5e => Ok(Self::Array(Decode::<V>::decode(reader)?)),
+            value => Err(DecodeError::InvalidFuncTypeTag { value }),
+        }
+    }
+}
+
+impl<V: VectorFactory> Debug for Comptype<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Func { params, results } => f
+                .debug_struct("Func")
+                .field("params", &params.as_ref())
+                .field("results", &results.as_ref())
+                .finish(),
+            Self::Struct(fields) => f.debug_tuple("Struct").field(&fields.as_ref()).finish(),
+            Self::Array(field) => f.debug_tuple("Array").field(field).finish(),
+        }
+    }
+}
+
+impl<V: VectorFactory> Clone for Comptype<V> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Func { params, results } => Self::Func {
+                params: V::clone_vector(params),
+                results: V::clone_vector(results),
+            },
+            Self::Struct(fields) => Self::Struct(V::clone_vector(fields)),
+            Self::Array(field) => Self::Array(*field),
+        }
+    }
+}
+
+pub struct Subtype<V: VectorFactory> {
+    pub is_final: bool,
+    pub supertypes: V::Vector<Typeidx>,
+    pub comptype: Comptype<V>,
+}
+
+impl<V: VectorFactory> Decode<V> for Subtype<V> {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let is_final = match reader.peek_u8()? {
+            0x50 => false,
+            0x4f => true,
+            _ => {
+                return Ok(Self {
+                    is_final: true,
+                    supertypes: V::create_vector(None),
+                    comptype: Decode::<V>::decode(reader)?,
+                })
+            }
+        };
+        reader.read_u8()?;
+        let supertypes = Decode::<V>::decode_vector(reader)?;
+        let comptype = Decode::<V>::decode(reader)?;
+        Ok(Self {
+            is_final,
+            supertypes,
+            comptype,
+        })
+    }
+}
+
+impl<V: VectorFactory> Debug for Subtype<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Subtype")
+            .field("is_final", &self.is_final)
+            .field("supertypes", &self.supertypes.as_ref())
+            .field("comptype", &self.comptype)
+            .finish()
+    }
+}
+
+impl<V: VectorFactory> Clone for Subtype<V> {
+    fn clone(&self) -> Self {
+        Self {
+            is_final: self.is_final,
+            supertypes: V::clone_vector(&self.supertypes),
+            comptype: self.comptype.clone(),
+        }
+    }
+}
+
+// A recursion group. Each subtype is assigned its own type index.
+pub struct Rectype<V: VectorFactory> {
+    pub subtypes: V::Vector<Subtype<V>>,
+}
+
+impl<V: VectorFactory> Decode<V> for Rectype<V> {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        if reader.peek_u8()? == 0x4e {
+            reader.read_u8()?;
+            return Ok(Self {
+                subtypes: Decode::<V>::decode_vector(reader)?,
+            });
+        }
+        let mut subtypes = V::create_vector(Some(1));
+        subtypes.push(Decode::<V>::decode(reader)?);
+        Ok(Self { subtypes })
+    }
+}
+
+impl<V: VectorFactory> Debug for Rectype<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Rectype")
+            .field("subtypes", &self.subtypes.as_ref())
+            .finish()
+    }
+}
+
+impl<V: VectorFactory> Clone for Rectype<V> {
+    fn clone(&self) -> Self {
+        Self {
+            subtypes: V::clone_vector(&self.subtypes),
+        }
+    }
+}
+
+type TypeSection<V> = (
+    <V as VectorFactory>::Vector<Rectype<V>>,
+    <V as VectorFactory>::Vector<Functype<V>>,
+);
+
+// Decodes the type section, also returning the types indexed by `Typeidx` as `Functype`s.
+// Types not representable as `Functype` are replaced with `[] -> []` placeholders
+// (the validator rejects modules containing them).
+pub(crate) fn decode_type_section<V: VectorFactory>(
+    reader: &mut Reader,
+) -> Result<TypeSection<V>, DecodeError> {
+    let rectypes: V::Vector<Rectype<V>> = Decode::<V>::decode_vector(reader)?;
+    let mut functypes = V::create_vector(None);
+    for subtype in rectypes.iter().flat_map(|r| r.subtypes.iter()) {
+        functypes.push(subtype.comptype.to_functype().unwrap_or(Functype {
+            params: V::create_vector(None),
+            result: Resulttype::new(None),
+        }));
+    }
+    Ok((rectypes, functypes))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum GcInstr {
+    StructNew(Typeidx),
+    StructNewDefault(Typeidx),
+    StructGet(Typeidx, u32),
+    StructGetS(Typeidx, u32),
+    StructGetU(Typeidx, u32),
+    StructSet(Typeidx, u32),
+    ArrayNew(Typeidx),
+    ArrayNewDefault(Typeidx),
+    ArrayNewFixed(Typeidx, u32),
+    ArrayNewData(Typeidx, Dataidx),
+    ArrayNewElem(Typeidx, Elemidx),
+    ArrayGet(Typeidx),
+    ArrayGetS(Typeidx),
+    ArrayGetU(Typeidx),
+    ArraySet(Typeidx),
+    ArrayLen,
+    ArrayFill(Typeidx),
+    ArrayCopy(Typeidx, Typeidx),
+    ArrayInitData(Typeidx, Dataidx),
+    ArrayInitElem(Typeidx, Elemidx),
+    RefTest(Reftype),
+    RefCast(Reftype),
+    BrOnCast {
+        label: Labelidx,
+        from: Reftype,
+        to: Reftype,
+    },
+    BrOnCastFail {
+        label: Labelidx,
+        from: Reftype,
+        to: Reftype,
+    },
+    AnyConvertExtern,
+    ExternConvertAny,
+    RefI31,
+    I31GetS,
+    I31GetU,
+}
+
+impl GcInstr {
+    fn decode_ref_test<V: VectorFactory>(
+        reader: &mut Reader,
+        nullable: bool,
+    ) -> Result<Reftype, DecodeError> {
+        let heaptype = Decode::<V>::decode(reader)?;
+        Ok(Reftype { nullable, heaptype })
+    }
+
+    fn decode_br_on_cast<V: VectorFactory>(
+        reader: &mut Reader,
+    ) -> Result<(Labelidx, Reftype, Reftype), DecodeError> {
+        let flags = reader.read_u8()?;
+        let label = Decode::<V>::decode(reader)?;
+        let from = Self::decode_ref_test::<V>(reader, flags & 0b01 != 0)?;
+        let to = Self::decode_ref_test::<V>(reader, flags & 0b10 != 0)?;
+        Ok((label, from, to))
+    }
+}
+
+impl<V: VectorFactory> Decode<V> for GcInstr {
+    // Decodes the sub-opcode that follows the 0xFB prefix.
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let ty =
+            |reader: &mut Reader| -> Result<Typeidx, DecodeError> { Decode::<V>::decode(reader) };
+        Ok(match reader.read_u32()? {
+            0 => Self::StructNew(ty(reader)?),
+            1 => Self::StructNewDefault(ty(reader)?),
+            2 => Self::StructGet(ty(reader)?, reader.read_u32()?),
+            3 => Self::StructGetS(ty(reader)?, reader.read_u32()?),
+            4 => Self::StructGetU(ty(reader)?, reader.read_u32()?),
+            5 => Self::StructSet(ty(reader)?, reader.read_u32()?),
+            6 => Self::ArrayNew(ty(reader)?),
+            7 => Self::ArrayNewDefault(ty(reader)?),
+            8 => Self::ArrayNewFixed(ty(reader)?, reader.read_u32()?),
+            9 => Self::ArrayNewData(ty(reader)?, Decode::<V>::decode(reader)?),
+            10 => Self::ArrayNewElem(ty(reader)?, Decode::<V>::decode(reader)?),
+            11 => Self::ArrayGet(ty(reader)?),
+            12 => Self::ArrayGetS(ty(reader)?),
+            13 => Self::ArrayGetU(ty(reader)?),
+            14 => Self::ArraySet(ty(reader)?),
+            15 => Self::ArrayLen,
+            16 => Self::ArrayFill(ty(reader)?),
+            17 => Self::ArrayCopy(ty(reader)?, ty(reader)?),
+            18 => Self::ArrayInitData(ty(reader)?, Decode::<V>::decode(reader)?),
+            19 => Self::ArrayInitElem(ty(reader)?, Decode::<V>::decode(reader)?),
+            20 => Self::RefTest(Self::decode_ref_test::<V>(reader, false)?),
+            21 => Self::RefTest(Self::decode_ref_test::<V>(reader, true)?),
+            22 => Self::RefCast(Self::decode_ref_test::<V>(reader, false)?),
+            23 => Self::RefCast(Self::decode_ref_test::<V>(reader, true)?),
+            24 => {
+                let (label, from, to) = Self::decode_br_on_cast::<V>(reader)?;
+                Self::BrOnCast { label, from, to }
+            }
+            25 => {
+                let (label, from, to) = Self::decode_br_on_cast::<V>(reader)?;
+                Self::BrOnCastFail { label, from, to }
+            }
+            26 => Self::AnyConvertExtern,
+            27 => Self::ExternConvertAny,
+            28 => Self::RefI31,
+            29 => Self::I31GetS,
+            30 => Self::I31GetU,
+            _ => return Err(DecodeError::InvalidOpcode { value: 0xfb }),
+        })
+    }
+}
+
+impl Display for GcInstr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::StructNew(ty) => write!(f, "struct.new {}", ty.get()),
+            Self::StructNewDefault(ty) => write!(f, "struct.new_default {}", ty.get()),
+            Self::StructGet(ty, field) => write!(f, "struct.get {} {field}", ty.get()),
+            Self::StructGetS(ty, field) => write!(f, "struct.get_s {} {field}", ty.get()),
+            Self::StructGetU(ty, field) => write!(f, "struct.get_u {} {field}", ty.get()),
+            Self::StructSet(ty, field) => write!(f, "struct.set {} {field}", ty.get()),
+            Self::ArrayNew(ty) => write!(f, "array.new {}", ty.get()),
+            Self::ArrayNewDefault(ty) => write!(f, "array.new_default {}", ty.get()),
+            Self::ArrayNewFixed(ty, n) => write!(f, "array.new_fixed {} {n}", ty.get()),
+            Self::ArrayNewData(ty, data) => {
+                write!(f, "array.new_data {} {}", ty.get(), data.get())
+            }
+            Self::ArrayNewElem(ty, elem) => {
+                write!(f, "array.new_elem {} {}", ty.get(), elem.get())
+            }
+            Self::ArrayGet(ty) => write!(f, "array.get {}", ty.get()),
+            Self::ArrayGetS(ty) => write!(f, "array.get_s {}", ty.get()),
+            Self::ArrayGetU(ty) => write!(f, "array.get_u {}", ty.get()),
+            Self::ArraySet(ty) => write!(f, "array.set {}", ty.get()),
+            Self::ArrayLen => f.write_str("array.len"),
+            Self::ArrayFill(ty) => write!(f, "array.fill {}", ty.get()),
+            Self::ArrayCopy(dst, src) => write!(f, "array.copy {} {}", dst.get(), src.get()),
+            Self::ArrayInitData(ty, data) => {
+                write!(f, "array.init_data {} {}", ty.get(), data.get())
+            }
+            Self::ArrayInitElem(ty, elem) => {
+                write!(f, "array.init_elem {} {}", ty.get(), elem.get())
+            }
+            Self::RefTest(ty) => write!(f, "ref.test {ty}"),
+            Self::RefCast(ty) => write!(f, "ref.cast {ty}"),
+            Self::BrOnCast { label, from, to } => {
+                write!(f, "br_on_cast {} {from} {to}", label.get())
+            }
+            Self::BrOnCastFail { label, from, to } => {
+                write!(f, "br_on_cast_fail {} {from} {to}", label.get())
+            }
+            Self::AnyConvertExtern => f.write_str("any.convert_extern"),
+            Self::ExternConvertAny => f.write_str("extern.convert_any"),
+            Self::RefI31 => f.write_str("ref.i31"),
+            Self::I31GetS => f.write_str("i31.get_s"),
+            Self::I31GetU => f.write_str("i31.get_u"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{instructions::Instr, Module, StdVectorFactory, ValidateError};
+
+    #[test]
+    fn decode_gc_module() {
+        // (module
+        //   (rec
+        //     (type $point (struct (field $x (mut i32)) (field $y i8)))
+        //     (type $points (array (ref null $point))))
+        //   (type $f (func (param i32) (result i32)))
+        //   (func (type $f)
+        //     local.get 0
+        //     ref.i31
+        //     i31.get_u))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 18, 2, 78, 2, 95, 2, 127, 1, 120, 0, 94, 99, 0, 0, 96,
+            1, 127, 1, 127, 3, 2, 1, 2, 10, 10, 1, 8, 0, 32, 0, 251, 28, 251, 30, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        assert_eq!(2, module.rectypes().len());
+        assert_eq!(3, module.types().len());
+
+        let Some(Comptype::Struct(fields)) = module.subtype(Typeidx::new(0)).map(|t| &t.comptype)
+        else {
+            panic!()
+        };
+        assert_eq!(Storagetype::Val(Valtype::I32), fields[0].storage);
+        assert!(fields[0].mutable);
+        assert_eq!(Storagetype::I8, fields[1].storage);
+        let Some(Comptype::Array(field)) = module.subtype(Typeidx::new(1)).map(|t| &t.comptype)
+        else {
+            panic!()
+        };
+        let point = Reftype {
+            nullable: true,
+            heaptype: Heaptype::Concrete(Typeidx::new(0)),
+        };
+        assert_eq!(Storagetype::Ref(point), field.storage);
+
+        assert!(matches!(
+            module.funcs()[0].body.instrs(),
+            [
+                Instr::LocalGet(_),
+                Instr::Gc(GcInstr::RefI31),
+                Instr::Gc(GcInstr::I31GetU)
+            ]
+        ));
+        assert_eq!(
+            Err(ValidateError::UnsupportedType { value: 0 }),
+            module.validate()
+        );
+    }
+}
@@ -0,0 +1,169 @@
+// Scoped mutable accesses to the state of an instance (see `ModuleInstance::{mem_mut, globals_mut,
+// table_mut}()`). They borrow the instance, so they can't be held across invocations, and only
+// allow the changes that the guest could also make, which keeps the state consistent with the
+// module (e.g. the size of the memory and the types of the globals).
+use crate::{
+    components::Funcidx,
+    execute::Executor,
+    instance::{FuncHandle, FuncRef},
+    GlobalVal, LinearMemory, StackBuffers, StackStorage, Val, VectorFactory,
+};
+use core::ops::Deref;
+
+// Dereferences to the memory for reading.
+pub struct MemMut<'a, V: VectorFactory, M, S: StackStorage = StackBuffers<'static>> {
+    executor: &'a mut Executor<V, M, S>,
+}
+
+impl<'a, V: VectorFactory, M: LinearMemory, S: StackStorage> MemMut<'a, V, M, S> {
+    pub(crate) fn new(executor: &'a mut Executor<V, M, S>) -> Self {
+        Self { executor }
+    }
+
+    pub fn write(&mut self, offset: usize, data: &[u8]) -> bool {
+        self.executor.mem.write(offset, data)
+    }
+
+    // Same as `memory.grow` (returns the previous size in pages, or `None` if the memory
+    // cannot grow by `delta` pages within the maximum size).
+    pub fn grow(&mut self, delta: u32) -> Option<u32> {
+        self.executor.grow_mem(delta)
+    }
+}
+
+#[cfg(feature = "memory_protection")]
+impl<V: VectorFactory, W: VectorFactory, M: LinearMemory, S: StackStorage>
+    MemMut<'_, V, crate::ProtectedMemory<W, M>, S>
+{
+    // See `ProtectedMemory::protect()`.
+    pub fn protect(&mut self, range: core::ops::Range<usize>, prot: crate::Prot) {
+        self.executor.mem.protect(range, prot);
+    }
+}
+
+impl<V: VectorFactory, M, S: StackStorage> Deref for MemMut<'_, V, M, S> {
+    type Target = M;
+
+    fn deref(&self) -> &Self::Target {
+        &self.executor.mem
+    }
+}
+
+// Dereferences to the globals for reading.
+pub struct GlobalsMut<'a> {
+    globals: &'a mut [GlobalVal],
+}
+
+impl<'a> GlobalsMut<'a> {
+    pub(crate) fn new(globals: &'a mut [GlobalVal]) -> Self {
+        Self { globals }
+    }
+
+    // Returns `false` if there is no such global, it is immutable or `val` is of another type.
+    pub fn set(&mut self, index: usize, val: Val) -> bool {
+        let Some(global) = self.globals.get_mut(index) else {
+            return false;
+        };
+        global.get().ty() == val.ty() && global.set(val)
+    }
+}
+
+impl Deref for GlobalsMut<'_> {
+    type Target = [GlobalVal];
+
+    fn deref(&self) -> &Self::Target {
+        self.globals
+    }
+}
+
+// Dereferences to the elements of the table for reading.
+pub struct TableMut<'a> {
+    table: &'a mut [Option<FuncRef>],
+    module_funcs_len: usize,
+    host_generations: &'a [u64],
+}
+
+impl<'a> TableMut<'a> {
+    pub(crate) fn new(
+        table: &'a mut [Option<FuncRef>],
+        module_funcs_len: usize,
+        host_generations: &'a [u64],
+    ) -> Self {
+        Self {
+            table,
+            module_funcs_len,
+            host_generations,
+        }
+    }
+
+    // Stores a function obtained from `func_handle()` or `table_get()` of the instance
+    // (or clears the element). Returns `false` if the index is out of range or the handle
+    // doesn't refer to a current function of the instance.
+    pub fn set(&mut self, index: usize, func: Option<FuncHandle>) -> bool {
+        if func.is_some_and(|f| !f.is_current(self.module_funcs_len, self.host_generations)) {
+            return false;
+        }
+        let funcidx = func.map(|f| f.funcidx().get());
+        let Some(elem) = self.table.get_mut(index) else {
+            return false;
+        };
+        *elem = funcidx.map(|idx| match idx.checked_sub(self.module_funcs_len) {
+            Some(i) => FuncRef::Host(i),
+            None => FuncRef::Func(Funcidx::new(idx as u32)),
+        });
+        true
+    }
+}
+
+impl Deref for TableMut<'_> {
+    type Target = [Option<FuncRef>];
+
+    fn deref(&self) -> &Self::Target {
+        self.table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LinearMemory, Module, StdVectorFactory, Val, PAGE_SIZE};
+
+    #[test]
+    fn scoped_handles() {
+        // (module
+        //   (memory 1 2)
+        //   (table 2 funcref)
+        //   (global i32 (i32.const 1))
+        //   (global (mut i32) (i32.const 2))
+        //   (func (export "f")))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 2, 1, 0, 4, 4, 1, 112, 0, 2, 5, 4,
+            1, 1, 1, 2, 6, 11, 2, 127, 0, 65, 1, 11, 127, 1, 65, 2, 11, 7, 5, 1, 1, 102, 0, 0, 10,
+            4, 1, 2, 0, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+
+        let mut mem = instance.mem_mut();
+        assert!(mem.write(0, b"hi"));
+        assert!(!mem.write(PAGE_SIZE - 1, b"hi"));
+        assert_eq!(Some(1), mem.grow(1));
+        assert_eq!(None, mem.grow(1));
+        assert_eq!(2 * PAGE_SIZE, mem.size());
+        assert_eq!(b"hi", &instance.mem().as_slice()[..2]);
+
+        let mut globals = instance.globals_mut();
+        assert!(!globals.set(0, Val::I32(3)));
+        assert!(!globals.set(1, Val::I64(3)));
+        assert!(!globals.set(2, Val::I32(3)));
+        assert!(globals.set(1, Val::I32(3)));
+        assert_eq!(Val::I32(3), globals[1].get());
+
+        let f = instance.func_handle("f").expect("func_handle");
+        let mut table = instance.table_mut();
+        assert!(table.set(1, Some(f)));
+        assert!(!table.set(2, Some(f)));
+        assert_eq!(Some(f), instance.table_get(1));
+        assert!(instance.table_mut().set(1, None));
+        assert_eq!(None, instance.table_get(1));
+    }
+}
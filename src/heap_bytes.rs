@@ -0,0 +1,256 @@
+use crate::{
+    components::{
+        ConstantExpr, CustomSection, Data, DataMode, Elem, ElemMode, Export, Expr, Func, Functype,
+        Global, Import, Name,
+    },
+    execute::Executor,
+    instance::InitialState,
+    instructions::Instr,
+    stack_buffers::{Stack, StackStorage},
+    vector::Vector,
+    LinearMemory, Module, ModuleInstance, VectorFactory,
+};
+use core::mem::size_of;
+
+// Bytes allocated by the vectors owned by a value (not including the value itself).
+pub(crate) trait HeapBytes {
+    fn heap_bytes(&self) -> usize;
+}
+
+// Bytes allocated by a vector for its items (not including those owned by the items).
+fn shallow<T, X: Vector<T>>(items: &X) -> usize {
+    items.capacity().saturating_mul(size_of::<T>())
+}
+
+fn deep<T: HeapBytes, X: Vector<T>>(items: &X) -> usize {
+    items.iter().fold(shallow(items), |n, item| {
+        n.saturating_add(item.heap_bytes())
+    })
+}
+
+impl<V: VectorFactory> Module<V> {
+    // Approximates the heap memory held by the decoded module from the capacities of its
+    // vectors (allocator overhead is not included).
+    pub fn approx_heap_bytes(&self) -> usize {
+        let bytes = [
+            deep(&self.types),
+            deep(&self.funcs),
+            deep(&self.imports),
+            deep(&self.globals),
+            deep(&self.elems),
+            deep(&self.datas),
+            deep(&self.exports),
+            deep(&self.custom_sections),
+            self.func_names
+                .iter()
+                .fold(shallow(&self.func_names), |n, (_, name)| {
+                    n.saturating_add(name.heap_bytes())
+                }),
+            #[cfg(feature = "gc")]
+            deep(&self.rectypes),
+        ];
+        bytes.into_iter().fold(0, usize::saturating_add)
+    }
+}
+
+impl<V: VectorFactory, H, M: LinearMemory, S: StackStorage> ModuleInstance<V, H, M, S> {
+    // Same as `Module::approx_heap_bytes()` but also includes the stacks, the globals, the table
+    // and the snapshot for `reset()` of the instance (as well as the module).
+    //
+    // The linear memory, the buffers given by `with_stack_buffers()` and the states of the host
+    // functions are not included (see `mem().size()` for the first).
+    pub fn approx_heap_bytes(&self) -> usize {
+        let bytes = [
+            self.module.approx_heap_bytes(),
+            self.executor.heap_bytes(),
+            shallow(&self.funcs),
+            self.initial.heap_bytes(),
+            shallow(&self.export_index),
+            shallow(&self.imported_global_ids),
+        ];
+        bytes.into_iter().fold(0, usize::saturating_add)
+    }
+}
+
+impl<V: VectorFactory, M, S: StackStorage> HeapBytes for Executor<V, M, S> {
+    fn heap_bytes(&self) -> usize {
+        let bytes = [
+            shallow(&self.table),
+            shallow(&self.dropped_elems),
+            shallow(&self.dropped_datas),
+            shallow(&self.globals),
+            self.locals.heap_bytes(),
+            self.values.heap_bytes(),
+            self.frames.heap_bytes(),
+            self.labels.heap_bytes(),
+            shallow(&self.breakpoints),
+            shallow(&self.trace),
+            self.coverage.as_ref().map_or(0, |coverage| {
+                coverage.iter().fold(shallow(coverage), |n, counts| {
+                    n.saturating_add(shallow(counts))
+                })
+            }),
+        ];
+        bytes.into_iter().fold(0, usize::saturating_add)
+    }
+}
+
+// Caller-provided buffers aren't counted.
+impl<V: VectorFactory, S: StackStorage, T> HeapBytes for Stack<V, S, T> {
+    fn heap_bytes(&self) -> usize {
+        match self {
+            Self::Growable(items) => shallow(items),
+            Self::Fixed { .. } => 0,
+        }
+    }
+}
+
+impl<V: VectorFactory> HeapBytes for InitialState<V> {
+    fn heap_bytes(&self) -> usize {
+        let table = self.table.as_ref().map_or(0, shallow);
+        let mem = self.mem.as_ref().map_or(0, shallow);
+        shallow(&self.globals)
+            .saturating_add(table)
+            .saturating_add(mem)
+    }
+}
+
+impl<V: VectorFactory> HeapBytes for Name<V> {
+    fn heap_bytes(&self) -> usize {
+        shallow(&self.0)
+    }
+}
+
+impl<V: VectorFactory> HeapBytes for Functype<V> {
+    fn heap_bytes(&self) -> usize {
+        shallow(&self.params)
+    }
+}
+
+impl<V: VectorFactory> HeapBytes for Func<V> {
+    fn heap_bytes(&self) -> usize {
+        shallow(&self.locals).saturating_add(self.body.heap_bytes())
+    }
+}
+
+impl<V: VectorFactory> HeapBytes for Expr<V> {
+    fn heap_bytes(&self) -> usize {
+        deep(&self.instrs)
+    }
+}
+
+impl<V: VectorFactory> HeapBytes for ConstantExpr<V> {
+    fn heap_bytes(&self) -> usize {
+        shallow(&self.instrs)
+    }
+}
+
+impl<V: VectorFactory> HeapBytes for Instr<V> {
+    fn heap_bytes(&self) -> usize {
+        match self {
+            Self::Block(block) => deep(&block.instrs),
+            Self::Loop(block) => deep(&block.instrs),
+            Self::If(block) => deep(&block.then_instrs).saturating_add(deep(&block.else_instrs)),
+            Self::BrTable(br_table) => shallow(&br_table.labels),
+            _ => 0,
+        }
+    }
+}
+
+impl<V: VectorFactory> HeapBytes for Import<V> {
+    fn heap_bytes(&self) -> usize {
+        self.module
+            .heap_bytes()
+            .saturating_add(self.name.heap_bytes())
+    }
+}
+
+impl<V: VectorFactory> HeapBytes for Export<V> {
+    fn heap_bytes(&self) -> usize {
+        self.name.heap_bytes()
+    }
+}
+
+impl<V: VectorFactory> HeapBytes for Global<V> {
+    fn heap_bytes(&self) -> usize {
+        self.init.heap_bytes()
+    }
+}
+
+impl<V: VectorFactory> HeapBytes for Elem<V> {
+    fn heap_bytes(&self) -> usize {
+        let offset = match &self.mode {
+            ElemMode::Active { offset, .. } => offset.heap_bytes(),
+            ElemMode::Passive | ElemMode::Declarative => 0,
+        };
+        shallow(&self.init).saturating_add(offset)
+    }
+}
+
+impl<V: VectorFactory> HeapBytes for Data<V> {
+    fn heap_bytes(&self) -> usize {
+        let offset = match &self.mode {
+            DataMode::Active { offset, .. } => offset.heap_bytes(),
+            DataMode::Passive => 0,
+        };
+        shallow(&self.init).saturating_add(offset)
+    }
+}
+
+impl<V: VectorFactory> HeapBytes for CustomSection<V> {
+    fn heap_bytes(&self) -> usize {
+        self.name.heap_bytes().saturating_add(shallow(&self.data))
+    }
+}
+
+#[cfg(feature = "gc")]
+impl<V: VectorFactory> HeapBytes for crate::gc::Rectype<V> {
+    fn heap_bytes(&self) -> usize {
+        deep(&self.subtypes)
+    }
+}
+
+#[cfg(feature = "gc")]
+impl<V: VectorFactory> HeapBytes for crate::gc::Subtype<V> {
+    fn heap_bytes(&self) -> usize {
+        let comptype = match &self.comptype {
+            crate::gc::Comptype::Func { params, results } => {
+                shallow(params).saturating_add(shallow(results))
+            }
+            crate::gc::Comptype::Struct(fields) => shallow(fields),
+            crate::gc::Comptype::Array(_) => 0,
+        };
+        shallow(&self.supertypes).saturating_add(comptype)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Module, StdVectorFactory};
+
+    #[test]
+    fn approx_heap_bytes() {
+        let module =
+            Module::<StdVectorFactory>::decode(&[0, 97, 115, 109, 1, 0, 0, 0]).expect("decode");
+        assert_eq!(0, module.approx_heap_bytes());
+
+        // (module
+        //   (memory 1)
+        //   (func (export "f") (result i32) (i32.const 1))
+        //   (data (i32.const 0) "0123456789"))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 127, 3, 2, 1, 0, 5, 3, 1, 0, 1, 7, 5,
+            1, 1, 102, 0, 0, 10, 6, 1, 4, 0, 65, 1, 11, 11, 16, 1, 0, 65, 0, 11, 10, 48, 49, 50,
+            51, 52, 53, 54, 55, 56, 57,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let module_bytes = module.approx_heap_bytes();
+        assert!(module_bytes > 10, "{module_bytes}");
+
+        let mut instance = module.instantiate(()).expect("instantiate");
+        let instance_bytes = instance.approx_heap_bytes();
+        assert!(instance_bytes > module_bytes);
+        instance.invoke("f", &[]).expect("invoke");
+        assert!(instance.approx_heap_bytes() >= instance_bytes);
+    }
+}
@@ -0,0 +1,194 @@
+use core::fmt::{Formatter, Write};
+
+// Formats a float in the hexadecimal notation (e.g., `0x1.8p1` for `3.0`).
+pub(crate) fn write_f32(f: &mut Formatter<'_>, v: f32) -> core::fmt::Result {
+    // Shifted so that the mantissa consists of whole hex digits.
+    write_bits(f, v.to_bits() as u64, 23, 8, 1)
+}
+
+pub(crate) fn write_f64(f: &mut Formatter<'_>, v: f64) -> core::fmt::Result {
+    write_bits(f, v.to_bits(), 52, 11, 0)
+}
+
+fn write_bits(
+    f: &mut Formatter<'_>,
+    bits: u64,
+    mant_bits: u32,
+    exp_bits: u32,
+    pad: u32,
+) -> core::fmt::Result {
+    let sign = bits >> (mant_bits + exp_bits) != 0;
+    let exp_max = (1 << exp_bits) - 1;
+    let bias = (exp_max >> 1) as i32;
+    let biased = ((bits >> mant_bits) & exp_max) as i32;
+    let mant = bits & ((1 << mant_bits) - 1);
+
+    if sign {
+        f.write_char('-')?;
+    }
+    if biased == exp_max as i32 {
+        return if mant == 0 {
+            f.write_str("inf")
+        } else {
+            write!(f, "nan:0x{mant:x}")
+        };
+    }
+    if biased == 0 && mant == 0 {
+        return f.write_str("0x0p0");
+    }
+
+    let (leading, exp) = if biased == 0 {
+        (0, 1 - bias)
+    } else {
+        (1, biased - bias)
+    };
+    write!(f, "0x{leading}")?;
+    if mant != 0 {
+        let digits = ((mant_bits + pad) / 4) as usize;
+        let mut mant = mant << pad;
+        let mut width = digits;
+        while mant & 0xf == 0 {
+            mant >>= 4;
+            width -= 1;
+        }
+        write!(f, ".{mant:0width$x}")?;
+    }
+    write!(f, "p{exp}")
+}
+
+pub(crate) fn parse_f32(s: &str) -> Option<f32> {
+    parse_bits(s, 23, 8).map(|bits| f32::from_bits(bits as u32))
+}
+
+pub(crate) fn parse_f64(s: &str) -> Option<f64> {
+    parse_bits(s, 52, 11).map(f64::from_bits)
+}
+
+// Parses `[+-]0x<hex>[.<hex>][p[+-]<dec>]`, rounding to the nearest (ties to even).
+fn parse_bits(s: &str, mant_bits: u32, exp_bits: u32) -> Option<u64> {
+    let (sign, s) = match s.as_bytes().first()? {
+        b'-' => (1u64 << (mant_bits + exp_bits), &s[1..]),
+        b'+' => (0, &s[1..]),
+        _ => (0, s),
+    };
+    let exp_max = (1i32 << exp_bits) - 1;
+    if s == "inf" {
+        return Some(sign | ((exp_max as u64) << mant_bits));
+    }
+    if let Some(payload) = s.strip_prefix("nan:0x") {
+        let payload = u64::from_str_radix(payload, 16).ok()?;
+        if payload == 0 || payload >> mant_bits != 0 {
+            return None;
+        }
+        return Some(sign | ((exp_max as u64) << mant_bits) | payload);
+    }
+    let s = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X"))?;
+    let (digits, exp) = match s.find(['p', 'P']) {
+        Some(i) => (&s[..i], s[i + 1..].parse::<i32>().ok()?),
+        None => (s, 0),
+    };
+    let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+    if int.is_empty() && frac.is_empty() {
+        return None;
+    }
+
+    // The value is `m * 2^e`, and `sticky` tells whether the digits dropped from `m` were non-zero.
+    let mut m = 0u64;
+    let mut e = exp;
+    let mut sticky = false;
+    for (i, c) in int.chars().chain(frac.chars()).enumerate() {
+        let d = c.to_digit(16)? as u64;
+        if i >= int.len() {
+            e = e.checked_sub(4)?;
+        }
+        if m >> 60 == 0 {
+            m = (m << 4) | d;
+        } else {
+            sticky |= d != 0;
+            e = e.checked_add(4)?;
+        }
+    }
+    if m == 0 {
+        return Some(sign);
+    }
+
+    let lz = m.leading_zeros();
+    let m = m << lz;
+    let e = e.saturating_sub(lz as i32);
+
+    let bias = exp_max >> 1;
+    let precision = mant_bits + 1;
+    let mut biased = e.saturating_add(63 + bias);
+    let mut shift = 64 - precision;
+    if biased < 1 {
+        // Subnormal (the biased exponent field is zero).
+        shift = (shift as i32)
+            .saturating_add(1i32.saturating_sub(biased))
+            .min(127) as u32;
+        biased = 0;
+    }
+    let m = m as u128;
+    let mut kept = (m >> shift) as u64;
+    let round = (m >> (shift - 1)) & 1 != 0;
+    let rest = m & ((1 << (shift - 1)) - 1) != 0;
+    if round && (rest || sticky || kept & 1 != 0) {
+        kept += 1;
+    }
+
+    if biased == 0 {
+        // A carry out of the mantissa yields the smallest normal number.
+        return Some(sign | kept);
+    }
+    if kept >> precision != 0 {
+        kept >>= 1;
+        biased += 1;
+    }
+    if biased >= exp_max {
+        return Some(sign | ((exp_max as u64) << mant_bits));
+    }
+    Some(sign | ((biased as u64) << mant_bits) | (kept & ((1 << mant_bits) - 1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Display;
+    use std::string::ToString;
+
+    struct Hex64(f64);
+
+    impl Display for Hex64 {
+        fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+            write_f64(f, self.0)
+        }
+    }
+
+    #[test]
+    fn parse_and_format() {
+        assert_eq!(Some(3.0), parse_f64("0x1.8p1"));
+        assert_eq!(Some(-0.5), parse_f64("-0x.8"));
+        assert_eq!(Some(f64::MAX), parse_f64("0x1.fffffffffffffp1023"));
+        assert_eq!(Some(f64::INFINITY), parse_f64("0x1.fffffffffffff8p1023"));
+        assert_eq!(Some(f64::INFINITY), parse_f64("0x1p2147483647"));
+        assert_eq!(Some(0.0), parse_f64("0x1p-2147483648"));
+        assert_eq!(Some(f32::MIN_POSITIVE), parse_f32("0x1.fffffep-127"));
+        assert_eq!(Some(1.0), parse_f32("0x1.000001p0")); // Ties to even.
+        assert_eq!(Some(1.0000002), parse_f32("0x1.000003p0"));
+        assert_eq!(None, parse_f64("0x"));
+        assert_eq!(None, parse_f64("1.5"));
+
+        // Round trip of assorted bit patterns (including subnormals).
+        let mut bits = 1u64;
+        for _ in 0..10000 {
+            bits = bits
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let v = f64::from_bits(bits >> (bits % 13));
+            if v.is_nan() {
+                continue;
+            }
+            let s = Hex64(v).to_string();
+            assert_eq!(Some(v.to_bits()), parse_f64(&s).map(f64::to_bits), "{s}");
+        }
+    }
+}
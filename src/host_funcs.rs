@@ -0,0 +1,322 @@
+use crate::{
+    components::{Importdesc, Valtype},
+    Env, HostFunc, Module, Val, VectorFactory,
+};
+use core::fmt::{Display, Formatter};
+
+// Declares host functions by their signatures and generates a resolver whose fields are
+// the implementations (closures without captures or `fn` items taking the `Env` first):
+//
+// ```ignore
+// nowasm::host_funcs! {
+//     pub struct Host {
+//         "env" {
+//             fn log(i32);
+//             fn add(i32, i32) -> i32;
+//         }
+//     }
+// }
+//
+// let host = Host {
+//     log: |_env, x| println!("{x}"),
+//     add: |_env, a, b| a.wrapping_add(b),
+// };
+// Host::check(&module)?; // Reports unresolved imports and signature mismatches.
+// let instance = module.instantiate(host)?;
+// ```
+//
+// The parameter and result types are `i32`, `i64`, `f32` or `f64`. Functions need distinct
+// names even if they belong to different modules. Stateful host functions should implement
+// `HostFunc` directly.
+#[macro_export]
+macro_rules! host_funcs {
+    (@result) => {
+        None
+    };
+    (@result $result:ident) => {
+        Some(<$result as $crate::HostType>::VALTYPE)
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $($module:literal {
+                $(fn $func:ident($($param:ident),* $(,)?) $(-> $result:ident)?;)*
+            })*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy)]
+        $vis struct $name {
+            $($(pub $func: fn(&mut $crate::Env, $($param),*) $(-> $result)?,)*)*
+        }
+
+        impl $crate::HostFuncTable for $name {
+            const SIGNATURES: &'static [$crate::HostFuncSig] = &[
+                $($($crate::HostFuncSig {
+                    module: $module,
+                    name: stringify!($func),
+                    params: &[$(<$param as $crate::HostType>::VALTYPE),*],
+                    result: $crate::host_funcs!(@result $($result)?),
+                },)*)*
+            ];
+
+            #[allow(unused_assignments, unused_mut, unused_variables)]
+            fn call(
+                &mut self,
+                index: usize,
+                args: &[$crate::Val],
+                env: &mut $crate::Env,
+            ) -> Option<$crate::Val> {
+                let mut i = 0;
+                $($(
+                    if index == i {
+                        let mut args = args.iter().copied();
+                        let result = (self.$func)(
+                            env,
+                            $(<$param as $crate::HostType>::from_val(args.next()?)?),*
+                        );
+                        return $crate::HostResult::into_result(result);
+                    }
+                    i += 1;
+                )*)*
+                None
+            }
+        }
+
+        impl $crate::Resolve for $name {
+            type HostFunc = $crate::TableFunc<$name>;
+
+            fn resolve_func(&self, module: &str, name: &str) -> Option<Self::HostFunc> {
+                $crate::TableFunc::resolve(*self, module, name)
+            }
+        }
+    };
+}
+
+// Signature of a host function declared by `host_funcs!`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostFuncSig {
+    pub module: &'static str,
+    pub name: &'static str,
+    pub params: &'static [Valtype],
+    pub result: Option<Valtype>,
+}
+
+impl Display for HostFuncSig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}.{}(", self.module, self.name)?;
+        for (i, ty) in self.params.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", ty)?;
+        }
+        write!(f, ")")?;
+        if let Some(ty) = self.result {
+            write!(f, " -> {}", ty)?;
+        }
+        Ok(())
+    }
+}
+
+// Implemented by `host_funcs!`.
+pub trait HostFuncTable: Copy {
+    const SIGNATURES: &'static [HostFuncSig];
+
+    // `args` are typed as `SIGNATURES[index].params`.
+    fn call(&mut self, index: usize, args: &[Val], env: &mut Env) -> Option<Val>;
+
+    // Checks that every function imported by `module` is declared with the same signature
+    // (otherwise the instantiation fails with `ExecuteError::UnresolvedImport` or a call traps).
+    fn check<V: VectorFactory>(module: &Module<V>) -> Result<(), HostFuncError> {
+        for (index, import) in module.imports().iter().enumerate() {
+            let Importdesc::Func(typeidx) = import.desc else {
+                continue;
+            };
+            let Some(sig) = Self::SIGNATURES.iter().find(|sig| {
+                sig.module == import.module.as_str() && sig.name == import.name.as_str()
+            }) else {
+                return Err(HostFuncError::Unresolved { index });
+            };
+            let matched = module
+                .types()
+                .get(typeidx.get())
+                .is_some_and(|ty| *ty.params == *sig.params && ty.result.get() == sig.result);
+            if !matched {
+                return Err(HostFuncError::SignatureMismatch {
+                    index,
+                    expected: *sig,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+// A function of a `HostFuncTable`.
+#[derive(Debug, Clone, Copy)]
+pub struct TableFunc<T> {
+    table: T,
+    index: usize,
+}
+
+impl<T: HostFuncTable> TableFunc<T> {
+    pub fn resolve(table: T, module: &str, name: &str) -> Option<Self> {
+        let index = T::SIGNATURES
+            .iter()
+            .position(|sig| sig.module == module && sig.name == name)?;
+        Some(Self { table, index })
+    }
+
+    pub fn signature(&self) -> HostFuncSig {
+        T::SIGNATURES[self.index]
+    }
+}
+
+impl<T: HostFuncTable> HostFunc for TableFunc<T> {
+    fn invoke(&mut self, args: &[Val], env: &mut Env) -> Option<Val> {
+        let sig = self.signature();
+        if !args.iter().map(|v| v.ty()).eq(sig.params.iter().copied()) {
+            // The import has a different type (see `HostFuncTable::check()`).
+            env.abort();
+            return None;
+        }
+        self.table.call(self.index, args, env)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostFuncError {
+    Unresolved { index: usize },
+    SignatureMismatch { index: usize, expected: HostFuncSig },
+}
+
+impl Display for HostFuncError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Unresolved { index } => write!(f, "Unresolved import: {}", index),
+            Self::SignatureMismatch { index, expected } => write!(
+                f,
+                "Signature mismatch for import {}: expected {}",
+                index, expected
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for HostFuncError {}
+
+// Types of the parameters and the results of the functions declared by `host_funcs!`.
+pub trait HostType: Sized {
+    const VALTYPE: Valtype;
+
+    fn from_val(v: Val) -> Option<Self>;
+}
+
+// Return types of the functions declared by `host_funcs!`.
+pub trait HostResult {
+    fn into_result(self) -> Option<Val>;
+}
+
+macro_rules! impl_host_type {
+    ($($ty:ty => $valtype:ident),*) => {
+        $(
+            impl HostType for $ty {
+                const VALTYPE: Valtype = Valtype::$valtype;
+
+                fn from_val(v: Val) -> Option<Self> {
+                    v.try_into().ok()
+                }
+            }
+
+            impl HostResult for $ty {
+                fn into_result(self) -> Option<Val> {
+                    Some(self.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_host_type!(i32 => I32, i64 => I64, f32 => F32, f64 => F64);
+
+impl HostResult for () {
+    fn into_result(self) -> Option<Val> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        components::Valtype, ExecuteError, HostFuncError, HostFuncSig, HostFuncTable, Module,
+        StdVectorFactory, TrapKind,
+    };
+    use core::sync::atomic::{AtomicI32, Ordering};
+
+    crate::host_funcs! {
+        struct Host {
+            "env" {
+                fn log(i32);
+                fn add(i32, i32) -> i32;
+            }
+        }
+    }
+
+    crate::host_funcs! {
+        struct WrongHost {
+            "env" {
+                fn log(i32);
+                fn add(i64, i64) -> i64;
+            }
+        }
+    }
+
+    #[test]
+    fn host_funcs() {
+        // (module
+        //   (import "env" "log" (func $log (param i32)))
+        //   (import "env" "add" (func $add (param i32 i32) (result i32)))
+        //   (func (export "f") (call $log (call $add (i32.const 1) (i32.const 2)))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 14, 3, 96, 1, 127, 0, 96, 2, 127, 127, 1, 127, 96, 0,
+            0, 2, 21, 2, 3, 101, 110, 118, 3, 108, 111, 103, 0, 0, 3, 101, 110, 118, 3, 97, 100,
+            100, 0, 1, 3, 2, 1, 2, 7, 5, 1, 1, 102, 0, 2, 10, 12, 1, 10, 0, 65, 1, 65, 2, 16, 1,
+            16, 0, 11,
+        ];
+        static LOGGED: AtomicI32 = AtomicI32::new(0);
+
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        Host::check(&module).expect("check");
+        let host = Host {
+            log: |_env, x| LOGGED.store(x, Ordering::SeqCst),
+            add: |_env, a, b| a.wrapping_add(b),
+        };
+        let mut instance = module.clone().instantiate(host).expect("instantiate");
+        instance.invoke("f", &[]).expect("invoke");
+        assert_eq!(3, LOGGED.load(Ordering::SeqCst));
+
+        let expected = HostFuncSig {
+            module: "env",
+            name: "add",
+            params: &[Valtype::I64, Valtype::I64],
+            result: Some(Valtype::I64),
+        };
+        assert_eq!(
+            Err(HostFuncError::SignatureMismatch { index: 1, expected }),
+            WrongHost::check(&module)
+        );
+        assert_eq!("env.add(i64, i64) -> i64", expected.to_string());
+
+        let wrong_host = WrongHost {
+            log: |_env, _x| {},
+            add: |_env, a, b| a + b,
+        };
+        let mut instance = module.instantiate(wrong_host).expect("instantiate");
+        assert!(matches!(
+            instance.invoke("f", &[]),
+            Err(ExecuteError::Trap(TrapKind::Aborted))
+        ));
+    }
+}
@@ -0,0 +1,56 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+
+// Identity of the memory of an instance.
+//
+// An imported memory has its own identity (it is a copy of the resolved bytes) unless it aliases
+// the memory of another instance and the identity is given by `Resolve::mem_id()`.
+// Equal identities thus mean that writes made by one instance are visible to the other (e.g.,
+// the data segments of the importer are applied to the memory of the exporter).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MemoryId {
+    instance: u32,
+}
+
+// Identity of a global of an instance.
+//
+// An imported global has its own identity (it is a copy of the resolved value) unless the identity
+// of the exported global is given by `Resolve::global_id()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GlobalId {
+    instance: u32,
+    index: u32,
+}
+
+// Unique (until wrapping around) number identifying an instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct InstanceId(u32);
+
+impl InstanceId {
+    pub(crate) fn next() -> Self {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+
+        #[cfg(target_has_atomic = "32")]
+        let id = NEXT.fetch_add(1, Ordering::Relaxed);
+
+        // Targets without atomic read-modify-write operations are assumed to be single-threaded.
+        #[cfg(not(target_has_atomic = "32"))]
+        let id = {
+            let id = NEXT.load(Ordering::Relaxed);
+            NEXT.store(id.wrapping_add(1), Ordering::Relaxed);
+            id
+        };
+
+        Self(id)
+    }
+
+    pub(crate) const fn memory_id(self) -> MemoryId {
+        MemoryId { instance: self.0 }
+    }
+
+    pub(crate) const fn global_id(self, index: u32) -> GlobalId {
+        GlobalId {
+            instance: self.0,
+            index,
+        }
+    }
+}
@@ -1,15 +1,107 @@
 use crate::{
-    components::{Exportdesc, Funcidx, Functype, Import, Importdesc, Valtype},
-    execute::Executor,
-    ExecuteError, Module, Vector, VectorFactory, PAGE_SIZE,
+    components::{
+        DataMode, ElemMode, Exportdesc, Funcidx, Functype, Globalidx, Import, Importdesc, Typeidx,
+        Valtype,
+    },
+    execute::{
+        grow_mem_pages, Backtrace, ExecStats, Executor, ExecutorConfig, Runtime, StepResult,
+        TraceFrame, MAX_PAGES,
+    },
+    handles::{GlobalsMut, MemMut, TableMut},
+    hex_float,
+    identity::{GlobalId, InstanceId, MemoryId},
+    memory::{pages_to_bytes, LinearMemory, VectorMemory},
+    validate::Validator,
+    ExecuteError, Module, StackBuffers, StackStorage, Vector, VectorFactory, PAGE_SIZE,
+};
+use core::{
+    fmt::{Debug, Display, Formatter},
+    ops::Deref,
+    str::FromStr,
 };
-use core::fmt::{Debug, Formatter};
 
 // TODO: rename
-#[derive(Debug)]
 pub struct Env<'a> {
-    pub mem: &'a mut [u8],
+    pub mem: EnvMem<'a>,
     pub globals: &'a mut [GlobalVal],
+    pub(crate) aborted: bool,
+    pub(crate) caller: &'a dyn Fn() -> Option<TraceFrame>,
+    pub(crate) caller_locals: &'a [Val],
+    pub(crate) call_depth: usize,
+}
+
+impl Env<'_> {
+    // Location of the `call` instruction invoking the host function
+    // (`None` if it's invoked directly by `ModuleInstance::invoke()`).
+    // The instruction offset is computed on demand.
+    pub fn caller(&self) -> Option<TraceFrame> {
+        (self.caller)()
+    }
+
+    // Locals (including the parameters) of the calling function.
+    pub fn caller_locals(&self) -> &[Val] {
+        self.caller_locals
+    }
+
+    // Number of the active Wasm function frames.
+    pub fn call_depth(&self) -> usize {
+        self.call_depth
+    }
+
+    // Makes the call trap with `TrapKind::Aborted` once the host function returns
+    // (the return value is ignored).
+    pub fn abort(&mut self) {
+        self.aborted = true;
+    }
+}
+
+// Dereferences to the memory for reading (see `Env::mem`). Like `MemMut`, it only allows the
+// changes that the guest could also make, so the memory grows by pages within its maximum size.
+pub struct EnvMem<'a> {
+    pub(crate) mem: &'a mut dyn LinearMemory,
+    pub(crate) max_pages: u32,
+    pub(crate) grown: &'a mut usize,
+}
+
+impl EnvMem<'_> {
+    pub fn write(&mut self, offset: usize, data: &[u8]) -> bool {
+        self.mem.write(offset, data)
+    }
+
+    // Same as `memory.grow` (returns the previous size in pages, or `None` if the memory
+    // cannot grow by `delta` pages within the maximum size).
+    pub fn grow(&mut self, delta: u32) -> Option<u32> {
+        grow_mem_pages(self.mem, self.max_pages, delta, self.grown)
+    }
+}
+
+impl<'a> Deref for EnvMem<'a> {
+    type Target = dyn LinearMemory + 'a;
+
+    fn deref(&self) -> &Self::Target {
+        self.mem
+    }
+}
+
+impl Debug for EnvMem<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("EnvMem")
+            .field("size", &self.mem.size())
+            .field("max_pages", &self.max_pages)
+            .finish()
+    }
+}
+
+impl Debug for Env<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Env")
+            .field("mem_size", &self.mem.size())
+            .field("globals", &self.globals)
+            .field("aborted", &self.aborted)
+            .field("caller_locals", &self.caller_locals)
+            .field("call_depth", &self.call_depth)
+            .finish()
+    }
 }
 
 // TODO: rename
@@ -23,14 +115,37 @@ impl HostFunc for () {
     }
 }
 
+// Shared handles let the resolver return the same host function for multiple imports
+// (e.g., a function imported by different names), so that the imports share its state.
+impl<H: HostFunc + ?Sized> HostFunc for &core::cell::RefCell<H> {
+    fn invoke(&mut self, args: &[Val], env: &mut Env) -> Option<Val> {
+        self.borrow_mut().invoke(args, env)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<H: HostFunc + ?Sized> HostFunc for std::rc::Rc<core::cell::RefCell<H>> {
+    fn invoke(&mut self, args: &[Val], env: &mut Env) -> Option<Val> {
+        self.borrow_mut().invoke(args, env)
+    }
+}
+
 #[derive(Debug)]
 pub enum FuncInst<H> {
     Imported { imports_index: usize, host_func: H },
     Module { funcs_index: usize },
+
+    // A host function installed into the table by `ModuleInstance::table_set_host()`
+    // (placed after the functions of the module, see `FuncRef::Host`).
+    Host { ty: Typeidx, host_func: H },
 }
 
 impl<H: HostFunc> FuncInst<H> {
     pub fn get_type<'a, V: VectorFactory>(&self, module: &'a Module<V>) -> Option<&'a Functype<V>> {
+        module.types().get(self.get_typeidx(module)?.get())
+    }
+
+    pub fn get_typeidx<V: VectorFactory>(&self, module: &Module<V>) -> Option<Typeidx> {
         match self {
             FuncInst::Imported { imports_index, .. } => {
                 let Import {
@@ -40,12 +155,13 @@ impl<H: HostFunc> FuncInst<H> {
                 else {
                     return None;
                 };
-                module.types().get(typeidx.get())
+                Some(*typeidx)
             }
             FuncInst::Module { funcs_index } => {
                 let func = module.funcs().get(*funcs_index)?;
-                module.types().get(func.ty.get())
+                Some(func.ty)
             }
+            FuncInst::Host { ty, .. } => Some(*ty),
         }
     }
 }
@@ -58,6 +174,28 @@ pub trait Resolve {
         None
     }
 
+    // Returns `true` if the imported memory should alias the memory passed to
+    // `Module::instantiate_with_memory()` as-is, instead of being initialized with
+    // a copy of the bytes returned by `resolve_mem()`.
+    #[allow(unused_variables)]
+    fn alias_mem(&self, module: &str, name: &str) -> bool {
+        false
+    }
+
+    // Upper bound of the size (in pages) of the imported memory, which `memory.grow` doesn't
+    // exceed even if the import declares a larger maximum (or none).
+    #[allow(unused_variables)]
+    fn mem_max_pages(&self, module: &str, name: &str) -> Option<u32> {
+        None
+    }
+
+    // Identity of the memory the aliased memory import (see `alias_mem()`) refers to,
+    // e.g., `ModuleInstance::memory_id()` of the instance that exports it.
+    #[allow(unused_variables)]
+    fn mem_id(&self, module: &str, name: &str) -> Option<MemoryId> {
+        None
+    }
+
     #[allow(unused_variables)]
     fn resolve_table(&self, module: &str, name: &str) -> Option<&[Option<Funcidx>]> {
         None
@@ -68,6 +206,14 @@ pub trait Resolve {
         None
     }
 
+    // Identity of the global an immutable global import is resolved to, e.g.,
+    // `ModuleInstance::global_id()` of the instance that exports it. Mutable globals are
+    // copied on import, so they always have their own identities.
+    #[allow(unused_variables)]
+    fn global_id(&self, module: &str, name: &str) -> Option<GlobalId> {
+        None
+    }
+
     #[allow(unused_variables)]
     fn resolve_func(&self, module: &str, name: &str) -> Option<Self::HostFunc> {
         None
@@ -78,52 +224,134 @@ impl Resolve for () {
     type HostFunc = ();
 }
 
-pub struct ModuleInstance<V: VectorFactory, H> {
-    pub module: Module<V>,
-    pub executor: Executor<V>,
-    pub funcs: V::Vector<FuncInst<H>>,
+// A value given to an import by `Module::instantiate_with()`.
+#[derive(Debug, Clone)]
+pub enum Extern<'a, H> {
+    Func(H),
+    Table(&'a [Option<Funcidx>]),
+    Memory(&'a [u8]),
+    Global(Val),
+}
+
+pub struct ModuleInstance<
+    V: VectorFactory,
+    H,
+    M = VectorMemory<V>,
+    S: StackStorage = StackBuffers<'static>,
+> {
+    pub(crate) module: Module<V>,
+    pub(crate) executor: Executor<V, M, S>,
+    pub(crate) funcs: V::Vector<FuncInst<H>>,
+    pub(crate) initial: InitialState<V>,
+    // Positions of the exports in `module.exports()`, sorted by name.
+    pub(crate) export_index: V::Vector<usize>,
+    id: InstanceId,
+    // Identities taken over from the resolver.
+    mem_id: Option<MemoryId>,
+    pub(crate) imported_global_ids: V::Vector<Option<GlobalId>>,
+    // Generations of the host functions installed by `table_set_host()` (see `FuncHandle`).
+    host_generations: V::Vector<u64>,
+    last_host_generation: u64,
 }
 
-impl<V: VectorFactory, H: HostFunc> ModuleInstance<V, H> {
-    pub(crate) fn new<R>(module: Module<V>, resolver: R) -> Result<Self, ExecuteError>
+// What an instance was instantiated with, kept to be able to reset it.
+pub(crate) struct InitialState<V: VectorFactory> {
+    pub(crate) globals: V::Vector<GlobalVal>,
+    pub(crate) table: Option<V::Vector<Option<Funcidx>>>,
+    pub(crate) mem: Option<V::Vector<u8>>,
+    aliased_mem: bool,
+    mem_size: usize,
+    funcs_len: usize,
+}
+
+impl<V: VectorFactory, H: HostFunc, M: LinearMemory, S: StackStorage> ModuleInstance<V, H, M, S> {
+    pub(crate) fn new<R>(module: Module<V>, resolver: R, mem: M) -> Result<Self, ExecuteError>
     where
         R: Resolve<HostFunc = H>,
+    {
+        let aliased_mem = module.imports().iter().any(|import| {
+            matches!(import.desc, Importdesc::Mem(_))
+                && resolver.alias_mem(import.module.as_str(), import.name.as_str())
+        });
+        let mem_max_pages = module
+            .imports()
+            .iter()
+            .find_map(|import| match import.desc {
+                Importdesc::Mem(_) => {
+                    resolver.mem_max_pages(import.module.as_str(), import.name.as_str())
+                }
+                _ => None,
+            });
+        let resolve = |_, import: &Import<V>| {
+            let (module, name) = (import.module.as_str(), import.name.as_str());
+            match import.desc {
+                Importdesc::Func(_) => resolver.resolve_func(module, name).map(Extern::Func),
+                Importdesc::Table(_) => resolver.resolve_table(module, name).map(Extern::Table),
+                Importdesc::Mem(_) => resolver.resolve_mem(module, name).map(Extern::Memory),
+                Importdesc::Global(_) => resolver.resolve_global(module, name).map(Extern::Global),
+            }
+        };
+        let mut this = Self::new_with_externs(module, resolve, aliased_mem, mem_max_pages, mem)?;
+        for import in this.module.imports() {
+            let (module, name) = (import.module.as_str(), import.name.as_str());
+            match import.desc {
+                Importdesc::Mem(_) if aliased_mem => this.mem_id = resolver.mem_id(module, name),
+                Importdesc::Global(ty) => {
+                    let id = resolver.global_id(module, name).filter(|_| ty.is_const());
+                    this.imported_global_ids.push(id);
+                }
+                _ => {}
+            }
+        }
+        Ok(this)
+    }
+
+    // `resolve` is called with the index of each import (except for the aliased memory).
+    pub(crate) fn new_with_externs<'a, F>(
+        module: Module<V>,
+        mut resolve: F,
+        aliased_mem: bool,
+        mem_max_pages: Option<u32>,
+        mem: M,
+    ) -> Result<Self, ExecuteError>
+    where
+        F: FnMut(usize, &Import<V>) -> Option<Extern<'a, H>>,
     {
         let mut imported_mem = None;
         let mut imported_table = None;
         let mut imported_globals = V::create_vector(None);
         let mut imported_funcs = V::create_vector(None);
         for (index, import) in module.imports().iter().enumerate() {
-            match &import.desc {
-                Importdesc::Func(_typeidx) => {
-                    let host_func = resolver
-                        .resolve_func(import.module.as_str(), import.name.as_str())
-                        .ok_or(ExecuteError::UnresolvedImport { index })?;
+            trace!(
+                "resolving import {index} ({}.{})",
+                import.module.as_str(),
+                import.name.as_str()
+            );
+            if aliased_mem && matches!(import.desc, Importdesc::Mem(_)) {
+                continue;
+            }
+            let resolved =
+                resolve(index, import).ok_or(ExecuteError::UnresolvedImport { index })?;
+            match (&import.desc, resolved) {
+                (Importdesc::Func(_typeidx), Extern::Func(host_func)) => {
                     imported_funcs.push(FuncInst::Imported {
                         imports_index: index,
                         host_func,
                     });
                 }
-                Importdesc::Table(_ty) => {
-                    let resolved = resolver
-                        .resolve_table(import.module.as_str(), import.name.as_str())
-                        .ok_or(ExecuteError::UnresolvedImport { index })?;
-                    let resolved = V::clone_vector(resolved);
-                    imported_table = Some(resolved);
+                (Importdesc::Table(_ty), Extern::Table(resolved)) => {
+                    imported_table = Some(V::clone_vector(resolved));
                 }
-                Importdesc::Mem(_ty) => {
-                    let resolved = resolver
-                        .resolve_mem(import.module.as_str(), import.name.as_str())
-                        .ok_or(ExecuteError::UnresolvedImport { index })?;
-                    let resolved = V::clone_vector(resolved);
+                (Importdesc::Mem(_ty), Extern::Memory(resolved)) => {
                     imported_mem = Some(resolved);
                 }
-                Importdesc::Global(ty) => {
-                    let resolved = resolver
-                        .resolve_global(import.module.as_str(), import.name.as_str())
-                        .ok_or(ExecuteError::UnresolvedImport { index })?;
+                (Importdesc::Global(ty), Extern::Global(resolved)) => {
+                    if resolved.ty() != ty.valtype() {
+                        return Err(ExecuteError::InvalidImportedGlobal { index });
+                    }
                     imported_globals.push(GlobalVal::new(ty.is_const(), resolved));
                 }
+                _ => return Err(ExecuteError::ExternKindMismatch { index }),
             }
         }
 
@@ -132,18 +360,61 @@ impl<V: VectorFactory, H: HostFunc> ModuleInstance<V, H> {
             funcs.push(FuncInst::Module { funcs_index: i });
         }
 
-        let globals = Self::init_globals(&imported_globals, &module)?;
-        let mem = Self::init_mem(&globals, imported_mem, &module)?;
-        let table = Self::init_table(&globals, &funcs, imported_table, &module)?;
+        let mut globals = V::create_vector(None);
+        Self::init_globals(&mut globals, &imported_globals, &module)?;
+        let mem = Self::init_mem(&globals, imported_mem, aliased_mem, mem, &module)?;
+        // The limit given by the resolver has to admit the imported memory as well.
+        if let Some(max) = mem_max_pages {
+            if mem.size() > pages_to_bytes(max).unwrap_or(usize::MAX) {
+                return Err(ExecuteError::InvalidImportedMem);
+            }
+        }
+        let mem_max_pages = module
+            .memtype()
+            .map_or(0, |ty| ty.limits.max.unwrap_or(MAX_PAGES))
+            .min(mem_max_pages.unwrap_or(MAX_PAGES));
+        let mut table = V::create_vector(None);
+        Self::init_table(
+            &mut table,
+            imported_table.as_deref(),
+            &globals,
+            &funcs,
+            &module,
+        )?;
 
-        let executor = Executor::<V>::new(mem, table, globals);
+        let initial = InitialState {
+            globals: imported_globals,
+            table: imported_table,
+            mem: imported_mem.map(V::clone_vector),
+            aliased_mem,
+            mem_size: mem.size(),
+            funcs_len: funcs.len(),
+        };
+        trace!(
+            "initialized {} globals, {} bytes of memory and {} table elements",
+            globals.len(),
+            mem.size(),
+            table.len()
+        );
+        let mut executor: Executor<V, M, S> = Executor::new(mem, table, globals);
+        executor.mem_max_pages = mem_max_pages;
+        Self::init_dropped_segments(&mut executor, &module);
+        let export_index = Self::index_exports(&module);
         let mut this = Self {
             module,
             executor,
             funcs,
+            initial,
+            export_index,
+            id: InstanceId::next(),
+            mem_id: None,
+            imported_global_ids: V::create_vector(None),
+            host_generations: V::create_vector(None),
+            last_host_generation: 0,
         };
 
         if let Some(funcidx) = this.module.start() {
+            trace!("calling start function {}", funcidx.get());
             // TODO: check function type (in decoding phase?)
             this.executor
                 .call_function(funcidx, &mut this.funcs, &this.module)?;
@@ -152,12 +423,56 @@ impl<V: VectorFactory, H: HostFunc> ModuleInstance<V, H> {
         Ok(this)
     }
 
+    // Restores the memory, globals and table to their freshly instantiated state without
+    // decoding or validating the module again. Host functions installed into the table after
+    // the instantiation are removed, while the breakpoints and the executor config are kept.
+    //
+    // If `run_start` is `false`, the state is the one before calling the start function.
+    // A memory aliased by `Resolve::alias_mem()` is left as is.
+    // Fails if the memory cannot be reset (see `LinearMemory::reset()`).
+    pub fn reset(&mut self, run_start: bool) -> Result<(), ExecuteError> {
+        let initial = &self.initial;
+        let executor = &mut self.executor;
+        self.funcs.truncate(initial.funcs_len);
+        self.host_generations.clear();
+        executor.clear_stacks();
+
+        Self::init_globals(&mut executor.globals, &initial.globals, &self.module)?;
+        // An aliased memory is owned by the other instances sharing it as well, so neither the
+        // memory nor the data segments written into it are restored.
+        if !initial.aliased_mem {
+            if self.module.memtype().is_some() {
+                if !executor.mem.reset(initial.mem_size) {
+                    return Err(ExecuteError::MemoryResetFailed);
+                }
+                if let Some(v) = &initial.mem {
+                    executor.mem.write(0, v);
+                }
+            }
+            Self::init_datas(&mut executor.mem, &executor.globals, &self.module)?;
+        }
+        Self::init_table(
+            &mut executor.table,
+            initial.table.as_deref(),
+            &executor.globals,
+            &self.funcs,
+            &self.module,
+        )?;
+        Self::init_dropped_segments(executor, &self.module);
+
+        if let Some(funcidx) = self.module.start().filter(|_| run_start) {
+            executor.call_function(funcidx, &mut self.funcs, &self.module)?;
+        }
+        Ok(())
+    }
+
     fn init_globals(
+        globals: &mut V::Vector<GlobalVal>,
         imported_globals: &[GlobalVal],
         module: &Module<V>,
-    ) -> Result<V::Vector<GlobalVal>, ExecuteError> {
-        let n = imported_globals.len() + module.globals().len();
-        let mut globals = V::create_vector(Some(n));
+    ) -> Result<(), ExecuteError> {
+        globals.clear();
+        globals.reserve(imported_globals.len() + module.globals().len());
 
         for global in imported_globals {
             globals.push(*global);
@@ -169,189 +484,675 @@ impl<V: VectorFactory, H: HostFunc> ModuleInstance<V, H> {
                 .ok_or(ExecuteError::InvalidGlobal { index })?;
             globals.push(v);
         }
-        Ok(globals)
+        Ok(())
     }
 
     fn init_mem(
         globals: &[GlobalVal],
-        mut mem: Option<V::Vector<u8>>,
+        imported_mem: Option<&[u8]>,
+        aliased_mem: bool,
+        mut mem: M,
         module: &Module<V>,
-    ) -> Result<V::Vector<u8>, ExecuteError> {
-        if let Some(ty) = module.mem() {
-            if let Some(v) = &mem {
+    ) -> Result<M, ExecuteError> {
+        if let Some(ty) = module.memtype() {
+            if aliased_mem {
+                let size = mem.size();
+                if !ty.contains(size) || !size.is_multiple_of(PAGE_SIZE) {
+                    return Err(ExecuteError::InvalidImportedMem);
+                }
+            } else if let Some(v) = imported_mem {
                 if !ty.contains(v.len()) || v.len() % PAGE_SIZE != 0 {
                     return Err(ExecuteError::InvalidImportedMem);
                 }
+                if !mem.grow(v.len().saturating_sub(mem.size())) || !mem.write(0, v) {
+                    return Err(ExecuteError::InvalidImportedMem);
+                }
             } else {
-                let mut m = V::create_vector(Some(ty.min_bytes()));
-                for _ in 0..ty.min_bytes() {
-                    m.push(0);
+                let min =
+                    pages_to_bytes(ty.limits.min).ok_or(ExecuteError::MemoryAllocationFailed)?;
+                if !mem.grow(min.saturating_sub(mem.size())) {
+                    return Err(ExecuteError::MemoryAllocationFailed);
                 }
-                mem = Some(m);
             }
-        } else if mem.is_some() {
+        } else if imported_mem.is_some() {
             return Err(ExecuteError::InvalidImportedMem);
         }
 
-        let mut mem = mem.unwrap_or_else(|| V::create_vector(None));
+        Self::init_datas(&mut mem, globals, module)?;
+        Ok(mem)
+    }
+
+    fn init_datas(
+        mem: &mut M,
+        globals: &[GlobalVal],
+        module: &Module<V>,
+    ) -> Result<(), ExecuteError> {
         for (index, data) in module.datas().iter().enumerate() {
-            if module.mem().is_none() {
+            let DataMode::Active { offset, .. } = &data.mode else {
+                continue;
+            };
+            if module.memtype().is_none() {
                 return Err(ExecuteError::InvalidData { index });
             }
-            let Some(offset) = data.offset.get(globals) else {
+            let Ok(Val::I32(offset)) = offset.evaluate(globals) else {
                 return Err(ExecuteError::InvalidData { index });
             };
-            if offset < 0 {
+            // Offsets are interpreted as unsigned 32-bit integers.
+            if !mem.write(offset as u32 as usize, &data.init) {
                 return Err(ExecuteError::InvalidData { index });
             }
-
-            let start = offset as usize;
-            let end = start + data.init.len();
-            if mem.len() < end {
-                return Err(ExecuteError::InvalidData { index });
-            }
-            mem[start..end].copy_from_slice(&data.init);
         }
-
-        Ok(mem)
+        Ok(())
     }
 
     fn init_table(
+        table: &mut V::Vector<Option<FuncRef>>,
+        imported_table: Option<&[Option<Funcidx>]>,
         globals: &[GlobalVal],
         funcs: &[FuncInst<H>],
-        mut table: Option<V::Vector<Option<Funcidx>>>,
         module: &Module<V>,
-    ) -> Result<V::Vector<Option<Funcidx>>, ExecuteError> {
-        if let Some(ty) = module.table() {
-            if let Some(v) = &table {
+    ) -> Result<(), ExecuteError> {
+        table.clear();
+        if let Some(ty) = module.tabletype() {
+            if let Some(v) = imported_table {
                 if !ty.contains(v.len()) {
                     return Err(ExecuteError::InvalidImportedTable);
                 }
-            } else {
-                let mut vs = V::create_vector(Some(ty.limits.min as usize));
-                for _ in 0..ty.limits.min {
-                    vs.push(None);
+                for funcidx in v {
+                    table.push(funcidx.map(FuncRef::Func));
                 }
-                table = Some(vs);
+            } else {
+                table.extend_with(ty.limits.min as usize, None);
             }
-        } else if table.is_some() {
+        } else if imported_table.is_some() {
             return Err(ExecuteError::InvalidImportedTable);
         }
 
-        let mut table = table.unwrap_or_else(|| V::create_vector(None));
         for (index, elem) in module.elems().iter().enumerate() {
-            if module.table().is_none() {
+            let ElemMode::Active { offset, .. } = &elem.mode else {
+                continue;
+            };
+            if module.tabletype().is_none() {
                 return Err(ExecuteError::InvalidElem { index });
             }
-            let Some(offset) = elem.offset.get(globals) else {
+            let Ok(Val::I32(offset)) = offset.evaluate(globals) else {
                 return Err(ExecuteError::InvalidElem { index });
             };
-            if offset < 0 {
-                return Err(ExecuteError::InvalidElem { index });
-            }
-
-            let start = offset as usize;
-            let end = start + elem.init.len();
-            if table.len() < end {
+            let start = offset as u32 as usize;
+            let end = start.checked_add(elem.init.len());
+            if end.is_none_or(|end| table.len() < end) {
                 return Err(ExecuteError::InvalidElem { index });
             }
             for (i, funcidx) in (start..).zip(elem.init.iter().copied()) {
-                table[i] = Some(funcidx);
+                table[i] = funcidx.map(FuncRef::Func);
             }
         }
 
         if table
             .iter()
-            .filter_map(|i| *i)
-            .any(|i| funcs.len() <= i.get())
+            .any(|r| matches!(r, Some(FuncRef::Func(i)) if funcs.len() <= i.get()))
         {
             return Err(ExecuteError::InvalidFuncidx);
         }
 
-        Ok(table)
+        Ok(())
+    }
+
+    // Only passive segments remain available to `table.init` and `memory.init`
+    // after instantiation.
+    fn init_dropped_segments(executor: &mut Executor<V, M, S>, module: &Module<V>) {
+        executor.dropped_elems.clear();
+        for elem in module.elems() {
+            let dropped = !matches!(elem.mode, ElemMode::Passive);
+            executor.dropped_elems.push(dropped);
+        }
+        executor.dropped_datas.clear();
+        for data in module.datas() {
+            let dropped = !matches!(data.mode, DataMode::Passive);
+            executor.dropped_datas.push(dropped);
+        }
     }
 
     pub fn module(&self) -> &Module<V> {
         &self.module
     }
 
-    pub fn mem(&self) -> &[u8] {
+    pub fn executor_config(&self) -> ExecutorConfig {
+        self.executor.config
+    }
+
+    // Note that the start function has already been run with the default configuration.
+    pub fn set_executor_config(&mut self, config: ExecutorConfig) {
+        self.executor.config = config;
+    }
+
+    pub fn stats(&self) -> ExecStats {
+        self.executor.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.executor.stats = ExecStats::default();
+        self.executor.instrs_at_call = 0;
+    }
+
+    // Reserves room for `values` values (operands and locals) and `frames` nested calls.
+    pub fn reserve_stacks(&mut self, values: usize, frames: usize) {
+        self.executor.reserve_stacks(values, frames);
+    }
+
+    // Replaces the stacks with caller-provided buffers (discarding the pending call, if any),
+    // which ties the instance to their lifetime. Afterwards, a call whose callee may not fit in
+    // the rest of the buffers traps with `TrapKind::StackExhausted` instead of growing the stacks.
+    pub fn with_stack_buffers(
+        self,
+        buffers: StackBuffers<'_>,
+    ) -> Result<ModuleInstance<V, H, M, StackBuffers<'_>>, ExecuteError> {
+        let frame_sizes = Validator::new(&self.module)
+            .validate_with_frame_sizes()
+            .map_err(ExecuteError::InvalidModule)?;
+        Ok(ModuleInstance {
+            module: self.module,
+            executor: self.executor.with_stack_buffers(buffers, frame_sizes),
+            funcs: self.funcs,
+            initial: self.initial,
+            export_index: self.export_index,
+            id: self.id,
+            mem_id: self.mem_id,
+            imported_global_ids: self.imported_global_ids,
+            host_generations: self.host_generations,
+            last_host_generation: self.last_host_generation,
+        })
+    }
+
+    pub fn runtime(&self) -> Runtime<'_, V, M, S> {
+        Runtime::new(&self.executor)
+    }
+
+    pub fn mem(&self) -> &M {
         &self.executor.mem
     }
 
-    pub fn mem_mut(&mut self) -> &mut [u8] {
-        &mut self.executor.mem
+    pub fn mem_mut(&mut self) -> MemMut<'_, V, M, S> {
+        MemMut::new(&mut self.executor)
     }
 
     pub fn globals(&self) -> &[GlobalVal] {
         &self.executor.globals
     }
 
-    pub fn globals_mut(&mut self) -> &mut [GlobalVal] {
-        &mut self.executor.globals
+    // Returns `None` if the module has no memory.
+    pub fn memory_id(&self) -> Option<MemoryId> {
+        self.module.memtype()?;
+        Some(self.mem_id.unwrap_or(self.id.memory_id()))
+    }
+
+    pub fn global_id(&self, idx: Globalidx) -> Option<GlobalId> {
+        if idx.get() >= self.executor.globals.len() {
+            return None;
+        }
+        let imported = self.imported_global_ids.get(idx.get()).copied().flatten();
+        Some(imported.unwrap_or(self.id.global_id(idx.get() as u32)))
+    }
+
+    pub fn globals_mut(&mut self) -> GlobalsMut<'_> {
+        GlobalsMut::new(&mut self.executor.globals)
     }
 
-    pub fn table(&self) -> &[Option<Funcidx>] {
+    pub fn table(&self) -> &[Option<FuncRef>] {
         &self.executor.table
     }
 
-    pub fn table_mut(&mut self) -> &mut [Option<Funcidx>] {
-        &mut self.executor.table
+    pub fn table_mut(&mut self) -> TableMut<'_> {
+        TableMut::new(
+            &mut self.executor.table,
+            self.initial.funcs_len,
+            &self.host_generations,
+        )
     }
 
     pub fn funcs(&self) -> &[FuncInst<H>] {
         &self.funcs
     }
 
+    // Host functions resolved for the imports (in the order of the imports).
+    pub fn host_funcs(&self) -> impl '_ + Iterator<Item = (&Import<V>, &H)> {
+        self.funcs.iter().filter_map(|func| match func {
+            FuncInst::Imported {
+                imports_index,
+                host_func,
+            } => Some((self.module.imports().get(*imports_index)?, host_func)),
+            _ => None,
+        })
+    }
+
+    pub fn host_func(&self, module: &str, name: &str) -> Option<&H> {
+        let i = self.imported_func_position(module, name)?;
+        match &self.funcs[i] {
+            FuncInst::Imported { host_func, .. } => Some(host_func),
+            _ => None,
+        }
+    }
+
+    pub fn host_func_mut(&mut self, module: &str, name: &str) -> Option<&mut H> {
+        let i = self.imported_func_position(module, name)?;
+        match &mut self.funcs[i] {
+            FuncInst::Imported { host_func, .. } => Some(host_func),
+            _ => None,
+        }
+    }
+
+    fn imported_func_position(&self, module: &str, name: &str) -> Option<usize> {
+        let imports = self.module.imports();
+        self.funcs.iter().position(|func| {
+            let FuncInst::Imported { imports_index, .. } = func else {
+                return false;
+            };
+            imports.get(*imports_index).is_some_and(|import| {
+                import.module.as_str() == module && import.name.as_str() == name
+            })
+        })
+    }
+
+    pub fn backtrace(&self) -> Backtrace<'_, V> {
+        Backtrace {
+            module: &self.module,
+            frames: &self.executor.trace,
+        }
+    }
+
+    // Returns a handle to the function stored in the given table slot,
+    // or `None` if the slot is out of range or uninitialized.
+    pub fn table_get(&self, index: usize) -> Option<FuncHandle> {
+        let funcref = (*self.executor.table.get(index)?)?;
+        let generation = match funcref {
+            FuncRef::Func(_) => 0,
+            FuncRef::Host(i) => self.host_generations[i],
+        };
+        Some(FuncHandle {
+            funcidx: funcref.funcidx(self.initial.funcs_len),
+            generation,
+        })
+    }
+
+    // Stores a host function of the type `ty` into the given table slot, so that
+    // `call_indirect` can dispatch to it (and returns a handle to the function).
+    //
+    // A host function that is no longer stored in any slot is replaced by the new one, so
+    // there are never more host functions than table slots. The handles to the replaced
+    // function become stale (see `FuncHandle`).
+    pub fn table_set_host(
+        &mut self,
+        index: usize,
+        ty: Typeidx,
+        host_func: H,
+    ) -> Result<FuncHandle, ExecuteError> {
+        if self.module.types().get(ty.get()).is_none() {
+            return Err(ExecuteError::InvalidTypeidx);
+        }
+        let table = &mut self.executor.table;
+        let Some(slot) = table.get_mut(index) else {
+            return Err(ExecuteError::InvalidTableIndex { index });
+        };
+        *slot = None;
+
+        let base = self.initial.funcs_len;
+        let mut in_use = V::create_vector(None);
+        in_use.extend_with(self.funcs.len() - base, false);
+        for funcref in table.iter() {
+            if let Some(FuncRef::Host(i)) = funcref {
+                in_use[*i] = true;
+            }
+        }
+        let func = FuncInst::Host { ty, host_func };
+        self.last_host_generation += 1;
+        let generation = self.last_host_generation;
+        let i = if let Some(i) = in_use.iter().position(|used| !used) {
+            self.funcs[base + i] = func;
+            self.host_generations[i] = generation;
+            i
+        } else {
+            self.funcs.push(func);
+            self.host_generations.push(generation);
+            in_use.len()
+        };
+        table[index] = Some(FuncRef::Host(i));
+        Ok(FuncHandle {
+            funcidx: FuncRef::Host(i).funcidx(base),
+            generation,
+        })
+    }
+
+    // Looks up an export by name in O(log n).
+    pub fn export(&self, name: &str) -> Option<Exportdesc> {
+        let exports = self.module.exports();
+        let i = self
+            .export_index
+            .binary_search_by(|&i| exports[i].name.as_str().cmp(name))
+            .ok()?;
+        Some(exports[self.export_index[i]].desc)
+    }
+
+    // Returns the current value of an exported global
+    // (`None` if there is no export of the name or it isn't a global).
+    pub fn get_global_export(&self, name: &str) -> Option<Val> {
+        let Some(Exportdesc::Global(idx)) = self.export(name) else {
+            return None;
+        };
+        self.globals().get(idx.get()).map(|global| global.get())
+    }
+
+    // Resolves an exported function once, so that it can be called repeatedly by
+    // `invoke_handle()` without looking up the name.
+    pub fn func_handle(&self, function_name: &str) -> Result<FuncHandle, ExecuteError> {
+        let funcidx = self.exported_func(function_name)?;
+        Ok(FuncHandle {
+            funcidx,
+            generation: 0,
+        })
+    }
+
+    // The stacks of the executor are kept across invocations, so once they have grown to
+    // the high-water mark (or have been reserved by `reserve_stacks()`), invoking a function
+    // doesn't allocate unless the host functions or a trap (recording the backtrace) do.
+    //
+    // Fails with `ExecuteError::CallPending` while an invocation started by `start_invoke()`
+    // is pending (as do the other invocation methods).
     pub fn invoke(
         &mut self,
         function_name: &str,
         args: &[Val],
     ) -> Result<Option<Val>, ExecuteError> {
-        let Some(export) = self.module.exports().iter().find(|export| {
-            matches!(export.desc, Exportdesc::Func(_)) && function_name == export.name.as_str()
-        }) else {
-            return Err(ExecuteError::NotExportedFunction);
-        };
-        let Exportdesc::Func(func_idx) = export.desc else {
-            unreachable!();
+        let func_idx = self.exported_func(function_name)?;
+        self.invoke_funcidx(func_idx, args)
+    }
+
+    // Invokes the exported functions in order and returns their results.
+    //
+    // All the names and arguments are checked before the first call, so an unknown name or
+    // a mismatched argument is reported without invoking anything. If a call fails, the
+    // effects of the preceding calls remain.
+    pub fn invoke_many(
+        &mut self,
+        calls: &[(&str, &[Val])],
+    ) -> Result<V::Vector<Option<Val>>, ExecuteError> {
+        let mut funcidxs = V::create_vector(Some(calls.len()));
+        for &(function_name, args) in calls {
+            let func_idx = self.exported_func(function_name)?;
+            self.func_type(func_idx)?
+                .validate_args(args, &self.module)?;
+            funcidxs.push(func_idx);
+        }
+
+        let mut results = V::create_vector(Some(calls.len()));
+        for (&func_idx, &(_, args)) in funcidxs.iter().zip(calls.iter()) {
+            self.executor.push_args(args)?;
+            let result = self
+                .executor
+                .call_function(func_idx, &mut self.funcs, &self.module)?;
+            results.push(result);
+        }
+        Ok(results)
+    }
+
+    // Parses `args` as the parameter types of the function (see `Val::parse_as()`)
+    // and formats the result by `Display` (e.g., for REPLs and script bindings).
+    #[cfg(feature = "std")]
+    pub fn invoke_dyn(
+        &mut self,
+        function_name: &str,
+        args: &[&str],
+    ) -> Result<Option<String>, InvokeDynError> {
+        let func_idx = self.exported_func(function_name)?;
+        let params = &self.func_type(func_idx)?.params;
+        if params.len() != args.len() {
+            return Err(ExecuteError::InvalidFuncArgs.into());
+        }
+        let args = params
+            .iter()
+            .zip(args)
+            .enumerate()
+            .map(|(index, (&ty, arg))| {
+                Val::parse_as(ty, arg).map_err(|error| InvokeDynError::InvalidArg { index, error })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let result = self.invoke_funcidx(func_idx, &args)?;
+        Ok(result.map(|v| v.to_string()))
+    }
+
+    // Invokes a function obtained from `func_handle()` or `table_get()` of this instance.
+    // Fails with `ExecuteError::StaleFuncHandle` if the function has been replaced.
+    pub fn invoke_handle(
+        &mut self,
+        handle: FuncHandle,
+        args: &[Val],
+    ) -> Result<Option<Val>, ExecuteError> {
+        if !handle.is_current(self.initial.funcs_len, &self.host_generations) {
+            return Err(ExecuteError::StaleFuncHandle);
+        }
+        self.invoke_funcidx(handle.funcidx, args)
+    }
+
+    fn invoke_funcidx(
+        &mut self,
+        func_idx: Funcidx,
+        args: &[Val],
+    ) -> Result<Option<Val>, ExecuteError> {
+        self.func_type(func_idx)?
+            .validate_args(args, &self.module)?;
+
+        self.executor.push_args(args)?;
+
+        self.executor
+            .call_function(func_idx, &mut self.funcs, &self.module)
+    }
+
+    // Starts an invocation that proceeds by `step()` or `resume()`.
+    pub fn start_invoke(&mut self, function_name: &str, args: &[Val]) -> Result<(), ExecuteError> {
+        let func_idx = self.exported_func(function_name)?;
+        self.func_type(func_idx)?
+            .validate_args(args, &self.module)?;
+
+        self.executor.push_args(args)?;
+        self.executor
+            .start_call(func_idx, &mut self.funcs, &self.module)
+    }
+
+    pub fn step(&mut self) -> Result<StepResult, ExecuteError> {
+        self.executor
+            .run(&mut self.funcs, &self.module, Some(1), true)
+    }
+
+    pub fn resume(&mut self) -> Result<StepResult, ExecuteError> {
+        self.executor.run(&mut self.funcs, &self.module, None, true)
+    }
+
+    // Same as `resume()` but executes at most `fuel` instructions
+    // (returns `StepResult::Running` if the invocation is still pending).
+    pub fn run(&mut self, fuel: usize) -> Result<StepResult, ExecuteError> {
+        self.executor
+            .run(&mut self.funcs, &self.module, Some(fuel), true)
+    }
+
+    // Discards the pending invocation (if any) without running it to the end.
+    pub(crate) fn cancel_invoke(&mut self) {
+        self.executor.clear_stacks();
+    }
+
+    // Positions of the frames of the pending call, innermost first.
+    pub fn call_stack(&self) -> impl '_ + Iterator<Item = TraceFrame> {
+        self.executor.call_stack(&self.module)
+    }
+
+    pub fn set_breakpoint(&mut self, funcidx: Funcidx, instr_offset: usize) {
+        if !self.executor.breakpoints.contains(&(funcidx, instr_offset)) {
+            self.executor.breakpoints.push((funcidx, instr_offset));
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, funcidx: Funcidx, instr_offset: usize) -> bool {
+        let Some(i) = self
+            .executor
+            .breakpoints
+            .iter()
+            .position(|&bp| bp == (funcidx, instr_offset))
+        else {
+            return false;
         };
+        self.executor.breakpoints.remove_range(i..i + 1);
+        true
+    }
+
+    pub fn breakpoints(&self) -> &[(Funcidx, usize)] {
+        &self.executor.breakpoints
+    }
 
-        let func_type = self
-            .funcs
+    fn func_type(&self, func_idx: Funcidx) -> Result<&Functype<V>, ExecuteError> {
+        self.funcs
             .get(func_idx.get())
             .ok_or(ExecuteError::InvalidFuncidx)?
             .get_type(&self.module)
-            .ok_or(ExecuteError::InvalidFuncidx)?;
-        func_type.validate_args(args, &self.module)?;
-        let result_type = func_type.result;
+            .ok_or(ExecuteError::InvalidFuncidx)
+    }
 
-        for v in args.iter().copied() {
-            self.executor.push_value(v);
+    fn index_exports(module: &Module<V>) -> V::Vector<usize> {
+        let exports = module.exports();
+        let mut index = V::create_vector(Some(exports.len()));
+        for i in 0..exports.len() {
+            index.push(i);
         }
+        index.sort_unstable_by(|&a, &b| exports[a].name.as_str().cmp(exports[b].name.as_str()));
+        index
+    }
 
-        self.executor
-            .call_function(func_idx, &mut self.funcs, &self.module)?;
+    fn exported_func(&self, function_name: &str) -> Result<Funcidx, ExecuteError> {
+        let Some(Exportdesc::Func(func_idx)) = self.export(function_name) else {
+            return Err(ExecuteError::NotExportedFunction);
+        };
+        Ok(func_idx)
+    }
+}
+
+// What the `Debug` output of `ModuleInstance` includes (see `ModuleInstance::debug_with()`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DebugOptions {
+    pub module: bool,
+    pub globals: bool,
+
+    // Sizes of the stacks and the current frame of the pending call (if any).
+    pub stacks: bool,
 
-        // TODO: validate return value type
-        match result_type.len() {
-            0 => Ok(None),
-            1 => Ok(Some(self.executor.pop_value())),
-            _ => unreachable!(),
+    // Number of the leading bytes of the memory to be dumped.
+    pub memory_bytes: usize,
+}
+
+impl Default for DebugOptions {
+    fn default() -> Self {
+        Self {
+            module: false,
+            globals: true,
+            stacks: true,
+            memory_bytes: 64,
         }
     }
 }
 
-impl<V: VectorFactory, H> Debug for ModuleInstance<V, H> {
+impl DebugOptions {
+    pub const VERBOSE: Self = Self {
+        module: true,
+        globals: true,
+        stacks: true,
+        memory_bytes: usize::MAX,
+    };
+}
+
+pub struct InstanceDebug<'a, V: VectorFactory, H, M, S: StackStorage = StackBuffers<'static>> {
+    instance: &'a ModuleInstance<V, H, M, S>,
+    options: DebugOptions,
+}
+
+impl<V: VectorFactory, H, M: LinearMemory, S: StackStorage> Debug
+    for InstanceDebug<'_, V, H, M, S>
+{
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("ModuleInstance")
-            .field("module", &self.module)
-            .field("executor", &self.executor)
-            .finish()
+        let instance = self.instance;
+        let mut s = f.debug_struct("ModuleInstance");
+        if self.options.module {
+            s.field("module", &instance.module);
+        }
+        s.field("funcs_len", &instance.funcs.len());
+        instance.executor.debug_fields(&mut s, self.options);
+        if self.options.stacks {
+            let position = instance.executor.call_stack(&instance.module).next();
+            s.field("position", &position);
+        }
+        s.finish()
+    }
+}
+
+impl<V: VectorFactory, H, M: LinearMemory, S: StackStorage> ModuleInstance<V, H, M, S> {
+    // e.g., `println!("{:#?}", instance.debug_with(DebugOptions::VERBOSE))`
+    pub fn debug_with(&self, options: DebugOptions) -> InstanceDebug<'_, V, H, M, S> {
+        InstanceDebug {
+            instance: self,
+            options,
+        }
+    }
+}
+
+impl<V: VectorFactory, H, M: LinearMemory, S: StackStorage> Debug for ModuleInstance<V, H, M, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.debug_with(DebugOptions::default()).fmt(f)
+    }
+}
+
+// An element of the table of an instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FuncRef {
+    // A function of the module (including the imported ones).
+    Func(Funcidx),
+
+    // The i-th host function installed by `ModuleInstance::table_set_host()`.
+    Host(usize),
+}
+
+impl FuncRef {
+    // Host functions are placed after the `module_funcs` functions of the module in `funcs()`.
+    pub(crate) fn funcidx(self, module_funcs: usize) -> Funcidx {
+        match self {
+            Self::Func(funcidx) => funcidx,
+            Self::Host(i) => Funcidx::new((module_funcs + i) as u32),
+        }
+    }
+}
+
+// A reference to a function of an instance, as stored in its table.
+//
+// The slot of a host function installed by `ModuleInstance::table_set_host()` may be reused
+// for another host function later, so the handle also records which installation it refers
+// to. A stale handle is rejected rather than referring to the new function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuncHandle {
+    funcidx: Funcidx,
+    // Zero for the functions of the module.
+    generation: u64,
+}
+
+impl FuncHandle {
+    pub const fn funcidx(self) -> Funcidx {
+        self.funcidx
+    }
+
+    pub(crate) fn is_current(self, module_funcs_len: usize, host_generations: &[u64]) -> bool {
+        match self.funcidx.get().checked_sub(module_funcs_len) {
+            Some(i) => host_generations.get(i) == Some(&self.generation),
+            None => self.generation == 0,
+        }
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GlobalVal {
     is_const: bool,
     val: Val,
@@ -380,7 +1181,11 @@ impl GlobalVal {
     }
 }
 
+// Variants will be added along with value types of future proposals (e.g., `v128`),
+// so downstream code should prefer the conversions and accessors to exhaustive matching.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum Val {
     I32(i32),
     I64(i64),
@@ -430,6 +1235,44 @@ impl Val {
         }
     }
 
+    // The bit patterns of floats are passed through as is (including NaN payloads).
+    pub const fn f32_from_bits(bits: u32) -> Self {
+        Self::F32(f32::from_bits(bits))
+    }
+
+    pub const fn f64_from_bits(bits: u64) -> Self {
+        Self::F64(f64::from_bits(bits))
+    }
+
+    // Integers are zero-extended (e.g., `I32(-1)` is `0xffff_ffff`).
+    pub const fn to_bits(self) -> u64 {
+        match self {
+            Self::I32(v) => v as u32 as u64,
+            Self::I64(v) => v as u64,
+            Self::F32(v) => v.to_bits() as u64,
+            Self::F64(v) => v.to_bits(),
+        }
+    }
+
+    // Equality as required by the spec tests: floats are compared by their bit patterns,
+    // so that NaNs with the same payload are equal while `0.0` and `-0.0` are not.
+    pub fn spec_eq(&self, other: &Self) -> bool {
+        self.ty() == other.ty() && self.to_bits() == other.to_bits()
+    }
+
+    // Matches `nan:canonical` or `nan:arithmetic` of the spec tests (regardless of the sign).
+    pub fn matches_nan(&self, pattern: NanPattern) -> bool {
+        let (bits, nan) = match *self {
+            Self::F32(v) => (v.to_bits() as u64 & 0x7fff_ffff, 0x7fc0_0000),
+            Self::F64(v) => (v.to_bits() & 0x7fff_ffff_ffff_ffff, 0x7ff8_0000_0000_0000),
+            _ => return false,
+        };
+        match pattern {
+            NanPattern::Canonical => bits == nan,
+            NanPattern::Arithmetic => bits & nan == nan,
+        }
+    }
+
     pub(crate) fn zero(ty: Valtype) -> Self {
         match ty {
             Valtype::I32 => Self::I32(0),
@@ -438,22 +1281,736 @@ impl Val {
             Valtype::F64 => Self::F64(0.0),
         }
     }
+}
+
+// NaNs that the spec tests expect without specifying the payload.
+//
+// - `Canonical`: Only the most significant bit of the payload is set
+// - `Arithmetic`: The most significant bit of the payload is set (i.e., a quiet NaN)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NanPattern {
+    Canonical,
+    Arithmetic,
+}
+
+impl From<i32> for Val {
+    fn from(v: i32) -> Self {
+        Self::I32(v)
+    }
+}
+
+impl TryFrom<Val> for i32 {
+    type Error = ConvertValError;
+
+    fn try_from(v: Val) -> Result<Self, Self::Error> {
+        v.as_i32().ok_or(ConvertValError {
+            expected: Valtype::I32,
+            actual: v.ty(),
+        })
+    }
+}
+
+impl From<i64> for Val {
+    fn from(v: i64) -> Self {
+        Self::I64(v)
+    }
+}
+
+impl TryFrom<Val> for i64 {
+    type Error = ConvertValError;
+
+    fn try_from(v: Val) -> Result<Self, Self::Error> {
+        v.as_i64().ok_or(ConvertValError {
+            expected: Valtype::I64,
+            actual: v.ty(),
+        })
+    }
+}
+
+impl From<f32> for Val {
+    fn from(v: f32) -> Self {
+        Self::F32(v)
+    }
+}
+
+impl TryFrom<Val> for f32 {
+    type Error = ConvertValError;
+
+    fn try_from(v: Val) -> Result<Self, Self::Error> {
+        v.as_f32().ok_or(ConvertValError {
+            expected: Valtype::F32,
+            actual: v.ty(),
+        })
+    }
+}
+
+impl From<f64> for Val {
+    fn from(v: f64) -> Self {
+        Self::F64(v)
+    }
+}
+
+impl TryFrom<Val> for f64 {
+    type Error = ConvertValError;
+
+    fn try_from(v: Val) -> Result<Self, Self::Error> {
+        v.as_f64().ok_or(ConvertValError {
+            expected: Valtype::F64,
+            actual: v.ty(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConvertValError {
+    pub expected: Valtype,
+    pub actual: Valtype,
+}
+
+impl Display for ConvertValError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "Expected {:?} value, but got {:?}",
+            self.expected, self.actual
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConvertValError {}
+
+// Formats as `<type>:<value>` (e.g., `i32:42` or `f64:3.5`).
+// The alternate flag (`{:#}`) formats floats in the hexadecimal notation (e.g., `f32:0x1.8p1`).
+impl Display for Val {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::I32(v) => write!(f, "i32:{v}"),
+            Self::I64(v) => write!(f, "i64:{v}"),
+            Self::F32(v) if f.alternate() => {
+                write!(f, "f32:")?;
+                hex_float::write_f32(f, v)
+            }
+            Self::F64(v) if f.alternate() => {
+                write!(f, "f64:")?;
+                hex_float::write_f64(f, v)
+            }
+            Self::F32(v) => write!(f, "f32:{v}"),
+            Self::F64(v) => write!(f, "f64:{v}"),
+        }
+    }
+}
+
+// Parses the format produced by `Display` (in either notation).
+// Integers may also be written in hex (`0x2a`) or as unsigned values, and
+// a value without the type prefix is parsed as an `i32`.
+impl FromStr for Val {
+    type Err = ParseValError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("i32", v)) => Self::parse_untyped(Valtype::I32, v),
+            Some(("i64", v)) => Self::parse_untyped(Valtype::I64, v),
+            Some(("f32", v)) => Self::parse_untyped(Valtype::F32, v),
+            Some(("f64", v)) => Self::parse_untyped(Valtype::F64, v),
+            Some(_) => Err(ParseValError::UnknownType),
+            None => Self::parse_untyped(Valtype::I32, s),
+        }
+    }
+}
 
-    pub(crate) fn byte_size(self) -> usize {
+impl Val {
+    // Same as `FromStr`, but a value without the type prefix is parsed as `ty`
+    // (a value with the prefix needs to be of `ty`).
+    pub fn parse_as(ty: Valtype, s: &str) -> Result<Self, ParseValError> {
+        let Some(("i32" | "i64" | "f32" | "f64", _)) = s.split_once(':') else {
+            return Self::parse_untyped(ty, s);
+        };
+        match s.parse::<Self>()? {
+            v if v.ty() == ty => Ok(v),
+            _ => Err(ParseValError::InvalidValue { ty }),
+        }
+    }
+
+    fn parse_untyped(ty: Valtype, v: &str) -> Result<Self, ParseValError> {
+        let is_hex_float = v.trim_start_matches(['-', '+']).starts_with("0x") || v.contains(':');
+        let val = match ty {
+            Valtype::I32 => parse_int(v, 32).map(|v| Self::I32(v as i32)),
+            Valtype::I64 => parse_int(v, 64).map(|v| Self::I64(v as i64)),
+            Valtype::F32 if is_hex_float => hex_float::parse_f32(v).map(Self::F32),
+            Valtype::F64 if is_hex_float => hex_float::parse_f64(v).map(Self::F64),
+            Valtype::F32 => v.parse().ok().map(Self::F32),
+            Valtype::F64 => v.parse().ok().map(Self::F64),
+        };
+        val.ok_or(ParseValError::InvalidValue { ty })
+    }
+}
+
+// Returns the two's complement representation of a (possibly unsigned) integer.
+fn parse_int(s: &str, bits: u32) -> Option<u64> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    if s.starts_with(['-', '+']) {
+        return None;
+    }
+    let v = match s.strip_prefix("0x") {
+        Some(s) => u64::from_str_radix(s, 16).ok()?,
+        None => s.parse::<u64>().ok()?,
+    };
+    if negative {
+        (v <= 1 << (bits - 1)).then(|| v.wrapping_neg())
+    } else {
+        (bits == 64 || v >> bits == 0).then_some(v)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseValError {
+    UnknownType,
+    InvalidValue { ty: Valtype },
+}
+
+impl Display for ParseValError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnknownType => write!(f, "Unknown value type"),
+            Self::InvalidValue { ty } => write!(f, "Invalid {:?} value", ty),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseValError {}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub enum InvokeDynError {
+    InvalidArg { index: usize, error: ParseValError },
+    Execute(ExecuteError),
+}
+
+#[cfg(feature = "std")]
+impl From<ExecuteError> for InvokeDynError {
+    fn from(e: ExecuteError) -> Self {
+        Self::Execute(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Display for InvokeDynError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         match self {
-            Self::I32(_) => 4,
-            Self::I64(_) => 8,
-            Self::F32(_) => 4,
-            Self::F64(_) => 8,
+            Self::InvalidArg { index, error } => write!(f, "Invalid argument {}: {}", index, error),
+            Self::Execute(e) => write!(f, "{}", e),
         }
     }
+}
 
-    pub(crate) fn copy_to(self, mem: &mut [u8]) {
+#[cfg(feature = "std")]
+impl std::error::Error for InvokeDynError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Self::I32(v) => mem.copy_from_slice(&v.to_le_bytes()),
-            Self::I64(v) => mem.copy_from_slice(&v.to_le_bytes()),
-            Self::F32(v) => mem.copy_from_slice(&v.to_le_bytes()),
-            Self::F64(v) => mem.copy_from_slice(&v.to_le_bytes()),
+            Self::InvalidArg { error, .. } => Some(error),
+            Self::Execute(e) => Some(e),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn val_display_and_parse() {
+        assert_eq!("i32:42", Val::I32(42).to_string());
+        assert_eq!("i64:-7", Val::I64(-7).to_string());
+        assert_eq!("f64:3.5", Val::F64(3.5).to_string());
+        assert_eq!("f32:0x1.8p1", format!("{:#}", Val::F32(3.0)));
+        assert_eq!(
+            "f64:-0x0.0000000000001p-1022",
+            format!("{:#}", Val::F64(-5e-324))
+        );
+
+        assert_eq!(Ok(Val::I32(42)), "42".parse());
+        assert_eq!(Ok(Val::I32(-1)), "i32:0xffffffff".parse());
+        assert_eq!(Ok(Val::I64(i64::MIN)), "i64:-9223372036854775808".parse());
+        assert_eq!(Ok(Val::F32(3.0)), "f32:0x1.8p1".parse());
+        assert_eq!(Ok(Val::F64(0.1)), "f64:0.1".parse());
+
+        assert_eq!(Err(ParseValError::UnknownType), "u8:1".parse::<Val>());
+        let invalid = ParseValError::InvalidValue { ty: Valtype::I32 };
+        assert_eq!(Err(invalid), "i32:4294967296".parse::<Val>());
+        assert_eq!(Err(invalid), "i32:--1".parse::<Val>());
+
+        let nan = "f32:nan:0x200000".parse::<Val>().expect("parse");
+        assert_eq!("f32:nan:0x200000", format!("{nan:#}"));
+
+        assert_eq!(Ok(Val::I64(-1)), Val::parse_as(Valtype::I64, "-1"));
+        assert_eq!(Ok(Val::F32(1.5)), Val::parse_as(Valtype::F32, "0x1.8p0"));
+        assert_eq!(Ok(Val::F64(2.0)), Val::parse_as(Valtype::F64, "f64:2"));
+        let invalid = ParseValError::InvalidValue { ty: Valtype::F64 };
+        assert_eq!(Err(invalid), Val::parse_as(Valtype::F64, "f32:2"));
+    }
+
+    #[test]
+    fn val_conversions() {
+        assert_eq!(Val::I64(7), Val::from(7i64));
+        assert_eq!(Val::F32(0.5), 0.5f32.into());
+        assert_eq!(Ok(3.5), f64::try_from(Val::F64(3.5)));
+        assert_eq!(
+            Err(ConvertValError {
+                expected: Valtype::I32,
+                actual: Valtype::I64
+            }),
+            i32::try_from(Val::I64(1))
+        );
+    }
+
+    #[test]
+    fn reuse_stacks() {
+        // (module
+        //   (func $sum (export "sum") (param i32) (result i32)
+        //     (if (result i32) (i32.eqz (local.get 0))
+        //       (then (i32.const 0))
+        //       (else (i32.add (local.get 0) (call $sum (i32.sub (local.get 0) (i32.const 1))))))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 3, 2, 1, 0, 7, 7, 1, 3, 115,
+            117, 109, 0, 0, 10, 23, 1, 21, 0, 32, 0, 69, 4, 127, 65, 0, 5, 32, 0, 32, 0, 65, 1,
+            107, 16, 0, 106, 11, 11,
+        ];
+        let module = Module::<crate::StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+        instance.reserve_stacks(256, 128);
+
+        let capacities = |instance: &ModuleInstance<crate::StdVectorFactory, ()>| {
+            let e = &instance.executor;
+            [
+                e.values.capacity(),
+                e.locals.capacity(),
+                e.frames.capacity(),
+                e.labels.capacity(),
+            ]
+        };
+        let reserved = capacities(&instance);
+        for _ in 0..3 {
+            let result = instance.invoke("sum", &[Val::I32(100)]).expect("invoke");
+            assert_eq!(Some(Val::I32(5050)), result);
+            assert_eq!(reserved, capacities(&instance));
+        }
+    }
+
+    #[test]
+    fn invoke_many_and_func_handle() {
+        // (module
+        //   (memory (export "mem") 1)
+        //   (global $g (mut i32) (i32.const 0))
+        //   (func (export "next") (result i32)
+        //     (global.set $g (i32.add (global.get $g) (i32.const 1)))
+        //     (global.get $g))
+        //   (func (export "add") (param i32 i32) (result i32)
+        //     (i32.add (local.get 0) (local.get 1))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 11, 2, 96, 0, 1, 127, 96, 2, 127, 127, 1, 127, 3, 3, 2,
+            0, 1, 5, 3, 1, 0, 1, 6, 6, 1, 127, 1, 65, 0, 11, 7, 20, 3, 4, 110, 101, 120, 116, 0, 0,
+            3, 109, 101, 109, 2, 0, 3, 97, 100, 100, 0, 1, 10, 21, 2, 11, 0, 35, 0, 65, 1, 106, 36,
+            0, 35, 0, 11, 7, 0, 32, 0, 32, 1, 106, 11,
+        ];
+        let module = Module::<crate::StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+
+        let results = instance
+            .invoke_many(&[
+                ("next", &[]),
+                ("add", &[Val::I32(2), Val::I32(3)]),
+                ("next", &[]),
+            ])
+            .expect("invoke_many");
+        assert_eq!(
+            &[Some(Val::I32(1)), Some(Val::I32(5)), Some(Val::I32(2))],
+            &results[..]
+        );
+
+        // Nothing is invoked if any of the calls is invalid.
+        let e = instance.invoke_many(&[("next", &[]), ("mem", &[])]).err();
+        assert!(matches!(e, Some(ExecuteError::NotExportedFunction)));
+        let e = instance
+            .invoke_many(&[("next", &[]), ("add", &[Val::I32(2)])])
+            .err();
+        assert!(matches!(e, Some(ExecuteError::InvalidFuncArgs)));
+        assert_eq!(
+            Some(Val::I32(3)),
+            instance.invoke("next", &[]).expect("invoke")
+        );
+
+        assert!(matches!(instance.export("mem"), Some(Exportdesc::Mem(_))));
+        assert!(instance.export("sub").is_none());
+        let add = instance.func_handle("add").expect("func_handle");
+        assert_eq!(Funcidx::new(1), add.funcidx());
+        assert_eq!(
+            Some(Val::I32(7)),
+            instance
+                .invoke_handle(add, &[Val::I32(3), Val::I32(4)])
+                .expect("invoke")
+        );
+        assert!(instance.func_handle("mem").is_err());
+
+        assert_eq!(
+            Some("i32:-1".to_string()),
+            instance
+                .invoke_dyn("add", &["0xffffffff", "i32:0"])
+                .expect("invoke_dyn")
+        );
+        let e = instance.invoke_dyn("add", &["1", "x"]).err();
+        assert!(matches!(
+            e,
+            Some(InvokeDynError::InvalidArg { index: 1, .. })
+        ));
+        let e = instance.invoke_dyn("add", &["1"]).err();
+        assert!(matches!(
+            e,
+            Some(InvokeDynError::Execute(ExecuteError::InvalidFuncArgs))
+        ));
+    }
+
+    #[test]
+    fn memory_and_global_ids() {
+        // (module
+        //   (memory (export "mem") 1)
+        //   (global (export "g") i32 (i32.const 1))
+        //   (global (export "m") (mut i32) (i32.const 2)))
+        let exporter = [
+            0, 97, 115, 109, 1, 0, 0, 0, 5, 3, 1, 0, 1, 6, 11, 2, 127, 0, 65, 1, 11, 127, 1, 65, 2,
+            11, 7, 15, 3, 3, 109, 101, 109, 2, 0, 1, 103, 3, 0, 1, 109, 3, 1,
+        ];
+        // (module
+        //   (import "a" "mem" (memory 1))
+        //   (import "a" "g" (global i32))
+        //   (import "a" "m" (global (mut i32)))
+        //   (global i32 (i32.const 3)))
+        let importer = [
+            0, 97, 115, 109, 1, 0, 0, 0, 2, 24, 3, 1, 97, 3, 109, 101, 109, 2, 0, 1, 1, 97, 1, 103,
+            3, 127, 0, 1, 97, 1, 109, 3, 127, 1, 6, 6, 1, 127, 0, 65, 3, 11,
+        ];
+
+        struct Resolver<'a, M>(&'a ModuleInstance<crate::StdVectorFactory, (), M>);
+        impl<M: LinearMemory> Resolve for Resolver<'_, M> {
+            type HostFunc = ();
+
+            fn alias_mem(&self, _module: &str, _name: &str) -> bool {
+                true
+            }
+
+            fn mem_id(&self, _module: &str, _name: &str) -> Option<MemoryId> {
+                self.0.memory_id()
+            }
+
+            fn resolve_global(&self, _module: &str, name: &str) -> Option<Val> {
+                self.0.get_global_export(name)
+            }
+
+            fn global_id(&self, _module: &str, name: &str) -> Option<GlobalId> {
+                let Some(Exportdesc::Global(idx)) = self.0.export(name) else {
+                    return None;
+                };
+                self.0.global_id(idx)
+            }
+        }
+
+        let shared = crate::SharedMemory::new();
+        let module = Module::<crate::StdVectorFactory>::decode(&exporter).expect("decode");
+        let a = module
+            .clone()
+            .instantiate_with_memory((), shared.clone())
+            .expect("instantiate");
+        let a2 = module.instantiate(()).expect("instantiate");
+        let module = Module::<crate::StdVectorFactory>::decode(&importer).expect("decode");
+        let b = module
+            .instantiate_with_memory(Resolver(&a), shared)
+            .expect("instantiate");
+
+        let global = Globalidx::new;
+        assert!(a.memory_id().is_some());
+        assert_eq!(a.memory_id(), b.memory_id());
+        assert_ne!(a.memory_id(), a2.memory_id());
+        assert_eq!(a.global_id(global(0)), b.global_id(global(0)));
+        assert_ne!(a.global_id(global(1)), b.global_id(global(1)));
+        assert_ne!(a.global_id(global(0)), a2.global_id(global(0)));
+        assert_ne!(b.global_id(global(1)), b.global_id(global(2)));
+        assert_eq!(None, b.global_id(global(3)));
+    }
+
+    #[test]
+    fn val_bits() {
+        let snan = Val::f32_from_bits(0x7fa0_0001);
+        assert!(snan.as_f32().is_some_and(f32::is_nan));
+        assert_eq!(0x7fa0_0001, snan.to_bits());
+        assert_eq!(
+            0xfff8_0000_0000_0002,
+            Val::f64_from_bits(0xfff8_0000_0000_0002).to_bits()
+        );
+        assert_eq!(0xffff_ffff, Val::I32(-1).to_bits());
+        assert_eq!(u64::MAX, Val::I64(-1).to_bits());
+    }
+
+    #[test]
+    fn val_spec_eq() {
+        assert!(Val::F32(0.0).spec_eq(&Val::F32(0.0)));
+        assert!(!Val::F32(0.0).spec_eq(&Val::F32(-0.0)));
+        assert!(!Val::F32(0.0).spec_eq(&Val::F64(0.0)));
+        let nan = Val::f64_from_bits(0x7ff0_0000_0000_0001);
+        assert!(nan.spec_eq(&nan));
+        assert!(!nan.spec_eq(&Val::F64(f64::NAN)));
+
+        let canonical = Val::f32_from_bits(0xffc0_0000);
+        let arithmetic = Val::f32_from_bits(0x7fc0_0001);
+        assert!(canonical.matches_nan(NanPattern::Canonical));
+        assert!(canonical.matches_nan(NanPattern::Arithmetic));
+        assert!(!arithmetic.matches_nan(NanPattern::Canonical));
+        assert!(arithmetic.matches_nan(NanPattern::Arithmetic));
+        assert!(!Val::f32_from_bits(0x7fa0_0000).matches_nan(NanPattern::Arithmetic));
+        assert!(!Val::F64(f64::INFINITY).matches_nan(NanPattern::Arithmetic));
+        assert!(!Val::I32(0x7fc0_0000).matches_nan(NanPattern::Canonical));
+    }
+
+    #[test]
+    fn shared_host_func() {
+        // (module
+        //   (import "env" "a" (func $a (param i32)))
+        //   (import "env" "b" (func $b (param i32)))
+        //   (func (export "f") (call $a (i32.const 1)) (call $b (i32.const 2))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 8, 2, 96, 1, 127, 0, 96, 0, 0, 2, 17, 2, 3, 101, 110,
+            118, 1, 97, 0, 0, 3, 101, 110, 118, 1, 98, 0, 0, 3, 2, 1, 1, 7, 5, 1, 1, 102, 0, 2, 10,
+            12, 1, 10, 0, 65, 1, 16, 0, 65, 2, 16, 1, 11,
+        ];
+
+        #[derive(Default)]
+        struct Log(Vec<Val>);
+        impl HostFunc for Log {
+            fn invoke(&mut self, args: &[Val], _env: &mut Env) -> Option<Val> {
+                self.0.extend_from_slice(args);
+                None
+            }
+        }
+
+        struct Resolver(std::rc::Rc<core::cell::RefCell<Log>>);
+        impl Resolve for Resolver {
+            type HostFunc = std::rc::Rc<core::cell::RefCell<Log>>;
+
+            fn resolve_func(&self, _module: &str, _name: &str) -> Option<Self::HostFunc> {
+                Some(self.0.clone())
+            }
+        }
+
+        let log = std::rc::Rc::new(core::cell::RefCell::new(Log::default()));
+        let module = Module::<crate::StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module
+            .instantiate(Resolver(log.clone()))
+            .expect("instantiate");
+        instance.invoke("f", &[]).expect("invoke");
+        assert_eq!([Val::I32(1), Val::I32(2)], *log.borrow().0);
+    }
+
+    #[test]
+    fn host_func_accessors() {
+        // (module
+        //   (import "env" "a" (func $a (param i32)))
+        //   (import "env" "b" (func $b (param i32)))
+        //   (func (export "f") (call $a (i32.const 1)) (call $b (i32.const 2))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 8, 2, 96, 1, 127, 0, 96, 0, 0, 2, 17, 2, 3, 101, 110,
+            118, 1, 97, 0, 0, 3, 101, 110, 118, 1, 98, 0, 0, 3, 2, 1, 1, 7, 5, 1, 1, 102, 0, 2, 10,
+            12, 1, 10, 0, 65, 1, 16, 0, 65, 2, 16, 1, 11,
+        ];
+
+        #[derive(Default)]
+        struct Log(Vec<Val>);
+        impl HostFunc for Log {
+            fn invoke(&mut self, args: &[Val], _env: &mut Env) -> Option<Val> {
+                self.0.extend_from_slice(args);
+                None
+            }
+        }
+
+        struct Resolver;
+        impl Resolve for Resolver {
+            type HostFunc = Log;
+
+            fn resolve_func(&self, _module: &str, _name: &str) -> Option<Self::HostFunc> {
+                Some(Log::default())
+            }
+        }
+
+        let module = Module::<crate::StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(Resolver).expect("instantiate");
+        instance.invoke("f", &[]).expect("invoke");
+        let names = instance
+            .host_funcs()
+            .map(|(import, log)| (import.name.as_str(), log.0.len()))
+            .collect::<Vec<_>>();
+        assert_eq!([("a", 1), ("b", 1)], *names);
+
+        let b = instance.host_func_mut("env", "b").expect("host_func_mut");
+        assert_eq!([Val::I32(2)], *b.0);
+        b.0.clear();
+        instance.invoke("f", &[]).expect("invoke");
+        let a = instance.host_func("env", "a").expect("host_func");
+        assert_eq!([Val::I32(1), Val::I32(1)], *a.0);
+        assert!(instance.host_func("env", "c").is_none());
+    }
+
+    #[test]
+    fn host_func_grows_mem() {
+        // (module
+        //   (import "env" "grow" (func $grow (param i32) (result i32)))
+        //   (memory 1 2)
+        //   (func (export "grow") (param i32) (result i32) (call $grow (local.get 0))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 2, 12, 1, 3, 101, 110, 118,
+            4, 103, 114, 111, 119, 0, 0, 3, 2, 1, 0, 5, 4, 1, 1, 1, 2, 7, 8, 1, 4, 103, 114, 111,
+            119, 0, 1, 10, 8, 1, 6, 0, 32, 0, 16, 0, 11,
+        ];
+
+        struct Grow;
+        impl HostFunc for Grow {
+            fn invoke(&mut self, args: &[Val], env: &mut Env) -> Option<Val> {
+                let delta = args[0].as_i32()? as u32;
+                Some(Val::I32(
+                    env.mem.grow(delta).map_or(-1, |pages| pages as i32),
+                ))
+            }
+        }
+
+        struct Resolver;
+        impl Resolve for Resolver {
+            type HostFunc = Grow;
+
+            fn resolve_func(&self, _module: &str, _name: &str) -> Option<Self::HostFunc> {
+                Some(Grow)
+            }
+        }
+
+        // The growth is page-based and limited by the maximum size of the memory.
+        let module = Module::<crate::StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(Resolver).expect("instantiate");
+        assert_eq!(
+            Some(Val::I32(-1)),
+            instance.invoke("grow", &[Val::I32(2)]).expect("invoke")
+        );
+        assert_eq!(
+            Some(Val::I32(1)),
+            instance.invoke("grow", &[Val::I32(1)]).expect("invoke")
+        );
+        assert_eq!(
+            Some(Val::I32(-1)),
+            instance.invoke("grow", &[Val::I32(1)]).expect("invoke")
+        );
+        assert_eq!(2 * PAGE_SIZE, instance.mem().size());
+        assert_eq!(PAGE_SIZE, instance.stats().mem_grown);
+    }
+
+    #[test]
+    fn get_global_export() {
+        // (module
+        //   (memory (export "mem") 1)
+        //   (global (export "g") i32 (i32.const 1))
+        //   (global (export "m") (mut i32) (i32.const 2)))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 5, 3, 1, 0, 1, 6, 11, 2, 127, 0, 65, 1, 11, 127, 1, 65, 2,
+            11, 7, 15, 3, 3, 109, 101, 109, 2, 0, 1, 103, 3, 0, 1, 109, 3, 1,
+        ];
+        let module = Module::<crate::StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+        assert_eq!(Some(Val::I32(1)), instance.get_global_export("g"));
+        assert_eq!(Some(Val::I32(2)), instance.get_global_export("m"));
+        assert_eq!(None, instance.get_global_export("mem"));
+        assert_eq!(None, instance.get_global_export("x"));
+
+        assert!(instance.globals_mut().set(1, Val::I32(3)));
+        assert_eq!(Some(Val::I32(3)), instance.get_global_export("m"));
+    }
+
+    #[test]
+    fn debug_with() {
+        // (module
+        //   (memory (export "mem") 1)
+        //   (global (export "g") i32 (i32.const 1))
+        //   (global (export "m") (mut i32) (i32.const 2)))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 5, 3, 1, 0, 1, 6, 11, 2, 127, 0, 65, 1, 11, 127, 1, 65, 2,
+            11, 7, 15, 3, 3, 109, 101, 109, 2, 0, 1, 103, 3, 0, 1, 109, 3, 1,
+        ];
+        let module = Module::<crate::StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+        assert!(instance.mem_mut().write(0, b"hi"));
+
+        let s = format!("{instance:?}");
+        assert!(s.contains("mem_size: 65536, mem: 68 69 00"), "{s}");
+        assert!(s.contains("globals: [GlobalVal { is_const: true, val: I32(1) }"));
+        assert!(s.contains("current_frame: None"));
+        assert!(!s.contains("module:"));
+
+        let options = DebugOptions {
+            globals: false,
+            stacks: false,
+            memory_bytes: 4,
+            ..DebugOptions::VERBOSE
+        };
+        let s = format!("{:?}", instance.debug_with(options));
+        assert!(s.contains("mem: 68 69 00 00, table_len"), "{s}");
+        assert!(s.contains("module:"));
+        assert!(!s.contains("GlobalVal"));
+        assert!(!s.contains("current_frame"));
+    }
+
+    #[test]
+    fn reset() {
+        // (module
+        //   (table 2 funcref)
+        //   (memory 1)
+        //   (global $g (mut i32) (i32.const 0))
+        //   (func $init (global.set $g (i32.const 5)))
+        //   (func $one (result i32) (i32.const 1))
+        //   (func (export "g") (result i32) (global.get $g))
+        //   (elem (i32.const 0) $one)
+        //   (start $init))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 8, 2, 96, 0, 0, 96, 0, 1, 127, 3, 4, 3, 0, 1, 1, 4, 4,
+            1, 112, 0, 2, 5, 3, 1, 0, 1, 6, 6, 1, 127, 1, 65, 0, 11, 7, 5, 1, 1, 103, 0, 2, 8, 1,
+            0, 9, 7, 1, 0, 65, 0, 11, 1, 1, 10, 18, 3, 6, 0, 65, 5, 36, 0, 11, 4, 0, 65, 1, 11, 4,
+            0, 35, 0, 11,
+        ];
+        let module = Module::<crate::StdVectorFactory>::decode(&input).expect("decode");
+        let mut instance = module.instantiate(()).expect("instantiate");
+        let initial_table = [Some(FuncRef::Func(Funcidx::new(1))), None];
+        assert_eq!(
+            Some(Val::I32(5)),
+            instance.invoke("g", &[]).expect("invoke")
+        );
+
+        assert!(instance.globals_mut().set(0, Val::I32(9)));
+        assert!(instance.table_mut().set(0, None));
+        instance
+            .table_set_host(1, Typeidx::new(1), ())
+            .expect("table_set_host");
+        instance.mem_mut().write(0, &[1, 2, 3]);
+
+        instance.reset(false).expect("reset");
+        assert_eq!(
+            Some(Val::I32(0)),
+            instance.invoke("g", &[]).expect("invoke")
+        );
+        assert_eq!(&initial_table, instance.table());
+        assert_eq!(3, instance.funcs().len());
+        assert!(instance.mem().as_slice().iter().all(|b| *b == 0));
+
+        instance.reset(true).expect("reset");
+        assert_eq!(
+            Some(Val::I32(5)),
+            instance.invoke("g", &[]).expect("invoke")
+        );
+    }
+}
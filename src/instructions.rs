@@ -1,15 +1,28 @@
+#[cfg(feature = "reference_types")]
+use crate::components::Valtype;
 use crate::vector::Vector;
 use crate::{
     components::{Blocktype, Funcidx, Globalidx, Labelidx, Localidx, Memarg, Typeidx},
     decode::Decode,
+    features::unknown_opcode_error,
+    hex_float,
     reader::Reader,
     DecodeError, VectorFactory,
 };
-use core::fmt::{Debug, Formatter};
+use core::fmt::{Debug, Display, Formatter};
 
+#[cfg(feature = "bulk_memory")]
+pub use crate::bulk_memory::BulkMemoryInstr;
+#[cfg(feature = "extension")]
+pub use crate::extension::ExtensionInstr;
+#[cfg(feature = "gc")]
+pub use crate::gc::GcInstr;
 #[cfg(feature = "sign_extension")]
 pub use crate::sign_extension::SignExtensionInstr;
 
+// New variants may be added along with new proposals, so analysis passes that only need the
+// operands of instructions should use the accessor methods below rather than exhaustive matches.
+#[non_exhaustive]
 pub enum Instr<V: VectorFactory> {
     // Control Instructions
     Unreachable,
@@ -27,6 +40,8 @@ pub enum Instr<V: VectorFactory> {
     // Parametric Instructions
     Drop,
     Select,
+    #[cfg(feature = "reference_types")]
+    SelectTyped(Valtype),
 
     // Variable Instructions
     LocalGet(Localidx),
@@ -65,8 +80,10 @@ pub enum Instr<V: VectorFactory> {
     // Numeric Instructions
     I32Const(i32),
     I64Const(i64),
-    F32Const(f32),
-    F64Const(f64),
+    // Float immediates are kept as bit patterns so that the NaN payloads are preserved
+    // until they are executed.
+    F32Const(u32),
+    F64Const(u64),
     I32Eqz,
     I32Eq,
     I32Ne,
@@ -194,6 +211,113 @@ pub enum Instr<V: VectorFactory> {
     // Sign Extension
     #[cfg(feature = "sign_extension")]
     SignExtension(SignExtensionInstr),
+
+    // Bulk Memory Instructions
+    #[cfg(feature = "bulk_memory")]
+    BulkMemory(BulkMemoryInstr),
+
+    // GC Instructions
+    #[cfg(feature = "gc")]
+    Gc(GcInstr),
+
+    // Embedder-defined Instructions
+    #[cfg(feature = "extension")]
+    Extension(ExtensionInstr),
+}
+
+impl<V: VectorFactory> Instr<V> {
+    // Memory argument of a load or store instruction.
+    pub fn memarg(&self) -> Option<Memarg> {
+        match *self {
+            Self::I32Load(arg)
+            | Self::I64Load(arg)
+            | Self::F32Load(arg)
+            | Self::F64Load(arg)
+            | Self::I32Load8S(arg)
+            | Self::I32Load8U(arg)
+            | Self::I32Load16S(arg)
+            | Self::I32Load16U(arg)
+            | Self::I64Load8S(arg)
+            | Self::I64Load8U(arg)
+            | Self::I64Load16S(arg)
+            | Self::I64Load16U(arg)
+            | Self::I64Load32S(arg)
+            | Self::I64Load32U(arg)
+            | Self::I32Store(arg)
+            | Self::I64Store(arg)
+            | Self::F32Store(arg)
+            | Self::F64Store(arg)
+            | Self::I32Store8(arg)
+            | Self::I32Store16(arg)
+            | Self::I64Store8(arg)
+            | Self::I64Store16(arg)
+            | Self::I64Store32(arg) => Some(arg),
+            _ => None,
+        }
+    }
+
+    // Label of a (conditional) branch instruction that has a single target.
+    // The targets of `br_table` are `BrTableInstr::labels`.
+    pub fn branch_target(&self) -> Option<Labelidx> {
+        match *self {
+            Self::Br(label) | Self::BrIf(label) => Some(label),
+            #[cfg(feature = "gc")]
+            Self::Gc(GcInstr::BrOnCast { label, .. } | GcInstr::BrOnCastFail { label, .. }) => {
+                Some(label)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn immediate_i32(&self) -> Option<i32> {
+        match *self {
+            Self::I32Const(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn immediate_i64(&self) -> Option<i64> {
+        match *self {
+            Self::I64Const(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn immediate_f32(&self) -> Option<f32> {
+        match *self {
+            Self::F32Const(v) => Some(f32::from_bits(v)),
+            _ => None,
+        }
+    }
+
+    pub fn immediate_f64(&self) -> Option<f64> {
+        match *self {
+            Self::F64Const(v) => Some(f64::from_bits(v)),
+            _ => None,
+        }
+    }
+
+    // Callee of `call` (the type of `call_indirect` is not a function index).
+    pub fn funcidx(&self) -> Option<Funcidx> {
+        match *self {
+            Self::Call(idx) => Some(idx),
+            _ => None,
+        }
+    }
+
+    pub fn localidx(&self) -> Option<Localidx> {
+        match *self {
+            Self::LocalGet(idx) | Self::LocalSet(idx) | Self::LocalTee(idx) => Some(idx),
+            _ => None,
+        }
+    }
+
+    pub fn globalidx(&self) -> Option<Globalidx> {
+        match *self {
+            Self::GlobalGet(idx) | Self::GlobalSet(idx) => Some(idx),
+            _ => None,
+        }
+    }
 }
 
 impl<V: VectorFactory> Decode<V> for Instr<V> {
@@ -225,6 +349,14 @@ impl<V: VectorFactory> Decode<V> for Instr<V> {
             // Parametric Instructions
             0x1a => Ok(Self::Drop),
             0x1b => Ok(Self::Select),
+            #[cfg(feature = "reference_types")]
+            0x1c => {
+                let value = reader.read_usize()?;
+                if value != 1 {
+                    return Err(DecodeError::InvalidResultArity { value });
+                }
+                Ok(Self::SelectTyped(Decode::<V>::decode(reader)?))
+            }
 
             // Variable Instructions
             0x20 => Ok(Self::LocalGet(Decode::<V>::decode(reader)?)),
@@ -275,8 +407,8 @@ impl<V: VectorFactory> Decode<V> for Instr<V> {
             // Numeric Instructions
             0x41 => Ok(Self::I32Const(reader.read_i32()?)),
             0x42 => Ok(Self::I64Const(reader.read_i64()?)),
-            0x43 => Ok(Self::F32Const(reader.read_f32()?)),
-            0x44 => Ok(Self::F64Const(reader.read_f64()?)),
+            0x43 => Ok(Self::F32Const(reader.read_f32_bits()?)),
+            0x44 => Ok(Self::F64Const(reader.read_f64_bits()?)),
             0x45 => Ok(Self::I32Eqz),
             0x46 => Ok(Self::I32Eq),
             0x47 => Ok(Self::I32Ne),
@@ -408,7 +540,27 @@ impl<V: VectorFactory> Decode<V> for Instr<V> {
                 Ok(Self::SignExtension(Decode::<V>::decode(reader)?))
             }
 
-            _ => Err(DecodeError::InvalidOpcode { value: opcode }),
+            // Bulk Memory Instructions
+            #[cfg(feature = "bulk_memory")]
+            0xFC => Ok(Self::BulkMemory(Decode::<V>::decode(reader)?)),
+
+            // GC Instructions
+            #[cfg(feature = "gc")]
+            0xFB => Ok(Self::Gc(Decode::<V>::decode(reader)?)),
+
+            // Embedder-defined Instructions (only for the prefixes not handled above)
+            #[cfg(feature = "extension")]
+            _ if reader
+                .config()
+                .extension
+                .is_some_and(|ext| ext.prefix == opcode) =>
+            {
+                let ext = reader.config().extension.expect("unreachable");
+                Ok(Self::Extension((ext.decode)(reader)?))
+            }
+
+            0xFB..=0xFE => Err(unknown_opcode_error(opcode, reader.read_u32()?)),
+            _ => Err(unknown_opcode_error(opcode, 0)),
         }
     }
 }
@@ -429,6 +581,8 @@ impl<V: VectorFactory> Debug for Instr<V> {
             Self::CallIndirect(v) => write!(f, "CallIndirect({v:?})"),
             Self::Drop => write!(f, "Drop"),
             Self::Select => write!(f, "Select"),
+            #[cfg(feature = "reference_types")]
+            Self::SelectTyped(v) => write!(f, "SelectTyped({v:?})"),
             Self::LocalGet(v) => write!(f, "LocalGet({v:?})"),
             Self::LocalSet(v) => write!(f, "LocalSet({v:?})"),
             Self::LocalTee(v) => write!(f, "LocalTee({v:?})"),
@@ -461,8 +615,8 @@ impl<V: VectorFactory> Debug for Instr<V> {
             Self::MemoryGrow => write!(f, "MemoryGrow"),
             Self::I32Const(v) => write!(f, "I32Const({v:?})"),
             Self::I64Const(v) => write!(f, "I64Const({v:?})"),
-            Self::F32Const(v) => write!(f, "F32Const({v:?})"),
-            Self::F64Const(v) => write!(f, "F64Const({v:?})"),
+            Self::F32Const(v) => write!(f, "F32Const({:?})", f32::from_bits(*v)),
+            Self::F64Const(v) => write!(f, "F64Const({:?})", f64::from_bits(*v)),
             Self::I32Eqz => write!(f, "I32Eqz"),
             Self::I32Eq => write!(f, "I32Eq"),
             Self::I32Ne => write!(f, "I32Ne"),
@@ -588,8 +742,255 @@ impl<V: VectorFactory> Debug for Instr<V> {
             Self::F64ReinterpretI64 => write!(f, "F64ReinterpretI64"),
             #[cfg(feature = "sign_extension")]
             Self::SignExtension(v) => write!(f, "SignExtension({v:?})"),
+            #[cfg(feature = "bulk_memory")]
+            Self::BulkMemory(v) => write!(f, "BulkMemory({v:?})"),
+            #[cfg(feature = "gc")]
+            Self::Gc(v) => write!(f, "Gc({v:?})"),
+            #[cfg(feature = "extension")]
+            Self::Extension(v) => write!(f, "Extension({v:?})"),
+        }
+    }
+}
+
+// Prints the instruction in the text format of the spec (e.g., `br_if 1` or
+// `i32.load offset=4 align=2`). Structured instructions are printed without their bodies
+// (e.g., `block (result i32)`).
+// Floats are printed in the decimal notation unless they are NaN or the alternate flag (`{:#}`)
+// is specified, in which case the hexadecimal notation is used (e.g., `f32.const nan:0x200000`).
+impl<V: VectorFactory> Display for Instr<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::Block(ref v) => write_blocktype(f, "block", v.blocktype),
+            Self::Loop(ref v) => write_blocktype(f, "loop", v.blocktype),
+            Self::If(ref v) => write_blocktype(f, "if", v.blocktype),
+            Self::Br(v) => write!(f, "br {}", v.get()),
+            Self::BrIf(v) => write!(f, "br_if {}", v.get()),
+            Self::BrTable(ref v) => {
+                f.write_str("br_table")?;
+                for label in v.labels.iter() {
+                    write!(f, " {}", label.get())?;
+                }
+                Ok(())
+            }
+            Self::Call(v) => write!(f, "call {}", v.get()),
+            Self::CallIndirect(v) => write!(f, "call_indirect (type {})", v.get()),
+            #[cfg(feature = "reference_types")]
+            Self::SelectTyped(v) => write!(f, "select (result {v})"),
+            Self::LocalGet(v) => write!(f, "local.get {}", v.get()),
+            Self::LocalSet(v) => write!(f, "local.set {}", v.get()),
+            Self::LocalTee(v) => write!(f, "local.tee {}", v.get()),
+            Self::GlobalGet(v) => write!(f, "global.get {}", v.get()),
+            Self::GlobalSet(v) => write!(f, "global.set {}", v.get()),
+            Self::I32Load(arg) => write_memarg(f, "i32.load", arg, 2),
+            Self::I64Load(arg) => write_memarg(f, "i64.load", arg, 3),
+            Self::F32Load(arg) => write_memarg(f, "f32.load", arg, 2),
+            Self::F64Load(arg) => write_memarg(f, "f64.load", arg, 3),
+            Self::I32Load8S(arg) => write_memarg(f, "i32.load8_s", arg, 0),
+            Self::I32Load8U(arg) => write_memarg(f, "i32.load8_u", arg, 0),
+            Self::I32Load16S(arg) => write_memarg(f, "i32.load16_s", arg, 1),
+            Self::I32Load16U(arg) => write_memarg(f, "i32.load16_u", arg, 1),
+            Self::I64Load8S(arg) => write_memarg(f, "i64.load8_s", arg, 0),
+            Self::I64Load8U(arg) => write_memarg(f, "i64.load8_u", arg, 0),
+            Self::I64Load16S(arg) => write_memarg(f, "i64.load16_s", arg, 1),
+            Self::I64Load16U(arg) => write_memarg(f, "i64.load16_u", arg, 1),
+            Self::I64Load32S(arg) => write_memarg(f, "i64.load32_s", arg, 2),
+            Self::I64Load32U(arg) => write_memarg(f, "i64.load32_u", arg, 2),
+            Self::I32Store(arg) => write_memarg(f, "i32.store", arg, 2),
+            Self::I64Store(arg) => write_memarg(f, "i64.store", arg, 3),
+            Self::F32Store(arg) => write_memarg(f, "f32.store", arg, 2),
+            Self::F64Store(arg) => write_memarg(f, "f64.store", arg, 3),
+            Self::I32Store8(arg) => write_memarg(f, "i32.store8", arg, 0),
+            Self::I32Store16(arg) => write_memarg(f, "i32.store16", arg, 1),
+            Self::I64Store8(arg) => write_memarg(f, "i64.store8", arg, 0),
+            Self::I64Store16(arg) => write_memarg(f, "i64.store16", arg, 1),
+            Self::I64Store32(arg) => write_memarg(f, "i64.store32", arg, 2),
+            Self::I32Const(v) => write!(f, "i32.const {v}"),
+            Self::I64Const(v) => write!(f, "i64.const {v}"),
+            Self::F32Const(v) => {
+                let v = f32::from_bits(v);
+                f.write_str("f32.const ")?;
+                if v.is_nan() || f.alternate() {
+                    hex_float::write_f32(f, v)
+                } else {
+                    write!(f, "{v}")
+                }
+            }
+            Self::F64Const(v) => {
+                let v = f64::from_bits(v);
+                f.write_str("f64.const ")?;
+                if v.is_nan() || f.alternate() {
+                    hex_float::write_f64(f, v)
+                } else {
+                    write!(f, "{v}")
+                }
+            }
+            Self::Unreachable => f.write_str("unreachable"),
+            Self::Nop => f.write_str("nop"),
+            Self::Return => f.write_str("return"),
+            Self::Drop => f.write_str("drop"),
+            Self::Select => f.write_str("select"),
+            Self::MemorySize => f.write_str("memory.size"),
+            Self::MemoryGrow => f.write_str("memory.grow"),
+            Self::I32Eqz => f.write_str("i32.eqz"),
+            Self::I32Eq => f.write_str("i32.eq"),
+            Self::I32Ne => f.write_str("i32.ne"),
+            Self::I32LtS => f.write_str("i32.lt_s"),
+            Self::I32LtU => f.write_str("i32.lt_u"),
+            Self::I32GtS => f.write_str("i32.gt_s"),
+            Self::I32GtU => f.write_str("i32.gt_u"),
+            Self::I32LeS => f.write_str("i32.le_s"),
+            Self::I32LeU => f.write_str("i32.le_u"),
+            Self::I32GeS => f.write_str("i32.ge_s"),
+            Self::I32GeU => f.write_str("i32.ge_u"),
+            Self::I64Eqz => f.write_str("i64.eqz"),
+            Self::I64Eq => f.write_str("i64.eq"),
+            Self::I64Ne => f.write_str("i64.ne"),
+            Self::I64LtS => f.write_str("i64.lt_s"),
+            Self::I64LtU => f.write_str("i64.lt_u"),
+            Self::I64GtS => f.write_str("i64.gt_s"),
+            Self::I64GtU => f.write_str("i64.gt_u"),
+            Self::I64LeS => f.write_str("i64.le_s"),
+            Self::I64LeU => f.write_str("i64.le_u"),
+            Self::I64GeS => f.write_str("i64.ge_s"),
+            Self::I64GeU => f.write_str("i64.ge_u"),
+            Self::F32Eq => f.write_str("f32.eq"),
+            Self::F32Ne => f.write_str("f32.ne"),
+            Self::F32Lt => f.write_str("f32.lt"),
+            Self::F32Gt => f.write_str("f32.gt"),
+            Self::F32Le => f.write_str("f32.le"),
+            Self::F32Ge => f.write_str("f32.ge"),
+            Self::F64Eq => f.write_str("f64.eq"),
+            Self::F64Ne => f.write_str("f64.ne"),
+            Self::F64Lt => f.write_str("f64.lt"),
+            Self::F64Gt => f.write_str("f64.gt"),
+            Self::F64Le => f.write_str("f64.le"),
+            Self::F64Ge => f.write_str("f64.ge"),
+            Self::I32Clz => f.write_str("i32.clz"),
+            Self::I32Ctz => f.write_str("i32.ctz"),
+            Self::I32Popcnt => f.write_str("i32.popcnt"),
+            Self::I32Add => f.write_str("i32.add"),
+            Self::I32Sub => f.write_str("i32.sub"),
+            Self::I32Mul => f.write_str("i32.mul"),
+            Self::I32DivS => f.write_str("i32.div_s"),
+            Self::I32DivU => f.write_str("i32.div_u"),
+            Self::I32RemS => f.write_str("i32.rem_s"),
+            Self::I32RemU => f.write_str("i32.rem_u"),
+            Self::I32And => f.write_str("i32.and"),
+            Self::I32Or => f.write_str("i32.or"),
+            Self::I32Xor => f.write_str("i32.xor"),
+            Self::I32Shl => f.write_str("i32.shl"),
+            Self::I32ShrS => f.write_str("i32.shr_s"),
+            Self::I32ShrU => f.write_str("i32.shr_u"),
+            Self::I32Rotl => f.write_str("i32.rotl"),
+            Self::I32Rotr => f.write_str("i32.rotr"),
+            Self::I64Clz => f.write_str("i64.clz"),
+            Self::I64Ctz => f.write_str("i64.ctz"),
+            Self::I64Popcnt => f.write_str("i64.popcnt"),
+            Self::I64Add => f.write_str("i64.add"),
+            Self::I64Sub => f.write_str("i64.sub"),
+            Self::I64Mul => f.write_str("i64.mul"),
+            Self::I64DivS => f.write_str("i64.div_s"),
+            Self::I64DivU => f.write_str("i64.div_u"),
+            Self::I64RemS => f.write_str("i64.rem_s"),
+            Self::I64RemU => f.write_str("i64.rem_u"),
+            Self::I64And => f.write_str("i64.and"),
+            Self::I64Or => f.write_str("i64.or"),
+            Self::I64Xor => f.write_str("i64.xor"),
+            Self::I64Shl => f.write_str("i64.shl"),
+            Self::I64ShrS => f.write_str("i64.shr_s"),
+            Self::I64ShrU => f.write_str("i64.shr_u"),
+            Self::I64Rotl => f.write_str("i64.rotl"),
+            Self::I64Rotr => f.write_str("i64.rotr"),
+            Self::F32Abs => f.write_str("f32.abs"),
+            Self::F32Neg => f.write_str("f32.neg"),
+            Self::F32Ceil => f.write_str("f32.ceil"),
+            Self::F32Floor => f.write_str("f32.floor"),
+            Self::F32Trunc => f.write_str("f32.trunc"),
+            Self::F32Nearest => f.write_str("f32.nearest"),
+            Self::F32Sqrt => f.write_str("f32.sqrt"),
+            Self::F32Add => f.write_str("f32.add"),
+            Self::F32Sub => f.write_str("f32.sub"),
+            Self::F32Mul => f.write_str("f32.mul"),
+            Self::F32Div => f.write_str("f32.div"),
+            Self::F32Min => f.write_str("f32.min"),
+            Self::F32Max => f.write_str("f32.max"),
+            Self::F32Copysign => f.write_str("f32.copysign"),
+            Self::F64Abs => f.write_str("f64.abs"),
+            Self::F64Neg => f.write_str("f64.neg"),
+            Self::F64Ceil => f.write_str("f64.ceil"),
+            Self::F64Floor => f.write_str("f64.floor"),
+            Self::F64Trunc => f.write_str("f64.trunc"),
+            Self::F64Nearest => f.write_str("f64.nearest"),
+            Self::F64Sqrt => f.write_str("f64.sqrt"),
+            Self::F64Add => f.write_str("f64.add"),
+            Self::F64Sub => f.write_str("f64.sub"),
+            Self::F64Mul => f.write_str("f64.mul"),
+            Self::F64Div => f.write_str("f64.div"),
+            Self::F64Min => f.write_str("f64.min"),
+            Self::F64Max => f.write_str("f64.max"),
+            Self::F64Copysign => f.write_str("f64.copysign"),
+            Self::I32WrapI64 => f.write_str("i32.wrap_i64"),
+            Self::I32TruncF32S => f.write_str("i32.trunc_f32_s"),
+            Self::I32TruncF32U => f.write_str("i32.trunc_f32_u"),
+            Self::I32TruncF64S => f.write_str("i32.trunc_f64_s"),
+            Self::I32TruncF64U => f.write_str("i32.trunc_f64_u"),
+            Self::I64ExtendI32S => f.write_str("i64.extend_i32_s"),
+            Self::I64ExtendI32U => f.write_str("i64.extend_i32_u"),
+            Self::I64TruncF32S => f.write_str("i64.trunc_f32_s"),
+            Self::I64TruncF32U => f.write_str("i64.trunc_f32_u"),
+            Self::I64TruncF64S => f.write_str("i64.trunc_f64_s"),
+            Self::I64TruncF64U => f.write_str("i64.trunc_f64_u"),
+            Self::F32ConvertI32S => f.write_str("f32.convert_i32_s"),
+            Self::F32ConvertI32U => f.write_str("f32.convert_i32_u"),
+            Self::F32ConvertI64S => f.write_str("f32.convert_i64_s"),
+            Self::F32ConvertI64U => f.write_str("f32.convert_i64_u"),
+            Self::F32DemoteF64 => f.write_str("f32.demote_f64"),
+            Self::F64ConvertI32S => f.write_str("f64.convert_i32_s"),
+            Self::F64ConvertI32U => f.write_str("f64.convert_i32_u"),
+            Self::F64ConvertI64S => f.write_str("f64.convert_i64_s"),
+            Self::F64ConvertI64U => f.write_str("f64.convert_i64_u"),
+            Self::F64PromoteF32 => f.write_str("f64.promote_f32"),
+            Self::I32ReinterpretF32 => f.write_str("i32.reinterpret_f32"),
+            Self::I64ReinterpretF64 => f.write_str("i64.reinterpret_f64"),
+            Self::F32ReinterpretI32 => f.write_str("f32.reinterpret_i32"),
+            Self::F64ReinterpretI64 => f.write_str("f64.reinterpret_i64"),
+            #[cfg(feature = "sign_extension")]
+            Self::SignExtension(v) => write!(f, "{v}"),
+            #[cfg(feature = "bulk_memory")]
+            Self::BulkMemory(v) => write!(f, "{v}"),
+            #[cfg(feature = "gc")]
+            Self::Gc(v) => write!(f, "{v}"),
+            #[cfg(feature = "extension")]
+            Self::Extension(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+fn write_blocktype(f: &mut Formatter<'_>, name: &str, ty: Blocktype) -> core::fmt::Result {
+    match ty {
+        Blocktype::Empty => f.write_str(name),
+        Blocktype::Val(ty) => write!(f, "{name} (result {ty})"),
+    }
+}
+
+// `natural_align` is the exponent of the access width, which is omitted as in the spec.
+fn write_memarg(
+    f: &mut Formatter<'_>,
+    name: &str,
+    arg: Memarg,
+    natural_align: u32,
+) -> core::fmt::Result {
+    f.write_str(name)?;
+    if arg.offset != 0 {
+        write!(f, " offset={}", arg.offset)?;
+    }
+    if arg.align != natural_align {
+        match 1u64.checked_shl(arg.align) {
+            Some(align) => write!(f, " align={align}")?,
+            None => write!(f, " align=2**{}", arg.align)?,
         }
     }
+    Ok(())
 }
 
 impl<V: VectorFactory> Clone for Instr<V> {
@@ -608,6 +1009,8 @@ impl<V: VectorFactory> Clone for Instr<V> {
             Self::CallIndirect(v) => Self::CallIndirect(*v),
             Self::Drop => Self::Drop,
             Self::Select => Self::Select,
+            #[cfg(feature = "reference_types")]
+            Self::SelectTyped(v) => Self::SelectTyped(*v),
             Self::LocalGet(v) => Self::LocalGet(*v),
             Self::LocalSet(v) => Self::LocalSet(*v),
             Self::LocalTee(v) => Self::LocalTee(*v),
@@ -767,6 +1170,12 @@ impl<V: VectorFactory> Clone for Instr<V> {
             Self::F64ReinterpretI64 => Self::F64ReinterpretI64,
             #[cfg(feature = "sign_extension")]
             Self::SignExtension(v) => Self::SignExtension(*v),
+            #[cfg(feature = "bulk_memory")]
+            Self::BulkMemory(v) => Self::BulkMemory(*v),
+            #[cfg(feature = "gc")]
+            Self::Gc(v) => Self::Gc(*v),
+            #[cfg(feature = "extension")]
+            Self::Extension(v) => Self::Extension(*v),
         }
     }
 }
@@ -916,7 +1325,7 @@ pub struct BrTableInstr<V: VectorFactory> {
 impl<V: VectorFactory> Decode<V> for BrTableInstr<V> {
     fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
         let n = reader.read_u32()? as usize + 1;
-        let mut labels = V::create_vector(Some(n));
+        let mut labels = V::create_vector(Some(reader.capacity_hint(n)));
         for _ in 0..n {
             labels.push(Decode::<V>::decode(reader)?);
         }
@@ -939,3 +1348,69 @@ impl<V: VectorFactory> Clone for BrTableInstr<V> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{components::Valtype, StdVector, StdVectorFactory};
+
+    #[test]
+    fn operand_accessors() {
+        type Instr = super::Instr<StdVectorFactory>;
+
+        let load = Instr::I64Load16U(Memarg {
+            align: 1,
+            offset: 8,
+        });
+        assert_eq!(Some(8), load.memarg().map(|arg| arg.offset));
+        assert!(load.branch_target().is_none());
+
+        let br_if = Instr::BrIf(Labelidx::new(2));
+        assert_eq!(Some(2), br_if.branch_target().map(|label| label.get()));
+        assert!(br_if.memarg().is_none());
+
+        assert_eq!(Some(-1), Instr::I32Const(-1).immediate_i32());
+        assert_eq!(None, Instr::I64Const(-1).immediate_i32());
+        assert_eq!(Some(0.5), Instr::F64Const(0.5f64.to_bits()).immediate_f64());
+        assert_eq!(
+            Some(3),
+            Instr::Call(Funcidx::new(3)).funcidx().map(|idx| idx.get())
+        );
+        assert!(Instr::Nop.globalidx().is_none());
+    }
+
+    #[test]
+    fn display_mnemonics() {
+        type Instr = super::Instr<StdVectorFactory>;
+
+        let load = |align, offset| Instr::I32Load(Memarg { align, offset });
+        assert_eq!("i32.add", Instr::I32Add.to_string());
+        assert_eq!("i64.trunc_f64_u", Instr::I64TruncF64U.to_string());
+        assert_eq!("br_if 1", Instr::BrIf(Labelidx::new(1)).to_string());
+        assert_eq!("i32.load", load(2, 0).to_string());
+        assert_eq!("i32.load offset=4 align=2", load(1, 4).to_string());
+        assert_eq!("i32.const -1", Instr::I32Const(-1).to_string());
+        assert_eq!(
+            "f64.const 0.5",
+            Instr::F64Const(0.5f64.to_bits()).to_string()
+        );
+        assert_eq!(
+            "f32.const 0x1.8p1",
+            format!("{:#}", Instr::F32Const(3f32.to_bits()))
+        );
+        assert_eq!(
+            "f32.const nan:0x200000",
+            Instr::F32Const(0x7fa0_0000).to_string()
+        );
+
+        let block = Instr::Block(BlockInstr {
+            blocktype: Blocktype::Val(Valtype::I32),
+            instrs: StdVector::new(vec![Instr::Nop]),
+        });
+        assert_eq!("block (result i32)", block.to_string());
+        let br_table = Instr::BrTable(BrTableInstr {
+            labels: StdVector::new(vec![Labelidx::new(0), Labelidx::new(2)]),
+        });
+        assert_eq!("br_table 0 2", br_table.to_string());
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{
+    components::{
+        Elem, Export, Exportdesc, Funcidx, Functype, Import, Importdesc, Name, Resulttype, Typeidx,
+        Valtype,
+    },
+    instructions::Instr,
+    vector::Vector,
+    Module, VectorFactory,
+};
+
+impl<V: VectorFactory> Module<V> {
+    // Injects gas accounting into the module: an imported `(func (param i32))` named `module.name`
+    // is called with the total `cost` of each instruction sequence at the entry of every function,
+    // block, loop and if-arm (a nested block is charged as a single instruction by the enclosing
+    // sequence, and its contents on its own entry).
+    //
+    // The host function can stop the execution by calling `Env::abort()` once the gas runs out.
+    // As the import is appended to the function imports, the indices of the functions defined in
+    // the module are shifted by one. Returns the index of the imported function.
+    //
+    // Note that the code offsets still refer to the original binary (and `digest()` returns
+    // `None` afterwards).
+    pub fn inject_gas<F>(&mut self, module: &str, name: &str, cost: F) -> Funcidx
+    where
+        F: Fn(&Instr<V>) -> u32,
+    {
+        self.digest = None;
+        let ty = self.gas_functype();
+        let gas = Funcidx::new(self.imported_funcs_len() as u32);
+        let position = self
+            .imports
+            .iter()
+            .rposition(|i| matches!(i.desc, Importdesc::Func(_)))
+            .map_or(0, |i| i + 1);
+        self.imports.insert(
+            position,
+            Import {
+                module: Name::new(module),
+                name: Name::new(name),
+                desc: Importdesc::Func(ty),
+            },
+        );
+
+        let shift = |idx: &mut Funcidx| {
+            if idx.get() >= gas.get() {
+                *idx = Funcidx::new(idx.get() as u32 + 1);
+            }
+        };
+        for func in self.funcs.iter_mut() {
+            instrument(func.body.instrs_mut(), gas, &cost, &shift);
+        }
+        if let Some(start) = &mut self.start {
+            shift(start);
+        }
+        for Export { desc, .. } in self.exports.iter_mut() {
+            if let Exportdesc::Func(idx) = desc {
+                shift(idx);
+            }
+        }
+        for Elem { init, .. } in self.elems.iter_mut() {
+            init.iter_mut().flatten().for_each(shift);
+        }
+        for (idx, _) in self.func_names.iter_mut() {
+            shift(idx);
+        }
+        gas
+    }
+
+    fn gas_functype(&mut self) -> Typeidx {
+        if let Some(i) = self
+            .types
+            .iter()
+            .position(|ty| ty.params[..] == [Valtype::I32] && ty.result.is_empty())
+        {
+            return Typeidx::new(i as u32);
+        }
+        let mut params = V::create_vector(Some(1));
+        params.push(Valtype::I32);
+        self.types.push(Functype {
+            params,
+            result: Resulttype::new(None),
+        });
+        Typeidx::new(self.types.len() as u32 - 1)
+    }
+}
+
+fn instrument<V, F, S>(instrs: &mut V::Vector<Instr<V>>, gas: Funcidx, cost: &F, shift: &S)
+where
+    V: VectorFactory,
+    F: Fn(&Instr<V>) -> u32,
+    S: Fn(&mut Funcidx),
+{
+    let mut total = 0u32;
+    for instr in instrs.iter_mut() {
+        total = total.saturating_add(cost(instr));
+        match instr {
+            Instr::Block(block) => instrument(&mut block.instrs, gas, cost, shift),
+            Instr::Loop(block) => instrument(&mut block.instrs, gas, cost, shift),
+            Instr::If(block) => {
+                instrument(&mut block.then_instrs, gas, cost, shift);
+                instrument(&mut block.else_instrs, gas, cost, shift);
+            }
+            Instr::Call(idx) => shift(idx),
+            _ => {}
+        }
+    }
+    if total > 0 {
+        instrs.insert(0, Instr::I32Const(total as i32));
+        instrs.insert(1, Instr::Call(gas));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Env, HostFunc, Resolve, StdVectorFactory, Val};
+
+    #[test]
+    fn inject_gas_test() {
+        // (module
+        //   (func (export "count") (param i32) (result i32)
+        //     (block
+        //       (loop
+        //         (br_if 1 (i32.eqz (local.get 0)))
+        //         (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+        //         (br 0)))
+        //     (call $seven))
+        //   (func $seven (result i32)
+        //     i32.const 7))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 10, 2, 96, 1, 127, 1, 127, 96, 0, 1, 127, 3, 3, 2, 0,
+            1, 7, 9, 1, 5, 99, 111, 117, 110, 116, 0, 0, 10, 31, 2, 24, 0, 2, 64, 3, 64, 32, 0, 69,
+            13, 1, 32, 0, 65, 1, 107, 33, 0, 12, 0, 11, 11, 16, 1, 11, 4, 0, 65, 7, 11,
+        ];
+        let mut module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        assert_eq!(Some(crate::ModuleDigest::of(&input)), module.digest());
+        let gas = module.inject_gas("env", "gas", |_| 1);
+        assert_eq!(0, gas.get());
+        assert_eq!(None, module.digest());
+        assert_eq!(3, module.types().len());
+
+        let mut instance = module.instantiate(Resolver).expect("instantiate");
+        let result = instance.invoke("count", &[Val::I32(3)]).expect("invoke");
+        assert_eq!(Some(Val::I32(7)), result);
+
+        let host_func = instance.host_func("env", "gas").expect("host_func");
+        // Function entries (2 + 1), the block (1) and four iterations of the loop (8 each).
+        assert_eq!(36, host_func.0);
+    }
+
+    #[derive(Debug)]
+    struct Resolver;
+
+    impl Resolve for Resolver {
+        type HostFunc = Gas;
+
+        fn resolve_func(&self, module: &str, name: &str) -> Option<Self::HostFunc> {
+            (module == "env" && name == "gas").then_some(Gas(0))
+        }
+    }
+
+    #[derive(Debug)]
+    struct Gas(i32);
+
+    impl HostFunc for Gas {
+        fn invoke(&mut self, args: &[Val], _env: &mut Env) -> Option<Val> {
+            self.0 += args[0].as_i32()?;
+            None
+        }
+    }
+}
@@ -1,24 +1,124 @@
 #![forbid(unsafe_code)]
 #![cfg_attr(not(feature = "std"), no_std)]
 
+// Emits a trace-level record through the `log` crate if the `log` feature is enabled
+// (otherwise the arguments are only type-checked).
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::trace!(target: "nowasm", $($arg)*);
+        #[cfg(not(feature = "log"))]
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    };
+}
+
+#[cfg(feature = "bulk_memory")]
+pub(crate) mod bulk_memory;
+pub(crate) mod call_graph;
+pub(crate) mod capacity;
+#[cfg(feature = "component")]
+pub(crate) mod component;
+pub(crate) mod coverage;
+#[cfg(feature = "debugger")]
+pub(crate) mod debugger;
 pub(crate) mod decode;
+pub(crate) mod digest;
+#[cfg(feature = "emscripten")]
+pub(crate) mod emscripten;
+pub(crate) mod encode;
+pub(crate) mod error;
 pub(crate) mod execute;
+#[cfg(feature = "extension")]
+pub(crate) mod extension;
+pub(crate) mod features;
+pub(crate) mod func_metrics;
+#[cfg(feature = "gc")]
+pub(crate) mod gc;
+pub(crate) mod handles;
+pub(crate) mod heap_bytes;
+pub(crate) mod hex_float;
+pub(crate) mod host_funcs;
+pub(crate) mod identity;
 pub(crate) mod instance;
+pub(crate) mod instrument;
+pub(crate) mod memory;
+pub(crate) mod metadata;
 pub(crate) mod module;
+pub(crate) mod optimize;
+pub(crate) mod pool;
 pub(crate) mod reader;
+pub(crate) mod scheduler;
+#[cfg(feature = "serde")]
+pub(crate) mod serialize;
 #[cfg(feature = "sign_extension")]
 pub(crate) mod sign_extension;
+pub(crate) mod stack_buffers;
+pub(crate) mod stack_usage;
+#[cfg(any(test, feature = "differential"))]
+pub(crate) mod test_util;
+pub(crate) mod transform;
+pub(crate) mod typed_memory;
+pub(crate) mod validate;
 pub(crate) mod vector;
+pub(crate) mod verify;
 
+pub mod abi;
 pub mod components;
 pub mod instructions;
 
-pub use decode::DecodeError;
-pub use execute::ExecuteError;
-pub use instance::{Env, FuncInst, GlobalVal, HostFunc, ModuleInstance, Resolve, Val};
-pub use module::Module;
+pub use call_graph::{CallEdge, CallGraph};
+pub use capacity::{Capacity, CapacityReport};
+pub use coverage::Coverage;
+#[cfg(feature = "debugger")]
+pub use debugger::Debugger;
+pub use decode::{DecodeConfig, DecodeError};
+pub use digest::ModuleDigest;
+#[cfg(feature = "emscripten")]
+pub use emscripten::{EmscriptenFunc, EmscriptenFuncKind, EmscriptenLikeResolver};
+pub use encode::CustomSectionBuilder;
+pub use error::{Error, ErrorKind};
+pub use execute::{
+    Backtrace, ExecStats, ExecuteError, ExecutorConfig, FpMode, Frame, Label, Runtime, StepResult,
+    TraceFrame, TrapInfo, TrapKind,
+};
+#[cfg(feature = "extension")]
+pub use extension::Extension;
+pub use features::{supported_features, Features};
+pub use func_metrics::FuncMetrics;
+#[cfg(feature = "gc")]
+pub use gc::{Comptype, Fieldtype, Heaptype, Rectype, Reftype, Storagetype, Subtype};
+pub use handles::{GlobalsMut, MemMut, TableMut};
+pub use host_funcs::{HostFuncError, HostFuncSig, HostFuncTable, HostResult, HostType, TableFunc};
+pub use identity::{GlobalId, MemoryId};
+#[cfg(feature = "std")]
+pub use instance::InvokeDynError;
+pub use instance::{
+    ConvertValError, DebugOptions, Env, EnvMem, Extern, FuncHandle, FuncInst, FuncRef, GlobalVal,
+    HostFunc, InstanceDebug, ModuleInstance, NanPattern, ParseValError, Resolve, Val,
+};
+pub use memory::{LinearMemory, SliceMemory, VectorMemory};
+#[cfg(feature = "memory_protection")]
+pub use memory::{Prot, ProtectedMemory};
+#[cfg(feature = "std")]
+pub use memory::{ReservedMemory, SharedMemory};
+pub use metadata::ModuleMetadata;
+pub use module::{Module, SectionId};
+pub use pool::InstancePool;
+pub use reader::Reader;
+pub use scheduler::{Scheduler, TaskId};
+pub use stack_buffers::{StackBuffers, StackStorage};
+pub use stack_usage::{StackUsage, StackUsages};
+#[cfg(feature = "differential")]
+#[doc(hidden)]
+pub use test_util::{leb128_s, leb128_u};
+pub use transform::TransformError;
+pub use typed_memory::{MemAccessError, MemValue, TypedMemory, TypedSlice};
+pub use validate::ValidateError;
 #[cfg(feature = "std")]
 pub use vector::{StdVector, StdVectorFactory};
 pub use vector::{Vector, VectorFactory};
+pub use verify::{Verify, SIGNATURE_SECTION_NAME};
 
 pub const PAGE_SIZE: usize = 65536;
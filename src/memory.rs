@@ -0,0 +1,596 @@
+use crate::{Vector, VectorFactory, PAGE_SIZE};
+use core::fmt::{Debug, Formatter};
+
+// Returns `None` if the size doesn't fit in `usize` (i.e., 4 GiB on 32-bit targets).
+pub(crate) fn pages_to_bytes(pages: u32) -> Option<usize> {
+    usize::try_from(pages as u64 * PAGE_SIZE as u64).ok()
+}
+
+// Sizes and offsets are in bytes.
+pub trait LinearMemory {
+    fn size(&self) -> usize;
+
+    // Returns `false` if the memory cannot grow.
+    fn grow(&mut self, additional: usize) -> bool;
+
+    // Returns `false` (and does nothing) if the range is out of bounds.
+    fn read(&self, offset: usize, buf: &mut [u8]) -> bool;
+
+    // Returns `false` (and does nothing) if the range is out of bounds.
+    fn write(&mut self, offset: usize, data: &[u8]) -> bool;
+
+    // Restores the memory to `size` zero-filled bytes (used to reset an instance).
+    // Returns `false` if that's not possible, which is the case by default if the memory
+    // has grown beyond `size`.
+    fn reset(&mut self, size: usize) -> bool {
+        const ZEROS: [u8; 4096] = [0; 4096];
+        let Some(additional) = size.checked_sub(self.size()) else {
+            return false;
+        };
+        let mut offset = 0;
+        while offset < self.size() {
+            let n = (self.size() - offset).min(ZEROS.len());
+            self.write(offset, &ZEROS[..n]);
+            offset += n;
+        }
+        self.grow(additional)
+    }
+
+    // Permission checked by the load and store instructions accessing the page
+    // (accesses by the host through `read()` and `write()` are not restricted).
+    #[cfg(feature = "memory_protection")]
+    fn page_prot(&self, _page: usize) -> Prot {
+        Prot::READ_WRITE
+    }
+}
+
+#[cfg(feature = "memory_protection")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Prot {
+    pub read: bool,
+    pub write: bool,
+}
+
+#[cfg(feature = "memory_protection")]
+impl Prot {
+    pub const NONE: Self = Self {
+        read: false,
+        write: false,
+    };
+    pub const READ_ONLY: Self = Self {
+        read: true,
+        write: false,
+    };
+    pub const READ_WRITE: Self = Self {
+        read: true,
+        write: true,
+    };
+}
+
+// A memory wrapper that restricts the accesses from the guest per page.
+// Pages are readable and writable unless specified otherwise by `protect()`.
+#[cfg(feature = "memory_protection")]
+pub struct ProtectedMemory<V: VectorFactory, M = VectorMemory<V>> {
+    inner: M,
+    prots: V::Vector<Prot>,
+}
+
+#[cfg(feature = "memory_protection")]
+impl<V: VectorFactory, M: LinearMemory> ProtectedMemory<V, M> {
+    pub fn new(inner: M) -> Self {
+        Self {
+            inner,
+            prots: V::create_vector(None),
+        }
+    }
+
+    pub fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut M {
+        &mut self.inner
+    }
+
+    // Sets the permission of all the pages overlapping `range` (which may exceed the current size).
+    pub fn protect(&mut self, range: core::ops::Range<usize>, prot: Prot) {
+        if range.is_empty() {
+            return;
+        }
+        let start = range.start / PAGE_SIZE;
+        let end = range.end.div_ceil(PAGE_SIZE);
+        if self.prots.len() < end {
+            self.prots
+                .extend_with(end - self.prots.len(), Prot::READ_WRITE);
+        }
+        for p in &mut self.prots[start..end] {
+            *p = prot;
+        }
+    }
+}
+
+#[cfg(feature = "memory_protection")]
+impl<V: VectorFactory, M: LinearMemory> LinearMemory for ProtectedMemory<V, M> {
+    fn size(&self) -> usize {
+        self.inner.size()
+    }
+
+    fn grow(&mut self, additional: usize) -> bool {
+        self.inner.grow(additional)
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8]) -> bool {
+        self.inner.read(offset, buf)
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> bool {
+        self.inner.write(offset, data)
+    }
+
+    fn reset(&mut self, size: usize) -> bool {
+        self.inner.reset(size)
+    }
+
+    fn page_prot(&self, page: usize) -> Prot {
+        self.prots.get(page).copied().unwrap_or(Prot::READ_WRITE)
+    }
+}
+
+#[cfg(feature = "memory_protection")]
+impl<V: VectorFactory, M: Debug> Debug for ProtectedMemory<V, M> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ProtectedMemory")
+            .field("inner", &self.inner)
+            .field("prots", &self.prots.as_ref())
+            .finish()
+    }
+}
+
+#[cfg(feature = "memory_protection")]
+impl<V: VectorFactory, M: Clone> Clone for ProtectedMemory<V, M> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            prots: V::clone_vector(&self.prots),
+        }
+    }
+}
+
+pub struct VectorMemory<V: VectorFactory>(V::Vector<u8>);
+
+impl<V: VectorFactory> VectorMemory<V> {
+    pub fn new() -> Self {
+        Self(V::create_vector(None))
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+impl<V: VectorFactory> Default for VectorMemory<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: VectorFactory> LinearMemory for VectorMemory<V> {
+    fn size(&self) -> usize {
+        self.0.len()
+    }
+
+    fn grow(&mut self, additional: usize) -> bool {
+        if !self.0.try_reserve(additional) {
+            return false;
+        }
+        self.0.extend_with(additional, 0);
+        true
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8]) -> bool {
+        let Some(src) = offset
+            .checked_add(buf.len())
+            .and_then(|end| self.0.get(offset..end))
+        else {
+            return false;
+        };
+        buf.copy_from_slice(src);
+        true
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> bool {
+        let Some(dst) = offset
+            .checked_add(data.len())
+            .and_then(|end| self.0.get_mut(offset..end))
+        else {
+            return false;
+        };
+        dst.copy_from_slice(data);
+        true
+    }
+
+    fn reset(&mut self, size: usize) -> bool {
+        self.0.truncate(size);
+        self.0.fill(0);
+        self.0.extend_with(size - self.0.len(), 0);
+        true
+    }
+}
+
+impl<V: VectorFactory> Debug for VectorMemory<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("VectorMemory")
+            .field("size", &self.0.len())
+            .finish()
+    }
+}
+
+impl<V: VectorFactory> Clone for VectorMemory<V> {
+    fn clone(&self) -> Self {
+        Self(V::clone_vector(&self.0))
+    }
+}
+
+// A memory backed by a buffer provided by the host (e.g. a statically allocated RAM region),
+// whose length is the maximum size of the memory.
+pub struct SliceMemory<'a> {
+    buf: &'a mut [u8],
+    size: usize,
+    zeroed: bool,
+}
+
+impl<'a> SliceMemory<'a> {
+    // Grown regions are zero-filled.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            size: 0,
+            zeroed: false,
+        }
+    }
+
+    // Skips zero-filling grown regions, so that instantiation is just copying data segments.
+    // `buf` must be known to be zero-filled (otherwise the guest can observe its contents).
+    pub fn assume_zeroed(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            size: 0,
+            zeroed: true,
+        }
+    }
+
+    pub fn max(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf[..self.size]
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.buf[..self.size]
+    }
+}
+
+impl LinearMemory for SliceMemory<'_> {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn grow(&mut self, additional: usize) -> bool {
+        let Some(new_size) = self
+            .size
+            .checked_add(additional)
+            .filter(|n| *n <= self.buf.len())
+        else {
+            return false;
+        };
+        if !self.zeroed {
+            self.buf[self.size..new_size].fill(0);
+        }
+        self.size = new_size;
+        true
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8]) -> bool {
+        let Some(src) = offset
+            .checked_add(buf.len())
+            .and_then(|end| self.as_slice().get(offset..end))
+        else {
+            return false;
+        };
+        buf.copy_from_slice(src);
+        true
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> bool {
+        let Some(dst) = offset
+            .checked_add(data.len())
+            .and_then(|end| self.as_mut_slice().get_mut(offset..end))
+        else {
+            return false;
+        };
+        dst.copy_from_slice(data);
+        true
+    }
+
+    fn reset(&mut self, size: usize) -> bool {
+        // The region beyond the current size is still zero-filled (if it's assumed to be).
+        self.buf[..self.size].fill(0);
+        self.size = 0;
+        self.grow(size)
+    }
+}
+
+impl Debug for SliceMemory<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SliceMemory")
+            .field("size", &self.size)
+            .field("max", &self.buf.len())
+            .field("zeroed", &self.zeroed)
+            .finish()
+    }
+}
+
+// A memory that reserves its maximum size up-front so that growing never reallocates
+// (and the address of the contents never changes).
+// Growing beyond the reserved size fails.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct ReservedMemory {
+    bytes: Vec<u8>,
+    max: usize,
+}
+
+#[cfg(feature = "std")]
+impl ReservedMemory {
+    pub fn new(max: usize) -> Self {
+        Self {
+            bytes: Vec::with_capacity(max),
+            max,
+        }
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+}
+
+#[cfg(feature = "std")]
+impl LinearMemory for ReservedMemory {
+    fn size(&self) -> usize {
+        self.bytes.len()
+    }
+
+    fn grow(&mut self, additional: usize) -> bool {
+        let Some(new_size) = self.bytes.len().checked_add(additional) else {
+            return false;
+        };
+        if new_size > self.max {
+            return false;
+        }
+        self.bytes.resize(new_size, 0);
+        true
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8]) -> bool {
+        let Some(src) = offset
+            .checked_add(buf.len())
+            .and_then(|end| self.bytes.get(offset..end))
+        else {
+            return false;
+        };
+        buf.copy_from_slice(src);
+        true
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> bool {
+        let Some(dst) = offset
+            .checked_add(data.len())
+            .and_then(|end| self.bytes.get_mut(offset..end))
+        else {
+            return false;
+        };
+        dst.copy_from_slice(data);
+        true
+    }
+
+    fn reset(&mut self, size: usize) -> bool {
+        if size > self.max {
+            return false;
+        }
+        self.bytes.truncate(size);
+        self.bytes.fill(0);
+        self.bytes.resize(size, 0);
+        true
+    }
+}
+
+// A handle to a memory that can be shared by multiple instances.
+//
+// Clones refer to the same underlying bytes, so writes and growth made through one instance
+// are visible to all the others.
+// To link an instance to an existing shared memory (instead of copying its contents),
+// pass a clone of the handle to `Module::instantiate_with_memory()` and
+// return `true` from `Resolve::alias_mem()` for the corresponding import.
+// (Memories imported via `Resolve::resolve_mem()` are copies, so their growth is not visible
+// to the provider.)
+#[cfg(feature = "std")]
+#[derive(Default, Clone)]
+pub struct SharedMemory(std::rc::Rc<SharedMemoryInner>);
+
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct SharedMemoryInner {
+    bytes: std::cell::RefCell<Vec<u8>>,
+    on_grow: std::cell::RefCell<Option<OnGrow>>,
+}
+
+#[cfg(feature = "std")]
+type OnGrow = Box<dyn FnMut(usize, usize)>;
+
+#[cfg(feature = "std")]
+impl SharedMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bytes(&self) -> std::cell::Ref<'_, [u8]> {
+        std::cell::Ref::map(self.0.bytes.borrow(), |bytes| bytes.as_slice())
+    }
+
+    pub fn bytes_mut(&self) -> std::cell::RefMut<'_, [u8]> {
+        std::cell::RefMut::map(self.0.bytes.borrow_mut(), |bytes| bytes.as_mut_slice())
+    }
+
+    // Sets the callback invoked with the old and new sizes whenever any handle grows the memory
+    // (e.g., by `memory.grow` executed in one of the sharing instances).
+    // The callback must not grow the memory by itself.
+    pub fn on_memory_grow<F>(&self, f: F)
+    where
+        F: 'static + FnMut(usize, usize),
+    {
+        *self.0.on_grow.borrow_mut() = Some(Box::new(f));
+    }
+
+    // Returns `true` if both handles refer to the same memory.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        std::rc::Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl LinearMemory for SharedMemory {
+    fn size(&self) -> usize {
+        self.0.bytes.borrow().len()
+    }
+
+    fn grow(&mut self, additional: usize) -> bool {
+        let mut bytes = self.0.bytes.borrow_mut();
+        let old_size = bytes.len();
+        let Some(new_size) = old_size.checked_add(additional) else {
+            return false;
+        };
+        bytes.resize(new_size, 0);
+        drop(bytes);
+
+        if additional > 0 {
+            if let Some(f) = self.0.on_grow.borrow_mut().as_mut() {
+                f(old_size, new_size);
+            }
+        }
+        true
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8]) -> bool {
+        let bytes = self.0.bytes.borrow();
+        let Some(src) = offset
+            .checked_add(buf.len())
+            .and_then(|end| bytes.get(offset..end))
+        else {
+            return false;
+        };
+        buf.copy_from_slice(src);
+        true
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> bool {
+        let mut bytes = self.0.bytes.borrow_mut();
+        let Some(dst) = offset
+            .checked_add(data.len())
+            .and_then(|end| bytes.get_mut(offset..end))
+        else {
+            return false;
+        };
+        dst.copy_from_slice(data);
+        true
+    }
+}
+
+#[cfg(feature = "std")]
+impl Debug for SharedMemory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SharedMemory")
+            .field("size", &self.size())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{Limits, Memtype};
+    use crate::{Module, StdVectorFactory};
+
+    #[test]
+    fn page_arithmetic() {
+        assert_eq!(Some(0), pages_to_bytes(0));
+        assert_eq!(Some(PAGE_SIZE), pages_to_bytes(1));
+        assert_eq!(Some(0xffff_0000), pages_to_bytes(0xffff));
+        #[cfg(target_pointer_width = "32")]
+        assert_eq!(None, pages_to_bytes(0x1_0000));
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(Some(0x1_0000_0000), pages_to_bytes(0x1_0000));
+
+        let ty = Memtype {
+            limits: Limits {
+                min: 0x1_0000,
+                max: Some(u32::MAX),
+            },
+        };
+        assert_eq!(
+            pages_to_bytes(0x1_0000).unwrap_or(usize::MAX),
+            ty.min_bytes()
+        );
+        assert_eq!(
+            Some(pages_to_bytes(u32::MAX).unwrap_or(usize::MAX)),
+            ty.max_bytes()
+        );
+    }
+
+    #[test]
+    fn slice_memory() {
+        // (module (memory 1 2) (data (i32.const 1) "hi"))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 5, 4, 1, 1, 1, 2, 11, 8, 1, 0, 65, 1, 11, 2, 104, 105,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut buf = vec![0xff; PAGE_SIZE + 1];
+        let instance = module
+            .clone()
+            .instantiate_with_memory((), SliceMemory::new(&mut buf))
+            .expect("instantiate");
+        assert_eq!(PAGE_SIZE, instance.mem().size());
+        assert_eq!(&[0, b'h', b'i', 0], &instance.mem().as_slice()[..4]);
+        assert!(instance.mem().as_slice()[3..].iter().all(|b| *b == 0));
+
+        // The buffer isn't zero-filled, so the guest would see its contents.
+        let mut buf = vec![0xff; PAGE_SIZE + 1];
+        let mut instance = module
+            .instantiate_with_memory((), SliceMemory::assume_zeroed(&mut buf))
+            .expect("instantiate");
+        assert_eq!(&[0xff, b'h', b'i', 0xff], &instance.mem().as_slice()[..4]);
+        assert_eq!(None, instance.mem_mut().grow(1));
+    }
+
+    #[test]
+    fn vector_memory_grow_failure() {
+        let mut mem = VectorMemory::<StdVectorFactory>::new();
+        assert!(mem.grow(PAGE_SIZE));
+        assert!(!mem.grow(usize::MAX));
+        assert!(!mem.grow(isize::MAX as usize));
+        assert_eq!(PAGE_SIZE, mem.size());
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{
+    components::{Export, Funcidx, Functype, Global, Import, Memtype, Tabletype, Typeidx},
+    decode::Decode,
+    module::{decode_memory_section, decode_table_section, read_section, Magic, Version},
+    reader::Reader,
+    DecodeError, ModuleDigest, SectionId, VectorFactory,
+};
+use core::fmt::{Debug, Formatter};
+
+// The interface of a module (i.e., everything but the function bodies and segments).
+//
+// The element, data count, code, data, and custom sections are skipped without being
+// decoded, so only their framing and placement are checked.
+pub struct ModuleMetadata<V: VectorFactory> {
+    types: V::Vector<Functype<V>>,
+    imports: V::Vector<Import<V>>,
+    funcs: V::Vector<Typeidx>,
+    table: Option<Tabletype>,
+    mem: Option<Memtype>,
+    globals: V::Vector<Global<V>>,
+    exports: V::Vector<Export<V>>,
+    start: Option<Funcidx>,
+    digest: ModuleDigest,
+}
+
+impl<V: VectorFactory> ModuleMetadata<V> {
+    pub fn decode(wasm_bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut this = Self {
+            types: V::create_vector(None),
+            imports: V::create_vector(None),
+            funcs: V::create_vector(None),
+            table: None,
+            mem: None,
+            globals: V::create_vector(None),
+            exports: V::create_vector(None),
+            start: None,
+            digest: ModuleDigest::of(wasm_bytes),
+        };
+        let mut reader = Reader::new(wasm_bytes);
+        let _ = Magic::decode(&mut reader)?;
+        let _ = Version::decode(&mut reader)?;
+
+        let mut last_section_id = None;
+        while !reader.is_empty() {
+            let (section_id, _, mut section_reader) =
+                read_section(&mut reader, &mut last_section_id)?;
+            match section_id {
+                #[cfg(not(feature = "gc"))]
+                SectionId::Type => {
+                    this.types = Decode::<V>::decode_vector(&mut section_reader)?;
+                }
+                #[cfg(feature = "gc")]
+                SectionId::Type => {
+                    this.types = crate::gc::decode_type_section::<V>(&mut section_reader)?.1;
+                }
+                SectionId::Import => {
+                    this.imports = Decode::<V>::decode_vector(&mut section_reader)?;
+                }
+                SectionId::Function => {
+                    this.funcs = Decode::<V>::decode_vector(&mut section_reader)?;
+                }
+                SectionId::Table => {
+                    this.table = decode_table_section::<V>(&mut section_reader)?;
+                }
+                SectionId::Memory => {
+                    this.mem = decode_memory_section::<V>(&mut section_reader)?;
+                }
+                SectionId::Global => {
+                    this.globals = Decode::<V>::decode_vector(&mut section_reader)?;
+                }
+                SectionId::Export => {
+                    this.exports = Decode::<V>::decode_vector(&mut section_reader)?;
+                }
+                SectionId::Start => {
+                    this.start = Some(Decode::<V>::decode(&mut section_reader)?);
+                }
+                SectionId::Custom
+                | SectionId::Element
+                | SectionId::DataCount
+                | SectionId::Code
+                | SectionId::Data => continue,
+            }
+
+            if !section_reader.is_empty() {
+                return Err(DecodeError::InvalidSectionByteSize {
+                    section_id,
+                    expected_byte_size: section_reader.position() + section_reader.len(),
+                    actual_byte_size: section_reader.position(),
+                });
+            }
+        }
+        Ok(this)
+    }
+
+    pub fn types(&self) -> &[Functype<V>] {
+        &self.types
+    }
+
+    pub fn imports(&self) -> &[Import<V>] {
+        &self.imports
+    }
+
+    // Type indices of the functions defined in the module (imported functions are not included).
+    pub fn funcs(&self) -> &[Typeidx] {
+        &self.funcs
+    }
+
+    pub fn table(&self) -> Option<Tabletype> {
+        self.table
+    }
+
+    pub fn mem(&self) -> Option<Memtype> {
+        self.mem
+    }
+
+    pub fn globals(&self) -> &[Global<V>] {
+        &self.globals
+    }
+
+    pub fn exports(&self) -> &[Export<V>] {
+        &self.exports
+    }
+
+    pub fn start(&self) -> Option<Funcidx> {
+        self.start
+    }
+
+    // Same as `Module::digest()` for the same bytes.
+    pub fn digest(&self) -> ModuleDigest {
+        self.digest
+    }
+}
+
+impl<V: VectorFactory> Debug for ModuleMetadata<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ModuleMetadata")
+            .field("types", &self.types.as_ref())
+            .field("imports", &self.imports.as_ref())
+            .field("funcs", &self.funcs.as_ref())
+            .field("table", &self.table)
+            .field("mem", &self.mem)
+            .field("globals", &self.globals.as_ref())
+            .field("exports", &self.exports.as_ref())
+            .field("start", &self.start)
+            .field("digest", &self.digest)
+            .finish()
+    }
+}
+
+impl<V: VectorFactory> Clone for ModuleMetadata<V> {
+    fn clone(&self) -> Self {
+        Self {
+            types: V::clone_vector(&self.types),
+            imports: V::clone_vector(&self.imports),
+            funcs: V::clone_vector(&self.funcs),
+            table: self.table,
+            mem: self.mem,
+            globals: V::clone_vector(&self.globals),
+            exports: V::clone_vector(&self.exports),
+            start: self.start,
+            digest: self.digest,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Module, StdVectorFactory};
+
+    #[test]
+    fn decode_metadata() {
+        // (module
+        //   (func (export "addTwo") (param i32 i32) (result i32)
+        //     local.get 0
+        //     local.get 1
+        //     i32.add))
+        //
+        // The code section is replaced with a framed but malformed one.
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 7, 1, 96, 2, 127, 127, 1, 127, 3, 2, 1, 0, 7, 10, 1, 6,
+            97, 100, 100, 84, 119, 111, 0, 0, 10, 3, 255, 255, 255,
+        ];
+        assert!(Module::<StdVectorFactory>::decode(&input).is_err());
+
+        let metadata = Module::<StdVectorFactory>::decode_metadata(&input).expect("decode");
+        assert_eq!(1, metadata.types().len());
+        assert_eq!(1, metadata.funcs().len());
+        assert_eq!(1, metadata.exports().len());
+        assert_eq!("addTwo", metadata.exports()[0].name.as_str());
+        assert!(metadata.mem().is_none());
+        assert!(metadata.start().is_none());
+        assert_eq!(crate::ModuleDigest::of(&input), metadata.digest());
+    }
+}
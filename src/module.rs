@@ -1,44 +1,145 @@
 use crate::{
     components::{
-        Code, Data, Elem, Export, Func, Funcidx, Functype, Global, Import, Memtype, Tabletype,
-        Typeidx,
+        Blocktype, Code, ConstantExpr, CustomSection, Data, DataMode, Elem, ElemMode, Export, Func,
+        Funcidx, Functype, Global, Import, Importdesc, Memtype, Name, Tabletype, Typeidx, Valtype,
     },
     decode::Decode,
     execute::ExecuteError,
+    instructions::Instr,
+    memory::{pages_to_bytes, LinearMemory, VectorMemory},
     reader::Reader,
+    validate::Validator,
     vector::Vector,
-    DecodeError, ModuleInstance, Resolve, VectorFactory,
+    DecodeConfig, DecodeError, Extern, Features, GlobalVal, HostFunc, ModuleDigest, ModuleInstance,
+    ModuleMetadata, Resolve, Val, ValidateError, VectorFactory, PAGE_SIZE,
 };
-use core::fmt::{Debug, Formatter};
-
-const SECTION_ID_CUSTOM: u8 = 0;
-const SECTION_ID_TYPE: u8 = 1;
-const SECTION_ID_IMPORT: u8 = 2;
-const SECTION_ID_FUNCTION: u8 = 3;
-const SECTION_ID_TABLE: u8 = 4;
-const SECTION_ID_MEMORY: u8 = 5;
-const SECTION_ID_GLOBAL: u8 = 6;
-const SECTION_ID_EXPORT: u8 = 7;
-const SECTION_ID_START: u8 = 8;
-const SECTION_ID_ELEMENT: u8 = 9;
-const SECTION_ID_CODE: u8 = 10;
-const SECTION_ID_DATA: u8 = 11;
+#[cfg(feature = "std")]
+use crate::{StdVector, StdVectorFactory};
+use core::fmt::{Debug, Display, Formatter};
+
+// Variants are declared in the order the sections must appear in a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SectionId {
+    Custom,
+    Type,
+    Import,
+    Function,
+    Table,
+    Memory,
+    Global,
+    Export,
+    Start,
+    Element,
+    DataCount,
+    Code,
+    Data,
+}
+
+impl SectionId {
+    pub const fn from_u8(id: u8) -> Option<Self> {
+        Some(match id {
+            0 => Self::Custom,
+            1 => Self::Type,
+            2 => Self::Import,
+            3 => Self::Function,
+            4 => Self::Table,
+            5 => Self::Memory,
+            6 => Self::Global,
+            7 => Self::Export,
+            8 => Self::Start,
+            9 => Self::Element,
+            10 => Self::Code,
+            11 => Self::Data,
+            12 => Self::DataCount,
+            _ => return None,
+        })
+    }
+
+    pub const fn as_u8(self) -> u8 {
+        match self {
+            Self::Custom => 0,
+            Self::Type => 1,
+            Self::Import => 2,
+            Self::Function => 3,
+            Self::Table => 4,
+            Self::Memory => 5,
+            Self::Global => 6,
+            Self::Export => 7,
+            Self::Start => 8,
+            Self::Element => 9,
+            Self::Code => 10,
+            Self::Data => 11,
+            Self::DataCount => 12,
+        }
+    }
+}
+
+impl Display for SectionId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            Self::Custom => "custom",
+            Self::Type => "type",
+            Self::Import => "import",
+            Self::Function => "function",
+            Self::Table => "table",
+            Self::Memory => "memory",
+            Self::Global => "global",
+            Self::Export => "export",
+            Self::Start => "start",
+            Self::Element => "element",
+            Self::DataCount => "data count",
+            Self::Code => "code",
+            Self::Data => "data",
+        };
+        write!(f, "{name}")
+    }
+}
+
+const NAME_SUBSECTION_ID_FUNCTION: u8 = 1;
 
 pub struct Module<V: VectorFactory> {
-    types: V::Vector<Functype<V>>,
-    funcs: V::Vector<Func<V>>,
-    table: Option<Tabletype>,
-    imports: V::Vector<Import<V>>,
-    mem: Option<Memtype>,
-    globals: V::Vector<Global>,
-    elems: V::Vector<Elem<V>>,
-    datas: V::Vector<Data<V>>,
-    start: Option<Funcidx>,
-    exports: V::Vector<Export<V>>,
+    pub(crate) types: V::Vector<Functype<V>>,
+    pub(crate) funcs: V::Vector<Func<V>>,
+    pub(crate) table: Option<Tabletype>,
+    pub(crate) imports: V::Vector<Import<V>>,
+    pub(crate) mem: Option<Memtype>,
+    pub(crate) globals: V::Vector<Global<V>>,
+    pub(crate) elems: V::Vector<Elem<V>>,
+    pub(crate) datas: V::Vector<Data<V>>,
+    pub(crate) start: Option<Funcidx>,
+    pub(crate) exports: V::Vector<Export<V>>,
+    pub(crate) custom_sections: V::Vector<CustomSection<V>>,
+    pub(crate) data_count: Option<u32>,
+    pub(crate) code_section_offset: Option<usize>,
+    pub(crate) func_names: V::Vector<(Funcidx, Name<V>)>,
+    pub(crate) digest: Option<ModuleDigest>,
+    #[cfg(feature = "gc")]
+    pub(crate) rectypes: V::Vector<crate::gc::Rectype<V>>,
 }
 
 impl<V: VectorFactory> Module<V> {
     pub fn decode(wasm_bytes: &[u8]) -> Result<Self, DecodeError> {
+        Self::decode_with_config(wasm_bytes, DecodeConfig::default())
+    }
+
+    // Decodes only the parts of a module describing its interface, skipping over
+    // the function bodies and segments. See `ModuleMetadata` for details.
+    pub fn decode_metadata(wasm_bytes: &[u8]) -> Result<ModuleMetadata<V>, DecodeError> {
+        ModuleMetadata::decode(wasm_bytes)
+    }
+
+    pub fn decode_with_config(
+        wasm_bytes: &[u8],
+        config: DecodeConfig,
+    ) -> Result<Self, DecodeError> {
+        Self::decode_inner(wasm_bytes, config, Decode::<V>::decode_vector)
+    }
+
+    fn decode_inner(
+        wasm_bytes: &[u8],
+        config: DecodeConfig,
+        decode_code_section: CodeSectionDecoder<V>,
+    ) -> Result<Self, DecodeError> {
         let mut this = Self {
             types: V::create_vector(None),
             funcs: V::create_vector(None),
@@ -50,83 +151,95 @@ impl<V: VectorFactory> Module<V> {
             start: None,
             imports: V::create_vector(None),
             exports: V::create_vector(None),
+            custom_sections: V::create_vector(None),
+            data_count: None,
+            code_section_offset: None,
+            func_names: V::create_vector(None),
+            digest: Some(ModuleDigest::of(wasm_bytes)),
+            #[cfg(feature = "gc")]
+            rectypes: V::create_vector(None),
         };
-        let mut reader = Reader::new(wasm_bytes);
+        let mut reader = Reader::with_config(wasm_bytes, config);
 
         // Preamble
         let _ = Magic::decode(&mut reader)?;
         let _ = Version::decode(&mut reader)?;
 
         // Sections
-        this.decode_sections(&mut reader)?;
+        this.decode_sections(&mut reader, decode_code_section)?;
 
         Ok(this)
     }
 
-    fn decode_sections(&mut self, reader: &mut Reader) -> Result<(), DecodeError> {
-        let mut last_section_id = SECTION_ID_CUSTOM;
+    fn decode_sections(
+        &mut self,
+        reader: &mut Reader,
+        decode_code_section: CodeSectionDecoder<V>,
+    ) -> Result<(), DecodeError> {
+        let mut last_section_id = None;
         let mut function_section: V::Vector<Typeidx> = V::create_vector(None);
         while !reader.is_empty() {
-            let section_id = reader.read_u8()?;
-            let section_size = reader.read_u32()? as usize;
-            let mut section_reader = Reader::new(reader.read(section_size)?);
+            let (section_id, section_offset, mut section_reader) =
+                read_section(reader, &mut last_section_id)?;
+            let section_size = section_reader.len();
+            trace!(
+                "decoding {section_id} section ({section_size} bytes at offset {section_offset})"
+            );
 
-            if section_id == SECTION_ID_CUSTOM {
+            if section_id == SectionId::Custom {
+                let section: CustomSection<V> = Decode::<V>::decode(&mut section_reader)?;
+                if section.name.as_str() == "name"
+                    && self
+                        .decode_name_section(&mut Reader::with_config(
+                            &section.data,
+                            reader.config(),
+                        ))
+                        .is_err()
+                {
+                    // Malformed name sections are not fatal.
+                    self.func_names = V::create_vector(None);
+                }
+                self.custom_sections.push(section);
                 continue;
             }
 
-            if section_id < last_section_id {
-                return Err(DecodeError::InvalidSectionOrder {
-                    current_section_id: section_id,
-                    last_section_id,
-                });
-            }
-
             match section_id {
-                SECTION_ID_TYPE => {
+                #[cfg(not(feature = "gc"))]
+                SectionId::Type => {
                     self.types = Decode::<V>::decode_vector(&mut section_reader)?;
                 }
-                SECTION_ID_IMPORT => {
+                #[cfg(feature = "gc")]
+                SectionId::Type => {
+                    (self.rectypes, self.types) =
+                        crate::gc::decode_type_section::<V>(&mut section_reader)?;
+                }
+                SectionId::Import => {
                     self.imports = Decode::<V>::decode_vector(&mut section_reader)?;
                 }
-                SECTION_ID_FUNCTION => {
+                SectionId::Function => {
                     function_section = Decode::<V>::decode_vector(&mut section_reader)?;
                 }
-                SECTION_ID_TABLE => {
-                    let value = section_reader.read_u32()? as usize;
-                    if value > 1 {
-                        return Err(DecodeError::InvalidTableCount { value });
-                    }
-                    if value == 1 {
-                        let table = Decode::<V>::decode(&mut section_reader)?;
-                        self.table = Some(table);
-                    }
+                SectionId::Table => {
+                    self.table = decode_table_section::<V>(&mut section_reader)?;
                 }
-                SECTION_ID_MEMORY => {
-                    let value = section_reader.read_u32()? as usize;
-                    if value > 1 {
-                        return Err(DecodeError::InvalidMemoryCount { value });
-                    }
-                    if value == 1 {
-                        let mem = Decode::<V>::decode(&mut section_reader)?;
-                        self.mem = Some(mem);
-                    }
+                SectionId::Memory => {
+                    self.mem = decode_memory_section::<V>(&mut section_reader)?;
                 }
-                SECTION_ID_GLOBAL => {
+                SectionId::Global => {
                     self.globals = Decode::<V>::decode_vector(&mut section_reader)?;
                 }
-                SECTION_ID_EXPORT => {
+                SectionId::Export => {
                     self.exports = Decode::<V>::decode_vector(&mut section_reader)?;
                 }
-                SECTION_ID_START => {
+                SectionId::Start => {
                     self.start = Some(Decode::<V>::decode(&mut section_reader)?);
                 }
-                SECTION_ID_ELEMENT => {
+                SectionId::Element => {
                     self.elems = Decode::<V>::decode_vector(&mut section_reader)?;
                 }
-                SECTION_ID_CODE => {
-                    let code_section: V::Vector<Code<V>> =
-                        Decode::<V>::decode_vector(&mut section_reader)?;
+                SectionId::Code => {
+                    self.code_section_offset = Some(section_offset);
+                    let code_section = decode_code_section(&mut section_reader)?;
                     if function_section.len() != code_section.len() {
                         return Err(DecodeError::MismatchFunctionAndCodeSectionSize {
                             function_section_size: function_section.len(),
@@ -139,17 +252,19 @@ impl<V: VectorFactory> Module<V> {
                             ty,
                             locals: V::clone_vector(&code.locals),
                             body: code.body.clone(),
+                            code_offset: section_offset + code.offset,
+                            code_size: code.size,
                         });
                     }
                 }
-                SECTION_ID_DATA => {
-                    self.datas = Decode::<V>::decode_vector(&mut section_reader)?;
+                SectionId::DataCount => {
+                    self.data_count = Some(section_reader.read_u32()?);
                 }
-                _ => {
-                    return Err(DecodeError::InvalidSectionId { value: section_id });
+                SectionId::Data => {
+                    self.datas = Decode::<V>::decode_vector(&mut section_reader)?;
                 }
+                SectionId::Custom => unreachable!(),
             }
-            last_section_id = section_id;
 
             if !section_reader.is_empty() {
                 return Err(DecodeError::InvalidSectionByteSize {
@@ -159,6 +274,34 @@ impl<V: VectorFactory> Module<V> {
                 });
             }
         }
+
+        if let Some(data_count) = self.data_count {
+            if data_count as usize != self.datas.len() {
+                return Err(DecodeError::MismatchDataCountAndDataSectionSize {
+                    data_count: data_count as usize,
+                    data_section_size: self.datas.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_name_section(&mut self, reader: &mut Reader) -> Result<(), DecodeError> {
+        while !reader.is_empty() {
+            let subsection_id = reader.read_u8()?;
+            let subsection_size = reader.read_u32()? as usize;
+            let mut subsection_reader = reader.sub_reader(subsection_size)?;
+            if subsection_id != NAME_SUBSECTION_ID_FUNCTION {
+                continue;
+            }
+
+            let n = subsection_reader.read_usize()?;
+            for _ in 0..n {
+                let idx = Decode::<V>::decode(&mut subsection_reader)?;
+                let name = Decode::<V>::decode(&mut subsection_reader)?;
+                self.func_names.push((idx, name));
+            }
+        }
         Ok(())
     }
 
@@ -166,15 +309,199 @@ impl<V: VectorFactory> Module<V> {
     where
         R: Resolve,
     {
-        // TODO: validate
-        let instance = ModuleInstance::new(self, resolver)?;
+        self.instantiate_with_memory(resolver, VectorMemory::new())
+    }
+
+    pub fn instantiate_with_memory<R, M>(
+        self,
+        resolver: R,
+        mem: M,
+    ) -> Result<ModuleInstance<V, R::HostFunc, M>, ExecuteError>
+    where
+        R: Resolve,
+        M: LinearMemory,
+    {
+        self.validate().map_err(ExecuteError::InvalidModule)?;
+        let instance = ModuleInstance::new(self, resolver, mem)?;
         Ok(instance)
     }
 
+    // Instantiates with the values of the imports given in the order of the import section,
+    // instead of resolving them by name.
+    pub fn instantiate_with<H>(
+        self,
+        externs: &[Extern<H>],
+    ) -> Result<ModuleInstance<V, H>, ExecuteError>
+    where
+        H: HostFunc + Clone,
+    {
+        self.validate().map_err(ExecuteError::InvalidModule)?;
+        if externs.len() != self.imports().len() {
+            return Err(ExecuteError::ExternCountMismatch {
+                expected: self.imports().len(),
+                actual: externs.len(),
+            });
+        }
+        let resolve = |index, _: &Import<V>| externs.get(index).cloned();
+        ModuleInstance::new_with_externs(self, resolve, false, None, VectorMemory::new())
+    }
+
+    pub fn validate(&self) -> Result<(), ValidateError> {
+        Validator::new(self).validate()
+    }
+
+    // Dry-runs the checks performed by `instantiate()` without allocating the instance state,
+    // and returns all the problems found (an empty vector means the module is instantiable).
+    //
+    // Note that traps raised by the start function cannot be detected in advance.
+    pub fn check_instantiable<R: Resolve>(&self, resolver: &R) -> V::Vector<ExecuteError> {
+        let mut problems = V::create_vector(None);
+        let valid = match self.validate() {
+            Ok(()) => true,
+            Err(e) => {
+                problems.push(ExecuteError::InvalidModule(e));
+                false
+            }
+        };
+
+        let mut imported_globals = V::create_vector(None);
+        let mut imported_mem_size = None;
+        let mut imported_table_size = None;
+        for (index, import) in self.imports.iter().enumerate() {
+            let (module, name) = (import.module.as_str(), import.name.as_str());
+            let problem = match &import.desc {
+                Importdesc::Func(_) => resolver
+                    .resolve_func(module, name)
+                    .is_none()
+                    .then_some(ExecuteError::UnresolvedImport { index }),
+                Importdesc::Table(ty) => match resolver.resolve_table(module, name) {
+                    None => Some(ExecuteError::UnresolvedImport { index }),
+                    Some(table) if !ty.contains(table.len()) => {
+                        Some(ExecuteError::InvalidImportedTable)
+                    }
+                    Some(table) => {
+                        imported_table_size = Some(table.len());
+                        None
+                    }
+                },
+                // The size of an aliased memory is only known at instantiation.
+                Importdesc::Mem(_) if resolver.alias_mem(module, name) => None,
+                Importdesc::Mem(ty) => match resolver.resolve_mem(module, name) {
+                    None => Some(ExecuteError::UnresolvedImport { index }),
+                    Some(mem) if !ty.contains(mem.len()) || mem.len() % PAGE_SIZE != 0 => {
+                        Some(ExecuteError::InvalidImportedMem)
+                    }
+                    Some(mem)
+                        if resolver.mem_max_pages(module, name).is_some_and(|max| {
+                            mem.len() > pages_to_bytes(max).unwrap_or(usize::MAX)
+                        }) =>
+                    {
+                        Some(ExecuteError::InvalidImportedMem)
+                    }
+                    Some(mem) => {
+                        imported_mem_size = Some(mem.len());
+                        None
+                    }
+                },
+                Importdesc::Global(ty) => {
+                    // Placeholders keep the indices of the following globals intact.
+                    let (v, problem) = match resolver.resolve_global(module, name) {
+                        None => (
+                            Val::zero(ty.valtype()),
+                            Some(ExecuteError::UnresolvedImport { index }),
+                        ),
+                        Some(v) if v.ty() != ty.valtype() => (
+                            Val::zero(ty.valtype()),
+                            Some(ExecuteError::InvalidImportedGlobal { index }),
+                        ),
+                        Some(v) => (v, None),
+                    };
+                    imported_globals.push(GlobalVal::new(ty.is_const(), v));
+                    problem
+                }
+            };
+            if let Some(problem) = problem {
+                problems.push(problem);
+            }
+        }
+        if !valid {
+            return problems;
+        }
+
+        for (index, global) in self.globals.iter().enumerate() {
+            if global.init(&imported_globals).is_none() {
+                problems.push(ExecuteError::InvalidGlobal { index });
+            }
+        }
+
+        let mem_size = imported_mem_size.or(self.memtype().map(|ty| ty.min_bytes()));
+        for (index, data) in self.datas.iter().enumerate() {
+            let DataMode::Active { offset, .. } = &data.mode else {
+                continue;
+            };
+            if !segment_fits(offset, data.init.len(), mem_size, &imported_globals) {
+                problems.push(ExecuteError::InvalidData { index });
+            }
+        }
+
+        let table_size = imported_table_size.or(self.tabletype().map(|ty| ty.limits.min as usize));
+        for (index, elem) in self.elems.iter().enumerate() {
+            let ElemMode::Active { offset, .. } = &elem.mode else {
+                continue;
+            };
+            if !segment_fits(offset, elem.init.len(), table_size, &imported_globals) {
+                problems.push(ExecuteError::InvalidElem { index });
+            }
+        }
+
+        problems
+    }
+
+    pub(crate) fn imported_funcs_len(&self) -> usize {
+        self.imports
+            .iter()
+            .filter(|i| matches!(i.desc, Importdesc::Func(_)))
+            .count()
+    }
+
+    // Type of the memory, whether it is defined in the module or imported.
+    pub(crate) fn memtype(&self) -> Option<Memtype> {
+        self.mem.or_else(|| {
+            self.imports.iter().find_map(|import| match import.desc {
+                Importdesc::Mem(ty) => Some(ty),
+                _ => None,
+            })
+        })
+    }
+
+    // Type of the table, whether it is defined in the module or imported.
+    pub(crate) fn tabletype(&self) -> Option<Tabletype> {
+        self.table.or_else(|| {
+            self.imports.iter().find_map(|import| match import.desc {
+                Importdesc::Table(ty) => Some(ty),
+                _ => None,
+            })
+        })
+    }
+
     pub fn types(&self) -> &[Functype<V>] {
         &self.types
     }
 
+    // Recursion groups of the type section (the subtypes are numbered in order by `Typeidx`).
+    #[cfg(feature = "gc")]
+    pub fn rectypes(&self) -> &[crate::gc::Rectype<V>] {
+        &self.rectypes
+    }
+
+    #[cfg(feature = "gc")]
+    pub fn subtype(&self, idx: Typeidx) -> Option<&crate::gc::Subtype<V>> {
+        self.rectypes
+            .iter()
+            .flat_map(|r| r.subtypes.iter())
+            .nth(idx.get())
+    }
+
     pub fn funcs(&self) -> &[Func<V>] {
         &self.funcs
     }
@@ -187,7 +514,7 @@ impl<V: VectorFactory> Module<V> {
         self.mem
     }
 
-    pub fn globals(&self) -> &[Global] {
+    pub fn globals(&self) -> &[Global<V>] {
         &self.globals
     }
 
@@ -199,6 +526,16 @@ impl<V: VectorFactory> Module<V> {
         &self.datas
     }
 
+    // SHA-256 digest of the bytes this module was decoded from, or `None` once the module has
+    // been modified (e.g., by `inject_gas()` or `optimize()`) as it no longer matches them.
+    pub fn digest(&self) -> Option<ModuleDigest> {
+        self.digest
+    }
+
+    pub fn data_count(&self) -> Option<u32> {
+        self.data_count
+    }
+
     pub fn start(&self) -> Option<Funcidx> {
         self.start
     }
@@ -210,22 +547,107 @@ impl<V: VectorFactory> Module<V> {
     pub fn exports(&self) -> &[Export<V>] {
         &self.exports
     }
+
+    pub fn custom_sections(&self) -> &[CustomSection<V>] {
+        &self.custom_sections
+    }
+
+    pub fn debug_sections(&self) -> impl '_ + Iterator<Item = &CustomSection<V>> {
+        self.custom_sections
+            .iter()
+            .filter(|s| s.name.as_str().starts_with(".debug_"))
+    }
+
+    pub fn source_mapping_url(&self) -> Option<&str> {
+        let section = self
+            .custom_sections
+            .iter()
+            .find(|s| s.name.as_str() == "sourceMappingURL")?;
+        let mut reader = Reader::new(&section.data);
+        let n = reader.read_usize().ok()?;
+        core::str::from_utf8(reader.read(n).ok()?).ok()
+    }
+
+    // Offset of the code section payload in the module binary (DWARF addresses are relative to it).
+    pub fn code_section_offset(&self) -> Option<usize> {
+        self.code_section_offset
+    }
+
+    // Maps an executor position (e.g., `TraceFrame`) to the byte offset of the instruction
+    // in the original module binary.
+    pub fn instr_position(
+        &self,
+        wasm_bytes: &[u8],
+        funcidx: Funcidx,
+        instr_offset: usize,
+    ) -> Option<usize> {
+        let func = self
+            .funcs
+            .get(funcidx.get().checked_sub(self.imported_funcs_len())?)?;
+        let code = wasm_bytes.get(func.code_offset()..func.code_offset() + func.code_size())?;
+        let position = Self::scan_instr_position(code, instr_offset).ok()??;
+        Some(func.code_offset() + position)
+    }
+
+    fn scan_instr_position(code: &[u8], instr_offset: usize) -> Result<Option<usize>, DecodeError> {
+        let mut reader = Reader::new(code);
+        let locals_len = reader.read_usize()?;
+        for _ in 0..locals_len {
+            let _ = reader.read_u32()?;
+            let _: Valtype = Decode::<V>::decode(&mut reader)?;
+        }
+
+        // Walk the instructions in the same (pre-order) order as the executor counts them.
+        let mut count = 0;
+        while !reader.is_empty() {
+            let position = reader.position();
+            match reader.read_u8()? {
+                0x05 | 0x0b => continue,
+                0x02..=0x04 => {
+                    let _: Blocktype = Decode::<V>::decode(&mut reader)?;
+                }
+                _ => {
+                    reader.unread_u8();
+                    let _: Instr<V> = Decode::<V>::decode(&mut reader)?;
+                }
+            }
+            if count == instr_offset {
+                return Ok(Some(position));
+            }
+            count += 1;
+        }
+        Ok(None)
+    }
+
+    pub fn func_name(&self, idx: Funcidx) -> Option<&str> {
+        self.func_names
+            .iter()
+            .find(|(i, _)| i.get() == idx.get())
+            .map(|(_, name)| name.as_str())
+    }
 }
 
 impl<V: VectorFactory> Debug for Module<V> {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("Module")
-            .field("types", &self.types.as_ref())
+        let mut s = f.debug_struct("Module");
+        s.field("types", &self.types.as_ref())
             .field("funcs", &self.funcs.as_ref())
             .field("table", &self.table)
             .field("mem", &self.mem)
             .field("globals", &self.globals.as_ref())
             .field("elems", &self.elems.as_ref())
             .field("datas", &self.datas.as_ref())
+            .field("data_count", &self.data_count)
             .field("start", &self.start)
             .field("imports", &self.imports.as_ref())
             .field("exports", &self.exports.as_ref())
-            .finish()
+            .field("custom_sections", &self.custom_sections.as_ref())
+            .field("code_section_offset", &self.code_section_offset)
+            .field("func_names", &self.func_names.as_ref())
+            .field("digest", &self.digest);
+        #[cfg(feature = "gc")]
+        s.field("rectypes", &self.rectypes.as_ref());
+        s.finish()
     }
 }
 
@@ -239,17 +661,169 @@ impl<V: VectorFactory> Clone for Module<V> {
             globals: V::clone_vector(&self.globals),
             elems: V::clone_vector(&self.elems),
             datas: V::clone_vector(&self.datas),
+            data_count: self.data_count,
             start: self.start,
             imports: V::clone_vector(&self.imports),
             exports: V::clone_vector(&self.exports),
+            custom_sections: V::clone_vector(&self.custom_sections),
+            code_section_offset: self.code_section_offset,
+            func_names: V::clone_vector(&self.func_names),
+            digest: self.digest,
+            #[cfg(feature = "gc")]
+            rectypes: V::clone_vector(&self.rectypes),
         }
     }
 }
 
-struct Magic;
+type CodeSectionDecoder<V> =
+    fn(&mut Reader) -> Result<<V as VectorFactory>::Vector<Code<V>>, DecodeError>;
+
+#[cfg(feature = "std")]
+impl Module<StdVectorFactory> {
+    // Same as `decode_with_config()`, but function bodies are decoded on multiple threads.
+    pub fn decode_parallel(wasm_bytes: &[u8], config: DecodeConfig) -> Result<Self, DecodeError> {
+        Self::decode_inner(wasm_bytes, config, decode_code_section_parallel)
+    }
+}
+
+// Code entries are independently framed by their sizes, so they can be split into
+// chunks after scanning the framing and decoded on scoped threads.
+#[cfg(feature = "std")]
+fn decode_code_section_parallel(
+    reader: &mut Reader,
+) -> Result<StdVector<Code<StdVectorFactory>>, DecodeError> {
+    const MIN_CHUNK_SIZE: usize = 64;
+
+    let len = reader.read_usize()?;
+    let mut entries = Vec::with_capacity(reader.capacity_hint(len));
+    for _ in 0..len {
+        let size = reader.read_usize()?;
+        let offset = reader.position();
+        entries.push((offset, reader.read(size)?));
+    }
+
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = entries.len().div_ceil(threads).max(MIN_CHUNK_SIZE);
+    decode_code_entries(&entries, reader.config(), chunk_size).map(StdVector::new)
+}
+
+#[cfg(feature = "std")]
+fn decode_code_entries(
+    entries: &[(usize, &[u8])],
+    config: DecodeConfig,
+    chunk_size: usize,
+) -> Result<Vec<Code<StdVectorFactory>>, DecodeError> {
+    let decode_chunk = move |chunk: &[(usize, &[u8])]| {
+        chunk
+            .iter()
+            .map(|&(offset, bytes)| {
+                let mut reader = Reader::with_config(bytes, config);
+                Code::decode_contents(&mut reader, offset, bytes.len())
+            })
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    if entries.len() <= chunk_size {
+        return decode_chunk(entries);
+    }
+
+    let chunks = std::thread::scope(|s| {
+        let handles = entries
+            .chunks(chunk_size)
+            .map(|chunk| s.spawn(move || decode_chunk(chunk)))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("code section decoder panicked"))
+            .collect::<Vec<_>>()
+    });
+    let mut codes = Vec::with_capacity(entries.len());
+    for chunk in chunks {
+        codes.extend(chunk?);
+    }
+    Ok(codes)
+}
+
+fn segment_fits<V: VectorFactory>(
+    offset: &ConstantExpr<V>,
+    len: usize,
+    size: Option<usize>,
+    globals: &[GlobalVal],
+) -> bool {
+    let Ok(Val::I32(offset)) = offset.evaluate(globals) else {
+        return false;
+    };
+    size.is_some_and(|size| {
+        (offset as u32 as usize)
+            .checked_add(len)
+            .is_some_and(|end| end <= size)
+    })
+}
+
+// Reads the header of the next section, checking that non-custom sections appear
+// at most once and in the prescribed order. Returns the section ID, the offset of
+// the section contents, and a reader over them.
+pub(crate) fn read_section<'a>(
+    reader: &mut Reader<'a>,
+    last_section_id: &mut Option<SectionId>,
+) -> Result<(SectionId, usize, Reader<'a>), DecodeError> {
+    let value = reader.read_u8()?;
+    let section_id = SectionId::from_u8(value).ok_or(DecodeError::InvalidSectionId { value })?;
+    let section_size = reader.read_u32()? as usize;
+    let section_offset = reader.position();
+    let section_reader = reader.sub_reader(section_size)?;
+
+    if section_id != SectionId::Custom {
+        if let Some(last_section_id) = *last_section_id {
+            if section_id == last_section_id {
+                return Err(DecodeError::DuplicateSection { section_id });
+            }
+            if section_id < last_section_id {
+                return Err(DecodeError::InvalidSectionOrder {
+                    current_section_id: section_id,
+                    last_section_id,
+                });
+            }
+        }
+        *last_section_id = Some(section_id);
+    }
+    Ok((section_id, section_offset, section_reader))
+}
+
+pub(crate) fn decode_table_section<V: VectorFactory>(
+    reader: &mut Reader,
+) -> Result<Option<Tabletype>, DecodeError> {
+    let value = reader.read_u32()? as usize;
+    if value > 1 {
+        return Err(DecodeError::UnsupportedFeature {
+            feature: Features::REFERENCE_TYPES,
+        });
+    }
+    if value == 0 {
+        return Ok(None);
+    }
+    Decode::<V>::decode(reader).map(Some)
+}
+
+pub(crate) fn decode_memory_section<V: VectorFactory>(
+    reader: &mut Reader,
+) -> Result<Option<Memtype>, DecodeError> {
+    let value = reader.read_u32()? as usize;
+    if value > 1 {
+        return Err(DecodeError::UnsupportedFeature {
+            feature: Features::MULTI_MEMORY,
+        });
+    }
+    if value == 0 {
+        return Ok(None);
+    }
+    Decode::<V>::decode(reader).map(Some)
+}
+
+pub(crate) struct Magic;
 
 impl Magic {
-    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+    pub(crate) fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
         let mut value = [0; 4];
         reader.read_exact(&mut value)?;
         if value != *b"\0asm" {
@@ -259,13 +833,17 @@ impl Magic {
     }
 }
 
-struct Version;
+pub(crate) struct Version;
 
 impl Version {
-    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+    pub(crate) fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
         let mut value = [0; 4];
         reader.read_exact(&mut value)?;
         if value != [1, 0, 0, 0] {
+            // The last two bytes are the layer, which is 1 for components.
+            if value[2..] == [1, 0] {
+                return Err(DecodeError::ComponentModelNotSupported);
+            }
             return Err(DecodeError::InvalidVersion { value });
         }
         Ok(Self)
@@ -275,7 +853,11 @@ impl Version {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::StdVectorFactory;
+    use crate::{
+        components::ConstantInstr,
+        test_util::{leb128_s, leb128_u},
+        StdVector, StdVectorFactory,
+    };
 
     fn decode(wasm: &[u8]) -> Module<StdVectorFactory> {
         Module::decode(wasm).expect("decode module")
@@ -302,5 +884,315 @@ mod tests {
         let module = decode(&input);
         assert_eq!(1, module.exports().len());
         assert_eq!("addTwo", module.exports()[0].name.as_str());
+
+        let func = &module.funcs()[0];
+        assert_eq!(37, func.code_offset());
+        assert_eq!(7, func.code_size());
+        assert_eq!([0, 32, 0, 32, 1, 106, 11], input[37..][..7]);
+    }
+
+    #[test]
+    fn decode_debug_sections() {
+        // The "addTwo" module followed by a ".debug_info" custom section.
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 7, 1, 96, 2, 127, 127, 1, 127, 3, 2, 1, 0, 7, 10, 1, 6,
+            97, 100, 100, 84, 119, 111, 0, 0, 10, 9, 1, 7, 0, 32, 0, 32, 1, 106, 11, 0, 15, 11, 46,
+            100, 101, 98, 117, 103, 95, 105, 110, 102, 111, 1, 2, 3,
+        ];
+        let module = decode(&input);
+        let sections = module.debug_sections().collect::<Vec<_>>();
+        assert_eq!(1, sections.len());
+        assert_eq!(".debug_info", sections[0].name.as_str());
+        assert_eq!(&[1, 2, 3], &sections[0].data[..]);
+
+        // `i32.add` is the third instruction of the function.
+        assert_eq!(Some(35), module.code_section_offset());
+        let position = module.instr_position(&input, Funcidx::new(0), 2);
+        assert_eq!(Some(42), position);
+        assert_eq!(106, input[42]);
+        assert_eq!(None, module.instr_position(&input, Funcidx::new(0), 3));
+    }
+
+    #[test]
+    fn check_instantiable_reports_all_problems() {
+        // (module
+        //   (import "env" "f" (func))
+        //   (import "env" "mem" (memory 1))
+        //   (import "env" "g" (global i32))
+        //   (data (i32.const 65535) "\01\02"))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 2, 29, 3, 3, 101, 110, 118, 1, 102, 0,
+            0, 3, 101, 110, 118, 3, 109, 101, 109, 2, 0, 1, 3, 101, 110, 118, 1, 103, 3, 127, 0,
+            11, 10, 1, 0, 65, 255, 255, 3, 11, 2, 1, 2,
+        ];
+
+        struct Resolver;
+        impl Resolve for Resolver {
+            type HostFunc = ();
+
+            fn resolve_global(&self, _module: &str, _name: &str) -> Option<Val> {
+                Some(Val::I64(0))
+            }
+        }
+
+        let module = decode(&input);
+        let problems = module.check_instantiable(&Resolver);
+        assert_eq!(4, problems.len());
+        assert!(matches!(
+            problems[0],
+            ExecuteError::UnresolvedImport { index: 0 }
+        ));
+        assert!(matches!(
+            problems[1],
+            ExecuteError::UnresolvedImport { index: 1 }
+        ));
+        assert!(matches!(
+            problems[2],
+            ExecuteError::InvalidImportedGlobal { index: 2 }
+        ));
+        assert!(matches!(
+            problems[3],
+            ExecuteError::InvalidData { index: 0 }
+        ));
+
+        let err = module.instantiate(Resolver).expect_err("error");
+        assert!(matches!(err, ExecuteError::UnresolvedImport { index: 0 }));
+
+        // The end of a segment may not be representable.
+        let offset = ConstantExpr::<StdVectorFactory> {
+            instrs: StdVector::new(vec![ConstantInstr::I32Const(-1)]),
+        };
+        assert!(segment_fits(&offset, 1, Some(usize::MAX), &[]));
+        assert!(!segment_fits(&offset, usize::MAX, Some(usize::MAX), &[]));
+    }
+
+    #[test]
+    fn instantiate_with_externs() {
+        // (module
+        //   (import "env" "f" (func $f0 (result i32)))
+        //   (import "env" "f" (func $f1 (result i32)))
+        //   (import "env" "mem" (memory 1))
+        //   (import "env" "g" (global $g i32))
+        //   (func (export "run") (result i32)
+        //     (i32.add (i32.add (call $f0) (call $f1))
+        //              (i32.add (global.get $g) (i32.load8_u (i32.const 0))))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 127, 2, 37, 4, 3, 101, 110, 118, 1,
+            102, 0, 0, 3, 101, 110, 118, 1, 102, 0, 0, 3, 101, 110, 118, 3, 109, 101, 109, 2, 0, 1,
+            3, 101, 110, 118, 1, 103, 3, 127, 0, 3, 2, 1, 0, 7, 7, 1, 3, 114, 117, 110, 0, 2, 10,
+            18, 1, 16, 0, 16, 0, 16, 1, 106, 35, 0, 65, 0, 45, 0, 0, 106, 106, 11,
+        ];
+
+        #[derive(Clone)]
+        struct Const(i32);
+        impl HostFunc for Const {
+            fn invoke(&mut self, _args: &[Val], _env: &mut crate::Env) -> Option<Val> {
+                Some(Val::I32(self.0))
+            }
+        }
+
+        // The imports with the same name are distinguished by their positions.
+        let mut mem = vec![0; PAGE_SIZE];
+        mem[0] = 100;
+        let externs = [
+            Extern::Func(Const(1)),
+            Extern::Func(Const(10)),
+            Extern::Memory(&mem),
+            Extern::Global(Val::I32(1000)),
+        ];
+        let mut instance = decode(&input)
+            .instantiate_with(&externs)
+            .expect("instantiate");
+        assert_eq!(
+            Some(Val::I32(1111)),
+            instance.invoke("run", &[]).expect("invoke")
+        );
+
+        let err = decode(&input)
+            .instantiate_with(&externs[..3])
+            .expect_err("error");
+        assert!(matches!(
+            err,
+            ExecuteError::ExternCountMismatch {
+                expected: 4,
+                actual: 3
+            }
+        ));
+
+        let mut externs = externs;
+        externs.swap(1, 2);
+        let err = decode(&input)
+            .instantiate_with(&externs)
+            .expect_err("error");
+        assert!(matches!(err, ExecuteError::ExternKindMismatch { index: 1 }));
+    }
+
+    #[test]
+    fn decode_huge_vector_length() {
+        // A type section claiming 0xFFFFFFFF entries in a five byte payload.
+        let input = [0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 255, 255, 255, 255, 15];
+        assert_eq!(
+            Err(DecodeError::UnexpectedEndOfBytes),
+            Module::<StdVectorFactory>::decode(&input).map(|_| ())
+        );
+
+        // Other fields exist depending on the features.
+        #[allow(clippy::needless_update)]
+        let config = DecodeConfig {
+            max_preallocation: 0,
+            ..Default::default()
+        };
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 7, 1, 96, 2, 127, 127, 1, 127, 3, 2, 1, 0, 7, 10, 1, 6,
+            97, 100, 100, 84, 119, 111, 0, 0, 10, 9, 1, 7, 0, 32, 0, 32, 1, 106, 11,
+        ];
+        let module =
+            Module::<StdVectorFactory>::decode_with_config(&input, config).expect("decode");
+        assert_eq!(1, module.exports().len());
+
+        // (module (func (local 3 i32)))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 2, 1, 0, 10, 6, 1, 4, 1, 3, 127, 11,
+        ];
+        let module =
+            Module::<StdVectorFactory>::decode_with_config(&input, config).expect("decode");
+        assert_eq!(3, module.funcs()[0].locals.len());
+    }
+
+    #[test]
+    fn decode_constant_expr() {
+        // (module (global i32 (i32.add (i32.const 40) (i32.const 2))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 6, 9, 1, 127, 0, 65, 40, 65, 2, 106, 11,
+        ];
+        let result = Module::<StdVectorFactory>::decode(&input);
+        if cfg!(feature = "extended_const") {
+            let module = result.expect("decode");
+            let v = module.globals()[0].init.evaluate(&[]).ok();
+            assert_eq!(Some(crate::Val::I32(42)), v);
+            module.instantiate(()).expect("instantiate");
+        } else {
+            assert_eq!(Err(DecodeError::UnexpectedExpr), result.map(|_| ()));
+        }
+    }
+
+    #[test]
+    fn decode_data_count_mismatch() {
+        // A data count section claiming one segment without a data section.
+        let input = [0, 97, 115, 109, 1, 0, 0, 0, 12, 1, 1];
+        assert_eq!(
+            Err(DecodeError::MismatchDataCountAndDataSectionSize {
+                data_count: 1,
+                data_section_size: 0
+            }),
+            Module::<StdVectorFactory>::decode(&input).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn decode_section_order() {
+        // Custom sections may appear between (and before) any other sections.
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 0, 2, 1, 97, 1, 1, 0, 0, 2, 1, 97, 5, 1, 0,
+        ];
+        let module = decode(&input);
+        assert_eq!(2, module.custom_sections().len());
+
+        let input = [0, 97, 115, 109, 1, 0, 0, 0, 1, 1, 0, 0, 2, 1, 97, 1, 1, 0];
+        assert_eq!(
+            Err(DecodeError::DuplicateSection {
+                section_id: SectionId::Type
+            }),
+            Module::<StdVectorFactory>::decode(&input).map(|_| ())
+        );
+
+        let input = [0, 97, 115, 109, 1, 0, 0, 0, 5, 1, 0, 1, 1, 0];
+        let error = Module::<StdVectorFactory>::decode(&input).expect_err("out of order");
+        assert_eq!(
+            "Invalid section order (type section after memory section)",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn decode_parallel() {
+        // A module with 300 functions of type `(func (param i32) (result i32))`,
+        // each of which returns `local.get 0` plus its index.
+        let n = 300;
+        let mut funcs = Vec::new();
+        let mut codes = Vec::new();
+        funcs.extend(leb128_u(n as u64, 0));
+        codes.extend(leb128_u(n as u64, 0));
+        for i in 0..n {
+            funcs.push(0);
+            let mut body = vec![0, 0x20, 0, 0x41];
+            body.extend(leb128_s(i as i64, 0));
+            body.extend([0x6a, 0x0b]);
+            codes.extend(leb128_u(body.len() as u64, 0));
+            codes.extend(body);
+        }
+        let mut input = vec![0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127];
+        let codes_len = codes.len();
+        for (id, section) in [(3, funcs), (10, codes)] {
+            input.push(id);
+            input.extend(leb128_u(section.len() as u64, 0));
+            input.extend(section);
+        }
+
+        let expected = decode(&input);
+        let module =
+            Module::decode_parallel(&input, DecodeConfig::default()).expect("decode module");
+        assert_eq!(format!("{expected:?}"), format!("{module:?}"));
+
+        // Force multiple threads regardless of the available parallelism.
+        let mut reader = Reader::new(&input[input.len() - codes_len..]);
+        let mut entries = Vec::new();
+        for _ in 0..reader.read_usize().expect("len") {
+            let size = reader.read_usize().expect("size");
+            let offset = reader.position();
+            entries.push((offset, reader.read(size).expect("code")));
+        }
+        let codes = decode_code_entries(&entries, DecodeConfig::default(), 7).expect("decode");
+        assert_eq!(n as usize, codes.len());
+        for (code, func) in codes.iter().zip(expected.funcs()) {
+            assert_eq!(format!("{:?}", func.body), format!("{:?}", code.body));
+        }
+    }
+
+    #[test]
+    fn decode_too_many_locals() {
+        // From binary.wast ("too many locals"):
+        //
+        // (module binary
+        //   "\00asm" "\01\00\00\00"
+        //   "\01\04\01\60\00\00"       ;; Type section
+        //   "\03\02\01\00"             ;; Function section
+        //   "\0a\0c\01"                ;; Code section
+        //   ;; function 0
+        //   "\0a\02"
+        //   "\ff\ff\ff\ff\0f\7f"       ;; 0xFFFFFFFF i32
+        //   "\02\7e"                   ;; 0x00000002 i64
+        //   "\0b"                      ;; end
+        // )
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 2, 1, 0, 10, 12, 1, 10, 2, 255, 255,
+            255, 255, 15, 127, 2, 126, 11,
+        ];
+        assert_eq!(
+            Err(DecodeError::TooManyLocals),
+            Module::<StdVectorFactory>::decode(&input).map(|_| ())
+        );
+
+        // (module (func (local 0xFFFFFFFF i32)))
+        //
+        // Valid according to the spec, but exceeds `DecodeConfig::max_locals`.
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 2, 1, 0, 10, 10, 1, 8, 1, 255, 255,
+            255, 255, 15, 127, 11,
+        ];
+        assert_eq!(
+            Err(DecodeError::TooManyLocals),
+            Module::<StdVectorFactory>::decode(&input).map(|_| ())
+        );
     }
 }
@@ -0,0 +1,447 @@
+use crate::{
+    components::{Exportdesc, Funcidx, Importdesc, Typeidx},
+    instructions::Instr,
+    vector::Vector,
+    Module, Val, VectorFactory,
+};
+use core::mem;
+
+impl<V: VectorFactory> Module<V> {
+    // Removes the functions that are not reachable from the exports, the start function and
+    // the element segments, and then the types no longer referenced.
+    // Imports are kept as-is (even if never called) since they are part of the module interface.
+    //
+    // Returns the number of removed functions.
+    // Out-of-range indices (only found in invalid modules) are left untouched.
+    pub fn eliminate_dead_code(&mut self) -> usize {
+        self.digest = None;
+        let imported_funcs = self.imported_funcs_len();
+        let mut live = V::create_vector(None);
+        live.extend_with(imported_funcs, true);
+        live.extend_with(self.funcs.len(), false);
+        let mut worklist = V::create_vector(None);
+        let roots = self
+            .exports
+            .iter()
+            .filter_map(|e| match e.desc {
+                Exportdesc::Func(idx) => Some(idx),
+                _ => None,
+            })
+            .chain(self.start)
+            .chain(
+                self.elems
+                    .iter()
+                    .flat_map(|e| e.init.iter().flatten().copied()),
+            );
+        for idx in roots {
+            mark(&mut live, &mut worklist, idx);
+        }
+        while let Some(idx) = worklist.pop() {
+            let Some(func) = idx.get().checked_sub(imported_funcs) else {
+                continue;
+            };
+            visit_instrs(self.funcs[func].body.instrs(), &mut |instr| {
+                if let Instr::Call(callee) = instr {
+                    mark(&mut live, &mut worklist, *callee);
+                }
+            });
+        }
+
+        let removed = self.funcs.len() - live[imported_funcs..].iter().filter(|x| **x).count();
+        if removed > 0 {
+            let funcidxs = compact_indices::<V>(&live);
+            for i in (0..self.funcs.len()).rev() {
+                if !live[imported_funcs + i] {
+                    self.funcs.remove_range(i..i + 1);
+                }
+            }
+            for i in (0..self.func_names.len()).rev() {
+                if live.get(self.func_names[i].0.get()) != Some(&true) {
+                    self.func_names.remove_range(i..i + 1);
+                }
+            }
+            let remap = |idx: &mut Funcidx| {
+                if let Some(&i) = funcidxs.get(idx.get()) {
+                    *idx = Funcidx::new(i);
+                }
+            };
+            for func in self.funcs.iter_mut() {
+                visit_instrs_mut(func.body.instrs_mut(), &mut |instr| {
+                    if let Instr::Call(idx) = instr {
+                        remap(idx);
+                    }
+                });
+            }
+            if let Some(start) = &mut self.start {
+                remap(start);
+            }
+            for export in self.exports.iter_mut() {
+                if let Exportdesc::Func(idx) = &mut export.desc {
+                    remap(idx);
+                }
+            }
+            for elem in self.elems.iter_mut() {
+                elem.init.iter_mut().flatten().for_each(remap);
+            }
+            for (idx, _) in self.func_names.iter_mut() {
+                remap(idx);
+            }
+        }
+
+        self.eliminate_unused_types();
+        removed
+    }
+
+    fn eliminate_unused_types(&mut self) {
+        let mut used = V::create_vector(None);
+        used.extend_with(self.types.len(), false);
+        for import in self.imports.iter() {
+            if let Importdesc::Func(ty) = import.desc {
+                mark_used(&mut used, ty);
+            }
+        }
+        for func in self.funcs.iter() {
+            mark_used(&mut used, func.ty);
+            visit_instrs(func.body.instrs(), &mut |instr| {
+                if let Instr::CallIndirect(ty) = instr {
+                    mark_used(&mut used, *ty);
+                }
+            });
+        }
+        if used.iter().all(|x| *x) {
+            return;
+        }
+
+        let typeidxs = compact_indices::<V>(&used);
+        for i in (0..self.types.len()).rev() {
+            if !used[i] {
+                self.types.remove_range(i..i + 1);
+            }
+        }
+        let remap = |ty: &mut Typeidx| {
+            if let Some(&i) = typeidxs.get(ty.get()) {
+                *ty = Typeidx::new(i);
+            }
+        };
+        for import in self.imports.iter_mut() {
+            if let Importdesc::Func(ty) = &mut import.desc {
+                remap(ty);
+            }
+        }
+        for func in self.funcs.iter_mut() {
+            remap(&mut func.ty);
+            visit_instrs_mut(func.body.instrs_mut(), &mut |instr| {
+                if let Instr::CallIndirect(ty) = instr {
+                    remap(ty);
+                }
+            });
+        }
+    }
+}
+
+impl<V: VectorFactory> Module<V> {
+    // Peephole optimizations over the function bodies that preserve the semantics
+    // (including traps):
+    //
+    // - Folds integer arithmetic and comparisons on constant operands (except those that may trap)
+    // - Removes `nop` and `<const> drop`
+    // - Rewrites `i32.const 0 i32.eq` (and the `i64` variant) into `i32.eqz`
+    //
+    // Returns the number of removed instructions. Note that the instruction offsets reported
+    // by the executor (e.g., in backtraces) no longer match the original binary afterwards.
+    pub fn optimize(&mut self) -> usize {
+        self.digest = None;
+        let mut removed = 0;
+        for func in self.funcs.iter_mut() {
+            removed += optimize_instrs::<V>(func.body.instrs_mut());
+        }
+        removed
+    }
+}
+
+fn optimize_instrs<V: VectorFactory>(instrs: &mut V::Vector<Instr<V>>) -> usize {
+    let len = instrs.len();
+    let mut removed = 0;
+
+    // `instrs[..n]` holds the already optimized instructions.
+    let mut n = 0;
+    for i in 0..len {
+        let mut instr = mem::replace(&mut instrs[i], Instr::Nop);
+        match &mut instr {
+            Instr::Block(block) => removed += optimize_instrs::<V>(&mut block.instrs),
+            Instr::Loop(block) => removed += optimize_instrs::<V>(&mut block.instrs),
+            Instr::If(block) => {
+                removed += optimize_instrs::<V>(&mut block.then_instrs);
+                removed += optimize_instrs::<V>(&mut block.else_instrs);
+            }
+            _ => {}
+        }
+        push_optimized(instrs, &mut n, instr);
+    }
+    instrs.truncate(n);
+    removed + len - n
+}
+
+fn push_optimized<V: VectorFactory>(instrs: &mut [Instr<V>], n: &mut usize, instr: Instr<V>) {
+    let prev = n.checked_sub(1).and_then(|i| constant(&instrs[i]));
+    let prev2 = n.checked_sub(2).and_then(|i| constant(&instrs[i]));
+    let (pops, replacement) = match (prev2, prev, &instr) {
+        (_, _, Instr::Nop) => return,
+        (_, Some(_), Instr::Drop) => (1, None),
+        (_, Some(Val::I32(0)), Instr::I32Eq) => (1, Some(Instr::I32Eqz)),
+        (_, Some(Val::I64(0)), Instr::I64Eq) => (1, Some(Instr::I64Eqz)),
+        (_, Some(v), _) if fold_unop(v, &instr).is_some() => (1, fold_unop(v, &instr)),
+        (Some(v0), Some(v1), _) => match fold_binop(v0, v1, &instr) {
+            Some(folded) => (2, Some(folded)),
+            None => (0, Some(instr)),
+        },
+        _ => (0, Some(instr)),
+    };
+    match (pops, replacement) {
+        (0, Some(instr)) => {
+            instrs[*n] = instr;
+            *n += 1;
+        }
+        (_, replacement) => {
+            *n -= pops;
+            if let Some(instr) = replacement {
+                push_optimized(instrs, n, instr);
+            }
+        }
+    }
+}
+
+fn constant<V: VectorFactory>(instr: &Instr<V>) -> Option<Val> {
+    match *instr {
+        Instr::I32Const(v) => Some(Val::I32(v)),
+        Instr::I64Const(v) => Some(Val::I64(v)),
+        _ => None,
+    }
+}
+
+fn fold_unop<V: VectorFactory>(v: Val, instr: &Instr<V>) -> Option<Instr<V>> {
+    let v = match (v, instr) {
+        (Val::I32(v), Instr::I32Eqz) => (v == 0) as i32,
+        (Val::I32(v), Instr::I32Clz) => v.leading_zeros() as i32,
+        (Val::I32(v), Instr::I32Ctz) => v.trailing_zeros() as i32,
+        (Val::I32(v), Instr::I32Popcnt) => v.count_ones() as i32,
+        (Val::I64(v), Instr::I64Eqz) => (v == 0) as i32,
+        (Val::I64(v), Instr::I32WrapI64) => v as i32,
+        (Val::I64(v), Instr::I64Clz) => return Some(Instr::I64Const(v.leading_zeros() as i64)),
+        (Val::I64(v), Instr::I64Ctz) => return Some(Instr::I64Const(v.trailing_zeros() as i64)),
+        (Val::I64(v), Instr::I64Popcnt) => return Some(Instr::I64Const(v.count_ones() as i64)),
+        (Val::I32(v), Instr::I64ExtendI32S) => return Some(Instr::I64Const(v as i64)),
+        (Val::I32(v), Instr::I64ExtendI32U) => return Some(Instr::I64Const(v as u32 as i64)),
+        _ => return None,
+    };
+    Some(Instr::I32Const(v))
+}
+
+fn fold_binop<V: VectorFactory>(v0: Val, v1: Val, instr: &Instr<V>) -> Option<Instr<V>> {
+    match (v0, v1) {
+        (Val::I32(a), Val::I32(b)) => {
+            let (ua, ub) = (a as u32, b as u32);
+            let v = match instr {
+                Instr::I32Eq => (a == b) as i32,
+                Instr::I32Ne => (a != b) as i32,
+                Instr::I32LtS => (a < b) as i32,
+                Instr::I32LtU => (ua < ub) as i32,
+                Instr::I32GtS => (a > b) as i32,
+                Instr::I32GtU => (ua > ub) as i32,
+                Instr::I32LeS => (a <= b) as i32,
+                Instr::I32LeU => (ua <= ub) as i32,
+                Instr::I32GeS => (a >= b) as i32,
+                Instr::I32GeU => (ua >= ub) as i32,
+                Instr::I32Add => a.wrapping_add(b),
+                Instr::I32Sub => a.wrapping_sub(b),
+                Instr::I32Mul => a.wrapping_mul(b),
+                Instr::I32And => a & b,
+                Instr::I32Or => a | b,
+                Instr::I32Xor => a ^ b,
+                Instr::I32Shl => a << (b & 31),
+                Instr::I32ShrS => a >> (b & 31),
+                Instr::I32ShrU => (ua >> (ub & 31)) as i32,
+                Instr::I32Rotl => a.rotate_left(ub),
+                Instr::I32Rotr => a.rotate_right(ub),
+                _ => return None,
+            };
+            Some(Instr::I32Const(v))
+        }
+        (Val::I64(a), Val::I64(b)) => {
+            let (ua, ub) = (a as u64, b as u64);
+            let cmp = match instr {
+                Instr::I64Eq => a == b,
+                Instr::I64Ne => a != b,
+                Instr::I64LtS => a < b,
+                Instr::I64LtU => ua < ub,
+                Instr::I64GtS => a > b,
+                Instr::I64GtU => ua > ub,
+                Instr::I64LeS => a <= b,
+                Instr::I64LeU => ua <= ub,
+                Instr::I64GeS => a >= b,
+                Instr::I64GeU => ua >= ub,
+                _ => {
+                    let v = match instr {
+                        Instr::I64Add => a.wrapping_add(b),
+                        Instr::I64Sub => a.wrapping_sub(b),
+                        Instr::I64Mul => a.wrapping_mul(b),
+                        Instr::I64And => a & b,
+                        Instr::I64Or => a | b,
+                        Instr::I64Xor => a ^ b,
+                        Instr::I64Shl => a << (b & 63),
+                        Instr::I64ShrS => a >> (b & 63),
+                        Instr::I64ShrU => (ua >> (ub & 63)) as i64,
+                        Instr::I64Rotl => a.rotate_left(ub as u32),
+                        Instr::I64Rotr => a.rotate_right(ub as u32),
+                        _ => return None,
+                    };
+                    return Some(Instr::I64Const(v));
+                }
+            };
+            Some(Instr::I32Const(cmp as i32))
+        }
+        _ => None,
+    }
+}
+
+fn mark<V: Vector<bool>, W: Vector<Funcidx>>(live: &mut V, worklist: &mut W, idx: Funcidx) {
+    if let Some(x) = live.get_mut(idx.get()) {
+        if !*x {
+            *x = true;
+            worklist.push(idx);
+        }
+    }
+}
+
+fn mark_used(used: &mut [bool], ty: Typeidx) {
+    if let Some(x) = used.get_mut(ty.get()) {
+        *x = true;
+    }
+}
+
+// Maps each kept index to its index after the removed ones are compacted away.
+fn compact_indices<V: VectorFactory>(kept: &[bool]) -> V::Vector<u32> {
+    let mut indices = V::create_vector(Some(kept.len()));
+    let mut next = 0;
+    for &kept in kept {
+        indices.push(next);
+        next += kept as u32;
+    }
+    indices
+}
+
+pub(crate) fn visit_instrs<V: VectorFactory>(instrs: &[Instr<V>], f: &mut impl FnMut(&Instr<V>)) {
+    for instr in instrs {
+        f(instr);
+        match instr {
+            Instr::Block(block) => visit_instrs(&block.instrs, f),
+            Instr::Loop(block) => visit_instrs(&block.instrs, f),
+            Instr::If(block) => {
+                visit_instrs(&block.then_instrs, f);
+                visit_instrs(&block.else_instrs, f);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn visit_instrs_mut<V: VectorFactory>(instrs: &mut [Instr<V>], f: &mut impl FnMut(&mut Instr<V>)) {
+    for instr in instrs {
+        f(instr);
+        match instr {
+            Instr::Block(block) => visit_instrs_mut(&mut block.instrs, f),
+            Instr::Loop(block) => visit_instrs_mut(&mut block.instrs, f),
+            Instr::If(block) => {
+                visit_instrs_mut(&mut block.then_instrs, f);
+                visit_instrs_mut(&mut block.else_instrs, f);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StdVectorFactory;
+
+    #[test]
+    fn optimize_test() {
+        // (module
+        //   (func (export "f") (param i32) (result i32)
+        //     nop
+        //     (i32.sub (i32.mul (i32.const 2) (i32.const 3)) (i32.const 1))
+        //     (drop (i32.const 9))
+        //     (i32.eq (local.get 0) (i32.const 0))
+        //     i32.add))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 3, 2, 1, 0, 7, 5, 1, 1, 102,
+            0, 0, 10, 22, 1, 20, 0, 1, 65, 2, 65, 3, 108, 65, 1, 107, 65, 9, 26, 32, 0, 65, 0, 70,
+            106, 11,
+        ];
+        let mut module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        assert_eq!(8, module.optimize());
+        assert_eq!(None, module.digest());
+        assert!(matches!(
+            module.funcs()[0].body.instrs(),
+            [
+                Instr::I32Const(5),
+                Instr::LocalGet(_),
+                Instr::I32Eqz,
+                Instr::I32Add
+            ]
+        ));
+
+        let mut instance = module.instantiate(()).expect("instantiate");
+        let result = instance.invoke("f", &[Val::I32(0)]).expect("invoke");
+        assert_eq!(Some(Val::I32(6)), result);
+    }
+
+    #[test]
+    fn eliminate_dead_code_test() {
+        // (module
+        //   (func (export "main") (result i32)
+        //     call $answer)
+        //   (func $unused (param i32) (result i32)
+        //     (call $unused (local.get 0)))
+        //   (func $answer (result i32)
+        //     i32.const 42))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 10, 2, 96, 0, 1, 127, 96, 1, 127, 1, 127, 3, 4, 3, 0,
+            1, 0, 7, 8, 1, 4, 109, 97, 105, 110, 0, 0, 10, 18, 3, 4, 0, 16, 2, 11, 6, 0, 32, 0, 16,
+            1, 11, 4, 0, 65, 42, 11,
+        ];
+        let mut module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        assert_eq!(1, module.eliminate_dead_code());
+        assert_eq!(2, module.funcs().len());
+        assert_eq!(1, module.types().len());
+        assert_eq!(0, module.eliminate_dead_code());
+
+        let mut instance = module.instantiate(()).expect("instantiate");
+        let result = instance.invoke("main", &[]).expect("invoke");
+        assert_eq!(Some(Val::I32(42)), result);
+
+        // Same as above but with an import that is never called.
+        //
+        // (module
+        //   (import "env" "unused" (func $imported (param i32) (result i32)))
+        //   ...)
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 10, 2, 96, 0, 1, 127, 96, 1, 127, 1, 127, 2, 14, 1, 3,
+            101, 110, 118, 6, 117, 110, 117, 115, 101, 100, 0, 1, 3, 4, 3, 0, 1, 0, 7, 8, 1, 4,
+            109, 97, 105, 110, 0, 1, 10, 18, 3, 4, 0, 16, 3, 11, 6, 0, 32, 0, 16, 2, 11, 4, 0, 65,
+            42, 11, 0, 16, 4, 110, 97, 109, 101, 1, 9, 1, 0, 6, 117, 110, 117, 115, 101, 100,
+        ];
+        let mut module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        assert_eq!(1, module.eliminate_dead_code());
+        assert_eq!(2, module.funcs().len());
+        assert_eq!(2, module.types().len());
+        assert_eq!(Some("unused"), module.func_name(Funcidx::new(0)));
+
+        let mut instance = module
+            .instantiate_with(&[crate::Extern::Func(())])
+            .expect("instantiate");
+        let result = instance.invoke("main", &[]).expect("invoke");
+        assert_eq!(Some(Val::I32(42)), result);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{
+    vector::Vector, ExecuteError, HostFunc, LinearMemory, ModuleInstance, StackBuffers,
+    StackStorage, VectorFactory, VectorMemory,
+};
+use core::fmt::{Debug, Formatter};
+
+// A set of pre-instantiated instances of a module, handed out one request at a time.
+//
+// Released instances are reset in place (the memory is zero-filled and the segments are
+// re-applied) instead of being instantiated again, so their memories and stacks are reused.
+pub struct InstancePool<
+    V: VectorFactory,
+    H,
+    M = VectorMemory<V>,
+    S: StackStorage = StackBuffers<'static>,
+> {
+    idle: V::Vector<ModuleInstance<V, H, M, S>>,
+}
+
+impl<V: VectorFactory, H: HostFunc, M: LinearMemory, S: StackStorage> InstancePool<V, H, M, S> {
+    // Creates `size` instances with `instantiate`
+    // (e.g. `|| module.clone().instantiate(resolver.clone())`).
+    pub fn new<F>(size: usize, mut instantiate: F) -> Result<Self, ExecuteError>
+    where
+        F: FnMut() -> Result<ModuleInstance<V, H, M, S>, ExecuteError>,
+    {
+        let mut idle = V::create_vector(Some(size));
+        for _ in 0..size {
+            idle.push(instantiate()?);
+        }
+        Ok(Self { idle })
+    }
+
+    // Returns `None` if all the instances are in use.
+    pub fn acquire(&mut self) -> Option<ModuleInstance<V, H, M, S>> {
+        self.idle.pop()
+    }
+
+    // Resets `instance` and returns it to the pool.
+    //
+    // If the reset fails (including a trap in the start function), the instance is dropped
+    // and the pool shrinks by one.
+    pub fn release(
+        &mut self,
+        mut instance: ModuleInstance<V, H, M, S>,
+    ) -> Result<(), ExecuteError> {
+        instance.reset(true)?;
+        self.idle.push(instance);
+        Ok(())
+    }
+
+    pub fn idle_len(&self) -> usize {
+        self.idle.len()
+    }
+}
+
+impl<V: VectorFactory, H, M: LinearMemory, S: StackStorage> Debug for InstancePool<V, H, M, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("InstancePool")
+            .field("idle", &self.idle.as_ref())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Module, StdVectorFactory, Val};
+
+    #[test]
+    fn reuse_pooled_instance() {
+        // (module
+        //   (memory 1)
+        //   (global $g (mut i32) (i32.const 0))
+        //   (func (export "bump") (result i32)
+        //     (i32.store8 (i32.const 0)
+        //       (i32.add (i32.load8_u (i32.const 0)) (i32.const 1)))
+        //     (drop (memory.grow (i32.const 1)))
+        //     (global.set $g (i32.add (global.get $g) (i32.const 1)))
+        //     (i32.add (global.get $g) (i32.load8_u (i32.const 0))))
+        //   (data (i32.const 0) "\0a"))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 127, 3, 2, 1, 0, 5, 3, 1, 0, 1, 6, 6,
+            1, 127, 1, 65, 0, 11, 7, 8, 1, 4, 98, 117, 109, 112, 0, 0, 10, 37, 1, 35, 0, 65, 0, 65,
+            0, 45, 0, 0, 65, 1, 106, 58, 0, 0, 65, 1, 64, 0, 26, 35, 0, 65, 1, 106, 36, 0, 35, 0,
+            65, 0, 45, 0, 0, 106, 11, 11, 7, 1, 0, 65, 0, 11, 1, 10,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let mut pool =
+            InstancePool::new(2, || module.clone().instantiate(())).expect("instantiate");
+        assert_eq!(2, pool.idle_len());
+
+        let mut instance = pool.acquire().expect("acquire");
+        assert_eq!(
+            Some(Val::I32(12)),
+            instance.invoke("bump", &[]).expect("invoke")
+        );
+        assert_eq!(
+            Some(Val::I32(14)),
+            instance.invoke("bump", &[]).expect("invoke")
+        );
+        assert_eq!(3 * crate::PAGE_SIZE, instance.mem().as_slice().len());
+        pool.release(instance).expect("release");
+
+        for _ in 0..2 {
+            let mut instance = pool.acquire().expect("acquire");
+            assert_eq!(
+                Some(Val::I32(12)),
+                instance.invoke("bump", &[]).expect("invoke")
+            );
+            assert_eq!(2 * crate::PAGE_SIZE, instance.mem().as_slice().len());
+            pool.release(instance).expect("release");
+        }
+    }
+}
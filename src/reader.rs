@@ -1,14 +1,43 @@
-use crate::DecodeError;
+use crate::{DecodeConfig, DecodeError};
 
+// Cursor over WebAssembly binary data.
+//
+// This is what the module decoder uses internally, and is also handy for parsing the payloads of
+// custom sections (e.g. `CustomSection::data`) with the same encoding rules.
 #[derive(Debug)]
 pub struct Reader<'a> {
     data: &'a [u8],
     position: usize,
+    config: DecodeConfig,
 }
 
 impl<'a> Reader<'a> {
     pub fn new(data: &'a [u8]) -> Self {
-        Reader { data, position: 0 }
+        Self::with_config(data, DecodeConfig::default())
+    }
+
+    pub fn with_config(data: &'a [u8], config: DecodeConfig) -> Self {
+        Reader {
+            data,
+            position: 0,
+            config,
+        }
+    }
+
+    pub fn config(&self) -> DecodeConfig {
+        self.config
+    }
+
+    // Splits off the next `n` bytes as a separate reader.
+    pub fn sub_reader(&mut self, n: usize) -> Result<Self, DecodeError> {
+        let data = self.read(n)?;
+        Ok(Self::with_config(data, self.config))
+    }
+
+    // Bounds a length declared in the input, which may be arbitrarily large, to a safe
+    // preallocation size. Vectors longer than this grow incrementally while being decoded.
+    pub fn capacity_hint(&self, len: usize) -> usize {
+        len.min(self.len()).min(self.config.max_preallocation)
     }
 
     pub fn len(&self) -> usize {
@@ -46,18 +75,40 @@ impl<'a> Reader<'a> {
 
     pub fn read(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
         let v = self
-            .data
-            .get(self.position..self.position + n)
+            .position
+            .checked_add(n)
+            .and_then(|end| self.data.get(self.position..end))
             .ok_or(DecodeError::UnexpectedEndOfBytes)?;
         self.position += n;
         Ok(v)
     }
 
+    // Reads a byte vector (a LEB128 length followed by the bytes).
+    pub fn read_bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        let n = self.read_usize()?;
+        self.read(n)
+    }
+
+    // Reads a UTF-8 string (encoded in the same way as `read_bytes()`).
+    pub fn read_name(&mut self) -> Result<&'a str, DecodeError> {
+        let bytes = self.read_bytes()?;
+        core::str::from_utf8(bytes).map_err(DecodeError::InvalidUtf8)
+    }
+
+    // Returns the unread bytes without consuming them.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.position..]
+    }
+
     pub fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), DecodeError> {
         buf.copy_from_slice(self.read(buf.len())?);
         Ok(())
     }
 
+    pub fn read_u64(&mut self) -> Result<u64, DecodeError> {
+        self.read_integer_u(64)
+    }
+
     pub fn read_i64(&mut self) -> Result<i64, DecodeError> {
         self.read_integer_s(64)
     }
@@ -71,21 +122,32 @@ impl<'a> Reader<'a> {
     }
 
     pub fn read_f32(&mut self) -> Result<f32, DecodeError> {
+        self.read_f32_bits().map(f32::from_bits)
+    }
+
+    pub fn read_f64(&mut self) -> Result<f64, DecodeError> {
+        self.read_f64_bits().map(f64::from_bits)
+    }
+
+    // Same as `read_f32()` but doesn't go through `f32`, which may quiet signaling NaNs on
+    // some platforms (e.g., x87).
+    pub fn read_f32_bits(&mut self) -> Result<u32, DecodeError> {
         let mut buf = [0u8; 4];
         self.read_exact(&mut buf)?;
-        Ok(f32::from_le_bytes(buf))
+        Ok(u32::from_le_bytes(buf))
     }
 
-    pub fn read_f64(&mut self) -> Result<f64, DecodeError> {
+    pub fn read_f64_bits(&mut self) -> Result<u64, DecodeError> {
         let mut buf = [0u8; 8];
         self.read_exact(&mut buf)?;
-        Ok(f64::from_le_bytes(buf))
+        Ok(u64::from_le_bytes(buf))
     }
 
     pub fn read_usize(&mut self) -> Result<usize, DecodeError> {
         self.read_u32().map(|v| v as usize)
     }
 
+    // Reads a LEB128 integer of `bits` (1..=64) bits.
     pub fn read_integer_u(&mut self, bits: usize) -> Result<u64, DecodeError> {
         let mut n = 0u64;
         let mut offset = 0;
@@ -111,6 +173,7 @@ impl<'a> Reader<'a> {
         Ok(n)
     }
 
+    // Reads a signed LEB128 integer of `bits` (1..=64) bits.
     pub fn read_integer_s(&mut self, bits: usize) -> Result<i64, DecodeError> {
         let mut n = 0i64;
         let mut offset = 0;
@@ -144,3 +207,151 @@ impl<'a> Reader<'a> {
         Ok(n)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{leb128_s, leb128_u};
+
+    const ITERATIONS: usize = 10000;
+
+    // xorshift64* (deterministic so that failures are reproducible).
+    struct Rng(u64);
+
+    impl Rng {
+        fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+
+        // Values of random bit widths, so that every encoded length is covered.
+        fn next_bits(&mut self, bits: u32) -> u64 {
+            let width = (self.next_u64() % (bits as u64 + 1)) as u32;
+            self.next_u64().checked_shr(64 - width).unwrap_or(0)
+        }
+    }
+
+    fn max_len(bits: u32) -> usize {
+        bits.div_ceil(7) as usize
+    }
+
+    fn assert_truncations_fail<T: core::fmt::Debug>(
+        bytes: &[u8],
+        f: impl Fn(&mut Reader) -> Result<T, DecodeError>,
+    ) {
+        for i in 0..bytes.len() {
+            let e = f(&mut Reader::new(&bytes[..i])).expect_err("truncated");
+            assert_eq!(DecodeError::UnexpectedEndOfBytes, e, "{:?}", &bytes[..i]);
+        }
+    }
+
+    #[test]
+    fn leb128_round_trip() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..ITERATIONS {
+            for bits in [32, 64] {
+                let n = rng.next_bits(bits);
+                let padded_len = 1 + rng.next_u64() as usize % max_len(bits);
+                let bytes = leb128_u(n, padded_len);
+                let mut reader = Reader::new(&bytes);
+                assert_eq!(Ok(n), reader.read_integer_u(bits as usize), "{bytes:?}");
+                assert!(reader.is_empty());
+                assert_truncations_fail(&bytes, |r| r.read_integer_u(bits as usize));
+
+                // Sign-extends the random bits.
+                let n = (n << (64 - bits)) as i64 >> (64 - bits);
+                let bytes = leb128_s(n, padded_len);
+                let mut reader = Reader::new(&bytes);
+                assert_eq!(Ok(n), reader.read_integer_s(bits as usize), "{bytes:?}");
+                assert!(reader.is_empty());
+                assert_truncations_fail(&bytes, |r| r.read_integer_s(bits as usize));
+            }
+        }
+    }
+
+    #[test]
+    fn leb128_malformed() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..ITERATIONS {
+            for bits in [32, 64] {
+                let n = rng.next_bits(bits);
+
+                // Too long.
+                let bytes = leb128_u(n, max_len(bits) + 1);
+                let e = Reader::new(&bytes).read_integer_u(bits as usize);
+                assert_eq!(Err(DecodeError::MalformedInteger), e, "{bytes:?}");
+                let bytes = leb128_s(n as i64 >> (64 - bits), max_len(bits) + 1);
+                let e = Reader::new(&bytes).read_integer_s(bits as usize);
+                assert_eq!(Err(DecodeError::MalformedInteger), e, "{bytes:?}");
+
+                // Unused bits of the last byte are set.
+                let mut bytes = leb128_u(n, max_len(bits));
+                *bytes.last_mut().expect("unreachable") |= 0x40;
+                let e = Reader::new(&bytes).read_integer_u(bits as usize);
+                assert_eq!(Err(DecodeError::MalformedInteger), e, "{bytes:?}");
+                let mut bytes = leb128_s(n as i64 >> (64 - bits), max_len(bits));
+                *bytes.last_mut().expect("unreachable") ^= 0x20;
+                let e = Reader::new(&bytes).read_integer_s(bits as usize);
+                assert_eq!(Err(DecodeError::MalformedInteger), e, "{bytes:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn float_round_trip() {
+        let mut rng = Rng(0x0123_4567_89ab_cdef);
+        for _ in 0..ITERATIONS {
+            // Random bit patterns include NaNs with arbitrary payloads, which must be preserved.
+            let n = rng.next_u64();
+            let bytes = (n as u32).to_le_bytes();
+            let v = Reader::new(&bytes).read_f32().expect("read_f32");
+            assert_eq!(n as u32, v.to_bits());
+            assert_truncations_fail(&bytes, |r| r.read_f32());
+
+            let bytes = n.to_le_bytes();
+            let v = Reader::new(&bytes).read_f64().expect("read_f64");
+            assert_eq!(n, v.to_bits());
+            assert_truncations_fail(&bytes, |r| r.read_f64());
+        }
+    }
+
+    #[test]
+    fn read_out_of_range() {
+        let mut reader = Reader::new(&[1, 2, 3]);
+        assert_eq!(Ok(1), reader.read_u8());
+        assert_eq!(
+            Err(DecodeError::UnexpectedEndOfBytes),
+            reader.read(usize::MAX)
+        );
+        assert!(reader.sub_reader(usize::MAX).is_err());
+        assert_eq!(Ok(&[2, 3][..]), reader.read(2));
+    }
+
+    #[test]
+    fn custom_section_payload() {
+        // A producers-like payload: one field, "language", with a single ("Rust", "1.79") pair.
+        let payload = [
+            1, 8, 108, 97, 110, 103, 117, 97, 103, 101, 1, 4, 82, 117, 115, 116, 4, 49, 46, 55, 57,
+            0xff,
+        ];
+        let mut reader = Reader::new(&payload);
+        assert_eq!(Ok(1), reader.read_u32());
+        assert_eq!(Ok("language"), reader.read_name());
+        assert_eq!(Ok(1), reader.read_u32());
+        assert_eq!(Ok(&b"Rust"[..]), reader.read_bytes());
+
+        let mut sub_reader = reader.sub_reader(5).expect("sub_reader");
+        assert_eq!(Ok("1.79"), sub_reader.read_name());
+        assert!(sub_reader.is_empty());
+        assert_eq!(&[0xff], reader.remaining());
+
+        // Invalid UTF-8.
+        let mut reader = Reader::new(&[1, 0xff]);
+        assert!(matches!(
+            reader.read_name(),
+            Err(DecodeError::InvalidUtf8(_))
+        ));
+    }
+}
@@ -0,0 +1,239 @@
+use crate::{
+    vector::Vector, ExecuteError, HostFunc, LinearMemory, ModuleInstance, StackBuffers,
+    StackStorage, StepResult, Val, VectorFactory, VectorMemory,
+};
+use core::fmt::{Debug, Formatter};
+
+// Runs invocations of several instances on a single thread by giving each of them a slice of
+// fuel (the number of instructions to execute) per turn in a round-robin fashion:
+//
+// ```ignore
+// let mut scheduler = Scheduler::new(1000);
+// let a = scheduler.spawn(instance_a, "main", &[])?;
+// let b = scheduler.spawn(instance_b, "main", &[])?;
+// while scheduler.tick() {
+//     // Service other work between the turns.
+// }
+// let result = scheduler.result(a);
+// let instance_a = scheduler.remove(a);
+// ```
+//
+// A task also ends its turn early when it hits a breakpoint.
+pub struct Scheduler<
+    V: VectorFactory,
+    H,
+    M = VectorMemory<V>,
+    S: StackStorage = StackBuffers<'static>,
+> {
+    tasks: V::Vector<Task<V, H, M, S>>,
+    next_task_id: usize,
+    fuel_per_slice: usize,
+}
+
+struct Task<V: VectorFactory, H, M, S: StackStorage> {
+    id: TaskId,
+    instance: ModuleInstance<V, H, M, S>,
+    result: Option<Result<Option<Val>, ExecuteError>>,
+}
+
+// Identifies a task within the scheduler that spawned it (the IDs of removed tasks are not reused).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TaskId(usize);
+
+impl TaskId {
+    // Tasks are numbered in the order they were spawned.
+    pub const fn get(self) -> usize {
+        self.0
+    }
+}
+
+impl<V: VectorFactory, H: HostFunc, M: LinearMemory, S: StackStorage> Scheduler<V, H, M, S> {
+    // `fuel_per_slice` is at least 1.
+    pub fn new(fuel_per_slice: usize) -> Self {
+        Self {
+            tasks: V::create_vector(None),
+            next_task_id: 0,
+            fuel_per_slice: fuel_per_slice.max(1),
+        }
+    }
+
+    // Starts an invocation of an exported function of `instance`, which proceeds by `tick()`.
+    pub fn spawn(
+        &mut self,
+        mut instance: ModuleInstance<V, H, M, S>,
+        function_name: &str,
+        args: &[Val],
+    ) -> Result<TaskId, ExecuteError> {
+        instance.start_invoke(function_name, args)?;
+        let id = TaskId(self.next_task_id);
+        self.next_task_id += 1;
+        self.tasks.push(Task {
+            id,
+            instance,
+            result: None,
+        });
+        Ok(id)
+    }
+
+    // Removes a task (whether finished or not) and returns its instance.
+    // The pending invocation of an unfinished task is discarded.
+    pub fn remove(&mut self, id: TaskId) -> Option<ModuleInstance<V, H, M, S>> {
+        let i = self.task_index(id)?;
+        self.tasks[i..].rotate_left(1);
+        let mut task = self.tasks.pop()?;
+        if task.result.is_none() {
+            task.instance.cancel_invoke();
+        }
+        Some(task.instance)
+    }
+
+    // Gives a slice of fuel to each of the pending tasks in turn.
+    // Returns `false` if all the tasks have finished.
+    //
+    // The tick ends early if a task is paused (see `ExecutorConfig::pause_requested`).
+    pub fn tick(&mut self) -> bool {
+        let mut pending = false;
+        for task in self.tasks.iter_mut().filter(|task| task.result.is_none()) {
+            match task.instance.run(self.fuel_per_slice) {
+                Ok(StepResult::Running | StepResult::Breakpoint(_)) => pending = true,
+                Ok(StepResult::Paused) => return true,
+                Ok(StepResult::Returned(v)) => task.result = Some(Ok(v)),
+                Err(e) => task.result = Some(Err(e)),
+            }
+        }
+        pending
+    }
+
+    pub fn run_to_completion(&mut self) {
+        while self.tick() {}
+    }
+
+    // Returns `None` while the task is pending or if there is no such task.
+    pub fn result(&self, id: TaskId) -> Option<Result<Option<Val>, ExecuteError>> {
+        self.task_index(id).and_then(|i| self.tasks[i].result)
+    }
+
+    pub fn pending_len(&self) -> usize {
+        self.tasks
+            .iter()
+            .filter(|task| task.result.is_none())
+            .count()
+    }
+
+    pub fn instance(&self, id: TaskId) -> Option<&ModuleInstance<V, H, M, S>> {
+        let i = self.task_index(id)?;
+        Some(&self.tasks[i].instance)
+    }
+
+    // Note that the pending invocation of the task must not be disturbed
+    // (e.g. by `start_invoke()` or `reset()`).
+    pub fn instance_mut(&mut self, id: TaskId) -> Option<&mut ModuleInstance<V, H, M, S>> {
+        let i = self.task_index(id)?;
+        Some(&mut self.tasks[i].instance)
+    }
+
+    // The tasks are kept in the order they were spawned (i.e., sorted by their IDs).
+    fn task_index(&self, id: TaskId) -> Option<usize> {
+        self.tasks
+            .binary_search_by_key(&id.0, |task| task.id.0)
+            .ok()
+    }
+}
+
+impl<V: VectorFactory, H, M: LinearMemory, S: StackStorage> Debug for Scheduler<V, H, M, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Scheduler")
+            .field("tasks", &self.tasks.as_ref())
+            .field("next_task_id", &self.next_task_id)
+            .field("fuel_per_slice", &self.fuel_per_slice)
+            .finish()
+    }
+}
+
+impl<V: VectorFactory, H, M: LinearMemory, S: StackStorage> Debug for Task<V, H, M, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Task")
+            .field("id", &self.id)
+            .field("instance", &self.instance)
+            .field("result", &self.result)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Module, StdVectorFactory};
+
+    #[test]
+    fn round_robin() {
+        // (module
+        //   (func (export "count") (param i32) (result i32) (local i32)
+        //     (loop
+        //       (local.set 1 (i32.add (local.get 1) (i32.const 1)))
+        //       (br_if 0 (i32.lt_u (local.get 1) (local.get 0))))
+        //     (local.get 1)))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 3, 2, 1, 0, 7, 9, 1, 5, 99,
+            111, 117, 110, 116, 0, 0, 10, 25, 1, 23, 1, 1, 127, 3, 64, 32, 1, 65, 1, 106, 33, 1,
+            32, 1, 32, 0, 73, 13, 0, 11, 32, 1, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let instantiate = || module.clone().instantiate(()).expect("instantiate");
+
+        let mut scheduler = Scheduler::new(50);
+        let long = scheduler
+            .spawn(instantiate(), "count", &[Val::I32(100)])
+            .expect("spawn");
+        let short = scheduler
+            .spawn(instantiate(), "count", &[Val::I32(3)])
+            .expect("spawn");
+        assert!(matches!(
+            scheduler.spawn(instantiate(), "foo", &[]),
+            Err(ExecuteError::NotExportedFunction)
+        ));
+
+        assert!(scheduler.tick());
+        assert!(scheduler.result(long).is_none());
+        assert!(matches!(
+            scheduler.result(short),
+            Some(Ok(Some(Val::I32(3))))
+        ));
+        assert_eq!(1, scheduler.pending_len());
+        assert_eq!(
+            Some(50),
+            scheduler.instance(long).map(|i| i.stats().instrs_executed)
+        );
+
+        scheduler.run_to_completion();
+        assert!(matches!(
+            scheduler.result(long),
+            Some(Ok(Some(Val::I32(100))))
+        ));
+        assert_eq!(0, scheduler.pending_len());
+
+        // Removed tasks (and tasks of other schedulers) are unknown.
+        assert!(scheduler.remove(short).is_some());
+        assert!(scheduler.remove(short).is_none());
+        assert!(scheduler.result(short).is_none());
+        assert!(scheduler.instance(short).is_none());
+        assert!(scheduler.instance_mut(TaskId(100)).is_none());
+        assert!(matches!(
+            scheduler.result(long),
+            Some(Ok(Some(Val::I32(100))))
+        ));
+
+        // A pending invocation is discarded, so the instance can be invoked again.
+        let pending = scheduler
+            .spawn(instantiate(), "count", &[Val::I32(100)])
+            .expect("spawn");
+        assert_eq!(TaskId(2), pending);
+        assert!(scheduler.tick());
+        let mut instance = scheduler.remove(pending).expect("remove");
+        assert_eq!(0, scheduler.pending_len());
+        assert!(matches!(
+            instance.invoke("count", &[Val::I32(5)]),
+            Ok(Some(Val::I32(5)))
+        ));
+    }
+}
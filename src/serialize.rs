@@ -0,0 +1,108 @@
+// `serde` implementations for the types that are generic over `VectorFactory`
+// (the other types derive them directly).
+//
+// These are serialize-only as the components are meant to be obtained by decoding a module.
+use crate::{
+    components::{Export, Functype, Import, Name},
+    ModuleMetadata, VectorFactory,
+};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+impl<V: VectorFactory> Serialize for Name<V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<V: VectorFactory> Serialize for Import<V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Import", 3)?;
+        s.serialize_field("module", &self.module)?;
+        s.serialize_field("name", &self.name)?;
+        s.serialize_field("desc", &self.desc)?;
+        s.end()
+    }
+}
+
+impl<V: VectorFactory> Serialize for Export<V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Export", 2)?;
+        s.serialize_field("name", &self.name)?;
+        s.serialize_field("desc", &self.desc)?;
+        s.end()
+    }
+}
+
+impl<V: VectorFactory> Serialize for Functype<V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Functype", 2)?;
+        s.serialize_field("params", &self.params[..])?;
+        s.serialize_field("result", &self.result)?;
+        s.end()
+    }
+}
+
+// Global initializers are not part of the interface, so only their types are serialized.
+impl<V: VectorFactory> Serialize for ModuleMetadata<V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Globaltypes<'a, V: VectorFactory>(&'a ModuleMetadata<V>);
+
+        impl<V: VectorFactory> Serialize for Globaltypes<'_, V> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq(self.0.globals().iter().map(|g| g.ty))
+            }
+        }
+
+        let mut s = serializer.serialize_struct("ModuleMetadata", 9)?;
+        s.serialize_field("types", self.types())?;
+        s.serialize_field("imports", self.imports())?;
+        s.serialize_field("funcs", self.funcs())?;
+        s.serialize_field("table", &self.table())?;
+        s.serialize_field("mem", &self.mem())?;
+        s.serialize_field("globals", &Globaltypes(self))?;
+        s.serialize_field("exports", self.exports())?;
+        s.serialize_field("start", &self.start())?;
+        s.serialize_field("digest", &self.digest())?;
+        s.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{components::Valtype, StdVectorFactory, Val};
+
+    #[test]
+    fn serialize_metadata() {
+        // (module
+        //   (func (export "addTwo") (param i32 i32) (result i32)
+        //     local.get 0
+        //     local.get 1
+        //     i32.add))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 7, 1, 96, 2, 127, 127, 1, 127, 3, 2, 1, 0, 7, 10, 1, 6,
+            97, 100, 100, 84, 119, 111, 0, 0, 10, 9, 1, 7, 0, 32, 0, 32, 1, 106, 11,
+        ];
+        let metadata = ModuleMetadata::<StdVectorFactory>::decode(&input).expect("decode");
+        let json = serde_json::to_value(&metadata).expect("serialize");
+        assert_eq!(
+            serde_json::json!([{"params": ["I32", "I32"], "result": "I32"}]),
+            json["types"]
+        );
+        assert_eq!(
+            serde_json::json!([{"name": "addTwo", "desc": {"Func": 0}}]),
+            json["exports"]
+        );
+    }
+
+    #[test]
+    fn val_round_trip() {
+        let vals = [Val::I32(-1), Val::I64(7), Val::F32(0.5), Val::F64(3.5)];
+        let json = serde_json::to_string(&vals).expect("serialize");
+        let decoded: Vec<Val> = serde_json::from_str(&json).expect("deserialize");
+        assert_eq!(&vals[..], decoded);
+
+        let ty: Valtype = serde_json::from_str("\"F64\"").expect("deserialize");
+        assert_eq!(Valtype::F64, ty);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{decode::Decode, reader::Reader, DecodeError, VectorFactory};
+use core::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone, Copy)]
 pub enum SignExtensionInstr {
@@ -22,3 +23,15 @@ impl<V: VectorFactory> Decode<V> for SignExtensionInstr {
         }
     }
 }
+
+impl Display for SignExtensionInstr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::I32Extend8S => f.write_str("i32.extend8_s"),
+            Self::I32Extend16S => f.write_str("i32.extend16_s"),
+            Self::I64Extend8S => f.write_str("i64.extend8_s"),
+            Self::I64Extend16S => f.write_str("i64.extend16_s"),
+            Self::I64Extend32S => f.write_str("i64.extend32_s"),
+        }
+    }
+}
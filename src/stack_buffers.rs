@@ -0,0 +1,197 @@
+use crate::{
+    execute::{Frame, Label},
+    vector::Vector,
+    Val, VectorFactory,
+};
+use core::ops::{Bound, Deref, DerefMut, RangeBounds};
+
+// Caller-provided storage of the stacks of an instance (see
+// `ModuleInstance::with_stack_buffers()`), e.g. arrays local to `main()` on targets without a heap,
+// so that the stacks never grow beyond them. `Frame::EMPTY` and `Label::EMPTY` are the initial
+// values of the items of `frames` and `labels`.
+//
+// `Module::stack_usage()` tells the sizes needed by a call of a function
+// (`StackUsage::values` bounds both `values` and `locals`).
+#[derive(Debug)]
+pub struct StackBuffers<'a> {
+    pub values: &'a mut [Val],
+    pub locals: &'a mut [Val],
+    pub frames: &'a mut [Frame],
+    pub labels: &'a mut [Label],
+}
+
+// Storage of the fixed stacks of an instance, which is what the last type parameter of
+// `ModuleInstance` stands for, so that `StackBuffers<'a>` ties the instance to `'a`.
+pub trait StackStorage {
+    type Buf<T: 'static>: DerefMut<Target = [T]>;
+}
+
+impl<'a> StackStorage for StackBuffers<'a> {
+    type Buf<T: 'static> = &'a mut [T];
+}
+
+// A stack of the executor, which is either a vector created by the `VectorFactory` or
+// a caller-provided buffer.
+pub(crate) enum Stack<V: VectorFactory, S: StackStorage, T: 'static> {
+    Growable(V::Vector<T>),
+    Fixed { buf: S::Buf<T>, len: usize },
+}
+
+impl<V: VectorFactory, S: StackStorage, T> Stack<V, S, T> {
+    pub fn new() -> Self {
+        Self::Growable(V::create_vector(None))
+    }
+
+    pub fn fixed(buf: S::Buf<T>) -> Self {
+        Self::Fixed { buf, len: 0 }
+    }
+
+    // Returns `false` if pushing `additional` more items would overflow the fixed buffer.
+    pub fn has_room(&self, additional: usize) -> bool {
+        match self {
+            Self::Growable(_) => true,
+            Self::Fixed { buf, len } => additional <= buf.len() - len,
+        }
+    }
+}
+
+impl<V: VectorFactory, S: StackStorage, T> Deref for Stack<V, S, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Growable(items) => items,
+            Self::Fixed { buf, len } => &buf[..*len],
+        }
+    }
+}
+
+impl<V: VectorFactory, S: StackStorage, T> DerefMut for Stack<V, S, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Self::Growable(items) => items,
+            Self::Fixed { buf, len } => &mut buf[..*len],
+        }
+    }
+}
+
+impl<V: VectorFactory, S: StackStorage, T: Copy> Vector<T> for Stack<V, S, T> {
+    // The executor checks `has_room()` beforehand, so a full buffer is a bug.
+    fn push(&mut self, item: T) {
+        match self {
+            Self::Growable(items) => items.push(item),
+            Self::Fixed { buf, len } => {
+                buf[*len] = item;
+                *len += 1;
+            }
+        }
+    }
+
+    // The popped item remains in the fixed buffer (until it is overwritten).
+    fn pop(&mut self) -> Option<T> {
+        match self {
+            Self::Growable(items) => items.pop(),
+            Self::Fixed { buf, len } => {
+                *len = len.checked_sub(1)?;
+                Some(buf[*len])
+            }
+        }
+    }
+
+    fn truncate(&mut self, new_len: usize) {
+        match self {
+            Self::Growable(items) => items.truncate(new_len),
+            Self::Fixed { len, .. } => *len = new_len.min(*len),
+        }
+    }
+
+    fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        match self {
+            Self::Growable(items) => items.remove_range(range),
+            Self::Fixed { buf, len } => {
+                let start = match range.start_bound() {
+                    Bound::Included(&i) => i,
+                    Bound::Excluded(&i) => i + 1,
+                    Bound::Unbounded => 0,
+                };
+                let end = match range.end_bound() {
+                    Bound::Included(&i) => i + 1,
+                    Bound::Excluded(&i) => i,
+                    Bound::Unbounded => *len,
+                };
+                assert!(start <= end && end <= *len);
+                buf[start..*len].rotate_left(end - start);
+                *len -= end - start;
+            }
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        match self {
+            Self::Growable(items) => items.capacity(),
+            Self::Fixed { buf, .. } => buf.len(),
+        }
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        if let Self::Growable(items) = self {
+            items.reserve(additional);
+        }
+    }
+
+    fn try_reserve(&mut self, additional: usize) -> bool {
+        match self {
+            Self::Growable(items) => items.try_reserve(additional),
+            Self::Fixed { buf, len } => additional <= buf.len() - *len,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExecuteError, Module, StdVectorFactory, TrapKind};
+
+    #[test]
+    fn fixed_stacks() {
+        // (module
+        //   (func $f (export "f") (param i32) (result i32)
+        //     (if (result i32) (local.get 0)
+        //       (then (i32.add (call $f (i32.sub (local.get 0) (i32.const 1))) (i32.const 1)))
+        //       (else (i32.const 0)))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 6, 1, 96, 1, 127, 1, 127, 3, 2, 1, 0, 7, 5, 1, 1, 102,
+            0, 0, 10, 22, 1, 20, 0, 32, 0, 4, 127, 32, 0, 65, 1, 107, 16, 0, 65, 1, 106, 5, 65, 0,
+            11, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let instance = module.instantiate(()).expect("instantiate");
+
+        let mut values = [Val::I32(0); 32];
+        let mut locals = [Val::I32(0); 8];
+        let mut frames = [Frame::EMPTY; 8];
+        let mut labels = [Label::EMPTY; 8];
+        let mut instance = instance
+            .with_stack_buffers(StackBuffers {
+                values: &mut values,
+                locals: &mut locals,
+                frames: &mut frames,
+                labels: &mut labels,
+            })
+            .expect("with_stack_buffers");
+
+        assert!(matches!(
+            instance.invoke("f", &[Val::I32(7)]),
+            Ok(Some(Val::I32(7)))
+        ));
+        assert!(matches!(
+            instance.invoke("f", &[Val::I32(8)]),
+            Err(ExecuteError::Trap(TrapKind::StackExhausted))
+        ));
+        assert!(matches!(
+            instance.invoke("f", &[Val::I32(3)]),
+            Ok(Some(Val::I32(3)))
+        ));
+        assert_eq!(8, instance.stats().max_call_depth);
+    }
+}
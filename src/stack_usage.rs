@@ -0,0 +1,249 @@
+use crate::{
+    components::{Exportdesc, Funcidx},
+    validate::{FrameSize, Validator},
+    vector::Vector,
+    CallGraph, Module, ValidateError, VectorFactory,
+};
+use core::fmt::{Debug, Formatter};
+
+// Upper bounds of the runtime resources used by a call of a function (including the functions
+// called from it), which can be used to size the vectors of the executor ahead of time.
+//
+// - `values`: Values held by the operand stack and the locals of the active frames
+//   (as limited by `ExecutorConfig::max_value_stack`)
+// - `labels`: Blocks entered by the active frames (as limited by `ExecutorConfig::max_labels`)
+// - `call_depth`: Active frames, host functions don't have frames
+//   (as limited by `ExecutorConfig::max_call_depth`)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StackUsage {
+    pub values: usize,
+    pub labels: usize,
+    pub call_depth: usize,
+
+    // The function may call itself (directly or indirectly), so the bounds only hold as long as
+    // every function in the cycles is active at most once.
+    pub recursive: bool,
+}
+
+impl<V: VectorFactory> Module<V> {
+    // Estimates the stack usage of every function.
+    // The candidates of `call_indirect` are those of `Module::call_graph()`.
+    pub fn stack_usage(&self) -> Result<StackUsages<'_, V>, ValidateError> {
+        let frame_sizes = Validator::new(self).validate_with_frame_sizes()?;
+        let graph = self.call_graph();
+        let funcs_len = self.imported_funcs_len() + self.funcs.len();
+
+        let mut sccs = Sccs::<V> {
+            graph: &graph,
+            next_index: 0,
+            indices: V::create_vector(None),
+            lowlinks: V::create_vector(None),
+            stack: V::create_vector(None),
+            on_stack: V::create_vector(None),
+            scc_ids: V::create_vector(None),
+            next_scc_id: 0,
+            usages: V::create_vector(None),
+        };
+        sccs.indices.extend_with(funcs_len, usize::MAX);
+        sccs.lowlinks.extend_with(funcs_len, 0);
+        sccs.on_stack.extend_with(funcs_len, false);
+        sccs.scc_ids.extend_with(funcs_len, usize::MAX);
+        sccs.usages.extend_with(funcs_len, StackUsage::default());
+        for i in 0..funcs_len {
+            if sccs.indices[i] == usize::MAX {
+                sccs.visit(self, &frame_sizes, i);
+            }
+        }
+
+        Ok(StackUsages {
+            module: self,
+            usages: sccs.usages,
+        })
+    }
+
+    // Usage of a function excluding its callees.
+    fn own_stack_usage(&self, frame_sizes: &[FrameSize], idx: usize) -> StackUsage {
+        let imported_funcs = self.imported_funcs_len();
+        let ty = self
+            .func_typeidx(Funcidx::new(idx as u32))
+            .and_then(|ty| self.types.get(ty.get()))
+            .expect("unreachable");
+        let Some(i) = idx.checked_sub(imported_funcs) else {
+            // The arguments of a host function are moved to the locals and replaced with the result.
+            return StackUsage {
+                values: ty.params.len().max(ty.result.len()),
+                ..Default::default()
+            };
+        };
+        StackUsage {
+            values: ty.params.len() + self.funcs[i].locals.len() + frame_sizes[i].operands,
+            labels: frame_sizes[i].labels,
+            call_depth: 1,
+            recursive: false,
+        }
+    }
+}
+
+// Usage of each function of a module.
+pub struct StackUsages<'a, V: VectorFactory> {
+    module: &'a Module<V>,
+    usages: V::Vector<StackUsage>,
+}
+
+impl<'a, V: VectorFactory> StackUsages<'a, V> {
+    pub fn func(&self, idx: Funcidx) -> Option<StackUsage> {
+        self.usages.get(idx.get()).copied()
+    }
+
+    pub fn exports(&self) -> impl '_ + Iterator<Item = (&'a str, StackUsage)> {
+        self.module
+            .exports()
+            .iter()
+            .filter_map(|export| match export.desc {
+                Exportdesc::Func(idx) => Some((export.name.as_str(), self.func(idx)?)),
+                _ => None,
+            })
+    }
+}
+
+impl<V: VectorFactory> Debug for StackUsages<'_, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StackUsages")
+            .field("usages", &self.usages.as_ref())
+            .finish_non_exhaustive()
+    }
+}
+
+// Tarjan's algorithm, which finds the strongly connected components (i.e., the cycles) of the
+// call graph in the reverse topological order so that the callees are estimated first.
+// The functions in a component are assumed to be active at the same time.
+struct Sccs<'a, V: VectorFactory> {
+    graph: &'a CallGraph<V>,
+    next_index: usize,
+    indices: V::Vector<usize>,
+    lowlinks: V::Vector<usize>,
+    stack: V::Vector<usize>,
+    on_stack: V::Vector<bool>,
+    scc_ids: V::Vector<usize>,
+    next_scc_id: usize,
+    usages: V::Vector<StackUsage>,
+}
+
+impl<V: VectorFactory> Sccs<'_, V> {
+    fn visit(&mut self, module: &Module<V>, frame_sizes: &[FrameSize], i: usize) {
+        self.indices[i] = self.next_index;
+        self.lowlinks[i] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(i);
+        self.on_stack[i] = true;
+
+        for edge in self.graph.callees(Funcidx::new(i as u32)) {
+            let j = edge.callee.get();
+            if self.indices[j] == usize::MAX {
+                self.visit(module, frame_sizes, j);
+                self.lowlinks[i] = self.lowlinks[i].min(self.lowlinks[j]);
+            } else if self.on_stack[j] {
+                self.lowlinks[i] = self.lowlinks[i].min(self.indices[j]);
+            }
+        }
+        if self.lowlinks[i] != self.indices[i] {
+            return;
+        }
+
+        let scc_id = self.next_scc_id;
+        self.next_scc_id += 1;
+        let start = self
+            .stack
+            .iter()
+            .rposition(|&j| j == i)
+            .expect("unreachable");
+        for &j in &self.stack[start..] {
+            self.on_stack[j] = false;
+            self.scc_ids[j] = scc_id;
+        }
+
+        let mut total = StackUsage::default();
+        let mut callees = StackUsage::default();
+        for &j in &self.stack[start..] {
+            let own = module.own_stack_usage(frame_sizes, j);
+            total.values += own.values;
+            total.labels += own.labels;
+            total.call_depth += own.call_depth;
+            for edge in self.graph.callees(Funcidx::new(j as u32)) {
+                let k = edge.callee.get();
+                if self.scc_ids[k] == scc_id {
+                    total.recursive = true;
+                    continue;
+                }
+                let callee = self.usages[k];
+                callees.values = callees.values.max(callee.values);
+                callees.labels = callees.labels.max(callee.labels);
+                callees.call_depth = callees.call_depth.max(callee.call_depth);
+                callees.recursive |= callee.recursive;
+            }
+        }
+        let usage = StackUsage {
+            values: total.values + callees.values,
+            labels: total.labels + callees.labels,
+            call_depth: total.call_depth + callees.call_depth,
+            recursive: total.recursive || callees.recursive,
+        };
+        for &j in &self.stack[start..] {
+            self.usages[j] = usage;
+        }
+        self.stack.truncate(start);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StdVectorFactory, Val};
+
+    #[test]
+    fn stack_usage() {
+        // (module
+        //   (func $leaf (param i32) (result i32) (local i64)
+        //     (i32.add (local.get 0) (i32.const 1)))
+        //   (func (export "main") (result i32)
+        //     (i32.add (block (result i32) (call $leaf (i32.const 1))) (i32.const 2)))
+        //   (func $fact (export "fact") (param i32) (result i32)
+        //     (if (result i32) (i32.eqz (local.get 0))
+        //       (then (i32.const 1))
+        //       (else (i32.mul (local.get 0)
+        //                      (call $fact (i32.sub (local.get 0) (i32.const 1))))))))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 10, 2, 96, 1, 127, 1, 127, 96, 0, 1, 127, 3, 4, 3, 0,
+            1, 0, 7, 15, 2, 4, 109, 97, 105, 110, 0, 1, 4, 102, 97, 99, 116, 0, 2, 10, 46, 3, 9, 1,
+            1, 126, 32, 0, 65, 1, 106, 11, 12, 0, 2, 127, 65, 1, 16, 0, 11, 65, 2, 106, 11, 21, 0,
+            32, 0, 69, 4, 127, 65, 1, 5, 32, 0, 32, 0, 65, 1, 107, 16, 2, 108, 11, 11,
+        ];
+        let module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+        let usages = module.stack_usage().expect("stack_usage");
+        let main = StackUsage {
+            values: 6,
+            labels: 1,
+            call_depth: 2,
+            recursive: false,
+        };
+        let fact = StackUsage {
+            values: 4,
+            labels: 1,
+            call_depth: 1,
+            recursive: true,
+        };
+        assert_eq!(
+            usages.exports().collect::<Vec<_>>(),
+            [("main", main), ("fact", fact)]
+        );
+
+        let mut instance = module.clone().instantiate(()).expect("instantiate");
+        assert_eq!(
+            Some(Val::I32(4)),
+            instance.invoke("main", &[]).expect("invoke")
+        );
+        let stats = instance.stats();
+        assert!(stats.max_value_stack <= main.values);
+        assert_eq!(main.call_depth, stats.max_call_depth);
+    }
+}
@@ -0,0 +1,42 @@
+// Helpers shared by the unit tests and the integration tests (re-exported with the
+// `differential` feature).
+
+// Encodes `n` as unsigned LEB128, with trailing padding bytes up to `len` bytes
+// (if it's longer than the minimum).
+pub fn leb128_u(mut n: u64, len: usize) -> Vec<u8> {
+    let mut buf = Vec::new();
+    loop {
+        let b = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            buf.push(b);
+            break;
+        }
+        buf.push(b | 0x80);
+    }
+    while buf.len() < len {
+        *buf.last_mut().expect("unreachable") |= 0x80;
+        buf.push(0);
+    }
+    buf
+}
+
+// Signed variant of `leb128_u()`.
+pub fn leb128_s(mut n: i64, len: usize) -> Vec<u8> {
+    let mut buf = Vec::new();
+    loop {
+        let b = (n & 0x7f) as u8;
+        n >>= 7;
+        if (n == 0 && b & 0x40 == 0) || (n == -1 && b & 0x40 != 0) {
+            buf.push(b);
+            break;
+        }
+        buf.push(b | 0x80);
+    }
+    let pad = if n < 0 { 0x7f } else { 0 };
+    while buf.len() < len {
+        *buf.last_mut().expect("unreachable") |= 0x80;
+        buf.push(pad);
+    }
+    buf
+}
@@ -0,0 +1,137 @@
+use crate::{
+    components::{CustomSection, Export, Exportdesc, Funcidx, Name},
+    vector::Vector,
+    Module, VectorFactory,
+};
+use core::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransformError {
+    UnknownExport,
+    DuplicateExportName,
+    InvalidFuncidx { value: usize },
+}
+
+impl Display for TransformError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnknownExport => write!(f, "Unknown export"),
+            Self::DuplicateExportName => write!(f, "Duplicate export name"),
+            Self::InvalidFuncidx { value } => write!(f, "Invalid function index {value:?}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TransformError {}
+
+// Mutators for lightweight post-processing of decoded modules.
+// Each of them keeps a valid module valid (and clears `digest()` once the module is modified).
+impl<V: VectorFactory> Module<V> {
+    // Removes all custom sections (including the name and debug sections).
+    pub fn strip_custom_sections(&mut self) {
+        self.digest = None;
+        self.custom_sections.clear();
+        self.func_names.clear();
+    }
+
+    // Appends a custom section (e.g. one built with `CustomSectionBuilder`).
+    pub fn add_custom_section(&mut self, section: CustomSection<V>) {
+        self.digest = None;
+        self.custom_sections.push(section);
+    }
+
+    pub fn rename_export(&mut self, old: &str, new: &str) -> Result<(), TransformError> {
+        let i = self
+            .exports
+            .iter()
+            .position(|e| e.name.as_str() == old)
+            .ok_or(TransformError::UnknownExport)?;
+        if old != new && self.exports.iter().any(|e| e.name.as_str() == new) {
+            return Err(TransformError::DuplicateExportName);
+        }
+        self.digest = None;
+        self.exports[i].name = Name::new(new);
+        Ok(())
+    }
+
+    // Returns the removed start function, if any.
+    pub fn remove_start(&mut self) -> Option<Funcidx> {
+        self.digest = None;
+        self.start.take()
+    }
+
+    pub fn add_export_for_func(
+        &mut self,
+        funcidx: Funcidx,
+        name: &str,
+    ) -> Result<(), TransformError> {
+        if funcidx.get() >= self.imported_funcs_len() + self.funcs.len() {
+            return Err(TransformError::InvalidFuncidx {
+                value: funcidx.get(),
+            });
+        }
+        if self.exports.iter().any(|e| e.name.as_str() == name) {
+            return Err(TransformError::DuplicateExportName);
+        }
+        self.digest = None;
+        self.exports.push(Export {
+            name: Name::new(name),
+            desc: Exportdesc::Func(funcidx),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StdVectorFactory;
+
+    #[test]
+    fn transform_test() {
+        // (module
+        //   (func (export "a"))
+        //   (func $start)
+        //   (start $start)
+        //   (@custom "foo" "\01\02"))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 3, 2, 0, 0, 7, 5, 1, 1, 97, 0, 0, 8,
+            1, 1, 10, 7, 2, 2, 0, 11, 2, 0, 11, 0, 6, 3, 102, 111, 111, 1, 2,
+        ];
+        let mut module = Module::<StdVectorFactory>::decode(&input).expect("decode");
+
+        assert!(module.digest().is_some());
+        module.strip_custom_sections();
+        assert!(module.custom_sections().is_empty());
+        assert_eq!(None, module.digest());
+
+        assert_eq!(Some(Funcidx::new(1)), module.remove_start());
+        assert_eq!(None, module.start());
+
+        assert_eq!(
+            Err(TransformError::UnknownExport),
+            module.rename_export("b", "c")
+        );
+        module.rename_export("a", "b").expect("rename");
+        assert_eq!(
+            Err(TransformError::InvalidFuncidx { value: 2 }),
+            module.add_export_for_func(Funcidx::new(2), "start")
+        );
+        assert_eq!(
+            Err(TransformError::DuplicateExportName),
+            module.add_export_for_func(Funcidx::new(1), "b")
+        );
+        module
+            .add_export_for_func(Funcidx::new(1), "start")
+            .expect("add export");
+
+        let names = module
+            .exports()
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(["b", "start"], &names[..]);
+        module.validate().expect("validate");
+    }
+}
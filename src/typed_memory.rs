@@ -0,0 +1,232 @@
+use crate::LinearMemory;
+use core::{
+    fmt::{Debug, Display, Formatter},
+    marker::PhantomData,
+};
+
+// Numbers that can be stored in a linear memory (in little endian).
+// Implemented only for the primitive integers and floats of at most 8 bytes.
+pub trait MemValue: Copy + sealed::Sealed {
+    const SIZE: usize;
+
+    // `bytes` has exactly `SIZE` bytes.
+    fn from_le_slice(bytes: &[u8]) -> Self;
+
+    // `bytes` has exactly `SIZE` bytes.
+    fn write_le_slice(self, bytes: &mut [u8]);
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! impl_mem_value {
+    ($($ty:ty),*) => {
+        $(
+            impl sealed::Sealed for $ty {}
+
+            impl MemValue for $ty {
+                const SIZE: usize = core::mem::size_of::<$ty>();
+
+                fn from_le_slice(bytes: &[u8]) -> Self {
+                    let mut buf = [0; core::mem::size_of::<$ty>()];
+                    buf.copy_from_slice(bytes);
+                    Self::from_le_bytes(buf)
+                }
+
+                fn write_le_slice(self, bytes: &mut [u8]) {
+                    bytes.copy_from_slice(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+// Floats are converted through their bit patterns, so NaN payloads are preserved.
+impl_mem_value!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemAccessError {
+    OutOfBounds,
+
+    // The offset isn't a multiple of the size of the values.
+    Misaligned,
+}
+
+impl Display for MemAccessError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::OutOfBounds => write!(f, "Out of bounds memory access"),
+            Self::Misaligned => write!(f, "Misaligned memory access"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MemAccessError {}
+
+// Accesses to arrays of numbers in a linear memory (including `Env::mem` of host functions).
+//
+// The values are copied byte-wise, so that the guest can place them at any (aligned) offset
+// regardless of the alignment of the host buffer.
+pub trait TypedMemory: LinearMemory {
+    fn read_values<T: MemValue>(&self, offset: usize, out: &mut [T]) -> Result<(), MemAccessError> {
+        check_range::<T>(self.size(), offset, out.len())?;
+        let mut buf = [0; 256];
+        let mut offset = offset;
+        for chunk in out.chunks_mut(buf.len() / T::SIZE) {
+            let bytes = &mut buf[..chunk.len() * T::SIZE];
+            self.read(offset, bytes);
+            for (v, b) in chunk.iter_mut().zip(bytes.chunks_exact(T::SIZE)) {
+                *v = T::from_le_slice(b);
+            }
+            offset += bytes.len();
+        }
+        Ok(())
+    }
+
+    fn write_values<T: MemValue>(
+        &mut self,
+        offset: usize,
+        values: &[T],
+    ) -> Result<(), MemAccessError> {
+        check_range::<T>(self.size(), offset, values.len())?;
+        let mut buf = [0; 256];
+        let mut offset = offset;
+        for chunk in values.chunks(buf.len() / T::SIZE) {
+            let bytes = &mut buf[..chunk.len() * T::SIZE];
+            for (v, b) in chunk.iter().zip(bytes.chunks_exact_mut(T::SIZE)) {
+                v.write_le_slice(b);
+            }
+            self.write(offset, bytes);
+            offset += bytes.len();
+        }
+        Ok(())
+    }
+
+    // Returns a read-only view of `count` values starting at `offset`.
+    fn as_slice_of<T: MemValue>(
+        &self,
+        offset: usize,
+        count: usize,
+    ) -> Result<TypedSlice<'_, Self, T>, MemAccessError> {
+        check_range::<T>(self.size(), offset, count)?;
+        Ok(TypedSlice {
+            mem: self,
+            offset,
+            len: count,
+            _value: PhantomData,
+        })
+    }
+}
+
+impl<M: LinearMemory + ?Sized> TypedMemory for M {}
+
+fn check_range<T: MemValue>(
+    mem_size: usize,
+    offset: usize,
+    count: usize,
+) -> Result<(), MemAccessError> {
+    if !offset.is_multiple_of(T::SIZE) {
+        return Err(MemAccessError::Misaligned);
+    }
+    count
+        .checked_mul(T::SIZE)
+        .and_then(|len| offset.checked_add(len))
+        .filter(|end| *end <= mem_size)
+        .map(|_| ())
+        .ok_or(MemAccessError::OutOfBounds)
+}
+
+// The bounds are checked when the view is created (the memory can't shrink while it's borrowed).
+pub struct TypedSlice<'a, M: ?Sized, T> {
+    mem: &'a M,
+    offset: usize,
+    len: usize,
+    _value: PhantomData<T>,
+}
+
+impl<M: LinearMemory + ?Sized, T: MemValue> TypedSlice<'_, M, T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<T> {
+        if index >= self.len {
+            return None;
+        }
+        let mut buf = [0; 8];
+        let bytes = &mut buf[..T::SIZE];
+        self.mem.read(self.offset + index * T::SIZE, bytes);
+        Some(T::from_le_slice(bytes))
+    }
+
+    pub fn iter(&self) -> impl '_ + Iterator<Item = T> {
+        (0..self.len).filter_map(|i| self.get(i))
+    }
+}
+
+impl<M: ?Sized, T> Clone for TypedSlice<'_, M, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M: ?Sized, T> Copy for TypedSlice<'_, M, T> {}
+
+impl<M: LinearMemory + ?Sized, T: MemValue + Debug> Debug for TypedSlice<'_, M, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SliceMemory;
+
+    #[test]
+    fn typed_memory() {
+        let mut buf = [0; 1024];
+        let mut mem = SliceMemory::new(&mut buf);
+        assert!(mem.grow(1000));
+
+        let values = (0..100).map(|i| i * 0x0101_0101).collect::<Vec<u32>>();
+        mem.write_values(8, &values).expect("write_values");
+        let mut byte = [0];
+        mem.read(12, &mut byte);
+        assert_eq!([1], byte);
+
+        let mut out = vec![0; 100];
+        mem.read_values(8, &mut out).expect("read_values");
+        assert_eq!(values, out);
+
+        let slice = mem.as_slice_of::<u32>(8, 100).expect("as_slice_of");
+        assert_eq!(100, slice.len());
+        assert_eq!(Some(0x0202_0202), slice.get(2));
+        assert_eq!(None, slice.get(100));
+        assert_eq!(values, slice.iter().collect::<Vec<_>>());
+
+        let snan = f32::from_bits(0x7fa0_0001);
+        mem.write_values(0, &[snan]).expect("write_values");
+        let slice = mem.as_slice_of::<f32>(0, 1).expect("as_slice_of");
+        assert_eq!(Some(0x7fa0_0001), slice.get(0).map(f32::to_bits));
+
+        assert_eq!(
+            Err(MemAccessError::Misaligned),
+            mem.as_slice_of::<u64>(4, 1).map(|_| ())
+        );
+        assert_eq!(
+            Err(MemAccessError::OutOfBounds),
+            mem.as_slice_of::<u64>(992, 2).map(|_| ())
+        );
+        assert_eq!(
+            Err(MemAccessError::OutOfBounds),
+            mem.read_values(usize::MAX - 3, &mut [0u32])
+        );
+    }
+}
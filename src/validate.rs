@@ -0,0 +1,1019 @@
+use crate::{
+    components::{
+        Blocktype, ConstantExpr, ConstantInstr, DataMode, ElemMode, Exportdesc, Functype,
+        Globaltype, Importdesc, Labelidx, Limits, Memarg, Valtype,
+    },
+    instructions::Instr,
+    Module, Vector, VectorFactory,
+};
+use core::fmt::{Display, Formatter};
+
+const MAX_MEMORY_PAGES: u32 = 65536;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidateError {
+    InvalidTypeidx { value: usize },
+    InvalidFuncidx { value: usize },
+    InvalidGlobalidx { value: usize },
+    InvalidElemidx { value: usize },
+    InvalidDataidx { value: usize },
+    DataCountRequired,
+    InvalidLocalidx { value: usize },
+    InvalidLabelidx { value: usize },
+    UnknownTable,
+    UnknownMemory,
+    MultipleTables,
+    MultipleMemories,
+    InvalidLimits,
+    InvalidAlignment { align: u32 },
+    ImmutableGlobal { index: usize },
+    InvalidConstantExpr,
+    InvalidStartFunction,
+    DuplicateExportName,
+    TypeMismatch { expected: Valtype, actual: Valtype },
+    OperandStackUnderflow,
+    UnbalancedOperandStack,
+    UnsupportedType { value: usize },
+    UnsupportedInstruction,
+}
+
+impl Display for ValidateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidTypeidx { value } => write!(f, "Invalid type index {value:?}"),
+            Self::InvalidFuncidx { value } => write!(f, "Invalid function index {value:?}"),
+            Self::InvalidGlobalidx { value } => write!(f, "Invalid global index {value:?}"),
+            Self::InvalidElemidx { value } => write!(f, "Invalid element index {value:?}"),
+            Self::InvalidDataidx { value } => write!(f, "Invalid data index {value:?}"),
+            Self::DataCountRequired => write!(f, "Data count section required"),
+            Self::InvalidLocalidx { value } => write!(f, "Invalid local index {value:?}"),
+            Self::InvalidLabelidx { value } => write!(f, "Invalid label index {value:?}"),
+            Self::UnknownTable => write!(f, "Unknown table"),
+            Self::UnknownMemory => write!(f, "Unknown memory"),
+            Self::MultipleTables => write!(f, "Multiple tables"),
+            Self::MultipleMemories => write!(f, "Multiple memories"),
+            Self::InvalidLimits => write!(f, "Invalid limits"),
+            Self::InvalidAlignment { align } => write!(f, "Invalid alignment {align:?}"),
+            Self::ImmutableGlobal { index } => write!(f, "Immutable global {index:?}"),
+            Self::InvalidConstantExpr => write!(f, "Invalid constant expression"),
+            Self::InvalidStartFunction => write!(f, "Invalid start function"),
+            Self::DuplicateExportName => write!(f, "Duplicate export name"),
+            Self::TypeMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Type mismatch (expected={expected:?}, actual={actual:?})"
+                )
+            }
+            Self::OperandStackUnderflow => write!(f, "Operand stack underflow"),
+            Self::UnbalancedOperandStack => write!(f, "Unbalanced operand stack"),
+            Self::UnsupportedType { value } => write!(f, "Unsupported type {value:?}"),
+            Self::UnsupportedInstruction => write!(f, "Unsupported instruction"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ValidateError {}
+
+pub(crate) struct Validator<'a, V: VectorFactory> {
+    module: &'a Module<V>,
+    func_types: V::Vector<&'a Functype<V>>,
+    globals: V::Vector<Globaltype>,
+    imported_globals: usize,
+    has_table: bool,
+    has_mem: bool,
+}
+
+impl<'a, V: VectorFactory> Validator<'a, V> {
+    pub fn new(module: &'a Module<V>) -> Self {
+        Self {
+            module,
+            func_types: V::create_vector(None),
+            globals: V::create_vector(None),
+            imported_globals: 0,
+            has_table: false,
+            has_mem: false,
+        }
+    }
+
+    // Only the types equivalent to those of the MVP are supported by the interpreter.
+    #[cfg(feature = "gc")]
+    fn validate_gc_types(&self) -> Result<(), ValidateError> {
+        let subtypes = self
+            .module
+            .rectypes()
+            .iter()
+            .flat_map(|r| r.subtypes.iter());
+        for (i, subtype) in subtypes.enumerate() {
+            if !subtype.supertypes.is_empty() || subtype.comptype.to_functype().is_none() {
+                return Err(ValidateError::UnsupportedType { value: i });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate(self) -> Result<(), ValidateError> {
+        self.validate_with_frame_sizes().map(|_| ())
+    }
+
+    // Also returns the frame sizes of the functions defined in the module.
+    pub fn validate_with_frame_sizes(mut self) -> Result<V::Vector<FrameSize>, ValidateError> {
+        #[cfg(feature = "gc")]
+        self.validate_gc_types()?;
+        self.validate_imports()?;
+        self.validate_table_and_mem()?;
+
+        for func in self.module.funcs() {
+            let ty = self.functype(func.ty.get())?;
+            self.func_types.push(ty);
+        }
+        for global in self.module.globals() {
+            self.validate_constant_expr(&global.init, global.ty.valtype())?;
+            self.globals.push(global.ty);
+        }
+
+        for elem in self.module.elems() {
+            if let ElemMode::Active { offset, .. } = &elem.mode {
+                if !self.has_table {
+                    return Err(ValidateError::UnknownTable);
+                }
+                self.validate_constant_expr(offset, Valtype::I32)?;
+            }
+            for funcidx in elem.init.iter().flatten() {
+                self.check_funcidx(funcidx.get())?;
+            }
+        }
+        for data in self.module.datas() {
+            if let DataMode::Active { offset, .. } = &data.mode {
+                if !self.has_mem {
+                    return Err(ValidateError::UnknownMemory);
+                }
+                self.validate_constant_expr(offset, Valtype::I32)?;
+            }
+        }
+
+        if let Some(funcidx) = self.module.start() {
+            let ty = self.check_funcidx(funcidx.get())?;
+            if !ty.params.is_empty() || !ty.result.is_empty() {
+                return Err(ValidateError::InvalidStartFunction);
+            }
+        }
+
+        self.validate_exports()?;
+
+        let imported_funcs = self.func_types.len() - self.module.funcs().len();
+        let mut frame_sizes = V::create_vector(Some(self.module.funcs().len()));
+        for (i, func) in self.module.funcs().iter().enumerate() {
+            let ty = self.func_types[imported_funcs + i];
+            let mut locals = V::create_vector(Some(ty.params.len() + func.locals.len()));
+            for &t in ty.params.iter().chain(func.locals.iter()) {
+                locals.push(t);
+            }
+            let mut validator = FuncValidator::<V> {
+                context: &self,
+                locals,
+                result: ty.result.get(),
+                operands: V::create_vector(None),
+                ctrls: V::create_vector(None),
+                frame_size: FrameSize::default(),
+            };
+            validator.validate_body(func.body.instrs())?;
+            frame_sizes.push(validator.frame_size);
+        }
+
+        Ok(frame_sizes)
+    }
+
+    fn validate_imports(&mut self) -> Result<(), ValidateError> {
+        for import in self.module.imports() {
+            match import.desc {
+                Importdesc::Func(typeidx) => {
+                    let ty = self.functype(typeidx.get())?;
+                    self.func_types.push(ty);
+                }
+                Importdesc::Table(ty) => {
+                    if self.has_table {
+                        return Err(ValidateError::MultipleTables);
+                    }
+                    validate_limits(ty.limits, u32::MAX)?;
+                    self.has_table = true;
+                }
+                Importdesc::Mem(ty) => {
+                    if self.has_mem {
+                        return Err(ValidateError::MultipleMemories);
+                    }
+                    validate_limits(ty.limits, MAX_MEMORY_PAGES)?;
+                    self.has_mem = true;
+                }
+                Importdesc::Global(ty) => {
+                    self.globals.push(ty);
+                    self.imported_globals += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_table_and_mem(&mut self) -> Result<(), ValidateError> {
+        if let Some(ty) = self.module.table() {
+            if self.has_table {
+                return Err(ValidateError::MultipleTables);
+            }
+            validate_limits(ty.limits, u32::MAX)?;
+            self.has_table = true;
+        }
+        if let Some(ty) = self.module.mem() {
+            if self.has_mem {
+                return Err(ValidateError::MultipleMemories);
+            }
+            validate_limits(ty.limits, MAX_MEMORY_PAGES)?;
+            self.has_mem = true;
+        }
+        Ok(())
+    }
+
+    fn validate_exports(&self) -> Result<(), ValidateError> {
+        let exports = self.module.exports();
+        for (i, export) in exports.iter().enumerate() {
+            match export.desc {
+                Exportdesc::Func(idx) => {
+                    self.check_funcidx(idx.get())?;
+                }
+                Exportdesc::Table(_) => {
+                    if !self.has_table {
+                        return Err(ValidateError::UnknownTable);
+                    }
+                }
+                Exportdesc::Mem(_) => {
+                    if !self.has_mem {
+                        return Err(ValidateError::UnknownMemory);
+                    }
+                }
+                Exportdesc::Global(idx) => {
+                    self.check_globalidx(idx.get())?;
+                }
+            }
+            if exports[..i]
+                .iter()
+                .any(|e| e.name.as_str() == export.name.as_str())
+            {
+                return Err(ValidateError::DuplicateExportName);
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_constant_expr(
+        &self,
+        expr: &ConstantExpr<V>,
+        ty: Valtype,
+    ) -> Result<(), ValidateError> {
+        let mut stack = V::create_vector(Some(expr.instrs().len()));
+        for instr in expr.instrs() {
+            let t = match *instr {
+                ConstantInstr::I32Const(_) => Valtype::I32,
+                ConstantInstr::I64Const(_) => Valtype::I64,
+                ConstantInstr::F32Const(_) => Valtype::F32,
+                ConstantInstr::F64Const(_) => Valtype::F64,
+                ConstantInstr::GlobalGet(idx) => self.imported_const_global(idx.get())?,
+                #[cfg(feature = "extended_const")]
+                ConstantInstr::I32Add | ConstantInstr::I32Sub | ConstantInstr::I32Mul => {
+                    Self::pop_constant_operands(&mut stack, Valtype::I32)?
+                }
+                #[cfg(feature = "extended_const")]
+                ConstantInstr::I64Add | ConstantInstr::I64Sub | ConstantInstr::I64Mul => {
+                    Self::pop_constant_operands(&mut stack, Valtype::I64)?
+                }
+            };
+            stack.push(t);
+        }
+        let actual = match &stack[..] {
+            [actual] => *actual,
+            _ => return Err(ValidateError::InvalidConstantExpr),
+        };
+        if actual != ty {
+            return Err(ValidateError::TypeMismatch {
+                expected: ty,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "extended_const")]
+    fn pop_constant_operands(
+        stack: &mut V::Vector<Valtype>,
+        ty: Valtype,
+    ) -> Result<Valtype, ValidateError> {
+        for _ in 0..2 {
+            let actual = stack.pop().ok_or(ValidateError::InvalidConstantExpr)?;
+            if actual != ty {
+                return Err(ValidateError::TypeMismatch {
+                    expected: ty,
+                    actual,
+                });
+            }
+        }
+        Ok(ty)
+    }
+
+    fn imported_const_global(&self, index: usize) -> Result<Valtype, ValidateError> {
+        if index >= self.imported_globals {
+            return Err(ValidateError::InvalidConstantExpr);
+        }
+        let ty = self.globals[index];
+        if !ty.is_const() {
+            return Err(ValidateError::InvalidConstantExpr);
+        }
+        Ok(ty.valtype())
+    }
+
+    fn functype(&self, index: usize) -> Result<&'a Functype<V>, ValidateError> {
+        self.module
+            .types()
+            .get(index)
+            .ok_or(ValidateError::InvalidTypeidx { value: index })
+    }
+
+    fn check_funcidx(&self, index: usize) -> Result<&'a Functype<V>, ValidateError> {
+        self.func_types
+            .get(index)
+            .copied()
+            .ok_or(ValidateError::InvalidFuncidx { value: index })
+    }
+
+    // Data indices in function bodies are checked against the data count section
+    // so that code can be validated before the data section is decoded.
+    #[cfg(feature = "bulk_memory")]
+    fn check_dataidx(&self, index: usize) -> Result<(), ValidateError> {
+        let data_count = self
+            .module
+            .data_count()
+            .ok_or(ValidateError::DataCountRequired)?;
+        if index >= data_count as usize {
+            return Err(ValidateError::InvalidDataidx { value: index });
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "bulk_memory")]
+    fn check_elemidx(&self, index: usize) -> Result<(), ValidateError> {
+        if index >= self.module.elems().len() {
+            return Err(ValidateError::InvalidElemidx { value: index });
+        }
+        Ok(())
+    }
+
+    fn check_globalidx(&self, index: usize) -> Result<Globaltype, ValidateError> {
+        self.globals
+            .get(index)
+            .copied()
+            .ok_or(ValidateError::InvalidGlobalidx { value: index })
+    }
+}
+
+fn validate_limits(limits: Limits, upper: u32) -> Result<(), ValidateError> {
+    if limits.min > upper {
+        return Err(ValidateError::InvalidLimits);
+    }
+    if let Some(max) = limits.max {
+        if max > upper || max < limits.min {
+            return Err(ValidateError::InvalidLimits);
+        }
+    }
+    Ok(())
+}
+
+// Peaks of the operand stack height and the number of the nested blocks (excluding the
+// function body itself) of a function.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct FrameSize {
+    pub operands: usize,
+    pub labels: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Ctrl {
+    label: Option<Valtype>,
+    end: Option<Valtype>,
+    height: usize,
+    unreachable: bool,
+}
+
+struct FuncValidator<'a, 'b, V: VectorFactory> {
+    context: &'b Validator<'a, V>,
+    locals: V::Vector<Valtype>,
+    result: Option<Valtype>,
+
+    // `None` represents an operand of unknown type (only appears in unreachable code).
+    operands: V::Vector<Option<Valtype>>,
+    ctrls: V::Vector<Ctrl>,
+    frame_size: FrameSize,
+}
+
+impl<'a, 'b, V: VectorFactory> FuncValidator<'a, 'b, V> {
+    fn validate_body(&mut self, instrs: &[Instr<V>]) -> Result<(), ValidateError> {
+        self.push_ctrl(self.result, self.result);
+        self.validate_instrs(instrs)?;
+        self.pop_ctrl()
+    }
+
+    fn validate_instrs(&mut self, instrs: &[Instr<V>]) -> Result<(), ValidateError> {
+        for instr in instrs {
+            self.validate_instr(instr)?;
+        }
+        Ok(())
+    }
+
+    fn validate_block(&mut self, ty: Blocktype, instrs: &[Instr<V>]) -> Result<(), ValidateError> {
+        let end = blocktype_result(ty);
+        self.push_ctrl(end, end);
+        self.validate_instrs(instrs)?;
+        self.pop_ctrl()?;
+        self.push_opt(end);
+        Ok(())
+    }
+
+    fn validate_instr(&mut self, instr: &Instr<V>) -> Result<(), ValidateError> {
+        use Valtype::{F32, F64, I32, I64};
+
+        match instr {
+            // Control Instructions
+            Instr::Unreachable => self.unreachable(),
+            Instr::Nop => {}
+            Instr::Block(block) => self.validate_block(block.blocktype, &block.instrs)?,
+            Instr::Loop(block) => {
+                let end = blocktype_result(block.blocktype);
+                self.push_ctrl(None, end);
+                self.validate_instrs(&block.instrs)?;
+                self.pop_ctrl()?;
+                self.push_opt(end);
+            }
+            Instr::If(block) => {
+                self.pop_expect(I32)?;
+                let end = blocktype_result(block.blocktype);
+                self.push_ctrl(end, end);
+                self.validate_instrs(&block.then_instrs)?;
+                self.pop_ctrl()?;
+                self.push_ctrl(end, end);
+                self.validate_instrs(&block.else_instrs)?;
+                self.pop_ctrl()?;
+                self.push_opt(end);
+            }
+            Instr::Br(label) => {
+                let ty = self.label_type(*label)?;
+                self.pop_opt(ty)?;
+                self.unreachable();
+            }
+            Instr::BrIf(label) => {
+                self.pop_expect(I32)?;
+                let ty = self.label_type(*label)?;
+                self.pop_opt(ty)?;
+                self.push_opt(ty);
+            }
+            Instr::BrTable(table) => {
+                self.pop_expect(I32)?;
+                let (default, labels) = table.labels.split_last().expect("unreachable");
+                let ty = self.label_type(*default)?;
+                for label in labels {
+                    if self.label_type(*label)? != ty {
+                        return Err(self.label_type_mismatch(ty, self.label_type(*label)?));
+                    }
+                }
+                self.pop_opt(ty)?;
+                self.unreachable();
+            }
+            Instr::Return => {
+                self.pop_opt(self.result)?;
+                self.unreachable();
+            }
+            Instr::Call(funcidx) => {
+                let ty = self.context.check_funcidx(funcidx.get())?;
+                self.call(ty)?;
+            }
+            Instr::CallIndirect(typeidx) => {
+                if !self.context.has_table {
+                    return Err(ValidateError::UnknownTable);
+                }
+                let ty = self.context.functype(typeidx.get())?;
+                self.pop_expect(I32)?;
+                self.call(ty)?;
+            }
+
+            // Parametric Instructions
+            Instr::Drop => {
+                self.pop()?;
+            }
+            Instr::Select => {
+                self.pop_expect(I32)?;
+                let t1 = self.pop()?;
+                let t2 = self.pop()?;
+                match (t1, t2) {
+                    (Some(t1), Some(t2)) if t1 != t2 => {
+                        return Err(ValidateError::TypeMismatch {
+                            expected: t1,
+                            actual: t2,
+                        });
+                    }
+                    // Both operands may be unknown in unreachable code, and so is the result.
+                    _ => self.push_operand(t1.or(t2)),
+                }
+            }
+            #[cfg(feature = "reference_types")]
+            Instr::SelectTyped(t) => {
+                self.pop_expect(I32)?;
+                self.pop_expect(*t)?;
+                self.pop_expect(*t)?;
+                self.push(*t);
+            }
+
+            // Variable Instructions
+            Instr::LocalGet(idx) => {
+                let t = self.local_type(idx.get())?;
+                self.push(t);
+            }
+            Instr::LocalSet(idx) => {
+                let t = self.local_type(idx.get())?;
+                self.pop_expect(t)?;
+            }
+            Instr::LocalTee(idx) => {
+                let t = self.local_type(idx.get())?;
+                self.pop_expect(t)?;
+                self.push(t);
+            }
+            Instr::GlobalGet(idx) => {
+                let ty = self.context.check_globalidx(idx.get())?;
+                self.push(ty.valtype());
+            }
+            Instr::GlobalSet(idx) => {
+                let ty = self.context.check_globalidx(idx.get())?;
+                if ty.is_const() {
+                    return Err(ValidateError::ImmutableGlobal { index: idx.get() });
+                }
+                self.pop_expect(ty.valtype())?;
+            }
+
+            // Memory Instructions
+            Instr::I32Load(arg) => self.load(arg, 4, I32)?,
+            Instr::I64Load(arg) => self.load(arg, 8, I64)?,
+            Instr::F32Load(arg) => self.load(arg, 4, F32)?,
+            Instr::F64Load(arg) => self.load(arg, 8, F64)?,
+            Instr::I32Load8S(arg) | Instr::I32Load8U(arg) => self.load(arg, 1, I32)?,
+            Instr::I32Load16S(arg) | Instr::I32Load16U(arg) => self.load(arg, 2, I32)?,
+            Instr::I64Load8S(arg) | Instr::I64Load8U(arg) => self.load(arg, 1, I64)?,
+            Instr::I64Load16S(arg) | Instr::I64Load16U(arg) => self.load(arg, 2, I64)?,
+            Instr::I64Load32S(arg) | Instr::I64Load32U(arg) => self.load(arg, 4, I64)?,
+            Instr::I32Store(arg) => self.store(arg, 4, I32)?,
+            Instr::I64Store(arg) => self.store(arg, 8, I64)?,
+            Instr::F32Store(arg) => self.store(arg, 4, F32)?,
+            Instr::F64Store(arg) => self.store(arg, 8, F64)?,
+            Instr::I32Store8(arg) => self.store(arg, 1, I32)?,
+            Instr::I32Store16(arg) => self.store(arg, 2, I32)?,
+            Instr::I64Store8(arg) => self.store(arg, 1, I64)?,
+            Instr::I64Store16(arg) => self.store(arg, 2, I64)?,
+            Instr::I64Store32(arg) => self.store(arg, 4, I64)?,
+            Instr::MemorySize => {
+                self.check_mem()?;
+                self.push(I32);
+            }
+            Instr::MemoryGrow => {
+                self.check_mem()?;
+                self.unop(I32, I32)?;
+            }
+
+            // Numeric Instructions
+            Instr::I32Const(_) => self.push(I32),
+            Instr::I64Const(_) => self.push(I64),
+            Instr::F32Const(_) => self.push(F32),
+            Instr::F64Const(_) => self.push(F64),
+            Instr::I32Eqz => self.unop(I32, I32)?,
+            Instr::I32Eq
+            | Instr::I32Ne
+            | Instr::I32LtS
+            | Instr::I32LtU
+            | Instr::I32GtS
+            | Instr::I32GtU
+            | Instr::I32LeS
+            | Instr::I32LeU
+            | Instr::I32GeS
+            | Instr::I32GeU => self.binop(I32, I32)?,
+            Instr::I64Eqz => self.unop(I64, I32)?,
+            Instr::I64Eq
+            | Instr::I64Ne
+            | Instr::I64LtS
+            | Instr::I64LtU
+            | Instr::I64GtS
+            | Instr::I64GtU
+            | Instr::I64LeS
+            | Instr::I64LeU
+            | Instr::I64GeS
+            | Instr::I64GeU => self.binop(I64, I32)?,
+            Instr::F32Eq
+            | Instr::F32Ne
+            | Instr::F32Lt
+            | Instr::F32Gt
+            | Instr::F32Le
+            | Instr::F32Ge => self.binop(F32, I32)?,
+            Instr::F64Eq
+            | Instr::F64Ne
+            | Instr::F64Lt
+            | Instr::F64Gt
+            | Instr::F64Le
+            | Instr::F64Ge => self.binop(F64, I32)?,
+            Instr::I32Clz | Instr::I32Ctz | Instr::I32Popcnt => self.unop(I32, I32)?,
+            Instr::I32Add
+            | Instr::I32Sub
+            | Instr::I32Mul
+            | Instr::I32DivS
+            | Instr::I32DivU
+            | Instr::I32RemS
+            | Instr::I32RemU
+            | Instr::I32And
+            | Instr::I32Or
+            | Instr::I32Xor
+            | Instr::I32Shl
+            | Instr::I32ShrS
+            | Instr::I32ShrU
+            | Instr::I32Rotl
+            | Instr::I32Rotr => self.binop(I32, I32)?,
+            Instr::I64Clz | Instr::I64Ctz | Instr::I64Popcnt => self.unop(I64, I64)?,
+            Instr::I64Add
+            | Instr::I64Sub
+            | Instr::I64Mul
+            | Instr::I64DivS
+            | Instr::I64DivU
+            | Instr::I64RemS
+            | Instr::I64RemU
+            | Instr::I64And
+            | Instr::I64Or
+            | Instr::I64Xor
+            | Instr::I64Shl
+            | Instr::I64ShrS
+            | Instr::I64ShrU
+            | Instr::I64Rotl
+            | Instr::I64Rotr => self.binop(I64, I64)?,
+            Instr::F32Abs
+            | Instr::F32Neg
+            | Instr::F32Ceil
+            | Instr::F32Floor
+            | Instr::F32Trunc
+            | Instr::F32Nearest
+            | Instr::F32Sqrt => self.unop(F32, F32)?,
+            Instr::F32Add
+            | Instr::F32Sub
+            | Instr::F32Mul
+            | Instr::F32Div
+            | Instr::F32Min
+            | Instr::F32Max
+            | Instr::F32Copysign => self.binop(F32, F32)?,
+            Instr::F64Abs
+            | Instr::F64Neg
+            | Instr::F64Ceil
+            | Instr::F64Floor
+            | Instr::F64Trunc
+            | Instr::F64Nearest
+            | Instr::F64Sqrt => self.unop(F64, F64)?,
+            Instr::F64Add
+            | Instr::F64Sub
+            | Instr::F64Mul
+            | Instr::F64Div
+            | Instr::F64Min
+            | Instr::F64Max
+            | Instr::F64Copysign => self.binop(F64, F64)?,
+            Instr::I32WrapI64 => self.unop(I64, I32)?,
+            Instr::I32TruncF32S | Instr::I32TruncF32U => self.unop(F32, I32)?,
+            Instr::I32TruncF64S | Instr::I32TruncF64U => self.unop(F64, I32)?,
+            Instr::I64ExtendI32S | Instr::I64ExtendI32U => self.unop(I32, I64)?,
+            Instr::I64TruncF32S | Instr::I64TruncF32U => self.unop(F32, I64)?,
+            Instr::I64TruncF64S | Instr::I64TruncF64U => self.unop(F64, I64)?,
+            Instr::F32ConvertI32S | Instr::F32ConvertI32U => self.unop(I32, F32)?,
+            Instr::F32ConvertI64S | Instr::F32ConvertI64U => self.unop(I64, F32)?,
+            Instr::F32DemoteF64 => self.unop(F64, F32)?,
+            Instr::F64ConvertI32S | Instr::F64ConvertI32U => self.unop(I32, F64)?,
+            Instr::F64ConvertI64S | Instr::F64ConvertI64U => self.unop(I64, F64)?,
+            Instr::F64PromoteF32 => self.unop(F32, F64)?,
+            Instr::I32ReinterpretF32 => self.unop(F32, I32)?,
+            Instr::I64ReinterpretF64 => self.unop(F64, I64)?,
+            Instr::F32ReinterpretI32 => self.unop(I32, F32)?,
+            Instr::F64ReinterpretI64 => self.unop(I64, F64)?,
+
+            // Sign Extension
+            #[cfg(feature = "sign_extension")]
+            Instr::SignExtension(instr) => match instr {
+                crate::sign_extension::SignExtensionInstr::I32Extend8S
+                | crate::sign_extension::SignExtensionInstr::I32Extend16S => self.unop(I32, I32)?,
+                crate::sign_extension::SignExtensionInstr::I64Extend8S
+                | crate::sign_extension::SignExtensionInstr::I64Extend16S
+                | crate::sign_extension::SignExtensionInstr::I64Extend32S => self.unop(I64, I64)?,
+            },
+
+            // Bulk Memory Instructions
+            #[cfg(feature = "bulk_memory")]
+            Instr::BulkMemory(instr) => match instr {
+                crate::bulk_memory::BulkMemoryInstr::MemoryInit(idx) => {
+                    if !self.context.has_mem {
+                        return Err(ValidateError::UnknownMemory);
+                    }
+                    self.context.check_dataidx(idx.get())?;
+                    self.pop_expect(I32)?;
+                    self.pop_expect(I32)?;
+                    self.pop_expect(I32)?;
+                }
+                crate::bulk_memory::BulkMemoryInstr::DataDrop(idx) => {
+                    self.context.check_dataidx(idx.get())?;
+                }
+                crate::bulk_memory::BulkMemoryInstr::MemoryCopy
+                | crate::bulk_memory::BulkMemoryInstr::MemoryFill => {
+                    if !self.context.has_mem {
+                        return Err(ValidateError::UnknownMemory);
+                    }
+                    self.pop_expect(I32)?;
+                    self.pop_expect(I32)?;
+                    self.pop_expect(I32)?;
+                }
+                crate::bulk_memory::BulkMemoryInstr::TableInit(idx) => {
+                    if !self.context.has_table {
+                        return Err(ValidateError::UnknownTable);
+                    }
+                    self.context.check_elemidx(idx.get())?;
+                    self.pop_expect(I32)?;
+                    self.pop_expect(I32)?;
+                    self.pop_expect(I32)?;
+                }
+                crate::bulk_memory::BulkMemoryInstr::ElemDrop(idx) => {
+                    self.context.check_elemidx(idx.get())?;
+                }
+                crate::bulk_memory::BulkMemoryInstr::TableCopy => {
+                    if !self.context.has_table {
+                        return Err(ValidateError::UnknownTable);
+                    }
+                    self.pop_expect(I32)?;
+                    self.pop_expect(I32)?;
+                    self.pop_expect(I32)?;
+                }
+            },
+
+            // GC Instructions
+            #[cfg(feature = "gc")]
+            Instr::Gc(_) => return Err(ValidateError::UnsupportedInstruction),
+
+            // Embedder-defined Instructions
+            #[cfg(feature = "extension")]
+            Instr::Extension(instr) => {
+                for &t in instr.params.iter().rev() {
+                    self.pop_expect(t)?;
+                }
+                self.push_opt(instr.result);
+            }
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, ty: &Functype<V>) -> Result<(), ValidateError> {
+        for &t in ty.params.iter().rev() {
+            self.pop_expect(t)?;
+        }
+        self.push_opt(ty.result.get());
+        Ok(())
+    }
+
+    fn load(&mut self, arg: &Memarg, width: u32, t: Valtype) -> Result<(), ValidateError> {
+        self.check_memarg(arg, width)?;
+        self.unop(Valtype::I32, t)
+    }
+
+    fn store(&mut self, arg: &Memarg, width: u32, t: Valtype) -> Result<(), ValidateError> {
+        self.check_memarg(arg, width)?;
+        self.pop_expect(t)?;
+        self.pop_expect(Valtype::I32)
+    }
+
+    fn check_memarg(&self, arg: &Memarg, width: u32) -> Result<(), ValidateError> {
+        self.check_mem()?;
+        if arg.align >= 32 || (1 << arg.align) > width {
+            return Err(ValidateError::InvalidAlignment { align: arg.align });
+        }
+        Ok(())
+    }
+
+    fn check_mem(&self) -> Result<(), ValidateError> {
+        if !self.context.has_mem {
+            return Err(ValidateError::UnknownMemory);
+        }
+        Ok(())
+    }
+
+    fn unop(&mut self, t: Valtype, result: Valtype) -> Result<(), ValidateError> {
+        self.pop_expect(t)?;
+        self.push(result);
+        Ok(())
+    }
+
+    fn binop(&mut self, t: Valtype, result: Valtype) -> Result<(), ValidateError> {
+        self.pop_expect(t)?;
+        self.pop_expect(t)?;
+        self.push(result);
+        Ok(())
+    }
+
+    fn local_type(&self, index: usize) -> Result<Valtype, ValidateError> {
+        self.locals
+            .get(index)
+            .copied()
+            .ok_or(ValidateError::InvalidLocalidx { value: index })
+    }
+
+    fn label_type(&self, label: Labelidx) -> Result<Option<Valtype>, ValidateError> {
+        let i = label.get();
+        if i >= self.ctrls.len() {
+            return Err(ValidateError::InvalidLabelidx { value: i });
+        }
+        Ok(self.ctrls[self.ctrls.len() - 1 - i].label)
+    }
+
+    fn label_type_mismatch(
+        &self,
+        expected: Option<Valtype>,
+        actual: Option<Valtype>,
+    ) -> ValidateError {
+        match (expected, actual) {
+            (Some(expected), Some(actual)) => ValidateError::TypeMismatch { expected, actual },
+            _ => ValidateError::UnbalancedOperandStack,
+        }
+    }
+
+    fn push(&mut self, t: Valtype) {
+        self.push_operand(Some(t));
+    }
+
+    // `None` is an operand of an unknown type.
+    fn push_operand(&mut self, t: Option<Valtype>) {
+        self.operands.push(t);
+        self.frame_size.operands = self.frame_size.operands.max(self.operands.len());
+    }
+
+    fn push_opt(&mut self, t: Option<Valtype>) {
+        if let Some(t) = t {
+            self.push(t);
+        }
+    }
+
+    fn pop(&mut self) -> Result<Option<Valtype>, ValidateError> {
+        let ctrl = self.ctrls.last().copied().expect("unreachable");
+        if self.operands.len() == ctrl.height {
+            if ctrl.unreachable {
+                return Ok(None);
+            }
+            return Err(ValidateError::OperandStackUnderflow);
+        }
+        Ok(self.operands.pop().expect("unreachable"))
+    }
+
+    fn pop_expect(&mut self, expected: Valtype) -> Result<(), ValidateError> {
+        match self.pop()? {
+            Some(actual) if actual != expected => {
+                Err(ValidateError::TypeMismatch { expected, actual })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn pop_opt(&mut self, expected: Option<Valtype>) -> Result<(), ValidateError> {
+        if let Some(t) = expected {
+            self.pop_expect(t)?;
+        }
+        Ok(())
+    }
+
+    fn push_ctrl(&mut self, label: Option<Valtype>, end: Option<Valtype>) {
+        self.ctrls.push(Ctrl {
+            label,
+            end,
+            height: self.operands.len(),
+            unreachable: false,
+        });
+        self.frame_size.labels = self.frame_size.labels.max(self.ctrls.len() - 1);
+    }
+
+    fn pop_ctrl(&mut self) -> Result<(), ValidateError> {
+        let ctrl = self.ctrls.last().copied().expect("unreachable");
+        self.pop_opt(ctrl.end)?;
+        if self.operands.len() != ctrl.height {
+            return Err(ValidateError::UnbalancedOperandStack);
+        }
+        self.ctrls.pop();
+        Ok(())
+    }
+
+    fn unreachable(&mut self) {
+        let ctrl = self.ctrls.last_mut().expect("unreachable");
+        ctrl.unreachable = true;
+        let height = ctrl.height;
+        self.operands.truncate(height);
+    }
+}
+
+fn blocktype_result(ty: Blocktype) -> Option<Valtype> {
+    match ty {
+        Blocktype::Empty => None,
+        Blocktype::Val(t) => Some(t),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StdVectorFactory;
+
+    fn validate(wasm: &[u8]) -> Result<(), ValidateError> {
+        Module::<StdVectorFactory>::decode(wasm)
+            .expect("decode module")
+            .validate()
+    }
+
+    #[test]
+    fn validate_add_two() {
+        // (module
+        //   (func (export "addTwo") (param i32 i32) (result i32)
+        //     local.get 0
+        //     local.get 1
+        //     i32.add))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 7, 1, 96, 2, 127, 127, 1, 127, 3, 2, 1, 0, 7, 10, 1, 6,
+            97, 100, 100, 84, 119, 111, 0, 0, 10, 9, 1, 7, 0, 32, 0, 32, 1, 106, 11,
+        ];
+        assert_eq!(Ok(()), validate(&input));
+    }
+
+    #[test]
+    fn select_operand_type_mismatch() {
+        // (module
+        //   (func (export "f") (result i32)
+        //     i32.const 1
+        //     i64.const 2
+        //     i32.const 0
+        //     select))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 127, 3, 2, 1, 0, 7, 5, 1, 1, 102, 0, 0,
+            10, 11, 1, 9, 0, 65, 1, 66, 2, 65, 0, 27, 11,
+        ];
+        assert_eq!(
+            Err(ValidateError::TypeMismatch {
+                expected: Valtype::I64,
+                actual: Valtype::I32
+            }),
+            validate(&input)
+        );
+    }
+
+    #[test]
+    fn select_unknown_operands() {
+        // (module
+        //   (func (result i32) unreachable select))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 127, 3, 2, 1, 0, 10, 6, 1, 4, 0, 0, 27,
+            11,
+        ];
+        assert_eq!(Ok(()), validate(&input));
+
+        // The result of `select` remains on the stack even if its type is unknown.
+        //
+        // (module
+        //   (func unreachable select))
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 2, 1, 0, 10, 6, 1, 4, 0, 0, 27, 11,
+        ];
+        assert_eq!(Err(ValidateError::UnbalancedOperandStack), validate(&input));
+    }
+
+    #[test]
+    fn set_immutable_global() {
+        // From `global.wast` of the spec test suite:
+        //
+        // (assert_invalid
+        //   (module (global i32 (i32.const 0)) (func (global.set 0 (i32.const 1))))
+        //   "global is immutable")
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 2, 1, 0, 6, 6, 1, 127, 0, 65, 0, 11,
+            10, 8, 1, 6, 0, 65, 1, 36, 0, 11,
+        ];
+        assert_eq!(
+            Err(ValidateError::ImmutableGlobal { index: 0 }),
+            validate(&input)
+        );
+
+        // (assert_invalid
+        //   (module (import "spectest" "global_i32" (global i32))
+        //           (func (global.set 0 (i32.const 1))))
+        //   "global is immutable")
+        let input = [
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 2, 24, 1, 8, 115, 112, 101, 99, 116,
+            101, 115, 116, 10, 103, 108, 111, 98, 97, 108, 95, 105, 51, 50, 3, 127, 0, 3, 2, 1, 0,
+            10, 8, 1, 6, 0, 65, 1, 36, 0, 11,
+        ];
+        assert_eq!(
+            Err(ValidateError::ImmutableGlobal { index: 0 }),
+            validate(&input)
+        );
+    }
+}
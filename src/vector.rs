@@ -1,10 +1,18 @@
 use core::ops::{Deref, DerefMut, RangeBounds};
 
+// Note that `Instr` contains `Self::Vector<Instr>` (block bodies), so an implementation storing
+// its items inline (e.g., a small-vector with inline capacity) would make `Instr` infinitely
+// sized. Implementations need to keep the items behind an indirection.
 pub trait VectorFactory {
     type Vector<T>: Vector<T>;
 
     fn create_vector<T>(capacity: Option<usize>) -> Self::Vector<T>;
-    fn clone_vector<T: Clone>(vector: &[T]) -> Self::Vector<T>;
+
+    fn clone_vector<T: Clone>(vector: &[T]) -> Self::Vector<T> {
+        let mut cloned = Self::create_vector(Some(vector.len()));
+        cloned.extend_from_slice(vector);
+        cloned
+    }
 }
 
 pub trait Vector<T>: Deref<Target = [T]> + DerefMut<Target = [T]> {
@@ -12,6 +20,51 @@ pub trait Vector<T>: Deref<Target = [T]> + DerefMut<Target = [T]> {
     fn pop(&mut self) -> Option<T>;
     fn truncate(&mut self, len: usize);
     fn remove_range<R: RangeBounds<usize>>(&mut self, range: R);
+
+    // Appends `n` clones of `value`.
+    fn extend_with(&mut self, n: usize, value: T)
+    where
+        T: Clone,
+    {
+        self.reserve(n);
+        for _ in 0..n {
+            self.push(value.clone());
+        }
+    }
+
+    fn extend_from_slice(&mut self, items: &[T])
+    where
+        T: Clone,
+    {
+        self.reserve(items.len());
+        for item in items {
+            self.push(item.clone());
+        }
+    }
+
+    fn insert(&mut self, index: usize, item: T) {
+        assert!(index <= self.len());
+        self.push(item);
+        self[index..].rotate_right(1);
+    }
+
+    fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    // Implementations that cannot report their capacity may return the current length.
+    fn capacity(&self) -> usize {
+        self.len()
+    }
+
+    // This is just a hint, so the default implementation does nothing.
+    fn reserve(&mut self, _additional: usize) {}
+
+    // Returns `false` if room for `additional` more items cannot be allocated.
+    // Implementations without fallible allocation may just return `true`.
+    fn try_reserve(&mut self, _additional: usize) -> bool {
+        true
+    }
 }
 
 #[cfg(feature = "std")]
@@ -67,6 +120,40 @@ impl<T> Vector<T> for StdVector<T> {
     fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) {
         self.0.drain(range);
     }
+
+    fn extend_with(&mut self, n: usize, value: T)
+    where
+        T: Clone,
+    {
+        self.0.resize(self.0.len() + n, value);
+    }
+
+    fn extend_from_slice(&mut self, items: &[T])
+    where
+        T: Clone,
+    {
+        self.0.extend_from_slice(items);
+    }
+
+    fn insert(&mut self, index: usize, item: T) {
+        self.0.insert(index, item);
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional);
+    }
+
+    fn try_reserve(&mut self, additional: usize) -> bool {
+        self.0.try_reserve(additional).is_ok()
+    }
 }
 
 #[cfg(feature = "std")]
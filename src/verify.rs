@@ -0,0 +1,93 @@
+use crate::{
+    module::{read_section, Magic, Version},
+    reader::Reader,
+    DecodeConfig, DecodeError, Module, SectionId, VectorFactory,
+};
+
+// Name of the custom section conventionally holding the signature of a module.
+pub const SIGNATURE_SECTION_NAME: &str = "signature";
+
+// Checks the raw bytes of a module before they are decoded (e.g. verifies the signature).
+pub trait Verify {
+    // `signature` is the payload of the first `SIGNATURE_SECTION_NAME` custom section, if any.
+    fn verify(&mut self, wasm_bytes: &[u8], signature: Option<&[u8]>) -> bool;
+}
+
+impl<F> Verify for F
+where
+    F: FnMut(&[u8], Option<&[u8]>) -> bool,
+{
+    fn verify(&mut self, wasm_bytes: &[u8], signature: Option<&[u8]>) -> bool {
+        self(wasm_bytes, signature)
+    }
+}
+
+impl<V: VectorFactory> Module<V> {
+    // Decodes a module only if `verifier` accepts it.
+    //
+    // Before calling the verifier, only the preamble and the section headers are read.
+    pub fn decode_verified<T: Verify>(
+        wasm_bytes: &[u8],
+        config: DecodeConfig,
+        mut verifier: T,
+    ) -> Result<Self, DecodeError> {
+        let signature = find_custom_section(wasm_bytes, SIGNATURE_SECTION_NAME)?;
+        if !verifier.verify(wasm_bytes, signature) {
+            return Err(DecodeError::RejectedByVerifier);
+        }
+        Self::decode_with_config(wasm_bytes, config)
+    }
+}
+
+fn find_custom_section<'a>(
+    wasm_bytes: &'a [u8],
+    name: &str,
+) -> Result<Option<&'a [u8]>, DecodeError> {
+    let mut reader = Reader::new(wasm_bytes);
+    let _ = Magic::decode(&mut reader)?;
+    let _ = Version::decode(&mut reader)?;
+
+    let mut last_section_id = None;
+    while !reader.is_empty() {
+        let (section_id, _, mut section_reader) = read_section(&mut reader, &mut last_section_id)?;
+        if section_id == SectionId::Custom && section_reader.read_name()? == name {
+            return Ok(Some(section_reader.remaining()));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CustomSectionBuilder, StdVectorFactory};
+
+    #[test]
+    fn decode_verified() {
+        let mut wasm = vec![0, 97, 115, 109, 1, 0, 0, 0];
+        let decode = |wasm: &[u8]| {
+            Module::<StdVectorFactory>::decode_verified(
+                wasm,
+                DecodeConfig::default(),
+                |_: &[u8], signature: Option<&[u8]>| signature == Some(b"ok"),
+            )
+        };
+        assert_eq!(Some(DecodeError::RejectedByVerifier), decode(&wasm).err());
+
+        let mut builder = CustomSectionBuilder::<StdVectorFactory>::new(SIGNATURE_SECTION_NAME);
+        builder.write_raw(b"ok");
+        let mut buf = StdVectorFactory::create_vector(None);
+        builder.finish().encode(&mut buf);
+        wasm.extend_from_slice(&buf);
+        assert!(decode(&wasm).is_ok());
+
+        // Malformed framing is reported before the verifier is called.
+        let e = Module::<StdVectorFactory>::decode_verified(
+            &wasm[..wasm.len() - 1],
+            DecodeConfig::default(),
+            |_: &[u8], _: Option<&[u8]>| unreachable!(),
+        )
+        .err();
+        assert_eq!(Some(DecodeError::UnexpectedEndOfBytes), e);
+    }
+}
@@ -0,0 +1,300 @@
+// Runs the same modules and inputs through nowasm and wasmi (as the reference engine),
+// and compares the results and traps.
+//
+// Run with `cargo test --features differential --test differential`.
+use nowasm::{leb128_u, Module, StdVectorFactory, Val};
+
+const I32: u8 = 0x7f;
+const I64: u8 = 0x7e;
+const F32: u8 = 0x7d;
+const F64: u8 = 0x7c;
+
+const I32_INPUTS: &[i32] = &[
+    0,
+    1,
+    2,
+    3,
+    7,
+    31,
+    32,
+    33,
+    -1,
+    -2,
+    -7,
+    0x1234_5678,
+    -0x1234_5678,
+    i32::MAX,
+    i32::MIN,
+];
+const I64_INPUTS: &[i64] = &[
+    0,
+    1,
+    2,
+    7,
+    63,
+    64,
+    65,
+    -1,
+    -7,
+    0x1234_5678_9abc_def0,
+    -0x1234_5678,
+    i64::MAX,
+    i64::MIN,
+];
+const F32_INPUTS: &[f32] = &[0.0, -0.0, 1.0, -1.5, 0.1, 3.0e10, f32::INFINITY];
+const F64_INPUTS: &[f64] = &[0.0, -0.0, 1.0, -1.5, 0.1, 3.0e100, f64::NEG_INFINITY];
+
+// (Opcode, operand type, result type)
+//
+// Operations that can divide by zero with the above inputs are not listed yet,
+// as the interpreter doesn't implement the trapping semantics of the spec for them.
+const BINOPS: &[(u8, u8, u8)] = &[
+    (0x46, I32, I32), // i32.eq
+    (0x47, I32, I32), // i32.ne
+    (0x48, I32, I32), // i32.lt_s
+    (0x49, I32, I32), // i32.lt_u
+    (0x4a, I32, I32), // i32.gt_s
+    (0x4b, I32, I32), // i32.gt_u
+    (0x4c, I32, I32), // i32.le_s
+    (0x4d, I32, I32), // i32.le_u
+    (0x4e, I32, I32), // i32.ge_s
+    (0x4f, I32, I32), // i32.ge_u
+    (0x51, I64, I32), // i64.eq
+    (0x52, I64, I32), // i64.ne
+    (0x53, I64, I32), // i64.lt_s
+    (0x54, I64, I32), // i64.lt_u
+    (0x55, I64, I32), // i64.gt_s
+    (0x56, I64, I32), // i64.gt_u
+    (0x57, I64, I32), // i64.le_s
+    (0x58, I64, I32), // i64.le_u
+    (0x59, I64, I32), // i64.ge_s
+    (0x5a, I64, I32), // i64.ge_u
+    (0x5b, F32, I32), // f32.eq
+    (0x5c, F32, I32), // f32.ne
+    (0x5d, F32, I32), // f32.lt
+    (0x5e, F32, I32), // f32.gt
+    (0x5f, F32, I32), // f32.le
+    (0x60, F32, I32), // f32.ge
+    (0x61, F64, I32), // f64.eq
+    (0x62, F64, I32), // f64.ne
+    (0x63, F64, I32), // f64.lt
+    (0x64, F64, I32), // f64.gt
+    (0x65, F64, I32), // f64.le
+    (0x66, F64, I32), // f64.ge
+    (0x6a, I32, I32), // i32.add
+    (0x6b, I32, I32), // i32.sub
+    (0x6c, I32, I32), // i32.mul
+    (0x71, I32, I32), // i32.and
+    (0x72, I32, I32), // i32.or
+    (0x73, I32, I32), // i32.xor
+    (0x74, I32, I32), // i32.shl
+    (0x75, I32, I32), // i32.shr_s
+    (0x76, I32, I32), // i32.shr_u
+    (0x77, I32, I32), // i32.rotl
+    (0x78, I32, I32), // i32.rotr
+    (0x7c, I64, I64), // i64.add
+    (0x7d, I64, I64), // i64.sub
+    (0x7e, I64, I64), // i64.mul
+    (0x83, I64, I64), // i64.and
+    (0x84, I64, I64), // i64.or
+    (0x85, I64, I64), // i64.xor
+    (0x86, I64, I64), // i64.shl
+    (0x87, I64, I64), // i64.shr_s
+    (0x88, I64, I64), // i64.shr_u
+    (0x89, I64, I64), // i64.rotl
+    (0x8a, I64, I64), // i64.rotr
+    (0x92, F32, F32), // f32.add
+    (0x93, F32, F32), // f32.sub
+    (0x94, F32, F32), // f32.mul
+    (0x95, F32, F32), // f32.div
+    (0x98, F32, F32), // f32.copysign
+    (0xa0, F64, F64), // f64.add
+    (0xa1, F64, F64), // f64.sub
+    (0xa2, F64, F64), // f64.mul
+    (0xa3, F64, F64), // f64.div
+    (0xa6, F64, F64), // f64.copysign
+];
+
+const UNOPS: &[(u8, u8, u8)] = &[
+    (0x45, I32, I32), // i32.eqz
+    (0x50, I64, I32), // i64.eqz
+    (0x67, I32, I32), // i32.clz
+    (0x68, I32, I32), // i32.ctz
+    (0x69, I32, I32), // i32.popcnt
+    (0x79, I64, I64), // i64.clz
+    (0x7a, I64, I64), // i64.ctz
+    (0x7b, I64, I64), // i64.popcnt
+    (0x8b, F32, F32), // f32.abs
+    (0x8c, F32, F32), // f32.neg
+    (0x99, F64, F64), // f64.abs
+    (0x9a, F64, F64), // f64.neg
+    (0xa7, I64, I32), // i32.wrap_i64
+    (0xac, I32, I64), // i64.extend_i32_s
+    (0xad, I32, I64), // i64.extend_i32_u
+    (0xbb, F32, F64), // f64.promote_f32
+    (0xbc, F32, I32), // i32.reinterpret_f32
+    (0xbd, F64, I64), // i64.reinterpret_f64
+];
+
+#[test]
+fn binops() {
+    for &(opcode, ty, result) in BINOPS {
+        let code = [0x20, 0, 0x20, 1, opcode];
+        let wasm = func_module(&[ty, ty], result, &[], &code);
+        for a in inputs(ty) {
+            for b in inputs(ty) {
+                compare(&wasm, &[a, b], &format!("opcode={opcode:#x}"));
+            }
+        }
+    }
+}
+
+#[test]
+fn unops() {
+    for &(opcode, ty, result) in UNOPS {
+        let code = [0x20, 0, opcode];
+        let wasm = func_module(&[ty], result, &[], &code);
+        for a in inputs(ty) {
+            compare(&wasm, &[a], &format!("opcode={opcode:#x}"));
+        }
+    }
+}
+
+#[test]
+fn control_flow() {
+    // (func (export "f") (param i64) (result i64) (local i64)
+    //   (local.set 1 (i64.const 1))
+    //   (block (loop
+    //     (br_if 1 (i64.eqz (local.get 0)))
+    //     (local.set 1 (i64.mul (local.get 1) (local.get 0)))
+    //     (local.set 0 (i64.sub (local.get 0) (i64.const 1)))
+    //     (br 0)))
+    //   (local.get 1))
+    let code = [
+        0x42, 1, 0x21, 1, 0x02, 0x40, 0x03, 0x40, 0x20, 0, 0x50, 0x0d, 1, 0x20, 1, 0x20, 0, 0x7e,
+        0x21, 1, 0x20, 0, 0x42, 1, 0x7d, 0x21, 0, 0x0c, 0, 0x0b, 0x0b, 0x20, 1,
+    ];
+    let wasm = func_module(&[I64], I64, &[I64], &code);
+    for n in 0..=20 {
+        compare(&wasm, &[Val::I64(n)], "factorial");
+    }
+
+    // (func (export "f") (param i32) (result i32)
+    //   (block (block (block (br_table 0 1 2 (local.get 0)))
+    //     (return (i32.const 10)))
+    //     (return (i32.const 20)))
+    //   (i32.const 30))
+    let code = [
+        0x02, 0x40, 0x02, 0x40, 0x02, 0x40, 0x20, 0, 0x0e, 2, 0, 1, 2, 0x0b, 0x41, 10, 0x0f, 0x0b,
+        0x41, 20, 0x0f, 0x0b, 0x41, 30,
+    ];
+    let wasm = func_module(&[I32], I32, &[], &code);
+    for a in inputs(I32) {
+        compare(&wasm, &[a], "br_table");
+    }
+
+    // (func (export "f") (param i32) (result i32)
+    //   (if (result i32) (local.get 0) (then (unreachable)) (else (i32.const 1))))
+    let code = [0x20, 0, 0x04, I32, 0x00, 0x05, 0x41, 1, 0x0b];
+    let wasm = func_module(&[I32], I32, &[], &code);
+    for a in [Val::I32(0), Val::I32(1)] {
+        compare(&wasm, &[a], "unreachable");
+    }
+}
+
+fn inputs(ty: u8) -> Vec<Val> {
+    match ty {
+        I32 => I32_INPUTS.iter().copied().map(Val::I32).collect(),
+        I64 => I64_INPUTS.iter().copied().map(Val::I64).collect(),
+        F32 => F32_INPUTS.iter().copied().map(Val::F32).collect(),
+        _ => F64_INPUTS.iter().copied().map(Val::F64).collect(),
+    }
+}
+
+// Builds `(module (func (export "f") (param params...) (result result) (local locals...) code))`.
+fn func_module(params: &[u8], result: u8, locals: &[u8], code: &[u8]) -> Vec<u8> {
+    let mut functype = vec![1, 0x60, params.len() as u8];
+    functype.extend_from_slice(params);
+    functype.extend_from_slice(&[1, result]);
+
+    let mut body = vec![locals.len() as u8];
+    for &local in locals {
+        body.extend_from_slice(&[1, local]);
+    }
+    body.extend_from_slice(code);
+    body.push(0x0b);
+    let mut codes = vec![1];
+    codes.extend(leb128_u(body.len() as u64, 0));
+    codes.extend_from_slice(&body);
+
+    let mut wasm = vec![0, 97, 115, 109, 1, 0, 0, 0];
+    section(&mut wasm, 1, &functype);
+    section(&mut wasm, 3, &[1, 0]);
+    section(&mut wasm, 7, &[1, 1, b'f', 0, 0]);
+    section(&mut wasm, 10, &codes);
+    wasm
+}
+
+fn section(wasm: &mut Vec<u8>, id: u8, payload: &[u8]) {
+    wasm.push(id);
+    wasm.extend(leb128_u(payload.len() as u64, 0));
+    wasm.extend_from_slice(payload);
+}
+
+// `None` means a trap. Floats are compared by their bits (NaNs are canonicalized).
+type Outcome = Option<(u8, u64)>;
+
+fn compare(wasm: &[u8], args: &[Val], context: &str) {
+    let expected = run_wasmi(wasm, args);
+    let actual = run_nowasm(wasm, args);
+    assert_eq!(expected, actual, "{context}, args={args:?}");
+}
+
+fn run_nowasm(wasm: &[u8], args: &[Val]) -> Outcome {
+    let module = Module::<StdVectorFactory>::decode(wasm).expect("decode");
+    let mut instance = module.instantiate(()).expect("instantiate");
+    let value = instance.invoke("f", args).ok()?.expect("result");
+    Some(match value {
+        Val::I32(v) => (I32, v as u32 as u64),
+        Val::I64(v) => (I64, v as u64),
+        Val::F32(v) if v.is_nan() => (F32, f32::NAN.to_bits() as u64),
+        Val::F32(v) => (F32, v.to_bits() as u64),
+        Val::F64(v) if v.is_nan() => (F64, f64::NAN.to_bits()),
+        Val::F64(v) => (F64, v.to_bits()),
+        _ => unreachable!(),
+    })
+}
+
+fn run_wasmi(wasm: &[u8], args: &[Val]) -> Outcome {
+    let engine = wasmi::Engine::default();
+    let module = wasmi::Module::new(&engine, wasm).expect("wasmi module");
+    let mut store = wasmi::Store::new(&engine, ());
+    let linker = wasmi::Linker::<()>::new(&engine);
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .and_then(|instance| instance.start(&mut store))
+        .expect("wasmi instance");
+    let func = instance.get_func(&store, "f").expect("wasmi func");
+
+    let args = args
+        .iter()
+        .map(|v| match *v {
+            Val::I32(v) => wasmi::Val::I32(v),
+            Val::I64(v) => wasmi::Val::I64(v),
+            Val::F32(v) => wasmi::Val::F32(v.into()),
+            Val::F64(v) => wasmi::Val::F64(v.into()),
+            _ => unreachable!(),
+        })
+        .collect::<Vec<_>>();
+    let mut results = [wasmi::Val::I32(0)];
+    func.call(&mut store, &args, &mut results).ok()?;
+    Some(match results[0] {
+        wasmi::Val::I32(v) => (I32, v as u32 as u64),
+        wasmi::Val::I64(v) => (I64, v as u64),
+        wasmi::Val::F32(v) if f32::from(v).is_nan() => (F32, f32::NAN.to_bits() as u64),
+        wasmi::Val::F32(v) => (F32, v.to_bits() as u64),
+        wasmi::Val::F64(v) if f64::from(v).is_nan() => (F64, f64::NAN.to_bits()),
+        wasmi::Val::F64(v) => (F64, v.to_bits()),
+        _ => unreachable!(),
+    })
+}
@@ -0,0 +1,119 @@
+// Runs the modules under `tests/golden/` and compares the call results and the final state of
+// the instances with the expected outputs, to verify that the interpreter behaves identically
+// on every platform (especially for floats and LEB128 corner cases).
+//
+// `<name>.txt` lists the `call <func> <args>...` lines to run against `<name>.wasm` (the arguments
+// are parsed as the parameter types). Each call is followed by its result (`=> ...`), and the
+// globals and the non-zero memory rows are dumped at the end. Lines starting with `#` are comments
+// (the sources of the modules).
+//
+// Run with `NOWASM_BLESS=1` to update the expected outputs.
+use nowasm::{Module, StdVectorFactory, Val};
+use std::{fmt::Write, fs, path::Path};
+
+#[test]
+fn golden() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut wasm_paths = fs::read_dir(&dir)
+        .expect("read_dir")
+        .map(|entry| entry.expect("entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+        .collect::<Vec<_>>();
+    wasm_paths.sort();
+    assert!(!wasm_paths.is_empty());
+
+    let bless = std::env::var_os("NOWASM_BLESS").is_some();
+    let mut failures = String::new();
+    for wasm_path in wasm_paths {
+        let txt_path = wasm_path.with_extension("txt");
+        let expected = fs::read_to_string(&txt_path).expect("read expected output");
+        let actual = run(&fs::read(&wasm_path).expect("read module"), &expected);
+        if actual == expected {
+            continue;
+        }
+        if bless {
+            fs::write(&txt_path, &actual).expect("write expected output");
+            continue;
+        }
+        writeln!(failures, "{}:", txt_path.display()).unwrap();
+        for (expected, actual) in expected.lines().zip(actual.lines()) {
+            if expected != actual {
+                writeln!(failures, "- {expected}\n+ {actual}").unwrap();
+            }
+        }
+        let (expected_len, actual_len) = (expected.lines().count(), actual.lines().count());
+        if expected_len != actual_len {
+            writeln!(
+                failures,
+                "({expected_len} lines expected, got {actual_len})"
+            )
+            .unwrap();
+        }
+    }
+    assert!(failures.is_empty(), "golden outputs differ:\n{failures}");
+}
+
+fn run(wasm: &[u8], script: &str) -> String {
+    let module = Module::<StdVectorFactory>::decode(wasm).expect("decode");
+    let mut instance = module.instantiate(()).expect("instantiate");
+
+    let mut out = String::new();
+    for line in script.lines() {
+        let Some(call) = line.strip_prefix("call ") else {
+            // The outputs of the previous run are regenerated below.
+            let is_output = ["=>", "global ", "memory "]
+                .iter()
+                .any(|prefix| line.starts_with(prefix));
+            if !is_output {
+                writeln!(out, "{line}").unwrap();
+            }
+            continue;
+        };
+        writeln!(out, "{line}").unwrap();
+
+        let mut tokens = call.split_whitespace();
+        let name = tokens.next().expect("function name");
+        let handle = instance.func_handle(name).expect("exported function");
+        let func = &instance.funcs()[handle.funcidx().get()];
+        let params = &func.get_type(instance.module()).expect("type").params;
+        let args = params
+            .iter()
+            .zip(tokens)
+            .map(|(&ty, arg)| Val::parse_as(ty, arg).expect("argument"))
+            .collect::<Vec<_>>();
+        match instance.invoke_handle(handle, &args) {
+            Ok(Some(v)) => writeln!(out, "=> {}", format_val(v)).unwrap(),
+            Ok(None) => writeln!(out, "=>").unwrap(),
+            Err(e) => writeln!(out, "=> error: {e}").unwrap(),
+        }
+    }
+
+    out.truncate(out.trim_end().len());
+    writeln!(out, "\n").unwrap();
+    for (i, global) in instance.globals().iter().enumerate() {
+        writeln!(out, "global {i} = {}", format_val(global.get())).unwrap();
+    }
+    for (i, row) in instance.mem().as_slice().chunks(16).enumerate() {
+        if row.iter().any(|b| *b != 0) {
+            write!(out, "memory {:#07x}:", i * 16).unwrap();
+            for b in row {
+                write!(out, " {b:02x}").unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+    }
+    out
+}
+
+// Floats are printed as their bit patterns, so that any difference (e.g., in the last bit of
+// the mantissa) shows up. The signs and payloads of NaNs are not (and need not be) printed as
+// they are nondeterministic.
+fn format_val(v: Val) -> String {
+    match v {
+        Val::F32(v) if v.is_nan() => "f32:nan".to_owned(),
+        Val::F64(v) if v.is_nan() => "f64:nan".to_owned(),
+        Val::F32(v) => format!("f32:{:#010x}", v.to_bits()),
+        Val::F64(v) => format!("f64:{:#018x}", v.to_bits()),
+        _ => v.to_string(),
+    }
+}
@@ -0,0 +1,82 @@
+// Decodes and runs a tiny module without relying on the `std` feature of nowasm.
+//
+// Run with `cargo test --no-default-features --test no_std_smoke` so that the library itself is
+// built as `no_std` (this test crate can still use `std` for its own helpers).
+use nowasm::{Module, Val, Vector, VectorFactory};
+use std::ops::{Deref, DerefMut, RangeBounds};
+
+// Number of items a vector can hold, as if it were a statically allocated buffer.
+const CAPACITY: usize = 1 << 17;
+
+#[derive(Debug)]
+struct FixedVectorFactory;
+
+impl VectorFactory for FixedVectorFactory {
+    type Vector<T> = FixedVector<T>;
+
+    fn create_vector<T>(capacity: Option<usize>) -> Self::Vector<T> {
+        FixedVector(Vec::with_capacity(capacity.unwrap_or(0).min(CAPACITY)))
+    }
+}
+
+#[derive(Debug)]
+struct FixedVector<T>(Vec<T>);
+
+impl<T> Vector<T> for FixedVector<T> {
+    fn push(&mut self, item: T) {
+        assert!(self.0.len() < CAPACITY, "capacity exceeded");
+        self.0.push(item);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.0.pop()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.0.truncate(len);
+    }
+
+    fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        self.0.drain(range);
+    }
+
+    fn try_reserve(&mut self, additional: usize) -> bool {
+        additional <= CAPACITY - self.0.len()
+    }
+}
+
+impl<T> Deref for FixedVector<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for FixedVector<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[test]
+fn decode_and_execute() {
+    // (module
+    //   (memory 1)
+    //   (func (export "add") (param i32 i32) (result i32)
+    //     (i32.add (local.get 0) (local.get 1)))
+    //   (data (i32.const 0) "hi"))
+    let input = [
+        0, 97, 115, 109, 1, 0, 0, 0, 1, 7, 1, 96, 2, 127, 127, 1, 127, 3, 2, 1, 0, 5, 3, 1, 0, 1,
+        7, 7, 1, 3, 97, 100, 100, 0, 0, 10, 9, 1, 7, 0, 32, 0, 32, 1, 106, 11, 11, 8, 1, 0, 65, 0,
+        11, 2, 104, 105,
+    ];
+    let module = Module::<FixedVectorFactory>::decode(&input).expect("decode");
+    let mut instance = module.instantiate(()).expect("instantiate");
+
+    let result = instance
+        .invoke("add", &[Val::I32(2), Val::I32(3)])
+        .expect("invoke");
+    assert_eq!(Some(Val::I32(5)), result);
+    assert_eq!(b"hi", &instance.mem().as_slice()[..2]);
+}